@@ -0,0 +1,109 @@
+//! Logs commands against a key-value store through a [`TypedWal`], then
+//! rebuilds the store by replaying them.
+//!
+//! Run with `cargo run --example command_log`.
+
+use std::collections::BTreeMap;
+
+use waly_rs::{Payload, Result, TypedWal, WalError};
+
+/// A change to the store. Encoded as a tag byte followed by the key and,
+/// for `Set`, a NUL and the value.
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Set { key: String, value: String },
+    Delete { key: String },
+}
+
+impl Payload for Command {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self {
+            Command::Set { key, value } => {
+                out.push(b'S');
+                out.extend_from_slice(key.as_bytes());
+                out.push(0);
+                out.extend_from_slice(value.as_bytes());
+            }
+            Command::Delete { key } => {
+                out.push(b'D');
+                out.extend_from_slice(key.as_bytes());
+            }
+        }
+        Ok(out)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let text = |b: &[u8]| {
+            String::from_utf8(b.to_vec()).map_err(|e| WalError::Serialization(e.to_string()))
+        };
+        match bytes.split_first() {
+            Some((b'S', rest)) => {
+                let nul = rest.iter().position(|&b| b == 0).ok_or_else(|| {
+                    WalError::Serialization("set command without a value".to_string())
+                })?;
+                Ok(Command::Set {
+                    key: text(&rest[..nul])?,
+                    value: text(&rest[nul + 1..])?,
+                })
+            }
+            Some((b'D', key)) => Ok(Command::Delete { key: text(key)? }),
+            _ => Err(WalError::Serialization(format!(
+                "unknown command {bytes:?}"
+            ))),
+        }
+    }
+}
+
+fn apply(store: &mut BTreeMap<String, String>, command: Command) {
+    match command {
+        Command::Set { key, value } => {
+            store.insert(key, value);
+        }
+        Command::Delete { key } => {
+            store.remove(&key);
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("command_log-{}.wal", std::process::id()));
+    let commands = [
+        Command::Set {
+            key: "colour".into(),
+            value: "red".into(),
+        },
+        Command::Set {
+            key: "size".into(),
+            value: "large".into(),
+        },
+        Command::Set {
+            key: "colour".into(),
+            value: "blue".into(),
+        },
+        Command::Delete { key: "size".into() },
+    ];
+
+    let mut live = BTreeMap::new();
+    {
+        let mut wal = TypedWal::<Command>::new(&path)?;
+        for command in commands {
+            wal.append(&command)?;
+            apply(&mut live, command);
+        }
+    }
+
+    // After a restart, the log is all there is.
+    let wal = TypedWal::<Command>::new(&path)?;
+    let mut replayed = BTreeMap::new();
+    for command in wal.read_all()? {
+        println!("replaying {command:?}");
+        apply(&mut replayed, command);
+    }
+    assert_eq!(replayed, live);
+    println!("rebuilt store: {replayed:?}");
+
+    drop(wal);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
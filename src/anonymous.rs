@@ -0,0 +1,56 @@
+use std::fs::{self, OpenOptions};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Result;
+use crate::fence;
+use crate::hasher;
+use crate::high_water;
+use crate::lock;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Opens a log backed by a fresh uniquely named file in the system temp
+    /// directory, deleted together with any segments and sidecars when the
+    /// log is dropped. Handy for tests and short-lived buffering.
+    pub fn anonymous() -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "waly-{}-{}-{nanos}.wal",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+        ));
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let mut wal = Self::new(&path)?;
+        wal.anonymous = true;
+        Ok(wal)
+    }
+
+    /// Deletes every file belonging to an anonymous log.
+    pub(crate) fn remove_files(&self) {
+        if let Ok(segments) = segment::all_segments(&self.path) {
+            for path in segments {
+                let _ = fs::remove_file(path);
+            }
+        }
+        let _ = fs::remove_file(self.progress_path());
+        let _ = fs::remove_file(self.consumers_path());
+        let _ = fs::remove_file(self.sequence_path());
+        let _ = fs::remove_file(self.checkpoint_path());
+        hasher::remove_sidecar(&self.path);
+        let _ = fs::remove_file(fence::epoch_path(&self.path));
+        let _ = fs::remove_file(high_water::high_water_path(&self.path));
+        let _ = fs::remove_file(lock::lock_path(&self.path));
+        if let Some(path) = self.quarantine_path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Applying appends and deletions together as one transaction.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::sync::Arc;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// One step of a transaction passed to [`WriteAheadLog::apply`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalOp {
+    /// Append a data entry with this payload.
+    Append(Vec<u8>),
+    /// Remove the data entry with this ID, if present. Unlike
+    /// [`clear_id`](WriteAheadLog::clear_id) this leaves no tombstone: the
+    /// entry is dropped from the rewritten file.
+    Delete(u64),
+}
+
+impl WriteAheadLog {
+    /// Applies `ops` as one transaction that lands entirely or not at all,
+    /// made durable with a single sync. Returns the appended entries, which
+    /// get contiguous IDs in the order given. Deletions apply to entries
+    /// already in the log.
+    ///
+    /// Without deletions the appends are written with one write, like
+    /// [`append_batch`](Self::append_batch). With deletions the active file
+    /// is rewritten into a temp file, new entries included, that replaces
+    /// it in a single rename. The entries to delete must therefore be in
+    /// the active file: deleting one in a sealed segment fails with
+    /// [`WalError::InvalidConfig`] before anything is written.
+    ///
+    /// Queued group-commit records are written first.
+    pub fn apply(&mut self, ops: Vec<WalOp>) -> Result<Vec<LogEntry>> {
+        self.flush()?;
+        let rewrite = Arc::clone(&self.rewrite_lock);
+        let _rewrite = rewrite.lock()?;
+        let mut deletes = HashSet::new();
+        let mut payloads = Vec::new();
+        for op in ops {
+            match op {
+                WalOp::Append(data) => payloads.push(data),
+                WalOp::Delete(id) => {
+                    deletes.insert(id);
+                }
+            }
+        }
+        self.gate.pass(self.pause_mode)?;
+        self.take_tokens(payloads.len())?;
+        self.make_room(payloads.len() as u64)?;
+
+        let first_id = self.current_id;
+        let timestamp = self.now()?;
+        let mut entries: Vec<LogEntry> = payloads
+            .into_iter()
+            .map(|data| LogEntry {
+                data,
+                timestamp,
+                ..LogEntry::default()
+            })
+            .collect();
+        for (id, entry) in (first_id..).zip(&mut entries) {
+            entry.id = id;
+            self.finalize(entry);
+        }
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        if deletes.is_empty() {
+            self.write_records(&mut file, &entries)?;
+        } else {
+            self.apply_rewrite(&mut file, &deletes, &entries)?;
+        }
+        self.current_id = first_id + entries.len() as u64;
+        self.sync_file(&file, self.current_id)?;
+        drop(file);
+        self.forward(&entries)?;
+        Ok(entries)
+    }
+
+    /// Replaces the active `file` with its records, minus the stream-0 data
+    /// entries in `deletes`, followed by `entries`.
+    fn apply_rewrite(
+        &self,
+        file: &mut File,
+        deletes: &HashSet<u64>,
+        entries: &[LogEntry],
+    ) -> Result<()> {
+        for (_, path) in segment::sealed_segments(&self.path)? {
+            for record in self.read_segment(&path, &File::open(&path)?)? {
+                if record.stream == 0
+                    && record.kind == EntryKind::Data
+                    && deletes.contains(&record.id)
+                {
+                    return Err(WalError::InvalidConfig(format!(
+                        "apply cannot delete entry {} in sealed segment {}",
+                        record.id,
+                        path.display()
+                    )));
+                }
+            }
+        }
+        let mut records = self.read_segment(&self.path, file)?;
+        records.retain(|r| r.stream != 0 || r.kind != EntryKind::Data || !deletes.contains(&r.id));
+        let incoming = entries
+            .iter()
+            .map(|e| self.encode_record(e).map(|record| record.len() as u64))
+            .sum::<Result<u64>>()?;
+        self.check_limits(file, incoming, entries.len() as u64)?;
+        records.extend_from_slice(entries);
+        let written = self.rewrite_records(&self.path, &records)?;
+        *file = segment::open_active(&self.path, &self.header())?;
+        self.writes.rewritten(written);
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(())
+    }
+}
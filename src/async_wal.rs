@@ -0,0 +1,271 @@
+//! A runtime-agnostic async front end for [`WriteAheadLog`].
+//!
+//! The log is owned by a worker thread that runs the operations sent to it
+//! one at a time, in order, and each operation resolves once it finishes,
+//! so awaiting never stalls the executor. No particular runtime is
+//! required. The log is the same [`WriteAheadLog`] underneath, so files are
+//! interchangeable with the blocking API.
+//!
+//! With the `tokio` feature, [`sync`](AsyncWriteAheadLog::sync) awaited
+//! within a tokio runtime leaves the worker free while the file is synced,
+//! through [`tokio::fs::File::sync_data`].
+//!
+//! An operation that panics resolves to [`WalError::Poisoned`], as does
+//! every later one, since the log may have been left half-updated, just as
+//! a panic while holding a lock on it would leave it.
+
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// An async handle to a [`WriteAheadLog`], holding its exclusive advisory
+/// lock on `<path>.lock` until the last clone is dropped; see
+/// [`WriteAheadLogBuilder::lock`](crate::WriteAheadLogBuilder::lock).
+/// Dropping the last clone does not wait: the operations already sent
+/// finish and the log is closed in the background. Use
+/// [`close`](Self::close) to wait for that.
+#[derive(Debug, Clone)]
+pub struct AsyncWriteAheadLog {
+    worker: Arc<Worker>,
+}
+
+impl AsyncWriteAheadLog {
+    /// Opens the log at `path`, failing with
+    /// [`WalError::Locked`](crate::WalError::Locked) if another handle
+    /// already holds it.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (worker, opened) = Worker::spawn(move || WriteAheadLog::new(&path));
+        opened.await?;
+        Ok(AsyncWriteAheadLog {
+            worker: Arc::new(worker),
+        })
+    }
+
+    /// See [`WriteAheadLog::append`].
+    pub async fn append(&self, data: Vec<u8>) -> Result<LogEntry> {
+        self.worker.run(move |wal| wal.append(data)).await
+    }
+
+    /// See [`WriteAheadLog::append_batch`].
+    pub async fn append_batch(&self, items: Vec<Vec<u8>>) -> Result<Vec<LogEntry>> {
+        self.worker.run(move |wal| wal.append_batch(items)).await
+    }
+
+    /// Appends `items` `chunk_len` at a time, handing control back to the
+    /// executor between chunks. See
+    /// [`WriteAheadLog::append_batch_chunked`].
+    pub async fn append_batch_chunked(
+        &self,
+        items: Vec<Vec<u8>>,
+        chunk_len: usize,
+    ) -> Result<Vec<LogEntry>> {
+        let mut appended = Vec::with_capacity(items.len());
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            let chunk: Vec<_> = items.by_ref().take(chunk_len.max(1)).collect();
+            appended.extend(self.worker.run(move |wal| wal.append_chunk(chunk)).await?);
+        }
+        Ok(appended)
+    }
+
+    /// See [`WriteAheadLog::read_all`].
+    pub async fn read_all(&self) -> Result<Vec<LogEntry>> {
+        self.worker.run(|wal| wal.read_all()).await
+    }
+
+    /// See [`WriteAheadLog::clear_id`].
+    pub async fn clear_id(&self, id: u64) -> Result<bool> {
+        self.worker.run(move |wal| wal.clear_id(id)).await
+    }
+
+    /// Syncs everything appended so far to disk. See
+    /// [`WriteAheadLog::sync`].
+    #[cfg(not(feature = "tokio"))]
+    pub async fn sync(&self) -> Result<()> {
+        self.worker.run(|wal| wal.sync()).await
+    }
+
+    /// Syncs everything appended so far to disk. See
+    /// [`WriteAheadLog::sync`]. Within a tokio runtime the file is synced
+    /// with [`tokio::fs::File::sync_data`], leaving the worker free to run
+    /// other operations meanwhile; elsewhere it is synced on the worker.
+    #[cfg(feature = "tokio")]
+    pub async fn sync(&self) -> Result<()> {
+        if tokio::runtime::Handle::try_current().is_err() {
+            return self.worker.run(|wal| wal.sync()).await;
+        }
+        let (file, up_to) = self.worker.run(|wal| wal.prepare_sync()).await?;
+        tokio::fs::File::from_std(file).sync_data().await?;
+        self.worker.run(move |wal| wal.synced(up_to)).await
+    }
+
+    /// Drops this handle and, if it is the last clone, waits for the
+    /// operations already sent to finish and the log to be closed,
+    /// releasing its lock. Errors writing out what remains go to the
+    /// [`on_drop_error`](crate::WriteAheadLogBuilder::on_drop_error)
+    /// handler, as on drop; [`sync`](Self::sync) first to handle them
+    /// directly.
+    pub async fn close(self) -> Result<()> {
+        match Arc::try_unwrap(self.worker) {
+            Ok(worker) => worker.close().await,
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl From<WriteAheadLog> for AsyncWriteAheadLog {
+    /// Hands an open log over to a worker thread, e.g. one opened through
+    /// [`WriteAheadLog::builder`] with options [`open`](Self::open) does
+    /// not take.
+    fn from(wal: WriteAheadLog) -> Self {
+        let (worker, _opened) = Worker::spawn(move || Ok(wal));
+        AsyncWriteAheadLog {
+            worker: Arc::new(worker),
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce(&mut WriteAheadLog) + Send>;
+
+/// The channel feeding operations to the thread owning the log. The thread
+/// closes the log and exits once every sender is gone.
+struct Worker {
+    jobs: Sender<Job>,
+    closed: Blocking<()>,
+}
+
+impl Worker {
+    /// Starts a worker on the log `open` returns, resolving the returned
+    /// future once it has been opened.
+    fn spawn<F>(open: F) -> (Self, Blocking<()>)
+    where
+        F: FnOnce() -> Result<WriteAheadLog> + Send + 'static,
+    {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (opened, opening) = blocking();
+        let (closing, closed) = blocking();
+        thread::spawn(move || {
+            let mut wal = match open() {
+                Ok(wal) => wal,
+                Err(err) => {
+                    opened.complete(Err(err));
+                    return closing.complete(Ok(()));
+                }
+            };
+            opened.complete(Ok(()));
+            let mut poisoned = false;
+            for job in queue {
+                // Dropping a job unrun resolves it to `Poisoned`.
+                if !poisoned {
+                    poisoned = panic::catch_unwind(AssertUnwindSafe(|| job(&mut wal))).is_err();
+                }
+            }
+            drop(wal);
+            closing.complete(Ok(()));
+        });
+        (Worker { jobs, closed }, opening)
+    }
+
+    /// Sends `f` to the worker, resolving to its result.
+    fn run<F, T>(&self, f: F) -> Blocking<T>
+    where
+        F: FnOnce(&mut WriteAheadLog) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (completion, future) = blocking();
+        let job: Job = Box::new(move |wal| completion.complete(f(wal)));
+        // If the worker is gone, the job comes back and is dropped.
+        let _ = self.jobs.send(job);
+        future
+    }
+
+    /// Stops sending operations, resolving once those already sent have
+    /// run and the log has been closed.
+    fn close(self) -> Blocking<()> {
+        drop(self.jobs);
+        self.closed
+    }
+}
+
+impl fmt::Debug for Worker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker").finish_non_exhaustive()
+    }
+}
+
+/// A future and the [`Completion`] that resolves it.
+fn blocking<T>() -> (Completion<T>, Blocking<T>) {
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+    }));
+    let completion = Completion {
+        shared: Some(Arc::clone(&shared)),
+    };
+    (completion, Blocking { shared })
+}
+
+struct Shared<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Resolves a [`Blocking`] future, to [`WalError::Poisoned`] if dropped
+/// unused, as when the operation panics or is never run.
+struct Completion<T> {
+    shared: Option<Arc<Mutex<Shared<T>>>>,
+}
+
+impl<T> Completion<T> {
+    fn complete(mut self, result: Result<T>) {
+        if let Some(shared) = self.shared.take() {
+            resolve(&shared, result);
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            resolve(&shared, Err(WalError::Poisoned));
+        }
+    }
+}
+
+fn resolve<T>(shared: &Mutex<Shared<T>>, result: Result<T>) {
+    let mut shared = shared.lock().unwrap_or_else(PoisonError::into_inner);
+    shared.result = Some(result);
+    if let Some(waker) = shared.waker.take() {
+        waker.wake();
+    }
+}
+
+struct Blocking<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        let mut shared = self.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Compaction run by a background thread whenever a policy finds enough
+//! dead records to make it worthwhile.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::callback::{Callback, CompactionFailed};
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::gate::AppendGate;
+use crate::status::DONE;
+use crate::sync::{SyncPolicy, Unsynced};
+use crate::wal::WriteAheadLog;
+
+/// When a [`background_compaction`](crate::WriteAheadLogBuilder::background_compaction)
+/// is worth running.
+///
+/// Compaction removes dead records: entries whose
+/// [`expires_at`](LogEntry::expires_at) has been reached, entries
+/// [marked done](WriteAheadLog::mark_done) together with their status
+/// records, and the tombstones left by
+/// [`clear_id`](WriteAheadLog::clear_id) together with what they cleared.
+/// Tombstones count as dead from when they were written. A log with
+/// nothing dead is never compacted. Otherwise it is compacted once either
+/// trigger that is set fires, provided it has grown to at least
+/// `min_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompactionPolicy {
+    /// Compact once at least this fraction of the records, between 0 and 1,
+    /// are dead.
+    pub dead_ratio: Option<f64>,
+    /// Compact once a record has been dead for this long: since its status
+    /// was written, or since it expired.
+    pub dead_age: Option<Duration>,
+    /// Leave logs smaller than this many bytes alone.
+    pub min_bytes: u64,
+}
+
+impl Default for CompactionPolicy {
+    /// Compacts once half the records are dead, whatever the log's size.
+    fn default() -> Self {
+        CompactionPolicy {
+            dead_ratio: Some(0.5),
+            dead_age: None,
+            min_bytes: 0,
+        }
+    }
+}
+
+/// The entries marked done, and when, as of one scan of the log.
+struct Dead {
+    done: HashMap<u64, u64>,
+    now: u64,
+}
+
+impl Dead {
+    /// Since when `record` has been dead, or `None` if it is live.
+    fn since(&self, record: &LogEntry) -> Option<u64> {
+        let done = match (record.kind, record.target) {
+            (EntryKind::Data, _) if record.stream == 0 => self.done.get(&record.id),
+            (EntryKind::Status, Some(target)) => self.done.get(&target),
+            (EntryKind::Tombstone, _) => Some(&record.timestamp),
+            _ => None,
+        };
+        let expired = record.expires_at.filter(|&at| at <= self.now);
+        match (done, expired) {
+            (Some(&a), Some(b)) => Some(a.min(b)),
+            (done, expired) => done.copied().or(expired),
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path` with a thread that checks it against
+    /// `policy` every `interval` and compacts it when warranted. See
+    /// [`WriteAheadLogBuilder::background_compaction`](crate::WriteAheadLogBuilder::background_compaction).
+    pub fn with_background_compaction<P: AsRef<std::path::Path>>(
+        path: P,
+        policy: CompactionPolicy,
+        interval: Duration,
+    ) -> Result<Self> {
+        Self::builder(path)
+            .background_compaction(policy, interval)
+            .build()
+    }
+
+    /// A handle on the same files for the compactor thread, sharing
+    /// everything a compaction touches with this one: the active file, the
+    /// rewrite lock, the cached entry count and the `get` cache. It never
+    /// appends.
+    pub(crate) fn compaction_view(&self) -> WriteAheadLog {
+        WriteAheadLog {
+            path: self.path.clone(),
+            file: Arc::clone(&self.file),
+            current_id: self.current_id,
+            start_id: self.start_id,
+            checksums: self.checksums,
+            format: self.format,
+            max_segment_bytes: self.max_segment_bytes,
+            max_segments: self.max_segments,
+            on_segment_evicted: None,
+            quarantine: self.quarantine.clone(),
+            max_file_size: self.max_file_size,
+            max_entries: self.max_entries,
+            evict_oldest: false,
+            evicted: 0,
+            entry_count: Arc::clone(&self.entry_count),
+            group_commit: false,
+            queue: Vec::new(),
+            gate: AppendGate::default(),
+            pause_mode: self.pause_mode,
+            writes: Arc::clone(&self.writes),
+            metrics: Arc::clone(&self.metrics),
+            get_cache: self.get_cache.clone(),
+            read_all_cache: self.read_all_cache.clone(),
+            mmap_reads: self.mmap_reads,
+            stream_ids: Mutex::new(HashMap::new()),
+            on_drop_error: None,
+            durable_id: AtomicU64::new(0),
+            written_id: AtomicU64::new(0),
+            idempotency_keys: None,
+            max_idempotency_keys: self.max_idempotency_keys,
+            anonymous: false,
+            rewrite_lock: Arc::clone(&self.rewrite_lock),
+            compacting: Arc::clone(&self.compacting),
+            high_water: Arc::clone(&self.high_water),
+            dictionary: self.dictionary.clone(),
+            cipher: self.cipher.clone(),
+            alignment: self.alignment,
+            hasher: self.hasher.clone(),
+            sync_policy: SyncPolicy::Never,
+            unsynced: Mutex::new(Unsynced::default()),
+            write_buffer: self.write_buffer.clone(),
+            token_bucket: None,
+            append_transform: None,
+            discarded_on_open: 0,
+            epoch: self.epoch,
+            clock: self.clock.clone(),
+            tombstones: Arc::clone(&self.tombstones),
+            _lock: None,
+            sinks: None,
+            sink_error_policy: self.sink_error_policy.clone(),
+            compression: self.compression,
+            encryption: self.encryption,
+            dedup_on_read: self.dedup_on_read,
+            compactor: None,
+            read_only: self.read_only,
+        }
+    }
+
+    /// Compacts the log if `policy` says so, returning how many records
+    /// were removed.
+    fn compact_if_due(&self, policy: &CompactionPolicy) -> Result<usize> {
+        let Some(dead) = self.dead_if_due(policy)? else {
+            return Ok(0);
+        };
+        self.compact_retain(|record| dead.since(record).is_none())
+    }
+
+    /// Scans the log and returns what is dead in it if `policy` calls for a
+    /// compaction.
+    fn dead_if_due(&self, policy: &CompactionPolicy) -> Result<Option<Dead>> {
+        let file = self.lock_file()?;
+        if self.total_bytes(&file)? < policy.min_bytes {
+            return Ok(None);
+        }
+        let mut done = HashMap::new();
+        for record in self.all_records()? {
+            let record = record?;
+            if let (EntryKind::Status, Some(target)) = (record.kind, record.target) {
+                if record.data == DONE {
+                    done.insert(target, record.timestamp);
+                } else {
+                    done.remove(&target);
+                }
+            }
+        }
+        let dead = Dead {
+            done,
+            now: self.now()?,
+        };
+        let (mut total, mut dead_count, mut oldest) = (0u64, 0u64, None::<u64>);
+        for record in self.all_records()? {
+            let record = record?;
+            total += 1;
+            if let Some(since) = dead.since(&record) {
+                dead_count += 1;
+                oldest = Some(oldest.map_or(since, |o| o.min(since)));
+            }
+        }
+        let Some(oldest) = oldest else {
+            return Ok(None);
+        };
+        let by_ratio = policy
+            .dead_ratio
+            .is_some_and(|ratio| dead_count as f64 >= ratio * total as f64);
+        let by_age = policy
+            .dead_age
+            .is_some_and(|age| dead.now.saturating_sub(oldest) >= age.as_secs());
+        Ok((by_ratio || by_age).then_some(dead))
+    }
+}
+
+/// The background thread started by
+/// [`background_compaction`](crate::WriteAheadLogBuilder::background_compaction).
+/// Dropping it stops the thread and waits for it, finishing any compaction
+/// under way.
+#[derive(Debug)]
+pub(crate) struct Compactor {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Compactor {
+    pub(crate) fn spawn(
+        view: WriteAheadLog,
+        policy: CompactionPolicy,
+        interval: Duration,
+        on_error: Option<Callback<CompactionFailed>>,
+    ) -> Result<Self> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("waly-compactor".to_string())
+            .spawn(move || {
+                // The sender is only ever dropped, which ends the wait early.
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if let (Err(err), Some(handler)) = (view.compact_if_due(&policy), &on_error) {
+                        handler(err);
+                    }
+                }
+            })?;
+        Ok(Compactor {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Compactor {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
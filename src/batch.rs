@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends `items` with contiguous IDs under one lock, one write and one
+    /// flush, returning the entries in order. The batch lands in the log
+    /// entirely or not at all: if it would break a limit, nothing is written
+    /// and no IDs are used up. With group commit the items are queued like
+    /// individual appends instead.
+    pub fn append_batch(&mut self, items: Vec<Vec<u8>>) -> Result<Vec<LogEntry>> {
+        self.append_chunk(items)
+    }
+
+    /// Appends `items` with contiguous IDs, `chunk_len` at a time, calling
+    /// `progress(written, total)` after each chunk so a huge batch can
+    /// report on itself instead of monopolizing the thread silently.
+    ///
+    /// Each chunk is written with one write and one flush and lands in the
+    /// log entirely or not at all, but a failure part-way leaves the chunks
+    /// before it in place. Returns the entries appended, in order.
+    pub fn append_batch_chunked<F>(
+        &mut self,
+        items: Vec<Vec<u8>>,
+        chunk_len: usize,
+        mut progress: F,
+    ) -> Result<Vec<LogEntry>>
+    where
+        F: FnMut(usize, usize),
+    {
+        let total = items.len();
+        let mut appended = Vec::with_capacity(total);
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            let chunk: Vec<_> = items.by_ref().take(chunk_len.max(1)).collect();
+            appended.extend(self.append_chunk(chunk)?);
+            progress(appended.len(), total);
+        }
+        Ok(appended)
+    }
+
+    /// Appends `items` as one write. With group commit they are queued
+    /// like individual appends instead.
+    pub(crate) fn append_chunk(&mut self, items: Vec<Vec<u8>>) -> Result<Vec<LogEntry>> {
+        if self.group_commit {
+            return items.into_iter().map(|data| self.append(data)).collect();
+        }
+        self.gate.pass(self.pause_mode)?;
+        self.take_tokens(items.len())?;
+        self.make_room(items.len() as u64)?;
+        let timestamp = self.now()?;
+        let first_id = self.current_id;
+        let mut entries: Vec<LogEntry> = items
+            .into_iter()
+            .map(|data| LogEntry {
+                data,
+                timestamp,
+                ..LogEntry::default()
+            })
+            .collect();
+        for entry in &mut entries {
+            self.assign(entry);
+        }
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        if let Err(err) = self.write_records(&mut file, &entries) {
+            self.current_id = first_id;
+            return Err(err);
+        }
+        self.sync_for_policy(&file, entries.len(), self.current_id)?;
+        drop(file);
+        self.forward(&entries)?;
+        Ok(entries)
+    }
+}
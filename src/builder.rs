@@ -0,0 +1,647 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::auto_compact::CompactionPolicy;
+use crate::callback::{AppendTransform, Callback, CompactionFailed, DropError, SegmentEvicted};
+use crate::clock::{Clock, SystemClock};
+use crate::compress::Compression;
+use crate::dictionary::Dictionary;
+use crate::encrypt::Cipher;
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::format::Format;
+use crate::gate::PauseMode;
+use crate::hasher::Hasher;
+use crate::header;
+use crate::idempotency::DedupPolicy;
+use crate::sink::{Sink, SinkErrorPolicy, Sinks};
+use crate::sync::SyncPolicy;
+use crate::wal::WriteAheadLog;
+
+/// Configures and opens a [`WriteAheadLog`].
+///
+/// ```no_run
+/// use waly_rs::WriteAheadLog;
+///
+/// let wal = WriteAheadLog::builder("data/app.wal")
+///     .create_dirs(true)
+///     .build()?;
+/// # Ok::<(), waly_rs::WalError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WriteAheadLogBuilder {
+    pub(crate) path: PathBuf,
+    pub(crate) create_dirs: bool,
+    pub(crate) checksums: bool,
+    pub(crate) format: Format,
+    pub(crate) max_segment_bytes: Option<u64>,
+    pub(crate) max_segments: Option<usize>,
+    pub(crate) on_segment_evicted: Option<Callback<SegmentEvicted>>,
+    pub(crate) quarantine: bool,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) max_entries: Option<u64>,
+    pub(crate) start_id: u64,
+    pub(crate) evict_oldest: bool,
+    pub(crate) group_commit: bool,
+    pub(crate) pause_mode: PauseMode,
+    pub(crate) get_cache: Option<usize>,
+    pub(crate) read_all_cache: bool,
+    pub(crate) mmap: bool,
+    pub(crate) on_drop_error: Option<Callback<DropError>>,
+    pub(crate) compression_dict: Option<Dictionary>,
+    pub(crate) alignment: Option<usize>,
+    pub(crate) hasher: Option<Callback<dyn Hasher>>,
+    pub(crate) sync_policy: SyncPolicy,
+    pub(crate) write_buffer: usize,
+    pub(crate) token_bucket: Option<(f64, u32)>,
+    pub(crate) append_transform: Option<Callback<AppendTransform>>,
+    pub(crate) background_compaction: Option<(CompactionPolicy, Duration)>,
+    pub(crate) on_compaction_error: Option<Callback<CompactionFailed>>,
+    pub(crate) epoch: Option<u64>,
+    pub(crate) clock: Callback<dyn Clock>,
+    pub(crate) lock: bool,
+    pub(crate) sinks: Option<Callback<Sinks>>,
+    pub(crate) sink_error_policy: SinkErrorPolicy,
+    pub(crate) compression: Option<Compression>,
+    pub(crate) encryption: Option<Cipher>,
+    pub(crate) authenticate_metadata: bool,
+    pub(crate) dedup_on_read: Option<DedupPolicy>,
+    pub(crate) max_idempotency_keys: Option<usize>,
+    pub(crate) read_only: bool,
+}
+
+impl Default for WriteAheadLogBuilder {
+    /// A builder with default options and no path yet, to be given with
+    /// [`path`](WriteAheadLogBuilder::path).
+    fn default() -> Self {
+        WriteAheadLogBuilder::new("")
+    }
+}
+
+impl WriteAheadLogBuilder {
+    /// Starts a builder for the log at `path` with default options.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        WriteAheadLogBuilder {
+            path: path.as_ref().to_path_buf(),
+            create_dirs: false,
+            checksums: true,
+            format: Format::default(),
+            max_segment_bytes: None,
+            max_segments: None,
+            on_segment_evicted: None,
+            quarantine: false,
+            max_file_size: None,
+            max_entries: None,
+            start_id: 0,
+            evict_oldest: false,
+            group_commit: false,
+            pause_mode: PauseMode::default(),
+            get_cache: None,
+            read_all_cache: false,
+            mmap: false,
+            on_drop_error: None,
+            compression_dict: None,
+            alignment: None,
+            hasher: None,
+            sync_policy: SyncPolicy::default(),
+            write_buffer: 0,
+            token_bucket: None,
+            append_transform: None,
+            background_compaction: None,
+            on_compaction_error: None,
+            epoch: None,
+            clock: Callback(Arc::new(SystemClock)),
+            lock: true,
+            sinks: None,
+            sink_error_policy: SinkErrorPolicy::default(),
+            compression: None,
+            encryption: None,
+            authenticate_metadata: false,
+            dedup_on_read: None,
+            max_idempotency_keys: None,
+            read_only: false,
+        }
+    }
+
+    /// Open the log at `path` instead of the path the builder was started
+    /// with.
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Create any missing parent directories of the log path before opening
+    /// it. Off by default, in which case a missing directory is an I/O error.
+    pub fn create_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    /// Stamp every appended record with a CRC-32 checksum, so that reads
+    /// catch silent corruption. On by default; turning it off saves the few
+    /// bytes a checksum takes per record. Existing records can be migrated
+    /// with [`WriteAheadLog::backfill_checksums`].
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Encoding used for records. Defaults to [`Format::Json`]. The format is
+    /// not recorded in the file, so a log must always be reopened with the
+    /// format it was written in.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Seal the active file into a numbered segment once it would grow past
+    /// `bytes`. A single record larger than the limit still gets a segment
+    /// of its own.
+    pub fn max_segment_bytes(mut self, bytes: u64) -> Self {
+        self.max_segment_bytes = Some(bytes);
+        self
+    }
+
+    /// Cap the number of segments, the active file included. When sealing a
+    /// segment takes the count past `max`, the oldest sealed segments are
+    /// deleted.
+    pub fn max_segments(mut self, max: usize) -> Self {
+        self.max_segments = Some(max);
+        self
+    }
+
+    /// Called with a segment's path just before it is evicted by
+    /// [`max_segments`](Self::max_segments), e.g. to archive it elsewhere. The
+    /// callback runs while the log is locked and must not call back into it.
+    pub fn on_segment_evicted<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path) + Send + Sync + 'static,
+    {
+        self.on_segment_evicted = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Copy records that fail to decode into `<path>.quarantine`, together
+    /// with their segment and byte offset, instead of silently dropping them.
+    /// They are still skipped by reads. Off by default.
+    pub fn quarantine(mut self, quarantine: bool) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    /// Refuse appends with [`WalError::LogFull`] once the log, across all
+    /// segments, would exceed `bytes`.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Hand out IDs from `id` onwards, rather than from 0, in a log that
+    /// holds no records yet, e.g. to give each shard of a larger log its
+    /// own range of IDs. A log with records continues after the largest ID
+    /// in it as usual, whatever `id` is, and so does one emptied by
+    /// compaction or pruning, even when reopened.
+    /// [`resequence`](WriteAheadLog::resequence) renumbers from `id` too.
+    pub fn start_id(mut self, id: u64) -> Self {
+        self.start_id = id;
+        self
+    }
+
+    /// Refuse appends with [`WalError::LogFull`] once the log holds `max`
+    /// data entries. Markers do not count. With
+    /// [`evict_oldest`](Self::evict_oldest), the oldest entries are dropped
+    /// instead.
+    pub fn max_entries(mut self, max: u64) -> Self {
+        self.max_entries = Some(max);
+        self
+    }
+
+    /// Instead of refusing appends past
+    /// [`max_entries`](Self::max_entries), make room by removing the oldest
+    /// data entries, turning the log into a rolling buffer of the most
+    /// recent ones. Off by default.
+    ///
+    /// Entries are evicted with tombstones, as by
+    /// [`clear_id`](WriteAheadLog::clear_id), so their space is reclaimed
+    /// by [`compact`](WriteAheadLog::compact) or
+    /// [`background_compaction`](Self::background_compaction); until then
+    /// they do not count towards the limit. Only the main stream is
+    /// counted and evicted from, so [`stream`](WriteAheadLog::stream)
+    /// appends are not limited. Appending more entries at once than the
+    /// limit fails with [`WalError::LogFull`]. Needs `max_entries` and
+    /// cannot be combined with [`group_commit`](Self::group_commit).
+    pub fn evict_oldest(mut self, evict: bool) -> Self {
+        self.evict_oldest = evict;
+        self
+    }
+
+    /// Queue appends in memory and write them together, with one
+    /// `sync_data`, on [`WriteAheadLog::flush`] or when the log is dropped.
+    /// Queued records are not visible to reads until flushed. Off by default.
+    pub fn group_commit(mut self, group_commit: bool) -> Self {
+        self.group_commit = group_commit;
+        self
+    }
+
+    /// Whether appends made while the log is paused fail or wait. Defaults
+    /// to [`PauseMode::Error`].
+    pub fn pause_mode(mut self, mode: PauseMode) -> Self {
+        self.pause_mode = mode;
+        self
+    }
+
+    /// Keep the results of up to `capacity` recent
+    /// [`get`](WriteAheadLog::get) calls in memory so repeated lookups skip
+    /// the file. Appends and deletions through this handle keep the cache
+    /// current; changes made by other handles are not seen.
+    pub fn get_cache(mut self, capacity: usize) -> Self {
+        self.get_cache = Some(capacity);
+        self
+    }
+
+    /// Keep the result of the last [`read_all`](WriteAheadLog::read_all) in
+    /// memory and return it again while the log's files keep the length and
+    /// modification time they had when it was read. Any append,
+    /// [`clear_id`](WriteAheadLog::clear_id), [`clear`](WriteAheadLog::clear)
+    /// or rewrite through this handle drops it, as does a change to the
+    /// files' length or modification time by anyone else. Off by default.
+    pub fn read_all_cache(mut self, enabled: bool) -> Self {
+        self.read_all_cache = enabled;
+        self
+    }
+
+    /// Called with the error if the final flush and sync made when the log is
+    /// dropped fail, since `Drop` cannot return it.
+    pub fn on_drop_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(WalError) + Send + Sync + 'static,
+    {
+        self.on_drop_error = Some(Callback(Arc::new(handler)));
+        self
+    }
+
+    /// Compress each payload against `dictionary`, a sample of content
+    /// typical of the log's payloads, for far better ratios on small,
+    /// similar entries than compressing each alone. Payloads that would not
+    /// shrink are stored as they are.
+    ///
+    /// The dictionary is saved in the header of the log's files on first
+    /// use and read from there whenever the log is opened, so it only needs
+    /// to be given once. Opening with a different dictionary is an
+    /// [`InvalidConfig`](WalError::InvalidConfig) error, since existing
+    /// records could no longer be read.
+    pub fn compression_dict(mut self, dictionary: Vec<u8>) -> Self {
+        self.compression_dict = Some(Dictionary::new(dictionary));
+        self
+    }
+
+    /// Like [`compression_dict`](Self::compression_dict), but compresses
+    /// with zstd against `dictionary`, which may be one trained by zstd or
+    /// just sample content. Replaces a dictionary given for the in-house
+    /// codec, and the other way round.
+    #[cfg(feature = "zstd")]
+    pub fn zstd_dict(mut self, dictionary: Vec<u8>) -> Self {
+        self.compression_dict = Some(Dictionary::zstd(dictionary));
+        self
+    }
+
+    /// Pad every record with filler the reader skips so that it takes up a
+    /// multiple of `bytes`, e.g. 512 or 4096, trading space for aligned
+    /// writes. Records then start on aligned offsets, counted from the end
+    /// of the file's header, as long as the whole log was written with the
+    /// same alignment.
+    pub fn alignment(mut self, bytes: usize) -> Self {
+        self.alignment = Some(bytes);
+        self
+    }
+
+    /// Give every appended record a [`digest`](crate::LogEntry::digest)
+    /// from `hasher`, such as [`Sha256`](crate::Sha256), for integrity
+    /// checks stronger than [`checksums`](Self::checksums); see
+    /// [`WriteAheadLog::verify_digests`].
+    ///
+    /// The hasher's name is saved in `<path>.hasher` on first use. Later
+    /// opens pick the same built-in hasher automatically, and opening with a
+    /// different one fails with [`WalError::ConfigMismatch`].
+    pub fn hasher<H: Hasher + 'static>(mut self, hasher: H) -> Self {
+        self.hasher = Some(Callback(Arc::new(hasher)));
+        self
+    }
+
+    /// When appends are synced to disk. Defaults to [`SyncPolicy::Never`],
+    /// leaving it to the OS.
+    pub fn sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync_policy = policy;
+        self
+    }
+
+    /// Hold appended records in a buffer of `capacity` bytes and write them
+    /// to the file together once it fills, so that many small appends cost
+    /// one `write` rather than one each. The buffer is also written out
+    /// before every sync, on [`WriteAheadLog::flush`], when the log is
+    /// dropped, and before anything reads or rewrites the log through it,
+    /// so reads never miss a buffered entry. Other handles and processes,
+    /// and [`tail_with`](WriteAheadLog::tail_with), see buffered records
+    /// only once written, and a crash loses them even under
+    /// [`SyncPolicy::EveryN`] or [`SyncPolicy::Interval`]. `0`, the
+    /// default, writes every append straight through.
+    pub fn write_buffer(mut self, capacity: usize) -> Self {
+        self.write_buffer = capacity;
+        self
+    }
+
+    /// Allow on average `rate` appends per second, with bursts of up to
+    /// `burst`. Appends beyond that fail straight away with
+    /// [`WalError::RateLimited`] rather than waiting, so callers can shed
+    /// load. A batch needs a token per entry and is taken whole or not at
+    /// all, so one larger than `burst` is always refused.
+    pub fn token_bucket(mut self, rate: f64, burst: u32) -> Self {
+        self.token_bucket = Some((rate, burst));
+        self
+    }
+
+    /// Call `transform` on every record after its ID and timestamp are
+    /// assigned and before it is checksummed and written, e.g. to stamp
+    /// [`tags`](LogEntry::tags) such as the host name on each entry.
+    /// Bookkeeping records like markers pass through it too; check
+    /// [`kind`](LogEntry::kind) to leave them alone. Changing the ID breaks
+    /// the log's ordering.
+    pub fn append_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&mut LogEntry) + Send + Sync + 'static,
+    {
+        self.append_transform = Some(Callback(Arc::new(transform)));
+        self
+    }
+
+    /// Start a thread that checks the log against `policy` every `interval`
+    /// and compacts it when the policy says it is worthwhile. The thread
+    /// stops when the log is dropped. Compactions run against a snapshot, as
+    /// [`compact_retain`](WriteAheadLog::compact_retain) does, so appends
+    /// carry on meanwhile. Errors go to
+    /// [`on_compaction_error`](Self::on_compaction_error).
+    pub fn background_compaction(mut self, policy: CompactionPolicy, interval: Duration) -> Self {
+        self.background_compaction = Some((policy, interval));
+        self
+    }
+
+    /// Called with any error met by
+    /// [`background_compaction`](Self::background_compaction), which has
+    /// nobody to return it to. Without a handler such errors are dropped and
+    /// the compactor tries again at the next interval.
+    pub fn on_compaction_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(WalError) + Send + Sync + 'static,
+    {
+        self.on_compaction_error = Some(Callback(Arc::new(handler)));
+        self
+    }
+
+    /// Write as the owner for `epoch`, fencing off writers with a lower one,
+    /// such as a former primary that was presumed dead. The highest epoch
+    /// seen is kept in `<path>.epoch`: opening with a higher one takes the
+    /// log over, opening with a lower one fails with [`WalError::Fenced`],
+    /// and so does anything that would change the log through a handle
+    /// whose epoch has been overtaken: appends, and also clearing,
+    /// truncation, compaction, rotation, [`repair`](WriteAheadLog::repair)
+    /// and [`drain`](WriteAheadLog::drain).
+    ///
+    /// The epoch is claimed under the [`lock`](Self::lock) on `<path>.lock`,
+    /// taken just for the claim if the handle does not hold it, so opening
+    /// fails with [`WalError::Locked`] while another handle holds it. It is
+    /// checked before each change, not under a lock shared between
+    /// processes, so a change already past the check when the log is taken
+    /// over can still land.
+    pub fn epoch(mut self, epoch: u64) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Take entry timestamps from `clock` instead of the system clock, e.g.
+    /// a closure returning fixed values for deterministic tests. The log
+    /// relies on timestamps rising with IDs only as far as
+    /// [`read_range`](WriteAheadLog::read_range) says.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Callback(Arc::new(clock));
+        self
+    }
+
+    /// Hold an exclusive advisory lock on `<path>.lock` while the log is
+    /// open, so that a second handle on the same path, in this process or
+    /// another, fails with [`WalError::Locked`] instead of handing out the
+    /// same IDs. On by default. The lock lives beside the log rather than
+    /// on it because rewrites replace the log file. Turn it off only when
+    /// something else guarantees a single writer, or for read-only handles.
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Open the log without changing it in any way: the files are opened
+    /// read-only, no file is created, a torn final write is left in place
+    /// rather than cut off, and every method that would append, clear,
+    /// rewrite or rotate fails with [`WalError::ReadOnly`]. No
+    /// [`lock`](Self::lock) is taken, so the log may be open for writing
+    /// elsewhere meanwhile. Cannot be combined with options that write
+    /// beside the log, such as [`quarantine`](Self::quarantine) or
+    /// [`epoch`](Self::epoch). See
+    /// [`WriteAheadLog::open_read_only`].
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Also write every record appended through the log to each of
+    /// `sinks`, in order, once it is in the log and synced as the
+    /// [`SyncPolicy`] says; under group commit, after the flush that syncs
+    /// it. Markers, statuses and tombstones are forwarded too. Sinks run
+    /// while the append is in progress, so a slow one slows appends down.
+    /// Failures are handled as [`sink_error_policy`](Self::sink_error_policy)
+    /// says.
+    pub fn sinks(mut self, sinks: Vec<Box<dyn Sink>>) -> Self {
+        self.sinks = Some(Callback(Arc::new(Mutex::new(sinks))));
+        self
+    }
+
+    /// What an append does when one of its [`sinks`](Self::sinks) fails.
+    /// Defaults to [`SinkErrorPolicy::Fail`].
+    pub fn sink_error_policy(mut self, policy: SinkErrorPolicy) -> Self {
+        self.sink_error_policy = policy;
+        self
+    }
+
+    /// Compress the payload of every appended data entry with `codec`,
+    /// recording it in [`LogEntry::compression`] so reads decompress it
+    /// transparently. Entries are compressed even when that does not make
+    /// them smaller. Existing entries are left as they are, so a log can
+    /// hold both, and a log opened without compression still reads
+    /// compressed entries. Takes the place of
+    /// [`compression_dict`](Self::compression_dict) for the entries it
+    /// compresses.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, codec: Compression) -> Self {
+        self.compression = Some(codec);
+        self
+    }
+
+    /// Encrypt the payload of every appended data entry under `key` with
+    /// ChaCha20-Poly1305 and a fresh random nonce, recording the scheme in
+    /// [`LogEntry::encryption`] so reads decrypt it transparently. The tag
+    /// doubles as tamper detection: a payload that fails to decrypt, under
+    /// a wrong key or after being altered, makes reads fail with
+    /// [`WalError::Decryption`] rather than skip it.
+    ///
+    /// Payloads are compressed, if configured, before they are encrypted.
+    /// Only `data` is encrypted: IDs, timestamps, tags and the other fields
+    /// stay readable, and bookkeeping records are stored in the clear. The
+    /// key is not stored anywhere, so it must be given every time the log
+    /// is opened. Existing entries are left as they are.
+    #[cfg(feature = "encryption")]
+    pub fn encryption(mut self, key: [u8; 32]) -> Self {
+        self.encryption = Some(Cipher::new(key));
+        self
+    }
+
+    /// Also authenticate each encrypted entry's `id`, `stream` and
+    /// `timestamp`, so that a payload swapped into another entry fails to
+    /// decrypt. Off by default. Needs [`encryption`](Self::encryption).
+    #[cfg(feature = "encryption")]
+    pub fn authenticate_metadata(mut self, authenticate: bool) -> Self {
+        self.authenticate_metadata = authenticate;
+        self
+    }
+
+    /// Read the log's files through read-only memory maps, parsing records
+    /// where they lie instead of copying each into a buffer first, which
+    /// makes repeated [`read_all`](WriteAheadLog::read_all) and
+    /// [`iter`](WriteAheadLog::iter) scans cheaper. Off by default; a file
+    /// that cannot be mapped is read the usual way.
+    ///
+    /// A scan maps a file as it was when the scan reached it, mapping it
+    /// again only when it runs out of records and the file has grown since.
+    /// An append made by another handle at that moment may be seen cut
+    /// short, and then ends the scan as a torn write would. A file must not
+    /// be cut short while a scan maps it, as by
+    /// [`truncate_after`](WriteAheadLog::truncate_after) or
+    /// [`truncate_to_last_valid`](WriteAheadLog::truncate_to_last_valid)
+    /// through another handle: on most platforms, touching the missing pages
+    /// kills the process with `SIGBUS`.
+    #[cfg(feature = "mmap")]
+    pub fn mmap(mut self, enabled: bool) -> Self {
+        self.mmap = enabled;
+        self
+    }
+
+    /// Collapse data entries sharing an
+    /// [`idempotency_key`](LogEntry::idempotency_key) in
+    /// [`read_all`](WriteAheadLog::read_all), keeping the one `policy`
+    /// picks, for logs holding duplicates written before
+    /// [`append_idempotent`](WriteAheadLog::append_idempotent) was used.
+    /// Off by default, in which case every entry is returned.
+    pub fn dedup_on_read(mut self, policy: DedupPolicy) -> Self {
+        self.dedup_on_read = Some(policy);
+        self
+    }
+
+    /// Remember at most `max` idempotency keys for
+    /// [`append_idempotent`](WriteAheadLog::append_idempotent), forgetting
+    /// the oldest first, so that memory stays bounded however many keys the
+    /// log holds. A bounded set costs roughly 65 bytes plus twice the
+    /// key's length per key. Deduplication then only covers the last `max`
+    /// keys: a retry of a forgotten key is written again, though
+    /// [`dedup_on_read`](Self::dedup_on_read) can still collapse it. `0`
+    /// turns the set off, and with it deduplication on append. Unbounded by
+    /// default.
+    pub fn max_idempotency_keys(mut self, max: usize) -> Self {
+        self.max_idempotency_keys = Some(max);
+        self
+    }
+
+    /// Opens the log with the configured options, failing with
+    /// [`WalError::InvalidConfig`] on options that contradict each other or
+    /// could never be met.
+    pub fn build(self) -> Result<WriteAheadLog> {
+        if self.path.as_os_str().is_empty() {
+            return Err(WalError::InvalidConfig("no path to open".to_string()));
+        }
+        let header_len = header::bytes(self.format, self.compression_dict.as_ref()).len() as u64;
+        if self.max_file_size.is_some_and(|max| max <= header_len) {
+            return Err(WalError::InvalidConfig(format!(
+                "max_file_size must leave room past the {header_len}-byte header"
+            )));
+        }
+        if self.max_entries == Some(0) {
+            return Err(WalError::InvalidConfig(
+                "max_entries must be at least 1".to_string(),
+            ));
+        }
+        if self.alignment == Some(0) {
+            return Err(WalError::InvalidConfig(
+                "alignment must be greater than zero".to_string(),
+            ));
+        }
+        if let Some((rate, burst)) = self.token_bucket {
+            if !(rate > 0.0 && rate.is_finite()) || burst == 0 {
+                return Err(WalError::InvalidConfig(
+                    "token_bucket needs a positive rate and burst".to_string(),
+                ));
+            }
+        }
+        if let Some((policy, interval)) = &self.background_compaction {
+            if interval.is_zero() {
+                return Err(WalError::InvalidConfig(
+                    "background compaction interval must be greater than zero".to_string(),
+                ));
+            }
+            if policy.dead_ratio.is_some_and(|r| !(r > 0.0 && r <= 1.0)) {
+                return Err(WalError::InvalidConfig(
+                    "dead_ratio must be in (0, 1]".to_string(),
+                ));
+            }
+        } else if self.on_compaction_error.is_some() {
+            return Err(WalError::InvalidConfig(
+                "on_compaction_error needs background_compaction".to_string(),
+            ));
+        }
+        if self.evict_oldest && (self.max_entries.is_none() || self.group_commit) {
+            return Err(WalError::InvalidConfig(
+                "evict_oldest needs max_entries and no group_commit".to_string(),
+            ));
+        }
+        if self.authenticate_metadata && self.encryption.is_none() {
+            return Err(WalError::InvalidConfig(
+                "authenticate_metadata needs encryption".to_string(),
+            ));
+        }
+        if self.max_segment_bytes == Some(0) {
+            return Err(WalError::InvalidConfig(
+                "max_segment_bytes must be greater than zero".to_string(),
+            ));
+        }
+        if self.max_segments == Some(0) {
+            return Err(WalError::InvalidConfig(
+                "max_segments must be at least 1".to_string(),
+            ));
+        }
+        if self.read_only
+            && (self.quarantine
+                || self.epoch.is_some()
+                || self.background_compaction.is_some()
+                || self.create_dirs)
+        {
+            return Err(WalError::InvalidConfig(
+                "read_only cannot be combined with quarantine, epoch, background_compaction \
+                 or create_dirs"
+                    .to_string(),
+            ));
+        }
+        if self.create_dirs {
+            if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        WriteAheadLog::open(self)
+    }
+}
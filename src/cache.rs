@@ -0,0 +1,155 @@
+//! Optional caches in front of [`WriteAheadLog::get`] and
+//! [`WriteAheadLog::read_all`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::PoisonError;
+use std::time::SystemTime;
+
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Hit and miss counts of a log's `get` cache, as reported by
+/// [`WriteAheadLog::get_cache_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Least-recently-used map from ID to lookup result. Recency is a counter;
+/// `order` maps each entry's last use back to its ID so the oldest is first.
+#[derive(Debug)]
+pub(crate) struct GetCache {
+    capacity: usize,
+    entries: HashMap<u64, (Option<LogEntry>, u64)>,
+    order: BTreeMap<u64, u64>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl GetCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        GetCache {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// The cached result for `id`, counting the hit or miss.
+    pub(crate) fn lookup(&mut self, id: u64) -> Option<Option<LogEntry>> {
+        self.tick += 1;
+        let Some((entry, used)) = self.entries.get_mut(&id) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, id);
+        self.stats.hits += 1;
+        Some(entry.clone())
+    }
+
+    pub(crate) fn insert(&mut self, id: u64, entry: Option<LogEntry>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(id, (entry, self.tick)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, id);
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub(crate) fn invalidate(&mut self, id: u64) {
+        if let Some((_, used)) = self.entries.remove(&id) {
+            self.order.remove(&used);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Length and modification time of one of the log's files.
+type FileStamp = (u64, Option<SystemTime>);
+
+/// The last result of [`WriteAheadLog::read_all`], with the stamps of the
+/// files it was read from.
+#[derive(Debug, Default)]
+pub(crate) struct ReadAllCache {
+    latest: Option<(Vec<FileStamp>, Vec<LogEntry>)>,
+}
+
+impl ReadAllCache {
+    /// The cached entries, if the files still carry `stamps`.
+    pub(crate) fn lookup(&self, stamps: &[FileStamp]) -> Option<Vec<LogEntry>> {
+        let (cached, entries) = self.latest.as_ref()?;
+        (cached == stamps).then(|| entries.clone())
+    }
+
+    pub(crate) fn insert(&mut self, stamps: Vec<FileStamp>, entries: Vec<LogEntry>) {
+        self.latest = Some((stamps, entries));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.latest = None;
+    }
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path` with an LRU cache of up to `capacity`
+    /// [`get`](Self::get) results. See
+    /// [`WriteAheadLogBuilder::get_cache`](crate::WriteAheadLogBuilder::get_cache).
+    pub fn with_get_cache<P: AsRef<Path>>(path: P, capacity: usize) -> Result<Self> {
+        Self::builder(path).get_cache(capacity).build()
+    }
+
+    /// Hit and miss counts of the `get` cache, or `None` if it is disabled.
+    pub fn get_cache_stats(&self) -> Option<CacheStats> {
+        let cache = self.get_cache.as_ref()?;
+        Some(cache.lock().unwrap_or_else(PoisonError::into_inner).stats)
+    }
+
+    /// Drops every cached lookup and `read_all` result, after a rewrite or
+    /// deletion.
+    pub(crate) fn invalidate_cache(&self) {
+        if let Some(cache) = &self.get_cache {
+            cache.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+        self.invalidate_read_all_cache();
+    }
+
+    /// Drops the cached `read_all` result, after anything is written.
+    pub(crate) fn invalidate_read_all_cache(&self) {
+        if let Some(cache) = &self.read_all_cache {
+            cache.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+    }
+
+    /// Length and modification time of every file of the log, oldest
+    /// first, which a cached `read_all` result must still match.
+    pub(crate) fn file_stamps(&self) -> Result<Vec<FileStamp>> {
+        segment::all_segments(&self.path)?
+            .iter()
+            .map(|path| {
+                let meta = fs::metadata(path)?;
+                Ok((meta.len(), meta.modified().ok()))
+            })
+            .collect()
+    }
+}
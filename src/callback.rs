@@ -0,0 +1,39 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A user-supplied hook stored in the builder and the log. Cheap to clone and
+/// opaque in `Debug` output.
+pub(crate) struct Callback<F: ?Sized>(pub(crate) Arc<F>);
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Self {
+        Callback(Arc::clone(&self.0))
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<callback>")
+    }
+}
+
+impl<F: ?Sized> Deref for Callback<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.0
+    }
+}
+
+/// Called with the path of a segment just before it is deleted.
+pub(crate) type SegmentEvicted = dyn Fn(&std::path::Path) + Send + Sync;
+
+/// Called with an error that dropping the log could not return.
+pub(crate) type DropError = dyn Fn(crate::WalError) + Send + Sync;
+
+/// Called on each record about to be appended, to enrich it.
+pub(crate) type AppendTransform = dyn Fn(&mut crate::LogEntry) + Send + Sync;
+
+/// Called with an error from a background compaction.
+pub(crate) type CompactionFailed = dyn Fn(crate::WalError) + Send + Sync;
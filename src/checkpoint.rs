@@ -0,0 +1,146 @@
+//! Checkpoints that let opening a large log skip most of the scan for the
+//! next ID.
+//!
+//! A checkpoint is a record of kind [`EntryKind::Checkpoint`]. Its own ID is
+//! the largest in the log when it was written, and its payload lists,
+//! space-separated, the IDs cleared by tombstones before it. Where the latest
+//! one starts in the active file is kept beside the log in
+//! `<path>.checkpoint` as `<offset> <id>`, replaced with an atomic rename.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::durable;
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::iter::{Decoding, SegmentReader};
+use crate::wal::WriteAheadLog;
+
+/// What the latest checkpoint says about the log up to it.
+#[derive(Debug)]
+pub(crate) struct Recovery {
+    /// Offset in the active file just past the checkpoint, where the scan
+    /// resumes.
+    pub(crate) resume_at: u64,
+    next_id: u64,
+    cleared: HashSet<u64>,
+}
+
+impl WriteAheadLog {
+    /// Writes a checkpoint record and syncs it, so that the next open scans
+    /// only the records after it to find the next ID, instead of the whole
+    /// log. Queued group-commit records are written first.
+    ///
+    /// Checkpoints take an ID like any other record but are not returned by
+    /// [`read_all`](Self::read_all). Only the latest one is used, and only
+    /// while it is still where it was written in the active file: after a
+    /// rotation seals it or a rewrite moves it, and if `<path>.checkpoint`
+    /// is missing or damaged, the open falls back to a full scan.
+    pub fn checkpoint(&mut self) -> Result<LogEntry> {
+        self.flush()?;
+        let mut cleared: Vec<u64> = self.tombstones.lock()?.iter().copied().collect();
+        cleared.sort_unstable();
+        let data = cleared
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut checkpoint = LogEntry {
+            kind: EntryKind::Checkpoint,
+            data: data.into_bytes(),
+            timestamp: self.now()?,
+            ..LogEntry::default()
+        };
+        self.stamp(&mut checkpoint);
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        let offset = self.write_record(&mut file, &checkpoint)?;
+        self.current_id += 1;
+        self.sync_file(&file, self.current_id)?;
+        drop(file);
+        write_sidecar(&self.checkpoint_path(), offset, checkpoint.id)?;
+        self.forward([&checkpoint])?;
+        Ok(checkpoint)
+    }
+
+    /// The latest checkpoint, if `<path>.checkpoint` names one that is still
+    /// where it says in the active `file`, or `None` for a full scan.
+    pub(crate) fn latest_checkpoint(&self, file: &File) -> Option<Recovery> {
+        let text = fs::read_to_string(self.checkpoint_path()).ok()?;
+        let mut parts = text.split_whitespace().map(str::parse::<u64>);
+        let (Some(Ok(offset)), Some(Ok(id))) = (parts.next(), parts.next()) else {
+            return None;
+        };
+        if offset < self.header_len() {
+            return None;
+        }
+        let mut reader = BufReader::new(file.try_clone().ok()?);
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut buf = Vec::new();
+        let consumed = self.format.read_frame(&mut reader, &mut buf).ok()?;
+        if consumed == 0 {
+            return None;
+        }
+        let (record, _) = self.format.decode_flagged(&buf).ok()?;
+        if record.kind != EntryKind::Checkpoint || record.stream != 0 || record.id != id {
+            return None;
+        }
+        let cleared = std::str::from_utf8(&record.data)
+            .ok()?
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .ok()?;
+        Some(Recovery {
+            resume_at: offset + consumed as u64,
+            next_id: id + 1,
+            cleared,
+        })
+    }
+
+    /// Like [`load_ids`](Self::load_ids), but starting from what `recovery`
+    /// says and scanning only the rest of the active file.
+    pub(crate) fn load_ids_after(&self, recovery: Recovery) -> Result<(u64, HashSet<u64>)> {
+        let Recovery {
+            resume_at,
+            mut next_id,
+            mut cleared,
+        } = recovery;
+        let decoding = Decoding {
+            payloads: false,
+            ..self.decoding()
+        };
+        let file = File::open(&self.path)?;
+        let mut reader = SegmentReader::starting_at(&self.path, file, decoding, resume_at)?;
+        while let Some(record) = reader.next_record()? {
+            if record.stream != 0 {
+                continue;
+            }
+            next_id = next_id.max(record.id + 1);
+            if let (EntryKind::Tombstone, Some(target)) = (record.kind, record.target) {
+                cleared.insert(target);
+            }
+        }
+        Ok((next_id, cleared))
+    }
+
+    pub(crate) fn checkpoint_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".checkpoint");
+        PathBuf::from(path)
+    }
+}
+
+fn write_sidecar(path: &Path, offset: u64, id: u64) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = File::create(&tmp)?;
+    writeln!(file, "{offset} {id}")?;
+    file.sync_data()?;
+    durable::rename(&tmp, path)?;
+    Ok(())
+}
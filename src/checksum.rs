@@ -0,0 +1,143 @@
+//! CRC-32 (IEEE 802.3) used to detect corrupted records.
+
+use std::collections::BTreeMap;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+const TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Incremental CRC-32 state.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Crc32(!0)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = TABLE[((self.0 ^ b as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub(crate) fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// The CRC-32 of an entry with the given `id`, `timestamp` and `data`.
+pub(crate) fn checksum_of(id: u64, timestamp: u64, data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(&id.to_le_bytes());
+    crc.update(&timestamp.to_le_bytes());
+    crc.update(data);
+    crc.finish()
+}
+
+impl LogEntry {
+    /// Computes the CRC-32 of this entry's `id`, `timestamp` and `data`.
+    pub fn compute_checksum(&self) -> u32 {
+        checksum_of(self.id, self.timestamp, &self.data)
+    }
+
+    /// Whether the entry carries a checksum at all. A stored value of zero
+    /// means the entry was written without one.
+    pub fn has_checksum(&self) -> bool {
+        self.checksum != 0
+    }
+
+    /// Whether the stored checksum matches the entry's contents. Entries
+    /// without a checksum are considered valid.
+    pub fn is_checksum_valid(&self) -> bool {
+        !self.has_checksum() || self.checksum == self.compute_checksum()
+    }
+}
+
+impl WriteAheadLog {
+    /// Adds a checksum to every record that lacks one, returning how many
+    /// were backfilled. Use this after enabling
+    /// [`checksums`](crate::WriteAheadLogBuilder::checksums) on an existing
+    /// log.
+    ///
+    /// Records that already carry a valid checksum are left untouched. If any
+    /// record's checksum does not match, nothing is rewritten and
+    /// [`WalError::ChecksumMismatch`] is returned for the first one, since
+    /// stamping a fresh checksum would hide the corruption.
+    pub fn backfill_checksums(&self) -> Result<usize> {
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        for record in self.all_records()? {
+            let record = record?;
+            if !record.is_checksum_valid() {
+                return Err(WalError::ChecksumMismatch { id: record.id });
+            }
+        }
+        let mut backfilled = 0;
+        self.rewrite_segments(&mut file, |records| {
+            let before = backfilled;
+            for record in records.iter_mut().filter(|e| !e.has_checksum()) {
+                record.checksum = record.compute_checksum();
+                backfilled += 1;
+            }
+            backfilled != before
+        })?;
+        Ok(backfilled)
+    }
+
+    /// Maps the ID of every data entry to the CRC-32 of its contents, for
+    /// comparing replicas with
+    /// [`verify_against_manifest`](Self::verify_against_manifest).
+    pub fn crc_manifest(&self) -> Result<BTreeMap<u64, u32>> {
+        let _file = self.lock_file()?;
+        let mut manifest = BTreeMap::new();
+        for record in self.records()? {
+            let record = record?;
+            if record.kind == EntryKind::Data {
+                manifest.insert(record.id, record.compute_checksum());
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// IDs, ascending, whose CRC differs from `manifest` or that appear on
+    /// only one side.
+    pub fn verify_against_manifest(&self, manifest: &BTreeMap<u64, u32>) -> Result<Vec<u64>> {
+        let local = self.crc_manifest()?;
+        let mut differing: Vec<u64> = manifest
+            .iter()
+            .filter(|(id, crc)| local.get(id) != Some(crc))
+            .map(|(id, _)| *id)
+            .chain(
+                local
+                    .keys()
+                    .filter(|id| !manifest.contains_key(id))
+                    .copied(),
+            )
+            .collect();
+        differing.sort_unstable();
+        Ok(differing)
+    }
+}
@@ -0,0 +1,57 @@
+//! Where entry timestamps come from.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// A source of the current time for entry timestamps, chosen with
+/// [`WriteAheadLogBuilder::clock`](crate::WriteAheadLogBuilder::clock).
+/// Tests can supply one returning fixed values.
+pub trait Clock: Send + Sync {
+    /// Seconds since the Unix epoch, or [`WalError::Clock`] if the time
+    /// cannot be told.
+    fn now_unix(&self) -> Result<u64>;
+}
+
+/// The system's wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> Result<u64> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .map_err(|err| {
+                WalError::Clock(format!(
+                    "system clock is {:?} before the Unix epoch",
+                    err.duration()
+                ))
+            })
+    }
+}
+
+impl<F: Fn() -> u64 + Send + Sync> Clock for F {
+    fn now_unix(&self) -> Result<u64> {
+        Ok(self())
+    }
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path` taking timestamps from `clock`. See
+    /// [`WriteAheadLogBuilder::clock`](crate::WriteAheadLogBuilder::clock).
+    pub fn with_clock<P, C>(path: P, clock: C) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        C: Clock + 'static,
+    {
+        Self::builder(path).clock(clock).build()
+    }
+
+    /// The current time from the log's clock.
+    pub(crate) fn now(&self) -> Result<u64> {
+        self.clock.now_unix()
+    }
+}
@@ -0,0 +1,193 @@
+//! Compaction against a snapshot of the log, so that reads and appends are
+//! held up only while the snapshot is taken and while the rewritten files
+//! are swapped in, not for the whole rewrite.
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use crate::durable;
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Rewrites the log keeping only the records, of every stream and kind,
+    /// for which `keep` returns `true`, and returns how many were removed.
+    /// Records cleared by [`clear_id`](Self::clear_id) and their tombstones
+    /// are removed whatever `keep` says, and are not passed to it.
+    ///
+    /// The log is locked only to note the active file's length at the start
+    /// and to swap the rewritten files in at the end. In between, reads see
+    /// the log as it was and stream appends carry on; records appended
+    /// meanwhile are copied over untouched. Segment rotation is put off until
+    /// the compaction finishes, and other rewrites wait for it.
+    pub fn compact_retain<F>(&self, keep: F) -> Result<usize>
+    where
+        F: FnMut(&LogEntry) -> bool,
+    {
+        let _rewrite = self.rewrite_lock.lock()?;
+        self.compact_snapshot(keep)
+    }
+
+    /// Physically removes the entries cleared by
+    /// [`clear_id`](Self::clear_id), and their tombstones, in one rewrite
+    /// that runs against a snapshot as [`compact_retain`](Self::compact_retain)
+    /// does. Call it periodically rather than after every delete.
+    pub fn compact(&self) -> Result<()> {
+        self.compact_retain(|_| true).map(drop)
+    }
+
+    /// Body of [`compact_retain`](Self::compact_retain), for callers that
+    /// already hold the rewrite lock.
+    pub(crate) fn compact_snapshot<F>(&self, mut keep: F) -> Result<usize>
+    where
+        F: FnMut(&LogEntry) -> bool,
+    {
+        self.check_writable()?;
+        self.check_epoch()?;
+        let (sealed, snapshot_len) = {
+            let file = self.lock_file()?;
+            self.compacting.store(true, Ordering::Release);
+            (
+                segment::sealed_segments(&self.path)?,
+                file.metadata()?.len(),
+            )
+        };
+        let result = self.compact_files(&sealed, snapshot_len, &mut keep);
+        self.compacting.store(false, Ordering::Release);
+        result
+    }
+
+    fn compact_files<F>(
+        &self,
+        sealed: &[(u64, PathBuf)],
+        snapshot_len: u64,
+        keep: &mut F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&LogEntry) -> bool,
+    {
+        let mut removed = 0;
+        // The view a background compaction runs on does not follow the
+        // IDs handed out since, so the largest is taken from the records.
+        let mut next_id = self.current_id;
+        let mut replaced = Vec::new();
+        for (_, path) in sealed {
+            let file = File::open(path)?;
+            let len = file.metadata()?.len();
+            if let Some(temp) =
+                self.filter_into_temp(path, file, len, keep, &mut removed, &mut next_id)?
+            {
+                replaced.push((temp, path.clone()));
+            }
+        }
+        let active = File::open(&self.path)?;
+        let active_temp = self.filter_into_temp(
+            &self.path,
+            active,
+            snapshot_len,
+            keep,
+            &mut removed,
+            &mut next_id,
+        )?;
+        if replaced.is_empty() && active_temp.is_none() {
+            return Ok(0);
+        }
+        self.keep_ids_below(next_id)?;
+
+        let mut file = self.lock_file()?;
+        for (temp, path) in replaced {
+            durable::rename(&temp, &path)?;
+        }
+        if let Some(temp) = active_temp {
+            let mut tail = Vec::new();
+            let mut reader = file.try_clone()?;
+            reader.seek(SeekFrom::Start(snapshot_len))?;
+            reader.read_to_end(&mut tail)?;
+            let mut out = fs::OpenOptions::new().append(true).open(&temp)?;
+            out.write_all(&tail)?;
+            out.sync_data()?;
+            durable::rename(&temp, &self.path)?;
+            *file = segment::open_active(&self.path, &self.header())?;
+        }
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(removed)
+    }
+
+    /// Copies the records among the first `len` bytes of `file` that `keep`
+    /// accepts into `<path>.compact` and returns its path, or `None` without
+    /// writing anything if `keep` accepted them all. `next_id` is raised
+    /// past every ID read.
+    fn filter_into_temp<F>(
+        &self,
+        path: &Path,
+        file: File,
+        len: u64,
+        keep: &mut F,
+        removed: &mut usize,
+        next_id: &mut u64,
+    ) -> Result<Option<PathBuf>>
+    where
+        F: FnMut(&LogEntry) -> bool,
+    {
+        let mut reader = BufReader::new(file.take(len));
+        let mut buf = Vec::new();
+        let mut kept = Vec::new();
+        let mut dropped = 0;
+        let mut offset = header::skip(&mut reader)?;
+        loop {
+            let read = self.format.read_frame(&mut reader, &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            let start = offset;
+            offset += read as u64;
+            // As with the in-place rewrites, undecodable records are
+            // quarantined, if enabled, and dropped. Payloads that fail to
+            // decrypt are not: under the wrong key that would be all of
+            // them.
+            let record = match self.format.decode_with(
+                &buf,
+                self.dictionary.as_deref(),
+                self.cipher.as_deref(),
+            ) {
+                Ok(record) => record,
+                Err(err @ WalError::Decryption { .. }) => return Err(err),
+                Err(_) => {
+                    if let Some(quarantine) = &self.quarantine {
+                        quarantine.record(path, start, &buf)?;
+                    }
+                    dropped += 1;
+                    continue;
+                }
+            };
+            if record.stream == 0 {
+                *next_id = (*next_id).max(record.id + 1);
+            }
+            let dead = record.kind == EntryKind::Tombstone || self.is_cleared(&record);
+            if !dead && keep(&record) {
+                kept.extend_from_slice(&self.encode_record(&record)?);
+            } else {
+                dropped += 1;
+            }
+        }
+        if dropped == 0 {
+            return Ok(None);
+        }
+        *removed += dropped;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".compact");
+        let temp = PathBuf::from(temp);
+        let mut out = File::create(&temp)?;
+        out.write_all(&self.header())?;
+        out.write_all(&kept)?;
+        out.sync_data()?;
+        self.writes.rewritten(kept.len() as u64);
+        Ok(Some(temp))
+    }
+}
@@ -0,0 +1,126 @@
+//! Checking ahead of a rollout whether a configuration can open an existing
+//! log.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::builder::WriteAheadLogBuilder;
+use crate::dictionary;
+use crate::error::{Result, WalError};
+use crate::fence;
+use crate::hasher;
+use crate::header;
+use crate::iter::{Decoding, Records};
+use crate::wal::WriteAheadLog;
+
+/// Verdict of [`WriteAheadLog::check_compatibility`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    /// The configuration opens the log as it is.
+    Compatible,
+    /// The configuration opens the log, but existing records fall short of
+    /// it until migrated, as `reason` describes.
+    NeedsMigration { reason: String },
+    /// Opening the log with the configuration fails or would damage it.
+    Incompatible { reason: String },
+}
+
+impl WriteAheadLog {
+    /// Compares the log at `path` with `config` without opening it or
+    /// changing anything on disk, so deployment tooling can tell whether a
+    /// new configuration needs a migration first. The builder's own path is
+    /// ignored.
+    ///
+    /// The format version, the record format and the compression dictionary
+    /// are judged by the file headers, the record format by the first
+    /// record where no header records it, as [`open_as`](Self::open_as)
+    /// does, and the hasher and epoch by what is stored beside the log.
+    /// Files with no header or an outdated one get a current one on open.
+    /// Turning on
+    /// [`checksums`](WriteAheadLogBuilder::checksums) for a log whose
+    /// records lack them needs
+    /// [`backfill_checksums`](Self::backfill_checksums). A log that does
+    /// not exist yet is compatible with anything.
+    pub fn check_compatibility<P: AsRef<Path>>(
+        path: P,
+        config: &WriteAheadLogBuilder,
+    ) -> Result<Compatibility> {
+        let path = path.as_ref();
+        let incompatible = |reason: String| Ok(Compatibility::Incompatible { reason });
+        match header::check(path) {
+            Ok(_) => {}
+            Err(WalError::UnsupportedVersion { found, expected }) => {
+                return incompatible(format!(
+                    "the log is in format version {found}, not {expected}"
+                ));
+            }
+            Err(err) => return Err(err),
+        }
+
+        match config.format.check_file(path) {
+            Ok(()) => {}
+            Err(WalError::FormatMismatch { expected, found }) => {
+                return incompatible(format!("the log is written as {found:?}, not {expected:?}"));
+            }
+            Err(WalError::InvalidEntry(reason)) => return incompatible(reason),
+            Err(err) => return Err(err),
+        }
+
+        let given = config.hasher.as_ref().map(|h| h.name().to_string());
+        match (hasher::stored_name(path)?, given) {
+            (Some(stored), Some(given)) if stored != given => {
+                return incompatible(format!(
+                    "the log was written with hasher `{stored}`, not `{given}`"
+                ));
+            }
+            (Some(stored), None) if !hasher::is_builtin(&stored) => {
+                return incompatible(format!(
+                    "the log was written with hasher `{stored}`, which must be given"
+                ));
+            }
+            _ => {}
+        }
+
+        let stored_dict = match dictionary::stored(path) {
+            Ok(stored) => stored,
+            Err(WalError::InvalidConfig(reason)) => return incompatible(reason),
+            Err(err) => return Err(err),
+        };
+        if let (Some(stored), Some(given)) = (&stored_dict, &config.compression_dict) {
+            if stored != given {
+                return incompatible(
+                    "the log already stores a different compression dictionary".to_string(),
+                );
+            }
+        }
+
+        let current = fence::stored_epoch(path)?;
+        if let Some(epoch) = config.epoch.filter(|&e| e < current) {
+            return incompatible(format!("epoch {epoch} is fenced off by epoch {current}"));
+        }
+
+        if config.checksums && path.exists() {
+            let decoding = Decoding {
+                format: config.format,
+                dictionary: stored_dict.map(Arc::new),
+                cipher: config.encryption.clone().map(Arc::new),
+                payloads: true,
+                checksums: false,
+                mapped: None,
+                quarantine: None,
+                tombstones: Arc::default(),
+                metrics: None,
+            };
+            let mut missing = 0;
+            for record in Records::open(path, decoding, None)? {
+                missing += usize::from(!record?.has_checksum());
+            }
+            if missing > 0 {
+                return Ok(Compatibility::NeedsMigration {
+                    reason: format!("{missing} records have no checksum; run backfill_checksums"),
+                });
+            }
+        }
+        Ok(Compatibility::Compatible)
+    }
+}
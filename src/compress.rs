@@ -0,0 +1,65 @@
+//! Per-entry payload compression.
+//!
+//! Each record says which codec, if any, its `data` was stored with, so a
+//! log can mix compressed and uncompressed entries and stays readable
+//! whatever the log is later opened with. Compressing on append needs the
+//! `compression` feature; reading compressed entries does not.
+
+#[cfg(feature = "compression")]
+use std::path::Path;
+
+use crate::dictionary;
+use crate::error::Result;
+#[cfg(feature = "compression")]
+use crate::wal::WriteAheadLog;
+
+/// Codec a payload is stored with; see
+/// [`LogEntry::compression`](crate::LogEntry::compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// The LZ77 codec of
+    /// [`compression_dict`](crate::WriteAheadLogBuilder::compression_dict),
+    /// without a dictionary. Suits large, repetitive payloads such as JSON
+    /// documents. Payloads carry their uncompressed length, which a damaged
+    /// one cannot make decoding exceed.
+    Lz77,
+}
+
+impl Compression {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Compression::Lz77 => "lz77",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        match s {
+            "lz77" => Some(Compression::Lz77),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::Lz77 => dictionary::pack(&[], data),
+        }
+    }
+
+    /// Reverses [`compress`](Self::compress), failing with
+    /// [`WalError::InvalidEntry`](crate::WalError::InvalidEntry) if `packed`
+    /// was not produced by it or holds more than its stated length.
+    pub(crate) fn decompress(self, packed: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::Lz77 => dictionary::unpack(&[], packed),
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+impl WriteAheadLog {
+    /// Opens the log at `path`, compressing appended payloads with `codec`.
+    /// See [`WriteAheadLogBuilder::compression`](crate::WriteAheadLogBuilder::compression).
+    pub fn with_compression<P: AsRef<Path>>(path: P, codec: Compression) -> Result<Self> {
+        Self::builder(path).compression(codec).build()
+    }
+}
@@ -0,0 +1,154 @@
+//! Named consumers and how far each has acknowledged the log.
+//!
+//! Acks are kept beside the log in `<path>.consumers`, one consumer per
+//! line as `<id> <next> <name>`, where `next` is the first ID the consumer
+//! has not acknowledged. The file is replaced with an atomic rename on
+//! every change, so acks survive a restart.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+use crate::entry::EntryKind;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// Handle for a consumer registered with
+/// [`WriteAheadLog::register_consumer`]. Stays the same across restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConsumerId(u32);
+
+#[derive(Debug)]
+struct Consumer {
+    id: ConsumerId,
+    next: u64,
+    name: String,
+}
+
+impl WriteAheadLog {
+    /// Registers a consumer under `name`, or returns the ID it was already
+    /// registered with. A new consumer has acknowledged nothing, holding
+    /// back [`min_acked`](Self::min_acked) until it acks. Names may not
+    /// contain line breaks.
+    pub fn register_consumer(&mut self, name: &str) -> Result<ConsumerId> {
+        self.check_writable()?;
+        if name.contains(['\n', '\r']) {
+            return Err(WalError::InvalidConfig(
+                "consumer names may not contain line breaks".to_string(),
+            ));
+        }
+        let mut consumers = read_consumers(&self.consumers_path())?;
+        if let Some(consumer) = consumers.iter().find(|c| c.name == name) {
+            return Ok(consumer.id);
+        }
+        let id = ConsumerId(consumers.iter().map(|c| c.id.0 + 1).max().unwrap_or(0));
+        consumers.push(Consumer {
+            id,
+            next: 0,
+            name: name.to_string(),
+        });
+        write_consumers(&self.consumers_path(), &consumers)?;
+        Ok(id)
+    }
+
+    /// Records that `consumer` has processed every entry up to and
+    /// including `id`. Acks never move a consumer backwards, so a stale one
+    /// is ignored. Acking an ID the log has not handed out yet, or acking
+    /// for an unregistered consumer, fails with
+    /// [`WalError::InvalidConfig`].
+    pub fn ack(&mut self, consumer: ConsumerId, id: u64) -> Result<()> {
+        self.check_writable()?;
+        if id >= self.current_id {
+            return Err(WalError::InvalidConfig(format!(
+                "cannot ack entry {id}; the next ID is {}",
+                self.current_id
+            )));
+        }
+        let mut consumers = read_consumers(&self.consumers_path())?;
+        let entry = consumers
+            .iter_mut()
+            .find(|c| c.id == consumer)
+            .ok_or_else(|| {
+                WalError::InvalidConfig(format!("consumer {} is not registered", consumer.0))
+            })?;
+        if id < entry.next {
+            return Ok(());
+        }
+        entry.next = id + 1;
+        write_consumers(&self.consumers_path(), &consumers)
+    }
+
+    /// The lowest ID not yet acknowledged by every registered consumer:
+    /// entries below it have been processed by all of them and are safe to
+    /// drop with [`compact_acked`](Self::compact_acked). With no consumers
+    /// registered that is [`next_id`](Self::next_id).
+    pub fn min_acked(&self) -> Result<u64> {
+        let consumers = read_consumers(&self.consumers_path())?;
+        Ok(consumers
+            .iter()
+            .map(|c| c.next)
+            .min()
+            .unwrap_or(self.current_id))
+    }
+
+    /// Removes the data entries below [`min_acked`](Self::min_acked), along
+    /// with anything [`compact`](Self::compact) removes, returning how many
+    /// records were dropped. Bookkeeping records and other streams are
+    /// kept. Runs as a [`compact_retain`](Self::compact_retain).
+    pub fn compact_acked(&self) -> Result<usize> {
+        let _rewrite = self.rewrite_lock.lock()?;
+        let acked = self.min_acked()?;
+        self.compact_snapshot(|e| e.kind != EntryKind::Data || e.stream != 0 || e.id >= acked)
+    }
+
+    pub(crate) fn consumers_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".consumers");
+        PathBuf::from(path)
+    }
+}
+
+fn read_consumers(path: &Path) -> Result<Vec<Consumer>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let (Some(id), Some(next), Some(name)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid_line(line));
+            };
+            Ok(Consumer {
+                id: ConsumerId(id.parse().map_err(|_| invalid_line(line))?),
+                next: next.parse().map_err(|_| invalid_line(line))?,
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn invalid_line(line: &str) -> WalError {
+    WalError::InvalidConfig(format!("`{line}` does not describe a consumer"))
+}
+
+fn write_consumers(path: &Path, consumers: &[Consumer]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = fs::File::create(&tmp)?;
+    for consumer in consumers {
+        writeln!(
+            file,
+            "{} {} {}",
+            consumer.id.0, consumer.next, consumer.name
+        )?;
+    }
+    file.sync_data()?;
+    durable::rename(&tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends `data` tagged with a MIME `content_type` so readers know how
+    /// to interpret it.
+    pub fn append_typed(&mut self, data: Vec<u8>, content_type: &str) -> Result<LogEntry> {
+        self.append_record(LogEntry {
+            data,
+            content_type: Some(content_type.to_string()),
+            ..LogEntry::default()
+        })
+    }
+
+    /// Returns the data entries whose content type equals `content_type`.
+    /// Untagged entries never match.
+    pub fn filter_by_content_type(&self, content_type: &str) -> Result<Vec<LogEntry>> {
+        let mut matches = Vec::new();
+        for entry in self.records()? {
+            let entry = entry?;
+            if entry.kind == EntryKind::Data && entry.content_type.as_deref() == Some(content_type)
+            {
+                matches.push(entry);
+            }
+        }
+        Ok(matches)
+    }
+}
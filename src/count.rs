@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+
+use crate::entry::EntryKind;
+use crate::error::{Result, WalError};
+use crate::format::Format;
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Damage assessment returned by [`WriteAheadLog::count_resilient`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CountReport {
+    /// Records that decode, markers included.
+    pub valid: u64,
+    /// Complete frames (for JSON, lines) that fail to decode.
+    pub corrupt: u64,
+    /// Best guess at how many records were written: every frame found, plus
+    /// one for each segment that ends in a torn, incomplete frame.
+    pub estimated_total: u64,
+}
+
+/// Records in the log by kind, returned by
+/// [`WriteAheadLog::entry_breakdown`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryBreakdown {
+    /// Data entries.
+    pub live: u64,
+    /// Markers.
+    pub markers: u64,
+    /// Status records from [`mark_done`](WriteAheadLog::mark_done) and
+    /// [`mark_failed`](WriteAheadLog::mark_failed).
+    pub statuses: u64,
+    /// Tombstones left by [`clear_id`](WriteAheadLog::clear_id).
+    pub tombstones: u64,
+    /// Recovery points written by [`checkpoint`](WriteAheadLog::checkpoint).
+    pub checkpoints: u64,
+    /// Every record, of any kind.
+    pub total: u64,
+}
+
+impl WriteAheadLog {
+    /// Counts the records of every stream by [`EntryKind`] in one streaming
+    /// pass, to show how much of the log is bookkeeping rather than data
+    /// when deciding whether compaction is worthwhile. Undecodable records
+    /// are skipped as by reads.
+    pub fn entry_breakdown(&self) -> Result<EntryBreakdown> {
+        let _file = self.lock_file()?;
+        let mut breakdown = EntryBreakdown::default();
+        for record in self.all_records()? {
+            match record?.kind {
+                EntryKind::Data => breakdown.live += 1,
+                EntryKind::Marker => breakdown.markers += 1,
+                EntryKind::Status => breakdown.statuses += 1,
+                EntryKind::Tombstone => breakdown.tombstones += 1,
+                EntryKind::Checkpoint => breakdown.checkpoints += 1,
+            }
+            breakdown.total += 1;
+        }
+        Ok(breakdown)
+    }
+
+    /// Number of data entries, as [`read_all`](Self::read_all) would
+    /// return, counted by walking the frames of each segment without
+    /// decoding them: lines for JSON, length prefixes for the binary
+    /// formats. Markers, statuses and other streams are told apart from the
+    /// framing and left out, and entries cleared by tombstones by the ID at
+    /// the start of the record. A damaged record is still counted, so on a
+    /// damaged log this can exceed what reads return.
+    pub fn len(&self) -> Result<usize> {
+        let _file = self.lock_file()?;
+        let tombstones = self.tombstones.lock()?.clone();
+        let mut count = 0;
+        let mut buf = Vec::new();
+        for path in segment::all_segments(&self.path)? {
+            let mut reader = BufReader::new(File::open(&path)?);
+            header::skip(&mut reader)?;
+            while self.format.read_frame(&mut reader, &mut buf)? > 0 {
+                if !self.format.is_data_frame(&buf) {
+                    continue;
+                }
+                let cleared = !tombstones.is_empty()
+                    && self
+                        .format
+                        .frame_id(&buf)
+                        .or_else(|| self.format.decode_flagged(&buf).ok().map(|(e, _)| e.id))
+                        .is_some_and(|id| tombstones.contains(&id));
+                count += usize::from(!cleared);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Whether [`len`](Self::len) is zero.
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Counts decodable and undecodable records without stopping at damage.
+    /// Unlike reads, this does not quarantine anything.
+    pub fn count_resilient(&self) -> Result<CountReport> {
+        let _file = self.lock_file()?;
+        let mut report = CountReport::default();
+        for path in segment::all_segments(&self.path)? {
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len();
+            let mut reader = BufReader::new(file);
+            let mut buf = Vec::new();
+            let mut offset = header::skip(&mut reader)?;
+            loop {
+                let consumed = self.format.read_frame(&mut reader, &mut buf)?;
+                if consumed == 0 {
+                    break;
+                }
+                offset += consumed as u64;
+                match self.format.decode_with(
+                    &buf,
+                    self.dictionary.as_deref(),
+                    self.cipher.as_deref(),
+                ) {
+                    Ok(_) => report.valid += 1,
+                    Err(_) => report.corrupt += 1,
+                }
+            }
+            report.estimated_total += u64::from(offset < len);
+        }
+        report.estimated_total += report.valid + report.corrupt;
+        Ok(report)
+    }
+
+    /// Bytes of a torn final write cut from the active file when the log was
+    /// opened: a trailing partial line for JSON, or an incomplete frame for
+    /// the binary formats. Usually zero.
+    pub fn discarded_on_open(&self) -> u64 {
+        self.discarded_on_open
+    }
+
+    /// Cuts an incomplete record left at the end of the active `file` by a
+    /// crash mid-append, returning how many bytes were removed. Unlike
+    /// [`truncate_to_last_valid`](Self::truncate_to_last_valid), a complete
+    /// record that fails to decode is left alone. Frames are walked from
+    /// offset `from`, which must be the start of a record, or from the
+    /// start of the file if it is 0.
+    pub(crate) fn trim_torn_tail(&self, file: &File, from: u64) -> Result<u64> {
+        let len = file.metadata()?.len();
+        let mut reader = BufReader::new(file.try_clone()?);
+        reader.seek(SeekFrom::Start(from))?;
+        let mut buf = Vec::new();
+        let mut offset = match from {
+            0 => header::skip(&mut reader)?,
+            from => from,
+        };
+        let mut good_end = offset;
+        loop {
+            let consumed = self.format.read_frame(&mut reader, &mut buf)?;
+            if consumed == 0 {
+                break;
+            }
+            offset += consumed as u64;
+            let unterminated =
+                matches!(self.format, Format::Json | Format::JsonBase64) && consumed == buf.len();
+            if !unterminated
+                || self
+                    .format
+                    .decode_with(&buf, self.dictionary.as_deref(), self.cipher.as_deref())
+                    .is_ok()
+            {
+                good_end = offset;
+            }
+        }
+        let torn = len - good_end;
+        if torn > 0 {
+            file.set_len(good_end)?;
+            file.sync_data()?;
+        }
+        Ok(torn)
+    }
+
+    /// Cuts the active file right after its last record that decodes,
+    /// dropping trailing garbage such as a torn write, and returns the new
+    /// length. Nothing before that record is touched.
+    pub fn truncate_to_last_valid(&self) -> Result<u64> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let file = self.lock_file()?;
+        let valid_end = self.valid_end(&file)?;
+        if file.metadata()?.len() != valid_end {
+            file.set_len(valid_end)?;
+            file.sync_data()?;
+        }
+        Ok(valid_end)
+    }
+
+    /// Offset in the active file just past its last record that decodes:
+    /// the logical end of the log, as opposed to the physical length
+    /// reported by [`std::fs::metadata`], which also takes in space
+    /// preallocated past the end or trailing garbage. Tools that copy or
+    /// cut the file should stop here. Without records this is the length
+    /// of the header.
+    pub fn logical_len(&self) -> Result<u64> {
+        let file = self.lock_file()?;
+        self.valid_end(&file)
+    }
+
+    /// Offset just past the last record of the active `file` that decodes.
+    fn valid_end(&self, file: &File) -> Result<u64> {
+        let mut reader = BufReader::new(file.try_clone()?);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut buf = Vec::new();
+        let mut offset = header::skip(&mut reader)?;
+        let mut valid_end = offset;
+        loop {
+            let consumed = self.format.read_frame(&mut reader, &mut buf)?;
+            if consumed == 0 {
+                break;
+            }
+            offset += consumed as u64;
+            // A payload that fails to decrypt is still a whole record.
+            if matches!(
+                self.format
+                    .decode_with(&buf, self.dictionary.as_deref(), self.cipher.as_deref()),
+                Ok(_) | Err(WalError::Decryption { .. })
+            ) {
+                valid_end = offset;
+            }
+        }
+        Ok(valid_end)
+    }
+}
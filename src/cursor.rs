@@ -0,0 +1,131 @@
+//! Polling for new entries from a position the caller holds on to.
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::iter::{Decoding, SegmentReader};
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// How far [`WriteAheadLog::read_since`] has read: the byte offset just
+/// past the last stream-0 record it passed and that record's ID. A fresh
+/// cursor stands at the start of the log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cursor {
+    last: Option<Passed>,
+    reset: bool,
+}
+
+/// The last stream-0 record a cursor passed, of any kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Passed {
+    id: u64,
+    start: u64,
+    end: u64,
+}
+
+impl Cursor {
+    /// A cursor at the start of the log.
+    pub fn new() -> Self {
+        Cursor::default()
+    }
+
+    /// Byte offset just past the last record read, in the segment that
+    /// holds it, or 0 at the start of the log.
+    pub fn offset(&self) -> u64 {
+        self.last.map_or(0, |last| last.end)
+    }
+
+    /// ID of the last record read, or `None` at the start of the log.
+    pub fn last_id(&self) -> Option<u64> {
+        self.last.map(|last| last.id)
+    }
+
+    /// Whether the last [`read_since`](WriteAheadLog::read_since) found the
+    /// log rewritten under the cursor and started over from the start.
+    pub fn was_reset(&self) -> bool {
+        self.reset
+    }
+}
+
+impl WriteAheadLog {
+    /// Returns the data entries appended since `cursor` was last advanced,
+    /// in file order, and moves it past them: an empty result means nothing
+    /// new. Reading resumes at the cursor's offset rather than scanning from
+    /// the start, following the log into the segments written since if it
+    /// has rotated.
+    ///
+    /// Before resuming, the record the cursor last passed is looked for at
+    /// its recorded place. If it is gone, because the log was cleared,
+    /// compacted or otherwise rewritten, the cursor starts over: every entry
+    /// now in the log is returned and [`Cursor::was_reset`] is set until the
+    /// next call. Undecodable records are skipped without being
+    /// quarantined, like [`tail_with`](Self::tail_with) does.
+    pub fn read_since(&self, cursor: &mut Cursor) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let segments = segment::all_segments(&self.path)?;
+        let resume = match cursor.last {
+            Some(last) => self.find_passed(&segments, last)?,
+            None => Some((0, 0)),
+        };
+        cursor.reset = resume.is_none();
+        if cursor.reset {
+            cursor.last = None;
+        }
+        let (first, offset) = resume.unwrap_or((0, 0));
+        let decoding = self.cursor_decoding(true);
+        let mut entries = Vec::new();
+        for (i, path) in segments.iter().enumerate().skip(first) {
+            let file = File::open(path)?;
+            let start = if i == first { offset } else { 0 };
+            let mut reader = SegmentReader::starting_at(path, file, decoding.clone(), start)?;
+            while let Some((record, size)) = reader.next_sized()? {
+                let Ok(record) = record else { continue };
+                if record.stream != 0 {
+                    continue;
+                }
+                let end = reader.offset();
+                cursor.last = Some(Passed {
+                    id: record.id,
+                    start: end - size,
+                    end,
+                });
+                if record.kind == EntryKind::Data && !self.is_cleared(&record) {
+                    entries.push(record);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The index in `segments` of the one still holding `last` where the
+    /// cursor passed it, newest first, with the offset to resume at there.
+    fn find_passed(&self, segments: &[PathBuf], last: Passed) -> Result<Option<(usize, u64)>> {
+        let decoding = self.cursor_decoding(false);
+        for (i, path) in segments.iter().enumerate().rev() {
+            let file = File::open(path)?;
+            if file.metadata()?.len() < last.end {
+                continue;
+            }
+            let mut reader = SegmentReader::starting_at(path, file, decoding.clone(), last.start)?;
+            if let Some((Ok(record), size)) = reader.next_sized()? {
+                if record.stream == 0 && record.id == last.id && last.start + size == last.end {
+                    return Ok(Some((i, last.end)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn cursor_decoding(&self, payloads: bool) -> Decoding {
+        Decoding {
+            payloads,
+            quarantine: None,
+            metrics: None,
+            ..self.decoding()
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Payload compression against a preset dictionary.
+//!
+//! Short, similar payloads barely compress on their own because each one is
+//! too small to repeat itself. Compressing against a dictionary of typical
+//! content lets matches reach back into it instead. Every compressed
+//! payload starts with the LEB128 varint length of the plaintext, which
+//! decoding never goes past, so a damaged record cannot make it allocate
+//! without bound.
+//!
+//! The in-house codec is a plain LZ77: a sequence of
+//! `(literal_len, literals, match_len, distance)` tokens in LEB128 varints,
+//! ended by a token with a zero `match_len` and no distance. Distances count
+//! back through the dictionary followed by the output so far. With the
+//! `zstd` feature, the dictionary can be a zstd one instead.
+//!
+//! The dictionary is stored in the [header](crate::header) of the log's
+//! files so the log can be read back without being told it again.
+
+use std::collections::HashMap;
+#[cfg(feature = "zstd")]
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::{Result, WalError};
+use crate::format::{write_varint, Cursor};
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+const MIN_MATCH: usize = 4;
+/// How many earlier positions with the same prefix are tried per match.
+const MAX_CANDIDATES: usize = 64;
+
+/// A dictionary of content typical of the log's payloads, and the codec
+/// payloads are compressed against it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Dictionary {
+    bytes: Vec<u8>,
+    codec: DictCodec,
+}
+
+/// Codec a [`Dictionary`] is used with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DictCodec {
+    Lz77,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Dictionary {
+    /// A dictionary for the in-house LZ77 codec.
+    pub(crate) fn new(bytes: Vec<u8>) -> Self {
+        Dictionary {
+            bytes,
+            codec: DictCodec::Lz77,
+        }
+    }
+
+    /// A zstd dictionary, either trained or raw content.
+    #[cfg(feature = "zstd")]
+    pub(crate) fn zstd(bytes: Vec<u8>) -> Self {
+        Dictionary {
+            bytes,
+            codec: DictCodec::Zstd,
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub(crate) fn codec(&self) -> DictCodec {
+        self.codec
+    }
+
+    /// Compresses `data`, failing only if zstd does.
+    pub(crate) fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self.codec {
+            DictCodec::Lz77 => Ok(pack(&self.bytes, data)),
+            #[cfg(feature = "zstd")]
+            DictCodec::Zstd => {
+                let mut out = Vec::new();
+                write_varint(&mut out, data.len() as u64);
+                let mut compressor = zstd::bulk::Compressor::with_dictionary(
+                    zstd::DEFAULT_COMPRESSION_LEVEL,
+                    &self.bytes,
+                )?;
+                out.extend(compressor.compress(data)?);
+                Ok(out)
+            }
+        }
+    }
+
+    /// Reverses [`compress`](Self::compress), failing with
+    /// [`WalError::InvalidEntry`] if `packed` was not produced by it with
+    /// this dictionary or holds more than the length it starts with.
+    pub(crate) fn decompress(&self, packed: &[u8]) -> Result<Vec<u8>> {
+        match self.codec {
+            DictCodec::Lz77 => unpack(&self.bytes, packed),
+            #[cfg(feature = "zstd")]
+            DictCodec::Zstd => {
+                let mut cur = Cursor(packed);
+                let len = stated_len(&mut cur)?;
+                let mismatch = |err: std::io::Error| {
+                    WalError::InvalidEntry(format!(
+                        "compressed data does not match the dictionary: {err}"
+                    ))
+                };
+                let decoder = zstd::stream::read::Decoder::with_dictionary(cur.0, &self.bytes)
+                    .map_err(mismatch)?;
+                let mut out = Vec::new();
+                decoder
+                    .take(len as u64 + 1)
+                    .read_to_end(&mut out)
+                    .map_err(mismatch)?;
+                check_len(out.len(), len)?;
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Compresses `data` with the LZ77 codec against `dictionary`.
+pub(crate) fn pack(dictionary: &[u8], data: &[u8]) -> Vec<u8> {
+    let start = dictionary.len();
+    let buf = [dictionary, data].concat();
+    let mut chains = Chains::new(buf.len());
+    for pos in 0..start {
+        chains.insert(&buf, pos);
+    }
+    let mut out = Vec::with_capacity(data.len() / 2 + 8);
+    write_varint(&mut out, data.len() as u64);
+    let mut literals = start;
+    let mut pos = start;
+    while pos + MIN_MATCH <= buf.len() {
+        let (len, from) = chains.longest_match(&buf, pos);
+        if len < MIN_MATCH {
+            chains.insert(&buf, pos);
+            pos += 1;
+            continue;
+        }
+        write_token(&mut out, &buf[literals..pos], len);
+        write_varint(&mut out, (pos - from) as u64);
+        for p in pos..pos + len {
+            chains.insert(&buf, p);
+        }
+        pos += len;
+        literals = pos;
+    }
+    write_token(&mut out, &buf[literals..], 0);
+    out
+}
+
+/// Reverses [`pack`] with the same `dictionary`, never producing more than
+/// the length `packed` starts with.
+pub(crate) fn unpack(dictionary: &[u8], packed: &[u8]) -> Result<Vec<u8>> {
+    let malformed = || WalError::InvalidEntry("compressed data is malformed".to_string());
+    let mut cur = Cursor(packed);
+    let stated = stated_len(&mut cur)?;
+    let end = dictionary.len().saturating_add(stated);
+    let mut buf = dictionary.to_vec();
+    loop {
+        let literals = next_len(&mut cur).ok_or_else(malformed)?;
+        check_room(end - buf.len(), literals, stated)?;
+        buf.extend_from_slice(cur.take(literals).map_err(|_| malformed())?);
+        let len = next_len(&mut cur).ok_or_else(malformed)?;
+        if len == 0 {
+            break;
+        }
+        check_room(end - buf.len(), len, stated)?;
+        let distance = next_len(&mut cur).ok_or_else(malformed)?;
+        let from = buf
+            .len()
+            .checked_sub(distance)
+            .filter(|_| distance > 0)
+            .ok_or_else(malformed)?;
+        // Byte by byte, since a match may overlap the bytes it produces.
+        for i in 0..len {
+            buf.push(buf[from + i]);
+        }
+    }
+    if !cur.0.is_empty() {
+        return Err(malformed());
+    }
+    check_len(buf.len() - dictionary.len(), stated)?;
+    Ok(buf.split_off(dictionary.len()))
+}
+
+fn next_len(cur: &mut Cursor<'_>) -> Option<usize> {
+    usize::try_from(cur.varint().ok()?).ok()
+}
+
+/// Fails if `more` bytes do not fit in the `room` left before the `stated`
+/// length, checked before anything is copied.
+fn check_room(room: usize, more: usize, stated: usize) -> Result<()> {
+    if more > room {
+        return Err(past_stated_len(stated));
+    }
+    Ok(())
+}
+
+/// Reads the plaintext length a compressed payload starts with.
+fn stated_len(cur: &mut Cursor<'_>) -> Result<usize> {
+    next_len(cur).ok_or_else(|| WalError::InvalidEntry("compressed data has no length".to_string()))
+}
+
+fn past_stated_len(stated: usize) -> WalError {
+    WalError::InvalidEntry(format!(
+        "compressed data runs past its stated length of {stated} bytes"
+    ))
+}
+
+/// Fails unless `found`, the length decompressed, is the `stated` one.
+fn check_len(found: usize, stated: usize) -> Result<()> {
+    match found.cmp(&stated) {
+        std::cmp::Ordering::Equal => Ok(()),
+        std::cmp::Ordering::Greater => Err(past_stated_len(stated)),
+        std::cmp::Ordering::Less => Err(WalError::InvalidEntry(format!(
+            "compressed data falls short of its stated length of {stated} bytes"
+        ))),
+    }
+}
+
+fn write_token(out: &mut Vec<u8>, literals: &[u8], match_len: usize) {
+    write_varint(out, literals.len() as u64);
+    out.extend_from_slice(literals);
+    write_varint(out, match_len as u64);
+}
+
+/// Earlier positions of each 4-byte prefix, most recent first.
+struct Chains {
+    head: HashMap<[u8; MIN_MATCH], usize>,
+    prev: Vec<Option<usize>>,
+}
+
+impl Chains {
+    fn new(len: usize) -> Self {
+        Chains {
+            head: HashMap::new(),
+            prev: vec![None; len],
+        }
+    }
+
+    fn insert(&mut self, buf: &[u8], pos: usize) {
+        if let Some(key) = prefix(buf, pos) {
+            self.prev[pos] = self.head.insert(key, pos);
+        }
+    }
+
+    /// The longest earlier match for the bytes at `pos`, as `(len, from)`.
+    fn longest_match(&self, buf: &[u8], pos: usize) -> (usize, usize) {
+        let mut best = (0, 0);
+        let mut candidate = prefix(buf, pos).and_then(|key| self.head.get(&key).copied());
+        for _ in 0..MAX_CANDIDATES {
+            let Some(from) = candidate else {
+                break;
+            };
+            let len = buf[pos..]
+                .iter()
+                .zip(&buf[from..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best.0 {
+                best = (len, from);
+            }
+            candidate = self.prev[from];
+        }
+        best
+    }
+}
+
+fn prefix(buf: &[u8], pos: usize) -> Option<[u8; MIN_MATCH]> {
+    buf.get(pos..pos + MIN_MATCH)?.try_into().ok()
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path`, compressing payloads against `dictionary`.
+    /// See [`WriteAheadLogBuilder::compression_dict`](crate::WriteAheadLogBuilder::compression_dict).
+    pub fn with_compression_dict<P: AsRef<Path>>(path: P, dictionary: Vec<u8>) -> Result<Self> {
+        Self::builder(path).compression_dict(dictionary).build()
+    }
+
+    /// Opens the log at `path`, compressing payloads with zstd against
+    /// `dictionary`.
+    /// See [`WriteAheadLogBuilder::zstd_dict`](crate::WriteAheadLogBuilder::zstd_dict).
+    #[cfg(feature = "zstd")]
+    pub fn with_zstd_dict<P: AsRef<Path>>(path: P, dictionary: Vec<u8>) -> Result<Self> {
+        Self::builder(path).zstd_dict(dictionary).build()
+    }
+}
+
+/// The dictionary stored in the headers of the log at `path`, if any, as
+/// the newest file that holds one has it.
+pub(crate) fn stored(path: &Path) -> Result<Option<Dictionary>> {
+    for path in segment::all_segments(path)?.iter().rev() {
+        if !path.exists() {
+            continue;
+        }
+        if let Some(dictionary) = header::fields(path)?.dictionary {
+            return Ok(Some(dictionary));
+        }
+    }
+    Ok(None)
+}
+
+/// The dictionary to open the log at `path` with: the one stored in its
+/// headers, if any, which `given` must then match, or else `given`.
+pub(crate) fn load(path: &Path, given: Option<Dictionary>) -> Result<Option<Dictionary>> {
+    match (stored(path)?, given) {
+        (Some(stored), Some(given)) if stored != given => Err(WalError::InvalidConfig(
+            "the log already stores a different compression dictionary".to_string(),
+        )),
+        (stored, given) => Ok(stored.or(given)),
+    }
+}
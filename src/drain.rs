@@ -0,0 +1,90 @@
+//! At-least-once consumption: entries are handed out first and removed
+//! together once the consumer is done with them.
+
+use std::collections::HashSet;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::iter::EntryIter;
+use crate::wal::WriteAheadLog;
+
+/// Hands out the data entries of a log in file order and removes the ones
+/// it handed out when finished; see [`WriteAheadLog::drain`].
+#[derive(Debug)]
+pub struct Drain<'a> {
+    wal: &'a mut WriteAheadLog,
+    entries: EntryIter,
+    consumed: HashSet<u64>,
+    finished: bool,
+}
+
+impl Drain<'_> {
+    /// Removes the entries handed out so far from the log in one
+    /// compaction and returns how many there were. Dropping the `Drain`
+    /// does the same, reporting an error to the
+    /// [`on_drop_error`](crate::WriteAheadLogBuilder::on_drop_error)
+    /// handler, if any; call this to handle it directly.
+    pub fn finish(mut self) -> Result<usize> {
+        self.remove_consumed()
+    }
+
+    fn remove_consumed(&mut self) -> Result<usize> {
+        self.finished = true;
+        if self.consumed.is_empty() {
+            return Ok(0);
+        }
+        let consumed = &self.consumed;
+        self.wal.compact_retain(|record| {
+            !(record.stream == 0 && record.kind == EntryKind::Data && consumed.contains(&record.id))
+        })?;
+        Ok(consumed.len())
+    }
+}
+
+impl Iterator for Drain<'_> {
+    type Item = Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next()?;
+        if let Ok(entry) = &entry {
+            self.consumed.insert(entry.id);
+        }
+        Some(entry)
+    }
+}
+
+impl Drop for Drain<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let (Err(err), Some(handler)) = (self.remove_consumed(), &self.wal.on_drop_error) {
+            handler(err);
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Consumes the log like a queue: the returned iterator yields data
+    /// entries in file order, as [`iter`](Self::iter) does, and the ones it
+    /// has yielded are removed from the log in a single compaction when it
+    /// is dropped or [finished](Drain::finish). Stopping early removes only
+    /// the entries already yielded; the rest stay in the log untouched.
+    ///
+    /// Nothing is removed until then, so a crash while entries are being
+    /// processed delivers them again on the next drain, unlike
+    /// [`take_next`](Self::take_next), which removes each entry before
+    /// handing it out. Queued group-commit records are written first.
+    pub fn drain(&mut self) -> Result<Drain<'_>> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        self.flush()?;
+        let entries = self.iter()?;
+        Ok(Drain {
+            wal: self,
+            entries,
+            consumed: HashSet::new(),
+            finished: false,
+        })
+    }
+}
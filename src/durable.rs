@@ -0,0 +1,33 @@
+//! Making renames and removals of the log's files survive a crash.
+//!
+//! Renaming or removing a file changes the directory holding it rather than
+//! the file itself, so a synced file renamed into place can still be lost,
+//! or reappear under its old name, unless the directory is synced as well.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Renames `from` to `to` and syncs the directory holding `to`.
+pub(crate) fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)?;
+    sync_dir(to)
+}
+
+/// Syncs the directory holding `path`, making the renames and removals
+/// done in it so far durable.
+#[cfg(unix)]
+pub(crate) fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened to sync them on this platform, where
+/// renames are durable once they return.
+#[cfg(not(unix))]
+pub(crate) fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! At-rest encryption of entry payloads.
+//!
+//! Each record says whether, and how, its `data` was stored encrypted, so a
+//! log can mix encrypted and plain entries. Encrypted payloads are stored as
+//! a random 12-byte nonce, the ChaCha20-Poly1305 ciphertext and its 16-byte
+//! tag, after any compression. Encrypting on append needs the `encryption`
+//! feature; reading encrypted entries needs the key, which only that
+//! feature lets a log be given.
+
+use std::fmt;
+use std::io;
+#[cfg(feature = "encryption")]
+use std::path::Path;
+
+use crate::chacha20poly1305::{self, KEY_LEN, NONCE_LEN};
+use crate::entry::LogEntry;
+#[cfg(feature = "encryption")]
+use crate::error::Result;
+#[cfg(feature = "encryption")]
+use crate::wal::WriteAheadLog;
+
+/// Scheme a payload is stored encrypted with; see
+/// [`LogEntry::encryption`](crate::LogEntry::encryption).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encryption {
+    /// ChaCha20-Poly1305 over `data` alone.
+    ChaCha20Poly1305,
+    /// ChaCha20-Poly1305 with the entry's `id`, `stream` and `timestamp` as
+    /// associated data, so a payload moved to another entry fails to
+    /// decrypt. See
+    /// [`authenticate_metadata`](crate::WriteAheadLogBuilder::authenticate_metadata).
+    ChaCha20Poly1305WithMetadata,
+}
+
+impl Encryption {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Encryption::ChaCha20Poly1305 => "chacha20-poly1305",
+            Encryption::ChaCha20Poly1305WithMetadata => "chacha20-poly1305+metadata",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        match s {
+            "chacha20-poly1305" => Some(Encryption::ChaCha20Poly1305),
+            "chacha20-poly1305+metadata" => Some(Encryption::ChaCha20Poly1305WithMetadata),
+            _ => None,
+        }
+    }
+
+    /// What `entry` is authenticated together with its payload.
+    fn associated_data(self, entry: &LogEntry) -> Vec<u8> {
+        match self {
+            Encryption::ChaCha20Poly1305 => Vec::new(),
+            Encryption::ChaCha20Poly1305WithMetadata => [
+                &entry.id.to_le_bytes()[..],
+                &entry.stream.to_le_bytes(),
+                &entry.timestamp.to_le_bytes(),
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// The key payloads are encrypted with. Kept out of `Debug` output.
+#[derive(Clone)]
+pub(crate) struct Cipher {
+    key: [u8; KEY_LEN],
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cipher(..)")
+    }
+}
+
+impl Cipher {
+    #[cfg(feature = "encryption")]
+    pub(crate) fn new(key: [u8; KEY_LEN]) -> Self {
+        Cipher { key }
+    }
+
+    /// Encrypts `data`, the stored form of `entry`'s payload, under a fresh
+    /// nonce, returning the nonce followed by the ciphertext and tag. Fails
+    /// if no nonce can be drawn.
+    pub(crate) fn seal(
+        &self,
+        scheme: Encryption,
+        entry: &LogEntry,
+        data: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let nonce = fresh_nonce()?;
+        let aad = scheme.associated_data(entry);
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&chacha20poly1305::seal(&self.key, &nonce, &aad, data));
+        Ok(out)
+    }
+
+    /// Reverses [`seal`](Self::seal), or returns `None` if `sealed` was
+    /// encrypted under another key, belongs to another entry or was
+    /// tampered with.
+    pub(crate) fn open(
+        &self,
+        scheme: Encryption,
+        entry: &LogEntry,
+        sealed: &[u8],
+    ) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let aad = scheme.associated_data(entry);
+        chacha20poly1305::open(&self.key, nonce.try_into().unwrap(), &aad, sealed)
+    }
+}
+
+/// A nonce drawn from the operating system's random source. At 96 bits,
+/// random nonces are not expected to repeat under one key before some 2^48
+/// payloads have been sealed with it.
+/// Fails if the random source cannot be read, since no nonce could safely
+/// be used instead.
+fn fresh_nonce() -> io::Result<[u8; NONCE_LEN]> {
+    let mut nonce = [0u8; NONCE_LEN];
+    fill_random(&mut nonce)?;
+    Ok(nonce)
+}
+
+#[cfg(unix)]
+fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::OnceLock;
+
+    // Opened once and shared, rather than on every append.
+    static URANDOM: OnceLock<File> = OnceLock::new();
+    let file = match URANDOM.get() {
+        Some(file) => file,
+        None => {
+            let file = File::open("/dev/urandom")?;
+            URANDOM.get_or_init(|| file)
+        }
+    };
+    let mut file = file;
+    file.read_exact(buf)
+}
+
+#[cfg(windows)]
+fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    #[link(name = "bcryptprimitives", kind = "raw-dylib")]
+    extern "system" {
+        fn ProcessPrng(data: *mut u8, len: usize) -> i32;
+    }
+    // SAFETY: fills exactly `buf.len()` bytes of `buf`, and is documented
+    // to always succeed.
+    unsafe {
+        ProcessPrng(buf.as_mut_ptr(), buf.len());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn fill_random(_buf: &mut [u8]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "no random source is known on this platform",
+    ))
+}
+
+#[cfg(feature = "encryption")]
+impl WriteAheadLog {
+    /// Opens the log at `path`, encrypting appended payloads under `key`.
+    /// See [`WriteAheadLogBuilder::encryption`](crate::WriteAheadLogBuilder::encryption).
+    pub fn with_encryption<P: AsRef<Path>>(path: P, key: [u8; KEY_LEN]) -> Result<Self> {
+        Self::builder(path).encryption(key).build()
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use crate::base64;
+use crate::compress::Compression;
+use crate::encrypt::Encryption;
+use crate::error::{Result, WalError};
+use crate::json::{self, Value};
+
+/// A single record in the log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Monotonically increasing identifier assigned at append time.
+    pub id: u64,
+    /// Seconds since the Unix epoch at which the entry was appended.
+    pub timestamp: u64,
+    /// Opaque payload. For markers this holds the UTF-8 label.
+    pub data: Vec<u8>,
+    /// What the record represents.
+    pub kind: EntryKind,
+    /// Optional MIME type describing `data`, e.g. `application/json`.
+    pub content_type: Option<String>,
+    /// CRC-32 over `id`, `timestamp` and `data`, or zero if the entry was
+    /// written without one.
+    pub checksum: u32,
+    /// Consumer progress committed together with this entry by
+    /// [`WriteAheadLog::append_and_checkpoint`](crate::WriteAheadLog::append_and_checkpoint).
+    pub processed_up_to: Option<u64>,
+    /// Seconds since the Unix epoch after which the entry may be dropped by
+    /// [`WriteAheadLog::compact_expired`](crate::WriteAheadLog::compact_expired).
+    pub expires_at: Option<u64>,
+    /// Logical stream the entry belongs to. Stream 0 is the log itself;
+    /// others are reached through
+    /// [`WriteAheadLog::stream`](crate::WriteAheadLog::stream) and number
+    /// their entries independently.
+    pub stream: u32,
+    /// For bookkeeping records that apply to another entry, such as status
+    /// updates, that entry's ID.
+    pub target: Option<u64>,
+    /// Producer-chosen key identifying the entry across retries; see
+    /// [`WriteAheadLog::append_idempotent`](crate::WriteAheadLog::append_idempotent).
+    pub idempotency_key: Option<String>,
+    /// Digest over `id`, `timestamp` and `data` from the log's
+    /// [`Hasher`](crate::Hasher), or empty if it was written without one.
+    pub digest: Vec<u8>,
+    /// Free-form metadata such as the host or trace ID the entry came from,
+    /// typically stamped by an
+    /// [`append_transform`](crate::WriteAheadLogBuilder::append_transform).
+    pub tags: BTreeMap<String, String>,
+    /// Codec `data` is stored with, or `None` if it is stored as it is.
+    /// Reads hand back the decompressed payload either way; see
+    /// [`WriteAheadLogBuilder::compression`](crate::WriteAheadLogBuilder::compression).
+    pub compression: Option<Compression>,
+    /// Scheme `data` is stored encrypted with, or `None` if it is stored in
+    /// the clear. Reads hand back the decrypted payload either way; see
+    /// [`WriteAheadLogBuilder::encryption`](crate::WriteAheadLogBuilder::encryption).
+    pub encryption: Option<Encryption>,
+}
+
+/// Distinguishes application data from bookkeeping records the log writes
+/// into itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    /// A payload appended by the application.
+    #[default]
+    Data,
+    /// A labelled point in the log written by
+    /// [`WriteAheadLog::append_marker`](crate::WriteAheadLog::append_marker).
+    Marker,
+    /// A processing status for the entry in `target`, written by
+    /// [`WriteAheadLog::mark_done`](crate::WriteAheadLog::mark_done) and
+    /// [`mark_failed`](crate::WriteAheadLog::mark_failed).
+    Status,
+    /// Marks the stream-0 record in `target` as removed, written by
+    /// [`WriteAheadLog::clear_id`](crate::WriteAheadLog::clear_id). Reads
+    /// skip both until [`compact`](crate::WriteAheadLog::compact) drops
+    /// them.
+    Tombstone,
+    /// A recovery point written by
+    /// [`WriteAheadLog::checkpoint`](crate::WriteAheadLog::checkpoint),
+    /// holding the IDs cleared by tombstones before it, so opening the log
+    /// can start from it instead of scanning everything.
+    Checkpoint,
+}
+
+impl EntryKind {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            EntryKind::Data => "data",
+            EntryKind::Marker => "marker",
+            EntryKind::Status => "status",
+            EntryKind::Tombstone => "tombstone",
+            EntryKind::Checkpoint => "checkpoint",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        match s {
+            "data" => Some(EntryKind::Data),
+            "marker" => Some(EntryKind::Marker),
+            "status" => Some(EntryKind::Status),
+            "tombstone" => Some(EntryKind::Tombstone),
+            "checkpoint" => Some(EntryKind::Checkpoint),
+            _ => None,
+        }
+    }
+}
+
+impl LogEntry {
+    /// Returns the marker label if this record is a marker.
+    pub fn marker_label(&self) -> Option<&str> {
+        match self.kind {
+            EntryKind::Marker => std::str::from_utf8(&self.data).ok(),
+            _ => None,
+        }
+    }
+
+    /// Serializes the entry as a single-line JSON object (without the
+    /// trailing newline), with `data` as a byte array or a base64 string.
+    /// `dict_compressed` flags `data` as compressed against the log's
+    /// dictionary.
+    pub(crate) fn to_json(&self, base64_data: bool, dict_compressed: bool) -> String {
+        let mut out = String::with_capacity(32 + self.data.len() * 4);
+        let _ = write!(
+            out,
+            "{{\"id\":{},\"timestamp\":{},\"data\":",
+            self.id, self.timestamp
+        );
+        if base64_data {
+            let _ = write!(out, "\"{}\"", base64::encode(&self.data));
+        } else {
+            out.push('[');
+            for (i, byte) in self.data.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "{byte}");
+            }
+            out.push(']');
+        }
+        if self.kind != EntryKind::Data {
+            let _ = write!(out, ",\"kind\":\"{}\"", self.kind.as_str());
+        }
+        if self.checksum != 0 {
+            let _ = write!(out, ",\"checksum\":{}", self.checksum);
+        }
+        if let Some(content_type) = &self.content_type {
+            out.push_str(",\"content_type\":");
+            json::write_str(&mut out, content_type);
+        }
+        if let Some(processed) = self.processed_up_to {
+            let _ = write!(out, ",\"processed_up_to\":{processed}");
+        }
+        if let Some(expires_at) = self.expires_at {
+            let _ = write!(out, ",\"expires_at\":{expires_at}");
+        }
+        if self.stream != 0 {
+            let _ = write!(out, ",\"stream\":{}", self.stream);
+        }
+        if let Some(target) = self.target {
+            let _ = write!(out, ",\"target\":{target}");
+        }
+        if let Some(key) = &self.idempotency_key {
+            out.push_str(",\"idempotency_key\":");
+            json::write_str(&mut out, key);
+        }
+        if !self.digest.is_empty() {
+            out.push_str(",\"digest\":\"");
+            for byte in &self.digest {
+                let _ = write!(out, "{byte:02x}");
+            }
+            out.push('"');
+        }
+        if !self.tags.is_empty() {
+            out.push_str(",\"tags\":{");
+            for (i, (key, value)) in self.tags.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                json::write_str(&mut out, key);
+                out.push(':');
+                json::write_str(&mut out, value);
+            }
+            out.push('}');
+        }
+        if let Some(compression) = self.compression {
+            let _ = write!(out, ",\"compression\":\"{}\"", compression.as_str());
+        }
+        if let Some(encryption) = self.encryption {
+            let _ = write!(out, ",\"encryption\":\"{}\"", encryption.as_str());
+        }
+        if dict_compressed {
+            out.push_str(",\"dict_compressed\":true");
+        }
+        out.push('}');
+        out
+    }
+
+    /// Parses an entry previously produced by [`LogEntry::to_json`], along
+    /// with its `dict_compressed` flag. Unknown fields are ignored.
+    pub(crate) fn from_json(line: &str) -> Result<(Self, bool)> {
+        Self::from_json_value(&json::parse(line).map_err(WalError::InvalidEntry)?)
+    }
+
+    /// Like [`from_json`](Self::from_json), for an already parsed object.
+    pub(crate) fn from_json_value(value: &Value) -> Result<(Self, bool)> {
+        let id = field_u64(value, "id")?;
+        let timestamp = field_u64(value, "timestamp")?;
+        let data = match value.get("data") {
+            None => return Err(WalError::InvalidEntry("missing field `data`".to_string())),
+            Some(Value::String(encoded)) => base64::decode(encoded)
+                .ok_or_else(|| WalError::InvalidEntry("`data` is not valid base64".to_string()))?,
+            Some(data) => data
+                .as_array()
+                .ok_or_else(|| WalError::InvalidEntry("`data` is not a byte array".to_string()))?
+                .iter()
+                .map(|v| v.as_u64().and_then(|n| u8::try_from(n).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| WalError::InvalidEntry("`data` is not a byte array".to_string()))?,
+        };
+        let kind = match value.get("kind") {
+            None => EntryKind::Data,
+            Some(Value::String(s)) => EntryKind::parse(s)
+                .ok_or_else(|| WalError::InvalidEntry(format!("unknown entry kind `{s}`")))?,
+            Some(_) => return Err(WalError::InvalidEntry("`kind` is not a string".to_string())),
+        };
+        let content_type = opt_string(value, "content_type")?;
+        let checksum = opt_u64(value, "checksum")?
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|_| WalError::InvalidEntry("invalid field `checksum`".to_string()))?
+            .unwrap_or(0);
+        let processed_up_to = opt_u64(value, "processed_up_to")?;
+        let expires_at = opt_u64(value, "expires_at")?;
+        let stream = opt_u64(value, "stream")?
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|_| WalError::InvalidEntry("invalid field `stream`".to_string()))?
+            .unwrap_or(0);
+        let target = opt_u64(value, "target")?;
+        let idempotency_key = opt_string(value, "idempotency_key")?;
+        let digest = match opt_string(value, "digest")? {
+            Some(hex) => decode_hex(&hex)
+                .ok_or_else(|| WalError::InvalidEntry("`digest` is not hex".to_string()))?,
+            None => Vec::new(),
+        };
+        let tags = match value.get("tags") {
+            None | Some(Value::Null) => BTreeMap::new(),
+            Some(Value::Object(fields)) => fields
+                .iter()
+                .map(|(key, value)| match value {
+                    Value::String(value) => Ok((key.clone(), value.clone())),
+                    _ => Err(WalError::InvalidEntry(format!(
+                        "tag `{key}` is not a string"
+                    ))),
+                })
+                .collect::<Result<_>>()?,
+            Some(_) => {
+                return Err(WalError::InvalidEntry(
+                    "`tags` is not an object".to_string(),
+                ))
+            }
+        };
+        let compression =
+            match opt_string(value, "compression")? {
+                Some(name) => Some(Compression::parse(&name).ok_or_else(|| {
+                    WalError::InvalidEntry(format!("unknown compression `{name}`"))
+                })?),
+                None => None,
+            };
+        let encryption =
+            match opt_string(value, "encryption")? {
+                Some(name) => Some(Encryption::parse(&name).ok_or_else(|| {
+                    WalError::InvalidEntry(format!("unknown encryption `{name}`"))
+                })?),
+                None => None,
+            };
+        let dict_compressed = matches!(value.get("dict_compressed"), Some(Value::Bool(true)));
+        let entry = LogEntry {
+            id,
+            timestamp,
+            data,
+            kind,
+            content_type,
+            checksum,
+            processed_up_to,
+            expires_at,
+            stream,
+            target,
+            idempotency_key,
+            digest,
+            tags,
+            compression,
+            encryption,
+        };
+        Ok((entry, dict_compressed))
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn field_u64(value: &Value, key: &str) -> Result<u64> {
+    value
+        .get(key)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| WalError::InvalidEntry(format!("missing or invalid field `{key}`")))
+}
+
+/// Reads an optional numeric field, treating `null` like a missing one.
+fn opt_u64(value: &Value, key: &str) -> Result<Option<u64>> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => v
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| WalError::InvalidEntry(format!("invalid field `{key}`"))),
+    }
+}
+
+/// Reads an optional string field, treating `null` like a missing one.
+fn opt_string(value: &Value, key: &str) -> Result<Option<String>> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(WalError::InvalidEntry(format!("`{key}` is not a string"))),
+    }
+}
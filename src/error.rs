@@ -0,0 +1,187 @@
+use std::fmt;
+use std::io;
+use std::sync::PoisonError;
+
+use crate::format::Format;
+
+/// Errors returned by [`WriteAheadLog`](crate::WriteAheadLog) operations.
+#[derive(Debug)]
+pub enum WalError {
+    /// The underlying file operation failed.
+    Io(io::Error),
+    /// A record could not be decoded into a [`LogEntry`](crate::LogEntry).
+    InvalidEntry(String),
+    /// A record's stored checksum does not match its contents.
+    ChecksumMismatch { id: u64 },
+    /// Appending would exceed a configured size or entry limit.
+    LogFull,
+    /// A read would have returned more than the caller allowed; see
+    /// [`WriteAheadLog::read_all_capped`](crate::WriteAheadLog::read_all_capped).
+    LimitExceeded,
+    /// A typed payload could not be encoded or decoded.
+    Serialization(String),
+    /// The builder was given options that cannot be used together.
+    InvalidConfig(String),
+    /// The log diverged from an expected sequence at position `index`.
+    Mismatch { index: usize, detail: String },
+    /// Appends are paused; see [`WriteAheadLog::pause`](crate::WriteAheadLog::pause).
+    Paused,
+    /// Another handle holds the log's exclusive lock.
+    Locked,
+    /// An operation did not finish within its time limit.
+    Timeout,
+    /// The log was written with settings that differ from those it was
+    /// opened with, such as another checksum algorithm.
+    ConfigMismatch(String),
+    /// Appending now would exceed the configured rate; see
+    /// [`WriteAheadLogBuilder::token_bucket`](crate::WriteAheadLogBuilder::token_bucket).
+    RateLimited,
+    /// Another writer has claimed the log with a higher epoch than the
+    /// `held` one; see
+    /// [`WriteAheadLogBuilder::epoch`](crate::WriteAheadLogBuilder::epoch).
+    Fenced { held: u64, current: u64 },
+    /// The log's [`Clock`](crate::Clock) could not tell the time, e.g.
+    /// because the system clock is set before the Unix epoch.
+    Clock(String),
+    /// The log's records are in the `found` format rather than the one it
+    /// was opened with; see
+    /// [`WriteAheadLog::open_as`](crate::WriteAheadLog::open_as).
+    FormatMismatch { expected: Format, found: Format },
+    /// The log's header names format version `found`, which this build,
+    /// reading and writing version `expected`, cannot open.
+    UnsupportedVersion { found: u16, expected: u16 },
+    /// Entry `id` is stored encrypted and its payload failed to decrypt:
+    /// the log was opened with another key or none, or the record was
+    /// tampered with; see
+    /// [`WriteAheadLogBuilder::encryption`](crate::WriteAheadLogBuilder::encryption).
+    Decryption { id: u64 },
+    /// A thread panicked while holding one of the log's internal locks, so
+    /// the state it guards may be half-updated. Reopen the log to recover.
+    Poisoned,
+    /// Reserved ID `id` can no longer be committed because a record with a
+    /// larger ID was written first; see
+    /// [`WriteAheadLog::reserve`](crate::WriteAheadLog::reserve).
+    Overtaken { id: u64 },
+    /// The log was opened read-only; see
+    /// [`WriteAheadLog::open_read_only`](crate::WriteAheadLog::open_read_only).
+    ReadOnly,
+}
+
+/// Convenience alias used throughout the crate.
+pub type Result<T> = std::result::Result<T, WalError>;
+
+impl fmt::Display for WalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalError::Io(err) => write!(f, "I/O error: {err}"),
+            WalError::InvalidEntry(msg) => write!(f, "invalid entry: {msg}"),
+            WalError::ChecksumMismatch { id } => write!(f, "checksum mismatch for entry {id}"),
+            WalError::LogFull => write!(f, "log is full"),
+            WalError::LimitExceeded => write!(f, "read exceeds the given limit"),
+            WalError::Serialization(msg) => write!(f, "serialization error: {msg}"),
+            WalError::InvalidConfig(msg) => write!(f, "invalid configuration: {msg}"),
+            WalError::Locked => write!(f, "log is locked by another handle"),
+            WalError::Paused => write!(f, "appends are paused"),
+            WalError::Timeout => write!(f, "operation timed out"),
+            WalError::RateLimited => write!(f, "append rate limit exceeded"),
+            WalError::Fenced { held, current } => {
+                write!(f, "writer with epoch {held} is fenced by epoch {current}")
+            }
+            WalError::Clock(msg) => write!(f, "clock error: {msg}"),
+            WalError::FormatMismatch { expected, found } => {
+                write!(f, "expected a {expected:?} log but found {found:?}")
+            }
+            WalError::UnsupportedVersion { found, expected } => {
+                write!(
+                    f,
+                    "log format version {found} is not supported; expected {expected}"
+                )
+            }
+            WalError::Decryption { id } => {
+                write!(f, "entry {id} could not be decrypted with the log's key")
+            }
+            WalError::Poisoned => write!(f, "a thread panicked while holding the log's lock"),
+            WalError::Overtaken { id } => {
+                write!(f, "reserved entry {id} was overtaken by a later record")
+            }
+            WalError::ReadOnly => write!(f, "log is open read-only"),
+            WalError::ConfigMismatch(msg) => write!(f, "configuration mismatch: {msg}"),
+            WalError::Mismatch { index, detail } => {
+                write!(f, "mismatch at entry {index}: {detail}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WalError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WalError {
+    fn from(err: io::Error) -> Self {
+        WalError::Io(err)
+    }
+}
+
+impl<T> From<PoisonError<T>> for WalError {
+    fn from(_: PoisonError<T>) -> Self {
+        WalError::Poisoned
+    }
+}
+
+impl WalError {
+    /// Whether this is a record that was read whole but whose payload fails
+    /// to decrypt or to match its checksum, which reads report rather than
+    /// skip.
+    pub(crate) fn is_damaged_payload(&self) -> bool {
+        matches!(
+            self,
+            WalError::Decryption { .. } | WalError::ChecksumMismatch { .. }
+        )
+    }
+
+    /// A copy of this error for handing to more than one caller. I/O errors
+    /// keep their kind and message but lose their source.
+    pub(crate) fn duplicate(&self) -> WalError {
+        match self {
+            WalError::Io(err) => WalError::Io(io::Error::new(err.kind(), err.to_string())),
+            WalError::InvalidEntry(msg) => WalError::InvalidEntry(msg.clone()),
+            WalError::ChecksumMismatch { id } => WalError::ChecksumMismatch { id: *id },
+            WalError::LogFull => WalError::LogFull,
+            WalError::LimitExceeded => WalError::LimitExceeded,
+            WalError::Serialization(msg) => WalError::Serialization(msg.clone()),
+            WalError::InvalidConfig(msg) => WalError::InvalidConfig(msg.clone()),
+            WalError::Mismatch { index, detail } => WalError::Mismatch {
+                index: *index,
+                detail: detail.clone(),
+            },
+            WalError::Paused => WalError::Paused,
+            WalError::Locked => WalError::Locked,
+            WalError::Timeout => WalError::Timeout,
+            WalError::ConfigMismatch(msg) => WalError::ConfigMismatch(msg.clone()),
+            WalError::RateLimited => WalError::RateLimited,
+            WalError::Fenced { held, current } => WalError::Fenced {
+                held: *held,
+                current: *current,
+            },
+            WalError::Clock(msg) => WalError::Clock(msg.clone()),
+            WalError::FormatMismatch { expected, found } => WalError::FormatMismatch {
+                expected: *expected,
+                found: *found,
+            },
+            WalError::UnsupportedVersion { found, expected } => WalError::UnsupportedVersion {
+                found: *found,
+                expected: *expected,
+            },
+            WalError::Decryption { id } => WalError::Decryption { id: *id },
+            WalError::Poisoned => WalError::Poisoned,
+            WalError::Overtaken { id } => WalError::Overtaken { id: *id },
+            WalError::ReadOnly => WalError::ReadOnly,
+        }
+    }
+}
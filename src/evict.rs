@@ -0,0 +1,65 @@
+//! Rolling logs that drop their oldest entries to stay under
+//! [`max_entries`](crate::WriteAheadLogBuilder::max_entries).
+
+use std::path::Path;
+
+use crate::entry::EntryKind;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Opens the log at `path` as a rolling buffer of the `max_entries`
+    /// most recent data entries. See
+    /// [`WriteAheadLogBuilder::evict_oldest`](crate::WriteAheadLogBuilder::evict_oldest).
+    pub fn with_rolling_window<P: AsRef<Path>>(path: P, max_entries: u64) -> Result<Self> {
+        Self::builder(path)
+            .max_entries(max_entries)
+            .evict_oldest(true)
+            .build()
+    }
+
+    /// The configured [`max_entries`](crate::WriteAheadLogBuilder::max_entries),
+    /// if any: the number of data entries past which appends are refused,
+    /// or past which the oldest are evicted with
+    /// [`evict_oldest`](crate::WriteAheadLogBuilder::evict_oldest).
+    pub fn entry_limit(&self) -> Option<u64> {
+        self.max_entries
+    }
+
+    /// Data entries evicted through this handle to stay under
+    /// [`entry_limit`](Self::entry_limit) since it was opened. The number
+    /// currently retained is [`len`](Self::len).
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Clears the oldest data entries, if evicting, so that `incoming` more
+    /// fit under the entry limit, failing with [`WalError::LogFull`] if
+    /// they would not fit in an empty log. Entries already cleared do not
+    /// count.
+    pub(crate) fn make_room(&mut self, incoming: u64) -> Result<()> {
+        let (Some(max), true) = (self.max_entries, self.evict_oldest) else {
+            return Ok(());
+        };
+        if incoming > max {
+            return Err(WalError::LogFull);
+        }
+        let live = {
+            let _file = self.lock_file()?;
+            let mut live = Vec::new();
+            for record in self.records()? {
+                let record = record?;
+                if record.kind == EntryKind::Data {
+                    live.push(record.id);
+                }
+            }
+            live
+        };
+        let excess = (live.len() as u64 + incoming).saturating_sub(max) as usize;
+        for &id in live.iter().take(excess) {
+            self.clear_id(id)?;
+        }
+        self.evicted += excess.min(live.len()) as u64;
+        Ok(())
+    }
+}
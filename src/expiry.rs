@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends `data` to expire `ttl` from now. Expired entries are still
+    /// returned by reads until [`compact_expired`](Self::compact_expired)
+    /// removes them.
+    pub fn append_with_ttl(&mut self, data: Vec<u8>, ttl: Duration) -> Result<LogEntry> {
+        self.append_record(LogEntry {
+            data,
+            expires_at: Some(self.now()?.saturating_add(ttl.as_secs())),
+            ..LogEntry::default()
+        })
+    }
+
+    /// The earliest `expires_at` in the log, so a scheduler can sleep until
+    /// the next [`compact_expired`](Self::compact_expired) is worthwhile.
+    pub fn next_expiry(&self) -> Result<Option<u64>> {
+        let _file = self.lock_file()?;
+        let mut earliest: Option<u64> = None;
+        for record in self.all_records()? {
+            if let Some(expires_at) = record?.expires_at {
+                earliest = Some(earliest.map_or(expires_at, |e| e.min(expires_at)));
+            }
+        }
+        Ok(earliest)
+    }
+
+    /// Removes every entry whose `expires_at` has been reached, returning how
+    /// many were removed. Runs as a [`compact_retain`](Self::compact_retain).
+    pub fn compact_expired(&self) -> Result<usize> {
+        let now = self.now()?;
+        self.compact_retain(|e| e.expires_at.is_none_or(|at| at > now))
+    }
+}
@@ -0,0 +1,90 @@
+//! Fencing off writers that have lost ownership of the log.
+//!
+//! The highest epoch any writer has opened the log with is kept beside it in
+//! `<path>.epoch`. A writer opening with a higher epoch takes the log over,
+//! and from then on appends, rewrites and other changes through handles
+//! holding a lower one fail.
+//!
+//! The epoch lives beside the log rather than in its header because the
+//! header is written once when a file is created and copied into every
+//! segment, and rewrites replace the files it is in. The sidecar is
+//! replaced whole by a rename, so a reader sees one epoch or the next.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+pub(crate) fn epoch_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".epoch");
+    PathBuf::from(path)
+}
+
+/// The epoch stored beside the log at `path`, or 0 if there is none.
+pub(crate) fn stored_epoch(path: &Path) -> Result<u64> {
+    match fs::read_to_string(epoch_path(path)) {
+        Ok(text) => text.trim().parse().map_err(|_| {
+            WalError::InvalidConfig(format!("`{}` does not hold an epoch", text.trim()))
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Claims the log at `path` for `epoch`, storing it unless a higher epoch
+/// already is, in which case this writer is fenced. The caller holds the
+/// lock on `<path>.lock`, so that two writers claiming at once cannot both
+/// read the old epoch and each store their own.
+pub(crate) fn claim(path: &Path, epoch: u64) -> Result<()> {
+    let current = stored_epoch(path)?;
+    if epoch < current {
+        return Err(WalError::Fenced {
+            held: epoch,
+            current,
+        });
+    }
+    if epoch > current || !epoch_path(path).exists() {
+        let sidecar = epoch_path(path);
+        let mut tmp = sidecar.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = fs::File::create(&tmp)?;
+        writeln!(file, "{epoch}")?;
+        file.sync_data()?;
+        durable::rename(&tmp, &sidecar)?;
+    }
+    Ok(())
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path` as the writer for `epoch`. See
+    /// [`WriteAheadLogBuilder::epoch`](crate::WriteAheadLogBuilder::epoch).
+    pub fn with_epoch<P: AsRef<Path>>(path: P, epoch: u64) -> Result<Self> {
+        Self::builder(path).epoch(epoch).build()
+    }
+
+    /// The epoch this handle writes under, if it was opened with one.
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    /// Fails with [`WalError::Fenced`] if another writer has since claimed
+    /// the log with a higher epoch than this handle's.
+    pub(crate) fn check_epoch(&self) -> Result<()> {
+        let Some(epoch) = self.epoch else {
+            return Ok(());
+        };
+        let current = stored_epoch(&self.path)?;
+        if epoch < current {
+            return Err(WalError::Fenced {
+                held: epoch,
+                current,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+//! An order-sensitive hash over a log's logical contents.
+
+use crate::error::Result;
+use crate::format::Format;
+use crate::wal::WriteAheadLog;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl WriteAheadLog {
+    /// A 64-bit FNV-1a hash folded over every record in order, markers and
+    /// other streams included. Logs holding the same records in the same
+    /// order share a fingerprint whatever their format, segmenting,
+    /// compression or padding; any change to a record or to the order
+    /// changes it, barring a hash collision.
+    ///
+    /// Each record is hashed in a canonical, length-prefixed encoding, so
+    /// the value is stable across versions and platforms. It is an equality
+    /// check, not a defence against deliberate tampering; see
+    /// [`verify_digests`](Self::verify_digests) for that.
+    pub fn fingerprint(&self) -> Result<u64> {
+        let _file = self.lock_file()?;
+        let mut hash = FNV_OFFSET;
+        for record in self.all_records()? {
+            for byte in Format::CompactBinary.encode(&record?) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+            }
+        }
+        Ok(hash)
+    }
+}
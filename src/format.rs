@@ -0,0 +1,763 @@
+//! On-disk encodings of a [`LogEntry`].
+//!
+//! JSON records are newline-terminated. The binary formats are
+//! length-prefixed instead, since payload bytes may contain anything. Binary
+//! bodies start with the `id`, `timestamp` and `data` fields and are followed
+//! by tagged extension fields for the optional parts of an entry, so new
+//! fields can be added without breaking old readers.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::compress::Compression;
+use crate::dictionary::Dictionary;
+use crate::encrypt::{Cipher, Encryption};
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::header;
+use crate::segment;
+
+/// How records are encoded in the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// One JSON object per line, with `data` as an array of byte values.
+    #[default]
+    Json,
+    /// Like [`Format::Json`] but with `data` as a base64 string, which is
+    /// more compact and suits tools that expect string fields. Either JSON
+    /// format reads both forms of `data`.
+    JsonBase64,
+    /// A 4-byte little-endian length prefix followed by fixed-width
+    /// little-endian `id` and `timestamp`, a 4-byte data length and the raw
+    /// payload.
+    Binary,
+    /// Like [`Format::Binary`] but with LEB128 varints for the length prefix,
+    /// `id`, `timestamp` and data length. Much smaller for logs of many short
+    /// entries.
+    CompactBinary,
+}
+
+const EXT_KIND: u8 = 1;
+const EXT_CONTENT_TYPE: u8 = 2;
+const EXT_CHECKSUM: u8 = 3;
+const EXT_PROCESSED_UP_TO: u8 = 4;
+const EXT_EXPIRES_AT: u8 = 5;
+const EXT_STREAM: u8 = 6;
+const EXT_TARGET: u8 = 7;
+const EXT_IDEMPOTENCY_KEY: u8 = 8;
+/// Present, with an empty value, when `data` is compressed against the log's
+/// dictionary.
+const EXT_DICT_COMPRESSED: u8 = 9;
+/// Zero bytes that pad a record out to the configured alignment.
+const EXT_PADDING: u8 = 10;
+const EXT_DIGEST: u8 = 11;
+/// One per tag: the varint-prefixed key followed by the value.
+const EXT_TAG: u8 = 12;
+/// Name of the codec `data` is compressed with.
+const EXT_COMPRESSION: u8 = 13;
+/// Name of the scheme `data` is encrypted with.
+const EXT_ENCRYPTION: u8 = 14;
+
+/// Formats a file can be recognised as. [`Format::Json`] stands for both JSON
+/// formats, which read each other's records.
+const RECOGNISED: [Format; 3] = [Format::Json, Format::Binary, Format::CompactBinary];
+
+impl Format {
+    /// Checks that the log at `path` was written in this format, as its
+    /// headers record it or, for files written before they did, judging by
+    /// the first record: if that does not decode in this format but does in
+    /// another, fails with [`WalError::FormatMismatch`]. An empty log passes.
+    pub(crate) fn check_file(self, path: &Path) -> Result<()> {
+        if self.check_recorded(path)? {
+            return Ok(());
+        }
+        for path in segment::all_segments(path)? {
+            if !path.exists() {
+                continue;
+            }
+            let file = File::open(&path)?;
+            if file.metadata()?.len() == header::skip(&mut BufReader::new(&file))? {
+                continue;
+            }
+            if self.decodes_first_frame(&path)? {
+                return Ok(());
+            }
+            for found in RECOGNISED {
+                if found.decodes_first_frame(&path)? {
+                    return Err(WalError::FormatMismatch {
+                        expected: self,
+                        found,
+                    });
+                }
+            }
+            return Err(WalError::InvalidEntry(format!(
+                "the first record of `{}` does not decode in any format",
+                path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks the format recorded in the header of each file of the log at
+    /// `path`, failing with [`WalError::FormatMismatch`] on one that cannot
+    /// be read in this format. Returns whether any file recorded one.
+    pub(crate) fn check_recorded(self, path: &Path) -> Result<bool> {
+        let mut recorded = false;
+        for path in segment::all_segments(path)? {
+            if !path.exists() {
+                continue;
+            }
+            if let Some(found) = header::fields(&path)?.format {
+                if !self.reads(found) {
+                    return Err(WalError::FormatMismatch {
+                        expected: self,
+                        found,
+                    });
+                }
+                recorded = true;
+            }
+        }
+        Ok(recorded)
+    }
+
+    /// Whether records written in `written` can be read in this format.
+    /// The JSON formats read each other's.
+    fn reads(self, written: Format) -> bool {
+        let json = |format| matches!(format, Format::Json | Format::JsonBase64);
+        self == written || json(self) && json(written)
+    }
+
+    /// The byte standing for this format in a file's header.
+    pub(crate) fn code(self) -> u8 {
+        match self {
+            Format::Json => 0,
+            Format::JsonBase64 => 1,
+            Format::Binary => 2,
+            Format::CompactBinary => 3,
+        }
+    }
+
+    /// Reverses [`code`](Self::code).
+    pub(crate) fn from_code(code: u8) -> Option<Format> {
+        match code {
+            0 => Some(Format::Json),
+            1 => Some(Format::JsonBase64),
+            2 => Some(Format::Binary),
+            3 => Some(Format::CompactBinary),
+            _ => None,
+        }
+    }
+
+    fn decodes_first_frame(self, path: &Path) -> Result<bool> {
+        let mut reader = BufReader::new(File::open(path)?);
+        header::skip(&mut reader)?;
+        let mut buf = Vec::new();
+        if self.read_frame(&mut reader, &mut buf)? == 0 {
+            return Ok(false);
+        }
+        Ok(self.decode_flagged(&buf).is_ok())
+    }
+
+    /// Encodes `entry` including its framing. With no cipher to hand, an
+    /// entry asking for encryption is stored in the clear.
+    pub(crate) fn encode(self, entry: &LogEntry) -> Vec<u8> {
+        let (mut stored, dict_compressed) = Self::compressed(entry, None);
+        if entry.encryption.is_some() {
+            stored.to_mut().encryption = None;
+        }
+        self.encode_flagged(&stored, dict_compressed, None)
+    }
+
+    /// Like [`encode`](Self::encode), but compresses `data` with the
+    /// entry's own [`compression`](LogEntry::compression), if any, or else
+    /// against `dictionary`, if given, whenever that makes it smaller, then
+    /// encrypts it with `cipher` if the entry asks for
+    /// [`encryption`](LogEntry::encryption), and pads the record to a
+    /// multiple of `alignment` bytes, if given. Without a cipher, an entry
+    /// asking for encryption is stored in the clear. Fails with
+    /// [`WalError::Io`] if no nonce can be drawn to encrypt it.
+    pub(crate) fn encode_with(
+        self,
+        entry: &LogEntry,
+        dictionary: Option<&Dictionary>,
+        cipher: Option<&Cipher>,
+        alignment: Option<usize>,
+    ) -> Result<Vec<u8>> {
+        let (mut stored, dict_compressed) = Self::compressed(entry, dictionary);
+        if let Some(scheme) = entry.encryption {
+            let stored = stored.to_mut();
+            match cipher {
+                Some(cipher) => stored.data = cipher.seal(scheme, entry, &stored.data)?,
+                None => stored.encryption = None,
+            }
+        }
+        Ok(self.encode_flagged(&stored, dict_compressed, alignment))
+    }
+
+    /// `entry` with its payload compressed as [`encode_with`](Self::encode_with)
+    /// describes, and whether that was against `dictionary`.
+    fn compressed<'a>(
+        entry: &'a LogEntry,
+        dictionary: Option<&Dictionary>,
+    ) -> (Cow<'a, LogEntry>, bool) {
+        let mut stored = Cow::Borrowed(entry);
+        let mut dict_compressed = false;
+        if let Some(codec) = entry.compression {
+            stored.to_mut().data = codec.compress(&entry.data);
+        } else if let Some(dictionary) = dictionary.filter(|_| !entry.data.is_empty()) {
+            // A payload zstd fails on is stored as it is, like one that
+            // would not shrink.
+            if let Some(packed) = dictionary
+                .compress(&entry.data)
+                .ok()
+                .filter(|packed| packed.len() < entry.data.len())
+            {
+                stored.to_mut().data = packed;
+                dict_compressed = true;
+            }
+        }
+        (stored, dict_compressed)
+    }
+
+    fn encode_flagged(
+        self,
+        entry: &LogEntry,
+        dict_compressed: bool,
+        alignment: Option<usize>,
+    ) -> Vec<u8> {
+        match self {
+            Format::Json | Format::JsonBase64 => {
+                let base64_data = self == Format::JsonBase64;
+                let mut line = entry.to_json(base64_data, dict_compressed).into_bytes();
+                if let Some(alignment) = alignment {
+                    // Trailing whitespace is ignored by the parser.
+                    line.resize((line.len() + 1).next_multiple_of(alignment) - 1, b' ');
+                }
+                self.framer().frame(line)
+            }
+            Format::Binary | Format::CompactBinary => {
+                let mut body = Vec::with_capacity(24 + entry.data.len());
+                if self == Format::Binary {
+                    body.extend_from_slice(&entry.id.to_le_bytes());
+                    body.extend_from_slice(&entry.timestamp.to_le_bytes());
+                    body.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+                } else {
+                    write_varint(&mut body, entry.id);
+                    write_varint(&mut body, entry.timestamp);
+                    write_varint(&mut body, entry.data.len() as u64);
+                }
+                body.extend_from_slice(&entry.data);
+                write_extensions(&mut body, entry, dict_compressed);
+                if let Some(alignment) = alignment {
+                    self.pad_body(&mut body, alignment);
+                }
+                self.framer().frame(body)
+            }
+        }
+    }
+
+    /// Size of the length prefix framing a binary body of `len` bytes.
+    fn prefix_len(self, len: usize) -> usize {
+        self.framer().overhead(len)
+    }
+
+    /// Appends a padding extension to a binary `body` so that the framed
+    /// record is a multiple of `alignment` bytes. The padding's own header
+    /// and, for varint framing, the prefix grow with it, so the smallest
+    /// boundary that can be hit exactly is searched for.
+    fn pad_body(self, body: &mut Vec<u8>, alignment: usize) {
+        let framed = self.prefix_len(body.len()) + body.len();
+        if framed.is_multiple_of(alignment) {
+            return;
+        }
+        let mut target = framed.next_multiple_of(alignment);
+        loop {
+            for (prefix, header) in (1..=10).flat_map(|p| (2..=11).map(move |h| (p, h))) {
+                let Some(zeros) = target.checked_sub(prefix + body.len() + header) else {
+                    continue;
+                };
+                let padded = body.len() + header + zeros;
+                if 1 + varint_len(zeros as u64) == header && self.prefix_len(padded) == prefix {
+                    write_ext(body, EXT_PADDING, &vec![0; zeros]);
+                    return;
+                }
+            }
+            target += alignment;
+        }
+    }
+
+    /// Reads the next record body into `buf` through this format's
+    /// [`Framer`](crate::frame::Framer), returning the number of bytes
+    /// consumed including framing, or 0 at the end of the input.
+    pub(crate) fn read_frame<R: BufRead>(
+        self,
+        reader: &mut R,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize> {
+        self.framer().read_frame(reader, buf)
+    }
+
+    /// Decodes a record body produced by [`read_frame`](Self::read_frame),
+    /// decrypting `data` with `cipher` if it was written encrypted, then
+    /// decompressing it with its codec or against `dictionary` if it was
+    /// written compressed. A record compressed against the dictionary
+    /// cannot be decoded without it, and one that fails to decrypt fails
+    /// with [`WalError::Decryption`].
+    pub(crate) fn decode_with(
+        self,
+        body: &[u8],
+        dictionary: Option<&Dictionary>,
+        cipher: Option<&Cipher>,
+    ) -> Result<LogEntry> {
+        let (mut entry, dict_compressed) = self.decode_flagged(body)?;
+        if let Some(scheme) = entry.encryption {
+            entry.data = cipher
+                .and_then(|cipher| cipher.open(scheme, &entry, &entry.data))
+                .ok_or(WalError::Decryption { id: entry.id })?;
+        }
+        if dict_compressed {
+            let dictionary = dictionary.ok_or_else(|| {
+                WalError::InvalidEntry("record needs the log's compression dictionary".to_string())
+            })?;
+            entry.data = dictionary.decompress(&entry.data)?;
+        }
+        if let Some(codec) = entry.compression {
+            entry.data = codec.decompress(&entry.data)?;
+        }
+        Ok(entry)
+    }
+
+    /// Whether a record body read by [`read_frame`](Self::read_frame) holds
+    /// a data entry of stream 0, told from the fields that mark anything
+    /// else without decoding the record. Blank JSON lines are not records;
+    /// other damage goes unnoticed, so a damaged record counts as data.
+    pub(crate) fn is_data_frame(self, body: &[u8]) -> bool {
+        match self {
+            Format::Json | Format::JsonBase64 => {
+                if body.iter().all(u8::is_ascii_whitespace) {
+                    return false;
+                }
+                // Top-level fields follow a comma and any quote inside a
+                // string is escaped, so only tag keys could look like these;
+                // tags come after both.
+                let fields = find(body, b",\"tags\":{").map_or(body, |at| &body[..at]);
+                find(fields, b",\"kind\":").is_none() && find(fields, b",\"stream\":").is_none()
+            }
+            Format::Binary | Format::CompactBinary => {
+                let mut cur = Cursor(body);
+                let header = if self == Format::Binary {
+                    cur.take(16)
+                        .and_then(|_| cur.array::<4>())
+                        .map(|len| u32::from_le_bytes(len) as usize)
+                } else {
+                    cur.varint()
+                        .and_then(|_| cur.varint())
+                        .and_then(|_| cur.varint())
+                        .map(|len| len as usize)
+                };
+                let Ok(()) = header.and_then(|len| cur.take(len).map(drop)) else {
+                    return true;
+                };
+                while let Ok(&[tag]) = cur.take(1) {
+                    if tag == EXT_KIND || tag == EXT_STREAM {
+                        return false;
+                    }
+                    let Ok(len) = cur.varint() else {
+                        break;
+                    };
+                    if cur.take(len as usize).is_err() {
+                        break;
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// The ID of a record body read by [`read_frame`](Self::read_frame),
+    /// taken from the start of the record without decoding the rest, or
+    /// `None` if it is not where this crate writes it.
+    pub(crate) fn frame_id(self, body: &[u8]) -> Option<u64> {
+        match self {
+            Format::Json | Format::JsonBase64 => {
+                let rest = body.strip_prefix(b"{\"id\":")?;
+                let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                std::str::from_utf8(&rest[..digits]).ok()?.parse().ok()
+            }
+            Format::Binary => Some(u64::from_le_bytes(Cursor(body).array().ok()?)),
+            Format::CompactBinary => Cursor(body).varint().ok(),
+        }
+    }
+
+    /// Decodes a record body read by [`read_frame`](Self::read_frame) into
+    /// `data` if it is a data entry of stream 0, returning its ID, timestamp
+    /// and stored checksum, or `None` for any other record. Records with no optional
+    /// fields besides a checksum, and for JSON with `data` as an array, are
+    /// decoded in place without allocating once `data` is large enough;
+    /// the rest are decoded in full and copied.
+    pub(crate) fn decode_data_into(
+        self,
+        body: &[u8],
+        dictionary: Option<&Dictionary>,
+        cipher: Option<&Cipher>,
+        data: &mut Vec<u8>,
+    ) -> Result<Option<(u64, u64, u32)>> {
+        let plain = match self {
+            Format::Json | Format::JsonBase64 => decode_json_array_into(body, data),
+            Format::Binary | Format::CompactBinary => self.decode_binary_into(body, data)?,
+        };
+        if let Some(found) = plain {
+            return Ok(found);
+        }
+        let entry = self.decode_with(body, dictionary, cipher)?;
+        data.clear();
+        if entry.stream != 0 || entry.kind != EntryKind::Data {
+            return Ok(None);
+        }
+        data.extend_from_slice(&entry.data);
+        Ok(Some((entry.id, entry.timestamp, entry.checksum)))
+    }
+
+    /// The in-place half of [`decode_data_into`](Self::decode_data_into)
+    /// for the binary formats, or `None` if the record has to be decoded in
+    /// full.
+    fn decode_binary_into(
+        self,
+        body: &[u8],
+        data: &mut Vec<u8>,
+    ) -> Result<Option<Option<(u64, u64, u32)>>> {
+        let mut cur = Cursor(body);
+        let (id, timestamp, len) = if self == Format::Binary {
+            let id = u64::from_le_bytes(cur.array()?);
+            let timestamp = u64::from_le_bytes(cur.array()?);
+            (id, timestamp, u32::from_le_bytes(cur.array()?) as usize)
+        } else {
+            (cur.varint()?, cur.varint()?, cur.varint()? as usize)
+        };
+        let payload = cur.take(len)?;
+        let mut other = false;
+        let mut checksum = 0;
+        while !cur.0.is_empty() {
+            let tag = cur.take(1)?[0];
+            let len = cur.varint()? as usize;
+            let value = cur.take(len)?;
+            match tag {
+                EXT_CHECKSUM if len == 4 => checksum = u32::from_le_bytes(fixed(value)?),
+                EXT_STREAM if len == 4 => other = true,
+                EXT_KIND
+                    if std::str::from_utf8(value).is_ok_and(|k| EntryKind::parse(k).is_some()) =>
+                {
+                    other = true;
+                }
+                EXT_PADDING => {}
+                _ => return Ok(None),
+            }
+        }
+        data.clear();
+        if other {
+            return Ok(Some(None));
+        }
+        data.extend_from_slice(payload);
+        Ok(Some(Some((id, timestamp, checksum))))
+    }
+
+    pub(crate) fn decode_flagged(self, body: &[u8]) -> Result<(LogEntry, bool)> {
+        match self {
+            Format::Json | Format::JsonBase64 => {
+                let text = std::str::from_utf8(body)
+                    .map_err(|_| WalError::InvalidEntry("record is not valid UTF-8".to_string()))?;
+                LogEntry::from_json(text)
+            }
+            Format::Binary => {
+                let mut cur = Cursor(body);
+                let id = u64::from_le_bytes(cur.array()?);
+                let timestamp = u64::from_le_bytes(cur.array()?);
+                let len = u32::from_le_bytes(cur.array()?) as usize;
+                let data = cur.take(len)?.to_vec();
+                read_extensions(cur, id, timestamp, data)
+            }
+            Format::CompactBinary => {
+                let mut cur = Cursor(body);
+                let id = cur.varint()?;
+                let timestamp = cur.varint()?;
+                let len = cur.varint()? as usize;
+                let data = cur.take(len)?.to_vec();
+                read_extensions(cur, id, timestamp, data)
+            }
+        }
+    }
+}
+
+/// Encodes a [`LogEntry`] as one record, framing included, and decodes it
+/// back, as the log does on disk. Implemented by each [`Format`], so a
+/// record can be built or read outside a log, e.g. to ship it elsewhere.
+pub trait WalFormat {
+    /// Encodes `entry` as one record, compressed with its own
+    /// [`compression`](LogEntry::compression) if it has one. An entry
+    /// asking for [`encryption`](LogEntry::encryption) is stored in the
+    /// clear, since there is no key to seal it with.
+    fn encode(&self, entry: &LogEntry) -> Vec<u8>;
+
+    /// Decodes `record`, one whole record as [`encode`](Self::encode)
+    /// produces. Fails with [`WalError::InvalidEntry`] if it is damaged, cut
+    /// short or followed by anything, or was compressed against a log's
+    /// dictionary.
+    fn decode(&self, record: &[u8]) -> Result<LogEntry>;
+}
+
+impl WalFormat for Format {
+    fn encode(&self, entry: &LogEntry) -> Vec<u8> {
+        Format::encode(*self, entry)
+    }
+
+    fn decode(&self, record: &[u8]) -> Result<LogEntry> {
+        let mut reader = record;
+        let mut body = Vec::new();
+        match self.read_frame(&mut reader, &mut body)? {
+            0 => Err(WalError::InvalidEntry("record is incomplete".to_string())),
+            len if len < record.len() => Err(WalError::InvalidEntry(format!(
+                "{} bytes follow the record",
+                record.len() - len
+            ))),
+            _ => self.decode_with(&body, None, None),
+        }
+    }
+}
+
+/// The in-place half of [`Format::decode_data_into`] for JSON: decodes a
+/// record written compactly with `data` as an array and no optional field
+/// but a checksum, or returns `None` for anything else.
+fn decode_json_array_into(body: &[u8], data: &mut Vec<u8>) -> Option<Option<(u64, u64, u32)>> {
+    let rest = body.strip_prefix(b"{\"id\":")?;
+    let (id, rest) = json_u64(rest)?;
+    let rest = rest.strip_prefix(b",\"timestamp\":")?;
+    let (timestamp, rest) = json_u64(rest)?;
+    let mut rest = rest.strip_prefix(b",\"data\":[")?;
+    data.clear();
+    if let Some(after) = rest.strip_prefix(b"]") {
+        rest = after;
+    } else {
+        loop {
+            let (byte, after) = json_u64(rest)?;
+            data.push(u8::try_from(byte).ok()?);
+            match after.split_first()? {
+                (b',', after) => rest = after,
+                (b']', after) => {
+                    rest = after;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+    }
+    let mut checksum = 0;
+    if let Some(after) = rest.strip_prefix(b",\"checksum\":") {
+        let (value, after) = json_u64(after)?;
+        checksum = u32::try_from(value).ok()?;
+        rest = after;
+    }
+    // Alignment pads the line with trailing spaces.
+    let rest = rest.strip_prefix(b"}")?;
+    rest.iter()
+        .all(|&b| b == b' ' || b == b'\r')
+        .then_some(Some((id, timestamp, checksum)))
+}
+
+/// Parses the unsigned integer at the start of `input` as this crate writes
+/// it, with no sign, fraction or leading zeros.
+fn json_u64(input: &[u8]) -> Option<(u64, &[u8])> {
+    let digits = input.iter().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 0 || (digits > 1 && input[0] == b'0') {
+        return None;
+    }
+    let mut value = 0u64;
+    for &digit in &input[..digits] {
+        value = value
+            .checked_mul(10)?
+            .checked_add(u64::from(digit - b'0'))?;
+    }
+    Some((value, &input[digits..]))
+}
+
+fn write_extensions(out: &mut Vec<u8>, entry: &LogEntry, dict_compressed: bool) {
+    if entry.kind != EntryKind::Data {
+        write_ext(out, EXT_KIND, entry.kind.as_str().as_bytes());
+    }
+    if let Some(content_type) = &entry.content_type {
+        write_ext(out, EXT_CONTENT_TYPE, content_type.as_bytes());
+    }
+    if entry.checksum != 0 {
+        write_ext(out, EXT_CHECKSUM, &entry.checksum.to_le_bytes());
+    }
+    if let Some(processed) = entry.processed_up_to {
+        write_ext(out, EXT_PROCESSED_UP_TO, &processed.to_le_bytes());
+    }
+    if let Some(expires_at) = entry.expires_at {
+        write_ext(out, EXT_EXPIRES_AT, &expires_at.to_le_bytes());
+    }
+    if entry.stream != 0 {
+        write_ext(out, EXT_STREAM, &entry.stream.to_le_bytes());
+    }
+    if let Some(target) = entry.target {
+        write_ext(out, EXT_TARGET, &target.to_le_bytes());
+    }
+    if let Some(key) = &entry.idempotency_key {
+        write_ext(out, EXT_IDEMPOTENCY_KEY, key.as_bytes());
+    }
+    if !entry.digest.is_empty() {
+        write_ext(out, EXT_DIGEST, &entry.digest);
+    }
+    for (key, value) in &entry.tags {
+        let mut tag = Vec::with_capacity(1 + key.len() + value.len());
+        write_varint(&mut tag, key.len() as u64);
+        tag.extend_from_slice(key.as_bytes());
+        tag.extend_from_slice(value.as_bytes());
+        write_ext(out, EXT_TAG, &tag);
+    }
+    if let Some(compression) = entry.compression {
+        write_ext(out, EXT_COMPRESSION, compression.as_str().as_bytes());
+    }
+    if let Some(encryption) = entry.encryption {
+        write_ext(out, EXT_ENCRYPTION, encryption.as_str().as_bytes());
+    }
+    if dict_compressed {
+        write_ext(out, EXT_DICT_COMPRESSED, &[]);
+    }
+}
+
+fn write_ext(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value);
+}
+
+fn read_extensions(
+    mut cur: Cursor<'_>,
+    id: u64,
+    timestamp: u64,
+    data: Vec<u8>,
+) -> Result<(LogEntry, bool)> {
+    let mut dict_compressed = false;
+    let mut entry = LogEntry {
+        id,
+        timestamp,
+        data,
+        ..LogEntry::default()
+    };
+    while !cur.0.is_empty() {
+        let tag = cur.take(1)?[0];
+        let len = cur.varint()? as usize;
+        let value = cur.take(len)?;
+        match tag {
+            EXT_KIND => {
+                let name = std::str::from_utf8(value).unwrap_or_default();
+                entry.kind = EntryKind::parse(name).ok_or_else(|| {
+                    WalError::InvalidEntry(format!("unknown entry kind `{name}`"))
+                })?;
+            }
+            EXT_CONTENT_TYPE => {
+                let content_type = std::str::from_utf8(value)
+                    .map_err(|_| WalError::InvalidEntry("content type is not UTF-8".to_string()))?;
+                entry.content_type = Some(content_type.to_string());
+            }
+            EXT_CHECKSUM => entry.checksum = u32::from_le_bytes(fixed(value)?),
+            EXT_PROCESSED_UP_TO => entry.processed_up_to = Some(u64::from_le_bytes(fixed(value)?)),
+            EXT_EXPIRES_AT => entry.expires_at = Some(u64::from_le_bytes(fixed(value)?)),
+            EXT_STREAM => entry.stream = u32::from_le_bytes(fixed(value)?),
+            EXT_TARGET => entry.target = Some(u64::from_le_bytes(fixed(value)?)),
+            EXT_IDEMPOTENCY_KEY => {
+                let key = std::str::from_utf8(value).map_err(|_| {
+                    WalError::InvalidEntry("idempotency key is not UTF-8".to_string())
+                })?;
+                entry.idempotency_key = Some(key.to_string());
+            }
+            EXT_COMPRESSION => {
+                let name = std::str::from_utf8(value).unwrap_or_default();
+                entry.compression = Some(Compression::parse(name).ok_or_else(|| {
+                    WalError::InvalidEntry(format!("unknown compression `{name}`"))
+                })?);
+            }
+            EXT_ENCRYPTION => {
+                let name = std::str::from_utf8(value).unwrap_or_default();
+                entry.encryption = Some(Encryption::parse(name).ok_or_else(|| {
+                    WalError::InvalidEntry(format!("unknown encryption `{name}`"))
+                })?);
+            }
+            EXT_DICT_COMPRESSED => dict_compressed = true,
+            EXT_PADDING => {}
+            EXT_DIGEST => entry.digest = value.to_vec(),
+            EXT_TAG => {
+                let mut tag = Cursor(value);
+                let key_len = tag.varint()? as usize;
+                let key = tag.take(key_len)?;
+                let (Ok(key), Ok(value)) = (std::str::from_utf8(key), std::str::from_utf8(tag.0))
+                else {
+                    return Err(WalError::InvalidEntry("tag is not UTF-8".to_string()));
+                };
+                entry.tags.insert(key.to_string(), value.to_string());
+            }
+            // Fields added by later versions are skipped.
+            _ => {}
+        }
+    }
+    Ok((entry, dict_compressed))
+}
+
+fn fixed<const N: usize>(value: &[u8]) -> Result<[u8; N]> {
+    value
+        .try_into()
+        .map_err(|_| WalError::InvalidEntry("extension field has the wrong size".to_string()))
+}
+
+/// Bounds-checked reads from a record body.
+pub(crate) struct Cursor<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(WalError::InvalidEntry("record is truncated".to_string()));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        fixed(self.take(N)?)
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(WalError::InvalidEntry("varint is too long".to_string()))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+pub(crate) fn varint_len(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+/// Appends `value` as an unsigned LEB128 varint.
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
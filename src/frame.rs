@@ -0,0 +1,179 @@
+//! How record bodies are delimited in a file.
+//!
+//! Each [`Format`] picks a [`Framer`], which is the only place that knows
+//! where one record ends and the next begins. Readers go through it, never
+//! splitting the file themselves, so a body's own bytes cannot be mistaken
+//! for a boundary: line framing relies on the JSON encoder escaping every
+//! newline inside a record, and the binary framings carry the body's length
+//! up front and so allow any byte in it.
+
+use std::io::{self, BufRead, Read};
+
+use crate::format::{varint_len, write_varint, Format};
+
+/// A way of delimiting record bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framer {
+    /// The body followed by `\n`, which the body may not contain.
+    Line,
+    /// A 4-byte little-endian length followed by the body.
+    LengthPrefixed,
+    /// A LEB128 varint length followed by the body.
+    VarintPrefixed,
+}
+
+impl Format {
+    /// How records of this format are delimited.
+    pub(crate) fn framer(self) -> Framer {
+        match self {
+            Format::Json | Format::JsonBase64 => Framer::Line,
+            Format::Binary => Framer::LengthPrefixed,
+            Format::CompactBinary => Framer::VarintPrefixed,
+        }
+    }
+}
+
+impl Framer {
+    /// Frames `body` as one record.
+    pub(crate) fn frame(self, mut body: Vec<u8>) -> Vec<u8> {
+        match self {
+            Framer::Line => {
+                debug_assert!(
+                    !body.contains(&b'\n'),
+                    "line-framed record contains a bare delimiter"
+                );
+                body.push(b'\n');
+                body
+            }
+            Framer::LengthPrefixed | Framer::VarintPrefixed => {
+                let mut out = Vec::with_capacity(self.overhead(body.len()) + body.len());
+                if self == Framer::LengthPrefixed {
+                    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+                } else {
+                    write_varint(&mut out, body.len() as u64);
+                }
+                out.extend_from_slice(&body);
+                out
+            }
+        }
+    }
+
+    /// Bytes the framing adds to a body of `len` bytes.
+    pub(crate) fn overhead(self, len: usize) -> usize {
+        match self {
+            Framer::Line => 1,
+            Framer::LengthPrefixed => 4,
+            Framer::VarintPrefixed => varint_len(len as u64),
+        }
+    }
+
+    /// Reads exactly one record body into `buf`, whatever bytes it holds,
+    /// returning the number of bytes consumed including framing, or 0 at
+    /// the end of the input. A frame cut short by the end of the file also
+    /// counts as the end, since it can only be a torn final write.
+    pub(crate) fn read_frame<R: BufRead>(
+        self,
+        reader: &mut R,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<usize> {
+        buf.clear();
+        match self {
+            Framer::Line => {
+                let read = reader.read_until(b'\n', buf)?;
+                if buf.last() == Some(&b'\n') {
+                    buf.pop();
+                }
+                Ok(read)
+            }
+            Framer::LengthPrefixed => {
+                let mut len = [0u8; 4];
+                if !read_exact_or_eof(reader, &mut len)? {
+                    return Ok(0);
+                }
+                let body = read_body(reader, u32::from_le_bytes(len) as u64, buf)?;
+                Ok(body.map_or(0, |n| n + 4))
+            }
+            Framer::VarintPrefixed => {
+                let Some((len, prefix)) = read_varint_from(reader)? else {
+                    return Ok(0);
+                };
+                let body = read_body(reader, len, buf)?;
+                Ok(body.map_or(0, |n| n + prefix))
+            }
+        }
+    }
+
+    /// Finds the first record in `bytes`, which start at a record boundary,
+    /// returning where its body lies in them and the bytes it takes up
+    /// including framing, or `None` if they hold no complete frame. Agrees
+    /// with [`read_frame`](Self::read_frame) on the same bytes, so a final
+    /// line without its `\n` still counts as a record.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn split_frame(
+        self,
+        bytes: &[u8],
+    ) -> io::Result<Option<(std::ops::Range<usize>, usize)>> {
+        let (prefix, len) = match self {
+            Framer::Line => {
+                if bytes.is_empty() {
+                    return Ok(None);
+                }
+                return Ok(Some(match bytes.iter().position(|&b| b == b'\n') {
+                    Some(end) => (0..end, end + 1),
+                    None => (0..bytes.len(), bytes.len()),
+                }));
+            }
+            Framer::LengthPrefixed => {
+                let Some(len) = bytes.get(..4) else {
+                    return Ok(None);
+                };
+                (4, u32::from_le_bytes(len.try_into().unwrap()) as u64)
+            }
+            Framer::VarintPrefixed => match read_varint_from(&mut &bytes[..])? {
+                Some((len, prefix)) => (prefix, len),
+                None => return Ok(None),
+            },
+        };
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| prefix.checked_add(len))
+            .filter(|&end| end <= bytes.len());
+        Ok(end.map(|end| (prefix..end, end)))
+    }
+}
+
+/// Reads a varint length prefix and its encoded size, returning `None` at a
+/// clean or torn end of input.
+fn read_varint_from<R: Read>(reader: &mut R) -> io::Result<Option<(u64, usize)>> {
+    let mut value = 0u64;
+    for (i, shift) in (0..64).step_by(7).enumerate() {
+        let mut byte = [0u8; 1];
+        if !read_exact_or_eof(reader, &mut byte)? {
+            return Ok(None);
+        }
+        value |= u64::from(byte[0] & 0x7F) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "varint is too long",
+    ))
+}
+
+/// Reads a `len`-byte body, returning its size, or `None` if the input ended
+/// first.
+fn read_body<R: Read>(reader: &mut R, len: u64, buf: &mut Vec<u8>) -> io::Result<Option<usize>> {
+    let read = reader.take(len).read_to_end(buf)?;
+    Ok((read as u64 == len).then_some(read))
+}
+
+/// Fills `buf` completely, returning `false` if the input ended first.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,78 @@
+//! Pausing appends for maintenance windows.
+
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// What [`append`](WriteAheadLog::append) does while the log is paused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PauseMode {
+    /// Fail with [`WalError::Paused`].
+    #[default]
+    Error,
+    /// Wait until the log is resumed. Resuming has to happen through an
+    /// [`AppendGate`] held by another thread.
+    Block,
+}
+
+/// A cloneable handle for pausing and resuming a log's appends from any
+/// thread, including while an append is blocked on it.
+#[derive(Debug, Clone, Default)]
+pub struct AppendGate {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl AppendGate {
+    /// Stops appends until [`resume`](Self::resume) is called.
+    pub fn pause(&self) {
+        *self.inner.0.lock().unwrap_or_else(PoisonError::into_inner) = true;
+    }
+
+    /// Lets appends through again, waking any that are blocked.
+    pub fn resume(&self) {
+        *self.inner.0.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        self.inner.1.notify_all();
+    }
+
+    /// Whether appends are currently paused.
+    pub fn is_paused(&self) -> bool {
+        *self.inner.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns once appends may proceed, or fails if paused in
+    /// [`PauseMode::Error`].
+    pub(crate) fn pass(&self, mode: PauseMode) -> Result<()> {
+        let (paused, resumed) = &*self.inner;
+        let mut paused = paused.lock().unwrap_or_else(PoisonError::into_inner);
+        match mode {
+            PauseMode::Error if *paused => Err(WalError::Paused),
+            PauseMode::Error => Ok(()),
+            PauseMode::Block => {
+                while *paused {
+                    paused = resumed.wait(paused).unwrap_or_else(PoisonError::into_inner);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Stops appends until [`resume`](Self::resume); what they do meanwhile
+    /// is set by [`pause_mode`](crate::WriteAheadLogBuilder::pause_mode).
+    /// Reads are unaffected.
+    pub fn pause(&self) {
+        self.gate.pause();
+    }
+
+    /// Lets appends through again.
+    pub fn resume(&self) {
+        self.gate.resume();
+    }
+
+    /// A handle for pausing and resuming this log from other threads.
+    pub fn append_gate(&self) -> AppendGate {
+        self.gate.clone()
+    }
+}
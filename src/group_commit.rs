@@ -0,0 +1,216 @@
+//! Group commit: appends queue in memory and reach the disk together, with a
+//! single `sync_data`, when the log is flushed.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+/// Run once a record is known to be durable.
+pub(crate) type OnDurable = Box<dyn FnOnce(&LogEntry) + Send>;
+
+/// A queued group-commit record.
+pub(crate) struct Pending {
+    /// The record to write. Its `id` is only meaningful once assigned.
+    pub(crate) entry: LogEntry,
+    /// Set for [`append_deferred`](WriteAheadLog::append_deferred) records,
+    /// which receive their ID at flush time.
+    pub(crate) slot: Option<Arc<OnceLock<Option<u64>>>>,
+    /// Whether `entry.id` has been assigned.
+    pub(crate) assigned: bool,
+    /// Behind a mutex only so that the log stays `Sync`; it is never
+    /// contended.
+    pub(crate) on_durable: Option<Mutex<OnDurable>>,
+}
+
+impl fmt::Debug for Pending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pending")
+            .field("entry", &self.entry)
+            .field("assigned", &self.assigned)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The outcome of an [`append_deferred`](WriteAheadLog::append_deferred)
+/// record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingState {
+    /// Not yet written.
+    Queued,
+    /// Written and synced with this ID.
+    Durable(u64),
+    /// Dropped, or not known to be durable, because the flush that would
+    /// have written it failed.
+    Discarded,
+}
+
+/// Handle to a record appended with
+/// [`append_deferred`](WriteAheadLog::append_deferred) that resolves to its
+/// real ID once flushed.
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    slot: Arc<OnceLock<Option<u64>>>,
+}
+
+impl PendingEntry {
+    /// Current state of the record.
+    pub fn state(&self) -> PendingState {
+        match self.slot.get() {
+            None => PendingState::Queued,
+            Some(Some(id)) => PendingState::Durable(*id),
+            Some(None) => PendingState::Discarded,
+        }
+    }
+
+    /// The record's ID once it is durable.
+    pub fn id(&self) -> Option<u64> {
+        self.slot.get().copied().flatten()
+    }
+}
+
+impl WriteAheadLog {
+    /// Queues `data` without giving it an ID; the ID is assigned when the
+    /// record is actually written by [`flush`](Self::flush).
+    ///
+    /// With [`group_commit`](crate::WriteAheadLogBuilder::group_commit),
+    /// plain [`append`](Self::append) hands out IDs immediately, so if a
+    /// flush fails the IDs of the records it dropped are never used and the
+    /// durable sequence has a gap. Deferred records trade knowing the ID up
+    /// front for a gap-free durable sequence: a failed flush discards them
+    /// before they consume an ID. Appending an eager record while deferred
+    /// ones are queued assigns their IDs first, to keep IDs ascending in file
+    /// order.
+    ///
+    /// Without group commit the record is written at once and the handle is
+    /// already resolved.
+    pub fn append_deferred(&mut self, data: Vec<u8>) -> Result<PendingEntry> {
+        self.check_writable()?;
+        let slot = Arc::new(OnceLock::new());
+        let entry = LogEntry {
+            data,
+            timestamp: self.now()?,
+            ..LogEntry::default()
+        };
+        if self.group_commit {
+            self.gate.pass(self.pause_mode)?;
+            self.queue.push(Pending {
+                entry,
+                slot: Some(Arc::clone(&slot)),
+                assigned: false,
+                on_durable: None,
+            });
+        } else {
+            let entry = self.append_record(entry)?;
+            let _ = slot.set(Some(entry.id));
+        }
+        Ok(PendingEntry { slot })
+    }
+
+    /// Writes every queued group-commit record and syncs the file once.
+    /// Handles and [`append_then`](Self::append_then) callbacks resolve only
+    /// after that sync.
+    ///
+    /// If a write fails, the file is cut back to the end of the last record
+    /// written, those written so far are synced, and every remaining queued
+    /// record is discarded before the error is returned. When nothing is
+    /// queued, only writes out the
+    /// [`write_buffer`](crate::WriteAheadLogBuilder::write_buffer), if any,
+    /// without syncing.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.queue.is_empty() {
+            return self.drain_write_buffer(&*self.file.lock()?);
+        }
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        let mut queued = std::mem::take(&mut self.queue).into_iter();
+        let mut result = Ok(());
+        let mut truncate_to = None;
+        let mut written = Vec::new();
+        for mut item in queued.by_ref() {
+            if item.slot.is_some() && !item.assigned {
+                self.assign(&mut item.entry);
+                item.assigned = true;
+            }
+            let len = file.metadata()?.len();
+            match self.write_record(&mut file, &item.entry) {
+                Ok(_) => written.push(item),
+                Err(err) => {
+                    truncate_to = Some(len);
+                    if item.slot.is_some() && item.entry.id + 1 == self.current_id {
+                        // Hand the ID back so no later record skips over it.
+                        self.current_id -= 1;
+                    }
+                    item.resolve(None);
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        queued.for_each(|item| item.resolve(None));
+        if let Some(len) = truncate_to {
+            file.set_len(len)?;
+        }
+        let up_to = written
+            .iter()
+            .map(|item| item.entry.id + 1)
+            .max()
+            .unwrap_or(0);
+        if let Err(err) = self.sync_file(&file, up_to) {
+            written.iter().for_each(|item| item.resolve(None));
+            return Err(err);
+        }
+        drop(file);
+        let forwarded = self.forward(written.iter().map(|item| &item.entry));
+        for mut item in written {
+            item.resolve(Some(item.entry.id));
+            if let Some(on_durable) = item.on_durable.take() {
+                on_durable
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)(&item.entry);
+            }
+        }
+        result.and(forwarded)
+    }
+
+    /// Appends `data` and calls `on_durable` with the entry once it has been
+    /// synced to disk: straight after the write normally, or after the
+    /// shared sync of the [`flush`](Self::flush) that writes it under group
+    /// commit. The callback is dropped uncalled if that flush fails.
+    pub fn append_then<F>(&mut self, data: Vec<u8>, on_durable: F) -> Result<LogEntry>
+    where
+        F: FnOnce(&LogEntry) + Send + 'static,
+    {
+        let entry = self.append(data)?;
+        if self.group_commit {
+            if let Some(item) = self.queue.last_mut() {
+                item.on_durable = Some(Mutex::new(Box::new(on_durable)));
+            }
+        } else {
+            self.sync_file(&*self.file.lock()?, self.current_id)?;
+            on_durable(&entry);
+        }
+        Ok(entry)
+    }
+
+    /// Gives queued deferred records their IDs, so an eagerly numbered record
+    /// appended after them still sorts after them.
+    pub(crate) fn assign_deferred(&mut self) {
+        let mut queue = std::mem::take(&mut self.queue);
+        for item in queue.iter_mut().filter(|item| !item.assigned) {
+            self.assign(&mut item.entry);
+            item.assigned = true;
+        }
+        self.queue = queue;
+    }
+}
+
+impl Pending {
+    fn resolve(&self, id: Option<u64>) {
+        if let Some(slot) = &self.slot {
+            let _ = slot.set(id);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Pluggable digests for integrity checks stronger than the built-in CRC-32.
+//!
+//! The name of the algorithm a log is written with is kept beside it in
+//! `<path>.hasher`, so that reopening it with a different one is caught
+//! instead of every record failing to verify.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::callback::Callback;
+use crate::checksum;
+use crate::durable;
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// A digest algorithm for [`LogEntry::digest`], chosen with
+/// [`WriteAheadLogBuilder::hasher`](crate::WriteAheadLogBuilder::hasher).
+pub trait Hasher: Send + Sync {
+    /// Identifies the algorithm. It is recorded with the log and must be the
+    /// same whenever the log is opened.
+    fn name(&self) -> &str;
+
+    /// Computes the digest of `bytes`.
+    fn digest(&self, bytes: &[u8]) -> Vec<u8>;
+}
+
+/// CRC-32 (IEEE 802.3), big-endian. Fast, but only guards against accidental
+/// corruption.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc32;
+
+impl Hasher for Crc32 {
+    fn name(&self) -> &str {
+        "crc32"
+    }
+
+    fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut crc = checksum::Crc32::new();
+        crc.update(bytes);
+        crc.finish().to_be_bytes().to_vec()
+    }
+}
+
+/// XXH64 with seed 0, big-endian. Much stronger than CRC-32 at a similar
+/// speed, though not cryptographic.
+#[cfg(feature = "xxhash")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XxHash64;
+
+#[cfg(feature = "xxhash")]
+impl Hasher for XxHash64 {
+    fn name(&self) -> &str {
+        "xxhash64"
+    }
+
+    fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        crate::xxhash::xxh64(bytes, 0).to_be_bytes().to_vec()
+    }
+}
+
+/// SHA-256, for when records must be protected against deliberate
+/// tampering as well as corruption.
+#[cfg(feature = "sha256")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+#[cfg(feature = "sha256")]
+impl Hasher for Sha256 {
+    fn name(&self) -> &str {
+        "sha256"
+    }
+
+    fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        crate::sha256::sha256(bytes).to_vec()
+    }
+}
+
+/// The built-in hasher called `name`, if it is compiled in.
+fn builtin(name: &str) -> Option<Arc<dyn Hasher>> {
+    match name {
+        "crc32" => Some(Arc::new(Crc32)),
+        #[cfg(feature = "xxhash")]
+        "xxhash64" => Some(Arc::new(XxHash64)),
+        #[cfg(feature = "sha256")]
+        "sha256" => Some(Arc::new(Sha256)),
+        _ => None,
+    }
+}
+
+impl LogEntry {
+    /// Computes the digest of this entry's `id`, `timestamp` and `data` with
+    /// `hasher`.
+    pub fn compute_digest(&self, hasher: &dyn Hasher) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.data.len());
+        bytes.extend_from_slice(&self.id.to_le_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        hasher.digest(&bytes)
+    }
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path`, giving every appended record a digest from
+    /// `hasher`. See
+    /// [`WriteAheadLogBuilder::hasher`](crate::WriteAheadLogBuilder::hasher).
+    pub fn with_checksum<P, H>(path: P, hasher: H) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        H: Hasher + 'static,
+    {
+        Self::builder(path).hasher(hasher).build()
+    }
+
+    /// IDs, ascending, of the data entries whose digest does not match their
+    /// contents. Entries written without a digest are not checked. Fails
+    /// with [`WalError::InvalidConfig`] if the log has no hasher.
+    pub fn verify_digests(&self) -> Result<Vec<u64>> {
+        let hasher = self
+            .hasher
+            .as_ref()
+            .ok_or_else(|| WalError::InvalidConfig("the log has no hasher".to_string()))?;
+        let _file = self.lock_file()?;
+        let mut mismatched = Vec::new();
+        for record in self.records()? {
+            let record = record?;
+            if record.kind == EntryKind::Data
+                && !record.digest.is_empty()
+                && record.digest != record.compute_digest(&**hasher)
+            {
+                mismatched.push(record.id);
+            }
+        }
+        mismatched.sort_unstable();
+        Ok(mismatched)
+    }
+}
+
+fn hasher_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".hasher");
+    PathBuf::from(path)
+}
+
+/// Name of the hasher stored beside the log at `path`, if any.
+pub(crate) fn stored_name(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(hasher_path(path)) {
+        Ok(name) => Ok(Some(name.trim().to_string())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Whether a stored hasher `name` is built in, so a log naming it opens
+/// without being given a hasher.
+pub(crate) fn is_builtin(name: &str) -> bool {
+    builtin(name).is_some()
+}
+
+/// The hasher to open the log at `path` with: `given`, which must match the
+/// name stored beside the log and is stored if there is none and `store` is
+/// set, or else the built-in hasher of the stored name.
+pub(crate) fn load_or_store(
+    path: &Path,
+    given: Option<Callback<dyn Hasher>>,
+    store: bool,
+) -> Result<Option<Callback<dyn Hasher>>> {
+    let sidecar = hasher_path(path);
+    match (stored_name(path)?, given) {
+        (Some(stored), Some(given)) if stored != given.name() => {
+            Err(WalError::ConfigMismatch(format!(
+                "the log was written with hasher `{stored}`, not `{}`",
+                given.name()
+            )))
+        }
+        (Some(_), Some(given)) => Ok(Some(given)),
+        (Some(stored), None) => builtin(&stored).map(|h| Some(Callback(h))).ok_or_else(|| {
+            WalError::ConfigMismatch(format!(
+                "the log was written with hasher `{stored}`; open it with that hasher"
+            ))
+        }),
+        (None, Some(given)) if store => {
+            let mut tmp = sidecar.as_os_str().to_owned();
+            tmp.push(".tmp");
+            let tmp = PathBuf::from(tmp);
+            let mut file = fs::File::create(&tmp)?;
+            writeln!(file, "{}", given.name())?;
+            file.sync_data()?;
+            durable::rename(&tmp, &sidecar)?;
+            Ok(Some(given))
+        }
+        (None, given) => Ok(given),
+    }
+}
+
+pub(crate) fn remove_sidecar(path: &Path) {
+    let _ = fs::remove_file(hasher_path(path));
+}
@@ -0,0 +1,259 @@
+//! The header every log file starts with.
+//!
+//! The magic `WALY` followed by the little-endian `u16` version of the
+//! on-disk format, so that a file written by an incompatible version is
+//! refused on open instead of being read as a stream of undecodable
+//! records, then the little-endian `u32` length of the fields that follow.
+//! Each field is a tag byte and a varint-prefixed value, as with record
+//! extensions, and records something every reader of the log needs, such
+//! as the format of its records and its compression dictionary. Files written before the header existed,
+//! or with the six bytes of version 1, which had no fields, are given a
+//! current one when the log is opened.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::dictionary::{DictCodec, Dictionary};
+use crate::durable;
+use crate::error::{Result, WalError};
+use crate::format::{varint_len, write_varint, Cursor, Format};
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+const MAGIC: &[u8; 4] = b"WALY";
+/// Version of the on-disk format this build reads and writes.
+pub(crate) const VERSION: u16 = 2;
+/// The version before headers held fields, which is still read.
+const V1: u16 = 1;
+/// Length of a version 1 header: the magic and the version.
+const V1_LEN: u64 = 6;
+/// Length of a header with no fields: the magic, the version and the length
+/// of the fields.
+pub(crate) const LEN: u64 = 10;
+
+/// The compression dictionary for the in-house LZ77 codec.
+const FIELD_DICTIONARY: u8 = 1;
+/// A compression dictionary for zstd.
+const FIELD_ZSTD_DICTIONARY: u8 = 2;
+/// The [`Format`] records are written in, as one byte.
+const FIELD_FORMAT: u8 = 3;
+/// Length of the format field.
+const FORMAT_FIELD_LEN: u64 = 3;
+
+/// What a log file starts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stored {
+    /// Nothing: the file is empty.
+    Empty,
+    /// A header of the given version.
+    Version(u16),
+    /// A record, as in files written before the header existed.
+    Missing,
+}
+
+/// What a header records about the log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Fields {
+    /// `None` in files written before the format was recorded.
+    pub(crate) format: Option<Format>,
+    pub(crate) dictionary: Option<Dictionary>,
+}
+
+/// The header bytes of a file in the current version, recording the
+/// `format` of its records and `dictionary`, if any.
+pub(crate) fn bytes(format: Format, dictionary: Option<&Dictionary>) -> Vec<u8> {
+    let mut body = vec![FIELD_FORMAT, 1, format.code()];
+    if let Some(dictionary) = dictionary {
+        body.push(match dictionary.codec() {
+            DictCodec::Lz77 => FIELD_DICTIONARY,
+            #[cfg(feature = "zstd")]
+            DictCodec::Zstd => FIELD_ZSTD_DICTIONARY,
+        });
+        write_varint(&mut body, dictionary.bytes().len() as u64);
+        body.extend_from_slice(dictionary.bytes());
+    }
+    let mut header = Vec::with_capacity(LEN as usize + body.len());
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    header.extend(body);
+    header
+}
+
+/// Writes `header` to `file` if it is empty, as a freshly created log file
+/// is.
+pub(crate) fn write_if_empty(file: &mut File, header: &[u8]) -> io::Result<()> {
+    if file.metadata()?.len() == 0 {
+        file.write_all(header)?;
+    }
+    Ok(())
+}
+
+/// Consumes the header at the start of `reader`, if there is one, and
+/// returns how many bytes it took up, or 0 if there is none.
+pub(crate) fn skip<R: BufRead>(reader: &mut R) -> io::Result<u64> {
+    Ok(read(reader)?.map_or(0, |(len, _)| len))
+}
+
+/// Consumes the header at the start of `reader`, if there is one, returning
+/// how many bytes it took up and its fields, undecoded.
+fn read<R: BufRead>(reader: &mut R) -> io::Result<Option<(u64, Vec<u8>)>> {
+    if !reader.fill_buf()?.starts_with(MAGIC) {
+        return Ok(None);
+    }
+    let mut fixed = [0u8; LEN as usize];
+    reader.read_exact(&mut fixed[..V1_LEN as usize])?;
+    if u16::from_le_bytes([fixed[4], fixed[5]]) == V1 {
+        return Ok(Some((V1_LEN, Vec::new())));
+    }
+    reader.read_exact(&mut fixed[V1_LEN as usize..])?;
+    let len = u32::from_le_bytes([fixed[6], fixed[7], fixed[8], fixed[9]]);
+    let mut fields = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut fields)?;
+    if fields.len() < len as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some((LEN + u64::from(len), fields)))
+}
+
+/// Splits `contents` of a log file into its header, if any, and records.
+pub(crate) fn split(contents: &[u8]) -> (&[u8], &[u8]) {
+    let mut reader = contents;
+    match read(&mut reader) {
+        Ok(Some((len, _))) => contents.split_at(len as usize),
+        _ => (&[], contents),
+    }
+}
+
+/// Reads what the file at `path` starts with.
+pub(crate) fn stored(path: &Path) -> io::Result<Stored> {
+    let mut start = Vec::with_capacity(V1_LEN as usize);
+    File::open(path)?.take(V1_LEN).read_to_end(&mut start)?;
+    Ok(match start.strip_prefix(MAGIC) {
+        _ if start.is_empty() => Stored::Empty,
+        Some(&[lo, hi]) => Stored::Version(u16::from_le_bytes([lo, hi])),
+        _ => Stored::Missing,
+    })
+}
+
+/// The fields in the header of the file at `path`, or none if it has no
+/// header or one of another version, which [`check`] deals with.
+pub(crate) fn fields(path: &Path) -> Result<Fields> {
+    if stored(path)? != Stored::Version(VERSION) {
+        return Ok(Fields::default());
+    }
+    let Some((_, body)) = read(&mut BufReader::new(File::open(path)?))? else {
+        return Ok(Fields::default());
+    };
+    let mut fields = Fields::default();
+    let mut cur = Cursor(&body);
+    while !cur.0.is_empty() {
+        let tag = cur.take(1)?[0];
+        let len = usize::try_from(cur.varint()?)
+            .map_err(|_| WalError::InvalidEntry("header field is too long".to_string()))?;
+        let value = cur.take(len)?.to_vec();
+        match tag {
+            FIELD_FORMAT => {
+                let format = match value[..] {
+                    [code] => Format::from_code(code),
+                    _ => None,
+                };
+                fields.format = Some(format.ok_or_else(|| {
+                    WalError::InvalidConfig(
+                        "the log is written in a format this build does not know".to_string(),
+                    )
+                })?);
+            }
+            FIELD_DICTIONARY => fields.dictionary = Some(Dictionary::new(value)),
+            #[cfg(feature = "zstd")]
+            FIELD_ZSTD_DICTIONARY => fields.dictionary = Some(Dictionary::zstd(value)),
+            #[cfg(not(feature = "zstd"))]
+            FIELD_ZSTD_DICTIONARY => {
+                return Err(WalError::InvalidConfig(
+                    "the log is compressed with zstd, which needs the `zstd` feature".to_string(),
+                ));
+            }
+            // Fields from later builds that older ones can do without.
+            _ => {}
+        }
+    }
+    Ok(fields)
+}
+
+/// Checks the header of every segment of the log at `path`, failing with
+/// [`WalError::UnsupportedVersion`] on one written in another version, and
+/// returns the segments whose header is missing or from version 1.
+pub(crate) fn check(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut outdated = Vec::new();
+    for path in segment::all_segments(path)? {
+        if !path.exists() {
+            continue;
+        }
+        match stored(&path)? {
+            Stored::Empty | Stored::Version(VERSION) => {}
+            Stored::Version(V1) | Stored::Missing => outdated.push(path),
+            Stored::Version(found) => {
+                return Err(WalError::UnsupportedVersion {
+                    found,
+                    expected: VERSION,
+                });
+            }
+        }
+    }
+    Ok(outdated)
+}
+
+/// Like [`check`], but gives the outdated segments `header` instead, and
+/// the active file too if its header differs, as when a dictionary is
+/// given for the first time. Each file is replaced with an atomic rename.
+pub(crate) fn check_or_upgrade(path: &Path, header: &[u8]) -> Result<()> {
+    let outdated = check(path)?;
+    for path in &outdated {
+        upgrade(path, header)?;
+    }
+    if path.exists() && !outdated.iter().any(|p| p == path) && current(path)? != header {
+        upgrade(path, header)?;
+    }
+    Ok(())
+}
+
+/// The header the file at `path` starts with, which is empty if it has none.
+fn current(path: &Path) -> io::Result<Vec<u8>> {
+    let len = skip(&mut BufReader::new(File::open(path)?))?;
+    let mut header = Vec::new();
+    File::open(path)?.take(len).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Rewrites the file at `path` with `header` in place of the one it has,
+/// if any.
+fn upgrade(path: &Path, header: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".upgrade");
+    let temp = PathBuf::from(temp);
+    let mut reader = BufReader::new(File::open(path)?);
+    skip(&mut reader)?;
+    let mut out = File::create(&temp)?;
+    out.write_all(header)?;
+    io::copy(&mut reader, &mut out)?;
+    out.sync_data()?;
+    durable::rename(&temp, path)?;
+    Ok(())
+}
+
+impl WriteAheadLog {
+    /// The header every file of the log starts with.
+    pub(crate) fn header(&self) -> Vec<u8> {
+        bytes(self.format, self.dictionary.as_deref())
+    }
+
+    /// Length of [`header`](Self::header), without building it.
+    pub(crate) fn header_len(&self) -> u64 {
+        LEN + FORMAT_FIELD_LEN
+            + self.dictionary.as_deref().map_or(0, |dictionary| {
+                let len = dictionary.bytes().len() as u64;
+                1 + varint_len(len) as u64 + len
+            })
+    }
+}
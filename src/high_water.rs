@@ -0,0 +1,79 @@
+//! Keeping IDs from going back once the records carrying them are gone.
+//!
+//! Opening a log continues after the largest ID left in it. Compaction,
+//! clearing, pruning, rotation and segment eviction can remove the record
+//! holding the largest ID, so before they do, the next ID is stored beside
+//! the log in `<path>.high_water`, and opening never goes below it. Only
+//! rollbacks that mean to hand IDs out again, such as
+//! [`truncate_after`](WriteAheadLog::truncate_after), lower it.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use crate::durable;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+pub(crate) fn high_water_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".high_water");
+    PathBuf::from(path)
+}
+
+/// The next ID stored beside the log at `path`, or 0 if there is none.
+pub(crate) fn stored(path: &Path) -> Result<u64> {
+    match fs::read_to_string(high_water_path(path)) {
+        Ok(text) => text.trim().parse().map_err(|_| {
+            WalError::InvalidEntry(format!("corrupt high-water file for {}", path.display()))
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn store(path: &Path, next: u64) -> Result<()> {
+    let sidecar = high_water_path(path);
+    let mut tmp = sidecar.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = fs::File::create(&tmp)?;
+    writeln!(file, "{next}")?;
+    file.sync_data()?;
+    durable::rename(&tmp, &sidecar)?;
+    Ok(())
+}
+
+impl WriteAheadLog {
+    /// The ID to continue from when the records left in the log end just
+    /// below `next_id`, or hold none if it is 0.
+    pub(crate) fn continue_after(&self, next_id: u64) -> u64 {
+        let floor = self.high_water.load(Ordering::Acquire);
+        match next_id {
+            0 => self.start_id.max(floor),
+            next_id => next_id.max(floor),
+        }
+    }
+
+    /// Makes sure reopening the log hands out no ID below `next`, before
+    /// records that may carry the largest IDs handed out so far are
+    /// removed. Only writes when that raises the stored value.
+    pub(crate) fn keep_ids_below(&self, next: u64) -> Result<()> {
+        if next <= self.high_water.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        store(&self.path, next)?;
+        self.high_water.fetch_max(next, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Stores `next` as the ID reopening the log continues from at the
+    /// least, lowering it if need be, for rollbacks that renumber or hand
+    /// IDs out again.
+    pub(crate) fn reset_ids_below(&self, next: u64) -> Result<()> {
+        store(&self.path, next)?;
+        self.high_water.store(next, Ordering::Release);
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+//! Sets of IDs that [`WriteAheadLog::read_ids`](crate::WriteAheadLog::read_ids)
+//! selects entries by.
+
+use std::collections::BTreeSet;
+
+/// A set of entry IDs that can be walked in ascending order, so that
+/// [`WriteAheadLog::read_ids`](crate::WriteAheadLog::read_ids) can step
+/// through it alongside the log. With the `roaring` feature it is
+/// implemented for `RoaringBitmap` and `RoaringTreemap`, compact for the
+/// millions of IDs a selective replay may ask for.
+pub trait IdSet {
+    /// The IDs in the set, smallest first, each once.
+    fn ascending(&self) -> impl Iterator<Item = u64> + '_;
+}
+
+impl IdSet for BTreeSet<u64> {
+    fn ascending(&self) -> impl Iterator<Item = u64> + '_ {
+        self.iter().copied()
+    }
+}
+
+#[cfg(feature = "roaring")]
+impl IdSet for roaring::RoaringBitmap {
+    fn ascending(&self) -> impl Iterator<Item = u64> + '_ {
+        self.iter().map(u64::from)
+    }
+}
+
+#[cfg(feature = "roaring")]
+impl IdSet for roaring::RoaringTreemap {
+    fn ascending(&self) -> impl Iterator<Item = u64> + '_ {
+        self.iter()
+    }
+}
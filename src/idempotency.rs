@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+/// Which of several data entries sharing an idempotency key reads keep; see
+/// [`WriteAheadLogBuilder::dedup_on_read`](crate::WriteAheadLogBuilder::dedup_on_read).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupPolicy {
+    /// The earliest in file order, as
+    /// [`append_idempotent`](WriteAheadLog::append_idempotent) would have.
+    KeepFirst,
+    /// The latest in file order.
+    KeepLast,
+}
+
+impl DedupPolicy {
+    /// Removes from `entries` all but one of each group sharing an
+    /// idempotency key. The survivor stays where it was; entries without a
+    /// key are left alone.
+    pub(crate) fn dedup(self, entries: &mut Vec<LogEntry>) {
+        let mut seen = HashSet::new();
+        let mut first = |e: &LogEntry| match &e.idempotency_key {
+            Some(key) => seen.insert(key.clone()),
+            None => true,
+        };
+        match self {
+            DedupPolicy::KeepFirst => entries.retain(|e| first(e)),
+            DedupPolicy::KeepLast => {
+                entries.reverse();
+                entries.retain(|e| first(e));
+                entries.reverse();
+            }
+        }
+    }
+}
+
+/// The idempotency keys [`append_idempotent`](WriteAheadLog::append_idempotent)
+/// remembers, each with the ID of its entry. With a limit, the order keys
+/// were added in is kept too, so that the oldest can be forgotten first.
+#[derive(Debug, Default)]
+pub(crate) struct SeenKeys {
+    ids: HashMap<String, u64>,
+    order: VecDeque<String>,
+    limit: Option<usize>,
+}
+
+impl SeenKeys {
+    fn new(limit: Option<usize>) -> Self {
+        SeenKeys {
+            limit,
+            ..SeenKeys::default()
+        }
+    }
+
+    fn insert(&mut self, key: String, id: u64) {
+        let Some(limit) = self.limit else {
+            self.ids.insert(key, id);
+            return;
+        };
+        if self.ids.insert(key.clone(), id).is_none() {
+            self.order.push_back(key);
+        }
+        while self.ids.len() > limit {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.ids.remove(&oldest);
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Appends `data` under `key` unless an entry with that key is already
+    /// in the log, in which case that entry is returned instead. The flag is
+    /// `true` if the entry was newly written.
+    ///
+    /// Keys are looked up in memory; the first call scans the log for the
+    /// keys already in it. An entry since removed, e.g. by
+    /// [`clear_id`](Self::clear_id), no longer counts.
+    ///
+    /// Every key is remembered for as long as the log is open, at a cost
+    /// of roughly 40 bytes plus the key itself. To bound that, see
+    /// [`max_idempotency_keys`](crate::WriteAheadLogBuilder::max_idempotency_keys).
+    pub fn append_idempotent(&mut self, key: String, data: Vec<u8>) -> Result<(LogEntry, bool)> {
+        if let Some(existing) = self.find_idempotent(&key)? {
+            return Ok((existing, false));
+        }
+        let entry = self.append_record(LogEntry {
+            data,
+            idempotency_key: Some(key.clone()),
+            ..LogEntry::default()
+        })?;
+        if let Some(keys) = &mut self.idempotency_keys {
+            keys.insert(key, entry.id);
+        }
+        Ok((entry, true))
+    }
+
+    fn find_idempotent(&mut self, key: &str) -> Result<Option<LogEntry>> {
+        if self.max_idempotency_keys == Some(0) {
+            return Ok(None);
+        }
+        if self.idempotency_keys.is_none() {
+            let mut keys = SeenKeys::new(self.max_idempotency_keys);
+            for record in self.records()? {
+                let record = record?;
+                if let (EntryKind::Data, Some(key)) = (record.kind, record.idempotency_key) {
+                    keys.insert(key, record.id);
+                }
+            }
+            self.idempotency_keys = Some(keys);
+        }
+        let Some(&id) = self
+            .idempotency_keys
+            .as_ref()
+            .and_then(|keys| keys.ids.get(key))
+        else {
+            return Ok(None);
+        };
+        if let Some(queued) = self
+            .queue
+            .iter()
+            .find(|item| item.assigned && item.entry.id == id)
+        {
+            return Ok(Some(queued.entry.clone()));
+        }
+        Ok(self
+            .get(id)?
+            .filter(|e| e.idempotency_key.as_deref() == Some(key)))
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Maps every key produced by `key_fn` over the data entries to the
+    /// highest ID carrying it, e.g. to find the latest value per key.
+    pub fn build_index<K, F>(&self, key_fn: F) -> Result<HashMap<K, u64>>
+    where
+        K: Eq + Hash,
+        F: Fn(&LogEntry) -> K,
+    {
+        let _file = self.lock_file()?;
+        self.scan_index(&key_fn)
+    }
+
+    /// Rewrites the log keeping only the newest data entry for each key
+    /// produced by `key_fn`, in their original order, and returns how many
+    /// entries were removed. Bookkeeping records are kept. Runs as a
+    /// [`compact_retain`](Self::compact_retain), so entries appended while
+    /// it runs are kept as well.
+    pub fn compact_by_key<K, F>(&self, key_fn: F) -> Result<usize>
+    where
+        K: Eq + Hash,
+        F: Fn(&LogEntry) -> K,
+    {
+        let _rewrite = self.rewrite_lock.lock()?;
+        let latest = self.scan_index(&key_fn)?;
+        self.compact_snapshot(|e| {
+            e.kind != EntryKind::Data || e.stream != 0 || latest.get(&key_fn(e)) == Some(&e.id)
+        })
+    }
+
+    fn scan_index<K, F>(&self, key_fn: &F) -> Result<HashMap<K, u64>>
+    where
+        K: Eq + Hash,
+        F: Fn(&LogEntry) -> K,
+    {
+        let mut index = HashMap::new();
+        for record in self.records()? {
+            let record = record?;
+            if record.kind == EntryKind::Data {
+                let id = index.entry(key_fn(&record)).or_insert(record.id);
+                *id = (*id).max(record.id);
+            }
+        }
+        Ok(index)
+    }
+}
@@ -0,0 +1,593 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+#[cfg(feature = "mmap")]
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::checksum::checksum_of;
+use crate::dictionary::Dictionary;
+use crate::encrypt::Cipher;
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::format::Format;
+use crate::frame::Framer;
+use crate::header;
+use crate::metrics::MetricCounters;
+#[cfg(feature = "mmap")]
+use crate::mmap::Mmap;
+use crate::quarantine::Quarantine;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Reads successive records from one segment file, tracking the byte offset
+/// of each so undecodable ones can be quarantined.
+#[derive(Debug)]
+pub(crate) struct SegmentReader {
+    path: PathBuf,
+    source: Source,
+    format: Format,
+    offset: u64,
+    /// Number of frames read so far, which is the line of the last one in a
+    /// JSON file read from the start.
+    line: u64,
+    buf: Vec<u8>,
+    quarantine: Option<Arc<Quarantine>>,
+    dictionary: Option<Arc<Dictionary>>,
+    cipher: Option<Arc<Cipher>>,
+    payloads: bool,
+    checksums: bool,
+    metrics: Option<Arc<MetricCounters>>,
+}
+
+/// Where a [`SegmentReader`] takes its frames from.
+#[derive(Debug)]
+enum Source {
+    /// Read through a buffered handle and copied into the reader's `buf`.
+    File(BufReader<File>),
+    /// Parsed where they lie in a memory map of the file.
+    #[cfg(feature = "mmap")]
+    Mapped(Mapped),
+}
+
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+struct Mapped {
+    map: Mmap,
+    /// Where the body of the frame just read lies in `map`.
+    body: Range<usize>,
+}
+
+impl Source {
+    /// Maps `file` if `mapped` asks for it and the map can be made, or
+    /// reads it through a buffer otherwise.
+    fn new(file: File, mapped: bool) -> Self {
+        #[cfg(feature = "mmap")]
+        if mapped {
+            if let Ok(map) = Mmap::map(&file) {
+                return Source::Mapped(Mapped { map, body: 0..0 });
+            }
+        }
+        #[cfg(not(feature = "mmap"))]
+        let _ = mapped;
+        Source::File(BufReader::new(file))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Mapped {
+    /// Finds the frame starting at `offset`, returning the bytes it takes
+    /// up, or 0 at the end of the file. Only sealed segments are mapped, so
+    /// the map never falls behind the file.
+    fn next_frame(&mut self, framer: Framer, offset: u64) -> Result<usize> {
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        let rest = self.map.as_slice().get(start..).unwrap_or_default();
+        Ok(match framer.split_frame(rest)? {
+            Some((body, consumed)) => {
+                self.body = start + body.start..start + body.end;
+                consumed
+            }
+            None => 0,
+        })
+    }
+}
+
+/// How to decode the records of a log: its format, compression dictionary,
+/// key and quarantine, the IDs whose records reads skip, and the counters that
+/// undecodable records are tallied in, if any. Scans that only need IDs
+/// and kinds can leave `data` as stored, compressed or encrypted, with
+/// `payloads` off. With `checksums` on, decoded payloads are checked
+/// against the checksum stored with them, if any. With `mapped` set to the
+/// log's path, sealed segments are memory-mapped rather than read through a
+/// buffer. The active file at that path never is: it can be cut short while
+/// being read, and touching a map past the end of its file faults.
+#[derive(Debug, Clone)]
+pub(crate) struct Decoding {
+    pub(crate) format: Format,
+    pub(crate) dictionary: Option<Arc<Dictionary>>,
+    pub(crate) cipher: Option<Arc<Cipher>>,
+    pub(crate) payloads: bool,
+    pub(crate) checksums: bool,
+    pub(crate) mapped: Option<PathBuf>,
+    pub(crate) quarantine: Option<Arc<Quarantine>>,
+    pub(crate) tombstones: Arc<Mutex<HashSet<u64>>>,
+    pub(crate) metrics: Option<Arc<MetricCounters>>,
+}
+
+/// Whether `record` is a stream-0 record other than a tombstone whose ID is
+/// in `tombstones`.
+fn is_cleared(tombstones: &Mutex<HashSet<u64>>, record: &LogEntry) -> bool {
+    record.stream == 0
+        && record.kind != EntryKind::Tombstone
+        && tombstones
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&record.id)
+}
+
+impl SegmentReader {
+    pub(crate) fn new(path: &Path, file: File, decoding: Decoding) -> Self {
+        SegmentReader {
+            path: path.to_path_buf(),
+            source: Source::new(
+                file,
+                decoding
+                    .mapped
+                    .as_deref()
+                    .is_some_and(|active| active != path),
+            ),
+            format: decoding.format,
+            offset: 0,
+            line: 0,
+            buf: Vec::new(),
+            quarantine: decoding.quarantine,
+            dictionary: decoding.dictionary,
+            cipher: decoding.cipher,
+            payloads: decoding.payloads,
+            checksums: decoding.checksums,
+            metrics: decoding.metrics,
+        }
+    }
+
+    /// Like [`new`](Self::new), but starts reading at `offset`, which must
+    /// be the start of a record past the file's header.
+    pub(crate) fn starting_at(
+        path: &Path,
+        mut file: File,
+        decoding: Decoding,
+        offset: u64,
+    ) -> Result<Self> {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = SegmentReader::new(path, file, decoding);
+        reader.offset = offset;
+        Ok(reader)
+    }
+
+    /// Byte offset in the file just past the last frame read.
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the next record that decodes. Undecodable records are handed
+    /// to the quarantine, if any, and skipped, except that a payload that
+    /// fails to decrypt or to match its checksum is an error.
+    pub(crate) fn next_record(&mut self) -> Result<Option<LogEntry>> {
+        loop {
+            match self.next_decoded()? {
+                Some(Ok(entry)) => return Ok(Some(entry)),
+                Some(Err(err)) if err.is_damaged_payload() => return Err(err),
+                Some(Err(_)) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Returns the ID and timestamp of the next data entry of stream 0 that
+    /// decodes, with its payload written into `data`. Other records are
+    /// skipped, and undecodable ones quarantined as by
+    /// [`next_record`](Self::next_record), including failing on a checksum
+    /// mismatch.
+    fn next_data_into(&mut self, data: &mut Vec<u8>) -> Result<Option<(u64, u64)>> {
+        self.skip_header()?;
+        loop {
+            let offset = self.offset;
+            let consumed = self.read_frame()?;
+            if consumed == 0 {
+                return Ok(None);
+            }
+            self.offset += consumed as u64;
+            match self.format.decode_data_into(
+                self.body(),
+                self.dictionary.as_deref(),
+                self.cipher.as_deref(),
+                data,
+            ) {
+                Ok(Some((id, timestamp, checksum))) => {
+                    if self.checksums
+                        && checksum != 0
+                        && checksum != checksum_of(id, timestamp, data)
+                    {
+                        self.undecodable(offset)?;
+                        return Err(WalError::ChecksumMismatch { id });
+                    }
+                    return Ok(Some((id, timestamp)));
+                }
+                Ok(None) => {}
+                Err(err @ WalError::Decryption { .. }) => {
+                    self.undecodable(offset)?;
+                    return Err(err);
+                }
+                Err(_) => self.undecodable(offset)?,
+            }
+        }
+    }
+
+    /// Returns the outcome of decoding the next record, handing it to the
+    /// quarantine, if any, when it does not decode.
+    fn next_decoded(&mut self) -> Result<Option<Result<LogEntry>>> {
+        self.skip_header()?;
+        let offset = self.offset;
+        let consumed = self.read_frame()?;
+        if consumed == 0 {
+            return Ok(None);
+        }
+        self.offset += consumed as u64;
+        let decoded = if self.payloads {
+            self.format
+                .decode_with(
+                    self.body(),
+                    self.dictionary.as_deref(),
+                    self.cipher.as_deref(),
+                )
+                .and_then(|entry| match entry.is_checksum_valid() || !self.checksums {
+                    true => Ok(entry),
+                    false => Err(WalError::ChecksumMismatch { id: entry.id }),
+                })
+        } else {
+            self.format
+                .decode_flagged(self.body())
+                .map(|(entry, _)| entry)
+        };
+        if decoded.is_err() {
+            self.undecodable(offset)?;
+        }
+        Ok(Some(decoded))
+    }
+
+    /// Like [`next_decoded`](Self::next_decoded), also returning how many
+    /// bytes of the file the record takes up, framing included.
+    pub(crate) fn next_sized(&mut self) -> Result<Option<(Result<LogEntry>, u64)>> {
+        self.skip_header()?;
+        let start = self.offset;
+        Ok(self
+            .next_decoded()?
+            .map(|decoded| (decoded, self.offset - start)))
+    }
+
+    /// Like [`next_record`](Self::next_record), but fails on the first
+    /// record that does not decode, with an
+    /// [`InvalidEntry`](WalError::InvalidEntry) error naming the file, the
+    /// record's line, or position for binary formats, and its bytes.
+    fn next_strict(&mut self) -> Result<Option<LogEntry>> {
+        match self.next_decoded()? {
+            Some(Ok(entry)) => Ok(Some(entry)),
+            Some(Err(err)) if err.is_damaged_payload() => Err(err),
+            Some(Err(err)) => Err(self.locate(err)),
+            None => Ok(None),
+        }
+    }
+
+    /// `err`, for the record just read, with where it lies in the file and
+    /// the first bytes of its body.
+    fn locate(&self, err: WalError) -> WalError {
+        const SHOWN: usize = 64;
+        let body = self.body();
+        let reason = match err {
+            WalError::InvalidEntry(msg) => msg,
+            err => err.to_string(),
+        };
+        let unit = match self.format.framer() {
+            Framer::Line => "line",
+            Framer::LengthPrefixed | Framer::VarintPrefixed => "record",
+        };
+        WalError::InvalidEntry(format!(
+            "{} {unit} {}: {reason}; bytes: \"{}\"{}",
+            self.path.display(),
+            self.line,
+            body[..body.len().min(SHOWN)].escape_ascii(),
+            if body.len() > SHOWN { "..." } else { "" },
+        ))
+    }
+
+    /// Hands the record just read, found at `offset`, to the quarantine, if
+    /// any, and counts it.
+    fn undecodable(&self, offset: u64) -> Result<()> {
+        if let Some(metrics) = &self.metrics {
+            metrics.corrupted();
+        }
+        if let Some(quarantine) = &self.quarantine {
+            quarantine.record(&self.path, offset, self.body())?;
+        }
+        Ok(())
+    }
+
+    /// Reads the frame at `offset`, returning the bytes it takes up, or 0
+    /// at the end of the file. Its body is then [`body`](Self::body).
+    fn read_frame(&mut self) -> Result<usize> {
+        let consumed = match &mut self.source {
+            Source::File(reader) => self.format.read_frame(reader, &mut self.buf)?,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(mapped) => mapped.next_frame(self.format.framer(), self.offset)?,
+        };
+        if consumed > 0 {
+            self.line += 1;
+        }
+        Ok(consumed)
+    }
+
+    /// Body of the frame last read.
+    fn body(&self) -> &[u8] {
+        match &self.source {
+            Source::File(_) => &self.buf,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(mapped) => &mapped.map.as_slice()[mapped.body.clone()],
+        }
+    }
+
+    /// Steps over the file's header before the first record is read.
+    fn skip_header(&mut self) -> Result<()> {
+        if self.offset == 0 {
+            self.offset = match &mut self.source {
+                Source::File(reader) => header::skip(reader)?,
+                #[cfg(feature = "mmap")]
+                Source::Mapped(mapped) => header::split(mapped.map.as_slice()).0.len() as u64,
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Streams well-formed records, markers included, from independent read
+/// handles so that appends through the log do not disturb the read position.
+/// Every segment is opened up front, so a concurrent rotation cannot make the
+/// stream skip one.
+pub(crate) struct Records {
+    pending: VecDeque<SegmentReader>,
+    /// Only records of this logical stream are yielded, if set.
+    stream: Option<u32>,
+    tombstones: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl Records {
+    /// Opens every segment of the log at `path`, without needing the log.
+    pub(crate) fn open(path: &Path, decoding: Decoding, stream: Option<u32>) -> Result<Self> {
+        let mut pending = VecDeque::new();
+        for path in segment::all_segments(path)? {
+            let file = File::open(&path)?;
+            pending.push_back(SegmentReader::new(&path, file, decoding.clone()));
+        }
+        Ok(Records {
+            pending,
+            stream,
+            tombstones: decoding.tombstones,
+        })
+    }
+}
+
+impl Iterator for Records {
+    type Item = Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reader = self.pending.front_mut()?;
+            match reader.next_record() {
+                Ok(Some(entry))
+                    if self.stream.is_none_or(|s| s == entry.stream)
+                        && !is_cleared(&self.tombstones, &entry) =>
+                {
+                    return Some(Ok(entry));
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    self.pending.pop_front();
+                }
+                Err(err) => {
+                    self.pending.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Streams the data entries of a log one record at a time; see
+/// [`WriteAheadLog::iter`].
+#[derive(Debug)]
+pub struct EntryIter {
+    pending: VecDeque<SegmentReader>,
+    tombstones: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl Iterator for EntryIter {
+    type Item = Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reader = self.pending.front_mut()?;
+            match reader.next_decoded() {
+                Ok(Some(Ok(entry)))
+                    if entry.stream == 0
+                        && entry.kind == EntryKind::Data
+                        && !is_cleared(&self.tombstones, &entry) =>
+                {
+                    return Some(Ok(entry));
+                }
+                Ok(Some(Ok(_))) => {}
+                Ok(Some(Err(err))) => return Some(Err(err)),
+                Ok(None) => {
+                    self.pending.pop_front();
+                }
+                Err(err) => {
+                    self.pending.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Streams data entries in file order, reading one record at a time so
+    /// memory use does not grow with the log. Unlike
+    /// [`read_all`](Self::read_all), a record that fails to decode yields an
+    /// [`InvalidEntry`](crate::WalError::InvalidEntry) error in its place,
+    /// after which iteration can continue.
+    ///
+    /// The iterator reads through its own handles, opened on every segment
+    /// up front, so appends made meanwhile do not disturb it; whether it
+    /// sees them is unspecified.
+    pub fn iter(&self) -> Result<EntryIter> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let mut pending = VecDeque::new();
+        for path in segment::all_segments(&self.path)? {
+            let file = File::open(&path)?;
+            pending.push_back(self.segment_reader(&path, file));
+        }
+        Ok(EntryIter {
+            pending,
+            tombstones: Arc::clone(&self.tombstones),
+        })
+    }
+
+    /// Like [`read_all`](Self::read_all), but fails on the first record that
+    /// does not decode instead of skipping it, for recovery code that must
+    /// not carry on past a damaged log. The
+    /// [`InvalidEntry`](crate::WalError::InvalidEntry) error names the file,
+    /// the record's line, counting from 1 (its position in the file for the
+    /// binary formats), and shows its first bytes. The
+    /// [`read_all_cache`](crate::WriteAheadLogBuilder::read_all_cache) is
+    /// not used.
+    pub fn read_all_strict(&self) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let mut entries = Vec::new();
+        for path in segment::all_segments(&self.path)? {
+            let mut reader = self.segment_reader(&path, File::open(&path)?);
+            while let Some(record) = reader.next_strict()? {
+                if record.stream == 0 && record.kind == EntryKind::Data && !self.is_cleared(&record)
+                {
+                    entries.push(record);
+                }
+            }
+        }
+        if let Some(policy) = self.dedup_on_read {
+            policy.dedup(&mut entries);
+        }
+        Ok(entries)
+    }
+
+    /// Calls `f` with the ID, timestamp and payload of each data entry in
+    /// file order, as [`read_all`](Self::read_all) would return them, but
+    /// with every payload written into `buf` in turn instead of a fresh
+    /// `Vec`. Once `buf` has grown to fit the largest payload, scanning
+    /// allocates nothing per entry for records that carry no optional
+    /// fields besides a checksum and, in JSON, keep `data` as an array;
+    /// others are decoded in full first. Undecodable records are skipped.
+    ///
+    /// Like [`iter`](Self::iter), the scan reads through its own handles,
+    /// opened on every segment up front.
+    pub fn read_into<F>(&self, buf: &mut Vec<u8>, mut f: F) -> Result<()>
+    where
+        F: FnMut(u64, u64, &[u8]),
+    {
+        self.metrics.read();
+        let mut pending = Vec::new();
+        {
+            let _file = self.lock_file()?;
+            for path in segment::all_segments(&self.path)? {
+                let file = File::open(&path)?;
+                pending.push(self.segment_reader(&path, file));
+            }
+        }
+        for mut reader in pending {
+            while let Some((id, timestamp)) = reader.next_data_into(buf)? {
+                if !self.tombstones.lock()?.contains(&id) {
+                    f(id, timestamp, buf);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn decoding(&self) -> Decoding {
+        Decoding {
+            format: self.format,
+            dictionary: self.dictionary.clone(),
+            cipher: self.cipher.clone(),
+            payloads: true,
+            checksums: true,
+            mapped: self.mmap_reads.then(|| self.path.clone()),
+            quarantine: self.quarantine.clone(),
+            tombstones: Arc::clone(&self.tombstones),
+            metrics: Some(Arc::clone(&self.metrics)),
+        }
+    }
+
+    /// Whether `record` has been removed by a tombstone, so reads skip it.
+    pub(crate) fn is_cleared(&self, record: &LogEntry) -> bool {
+        is_cleared(&self.tombstones, record)
+    }
+
+    pub(crate) fn segment_reader(&self, path: &Path, file: File) -> SegmentReader {
+        SegmentReader::new(path, file, self.decoding())
+    }
+
+    /// Records of the log itself, i.e. logical stream 0.
+    pub(crate) fn records(&self) -> Result<Records> {
+        self.stream_records(Some(0))
+    }
+
+    /// Records of stream 0 with `data` left as stored, for scans that
+    /// only need IDs and kinds and so should not fail for want of the key.
+    pub(crate) fn record_headers(&self) -> Result<Records> {
+        let decoding = Decoding {
+            payloads: false,
+            ..self.decoding()
+        };
+        Records::open(&self.path, decoding, Some(0))
+    }
+
+    /// Records of every logical stream.
+    pub(crate) fn all_records(&self) -> Result<Records> {
+        self.stream_records(None)
+    }
+
+    /// Records of every logical stream with their checksums left
+    /// unchecked, for scans that report mismatches themselves.
+    pub(crate) fn unchecked_records(&self) -> Result<Records> {
+        let decoding = Decoding {
+            checksums: false,
+            ..self.decoding()
+        };
+        Records::open(&self.path, decoding, None)
+    }
+
+    pub(crate) fn stream_records(&self, stream: Option<u32>) -> Result<Records> {
+        Records::open(&self.path, self.decoding(), stream)
+    }
+
+    /// Reads every decodable record of the segment at `path` through `file`,
+    /// which may be the active handle.
+    pub(crate) fn read_segment(&self, path: &Path, file: &File) -> Result<Vec<LogEntry>> {
+        let mut file = file.try_clone()?;
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = self.segment_reader(path, file);
+        let mut records = Vec::new();
+        while let Some(record) = reader.next_record()? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
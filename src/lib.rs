@@ -0,0 +1,154 @@
+//! A small, file-backed write-ahead log.
+//!
+//! Entries are appended to a single file as newline-delimited JSON, each
+//! carrying a monotonically increasing ID and the time it was written. The
+//! file starts with a short header naming the version of the on-disk format.
+//!
+//! ```no_run
+//! use waly_rs::WriteAheadLog;
+//!
+//! let mut wal = WriteAheadLog::new("app.wal")?;
+//! let entry = wal.append(b"hello".to_vec())?;
+//! assert_eq!(wal.read_all()?.last(), Some(&entry));
+//! # Ok::<(), waly_rs::WalError>(())
+//! ```
+
+mod anonymous;
+mod apply;
+mod async_wal;
+mod auto_compact;
+mod base64;
+mod batch;
+mod builder;
+mod cache;
+mod callback;
+mod chacha20poly1305;
+mod checkpoint;
+mod checksum;
+mod clock;
+mod compaction;
+mod compat;
+mod compress;
+mod consumer;
+mod content_type;
+mod count;
+mod cursor;
+mod dictionary;
+mod drain;
+mod durable;
+mod encrypt;
+mod entry;
+mod error;
+mod evict;
+mod expiry;
+mod export;
+mod fence;
+mod fingerprint;
+mod format;
+mod frame;
+mod gate;
+mod group_commit;
+mod hasher;
+mod header;
+mod high_water;
+mod id_set;
+mod idempotency;
+mod index;
+mod iter;
+mod json;
+mod limits;
+mod lock;
+mod marker;
+mod metrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod normalize;
+mod offset;
+mod progress;
+#[cfg(feature = "prost")]
+mod proto;
+mod prune;
+mod quarantine;
+mod rate;
+mod readonly;
+mod region;
+mod repair;
+mod replay;
+mod resequence;
+mod reserve;
+mod ring;
+mod segment;
+mod sequence;
+#[cfg(feature = "sha256")]
+mod sha256;
+mod shift;
+mod ship;
+mod sink;
+mod sort;
+mod stats;
+mod status;
+mod stream;
+mod sync;
+mod tags;
+mod tail;
+mod tailer;
+mod take;
+mod timeout;
+mod token;
+mod truncate;
+mod typed;
+mod verify;
+mod wal;
+mod write_buffer;
+mod writer;
+#[cfg(feature = "xxhash")]
+mod xxhash;
+
+pub use apply::WalOp;
+pub use async_wal::AsyncWriteAheadLog;
+pub use auto_compact::CompactionPolicy;
+pub use builder::WriteAheadLogBuilder;
+pub use cache::CacheStats;
+pub use clock::{Clock, SystemClock};
+pub use compat::Compatibility;
+pub use compress::Compression;
+pub use consumer::ConsumerId;
+pub use count::{CountReport, EntryBreakdown};
+pub use cursor::Cursor;
+pub use drain::Drain;
+pub use encrypt::Encryption;
+pub use entry::{EntryKind, LogEntry};
+pub use error::{Result, WalError};
+pub use export::{CsvColumn, DumpFormat};
+pub use format::{Format, WalFormat};
+pub use gate::{AppendGate, PauseMode};
+pub use group_commit::{PendingEntry, PendingState};
+#[cfg(feature = "sha256")]
+pub use hasher::Sha256;
+#[cfg(feature = "xxhash")]
+pub use hasher::XxHash64;
+pub use hasher::{Crc32, Hasher};
+pub use id_set::IdSet;
+pub use idempotency::DedupPolicy;
+pub use iter::EntryIter;
+pub use limits::Capacity;
+pub use metrics::WalMetrics;
+pub use normalize::NormalizeStats;
+#[cfg(feature = "prost")]
+pub use proto::{Message, ProtoWal};
+pub use region::RegionWal;
+pub use repair::RepairReport;
+pub use resequence::ResequenceMap;
+pub use reserve::ReservedEntry;
+pub use ring::{RingWal, DEFAULT_SLOT_BYTES};
+pub use ship::Shipper;
+pub use sink::{Sink, SinkErrorPolicy, SinkFailed};
+pub use stats::{WalStats, WriteAmpStats};
+pub use stream::StreamView;
+pub use sync::SyncPolicy;
+pub use tailer::TailHandle;
+pub use token::ReadToken;
+pub use typed::{Payload, TypedWal};
+pub use verify::VerifyReport;
+pub use wal::WriteAheadLog;
+pub use writer::{ConcurrentWriter, DEFAULT_CHANNEL_CAPACITY};
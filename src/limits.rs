@@ -0,0 +1,131 @@
+use std::fs::File;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Headroom left before a configured size or entry limit is reached, as
+/// reported by [`WriteAheadLog::remaining_capacity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capacity {
+    /// Bytes that can still be written, or `None` without a
+    /// [`max_file_size`](crate::WriteAheadLogBuilder::max_file_size).
+    pub remaining_bytes: Option<u64>,
+    /// Entries that can still be appended: the exact headroom under
+    /// [`max_entries`](crate::WriteAheadLogBuilder::max_entries), further
+    /// limited by how many entries of the current average size fit in
+    /// `remaining_bytes`. `None` when neither limit applies or the log is too
+    /// empty to estimate an average.
+    pub remaining_entries: Option<u64>,
+}
+
+impl WriteAheadLog {
+    /// Reports how much more can be appended before hitting
+    /// [`max_file_size`](crate::WriteAheadLogBuilder::max_file_size) or
+    /// [`max_entries`](crate::WriteAheadLogBuilder::max_entries).
+    pub fn remaining_capacity(&self) -> Result<Capacity> {
+        let file = self.lock_file()?;
+        let bytes = self.total_bytes(&file)?;
+        let entries = self.entry_count()?;
+        // File headers do not grow with the entries.
+        let headers = segment::all_segments(&self.path)?.len() as u64 * self.header_len();
+
+        let remaining_bytes = self.max_file_size.map(|max| max.saturating_sub(bytes));
+        let by_count = self.max_entries.map(|max| max.saturating_sub(entries));
+        let by_size = match (
+            remaining_bytes,
+            bytes.saturating_sub(headers).checked_div(entries),
+        ) {
+            (Some(remaining), Some(avg)) if avg > 0 => Some(remaining / avg),
+            _ => None,
+        };
+        let remaining_entries = match (by_count, by_size) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Ok(Capacity {
+            remaining_bytes,
+            remaining_entries,
+        })
+    }
+
+    /// How many bytes appending `data` would add to the log right now,
+    /// framing, compression and padding included, without writing anything.
+    pub fn serialized_size(&self, data: &[u8]) -> Result<usize> {
+        let mut entry = LogEntry {
+            data: data.to_vec(),
+            timestamp: self.now()?,
+            ..LogEntry::default()
+        };
+        self.stamp(&mut entry);
+        Ok(self.encode_record(&entry)?.len())
+    }
+
+    /// Fails with [`WalError::LogFull`] if appending `incoming` bytes holding
+    /// `entries` data records would break a configured limit.
+    pub(crate) fn check_limits(&self, active: &File, incoming: u64, entries: u64) -> Result<()> {
+        if let Some(max) = self.max_file_size {
+            if self.total_bytes(active)? + incoming > max {
+                return Err(WalError::LogFull);
+            }
+        }
+        if let (Some(max), false) = (self.max_entries, self.evict_oldest) {
+            if entries > 0 && self.entry_count()? + entries > max {
+                return Err(WalError::LogFull);
+            }
+        }
+        Ok(())
+    }
+
+    /// Size of every segment, the active one read through `active` and
+    /// counting buffered appends.
+    pub(crate) fn total_bytes(&self, active: &File) -> Result<u64> {
+        let mut total = active.metadata()?.len() + self.buffered_bytes()?;
+        for (_, path) in segment::sealed_segments(&self.path)? {
+            total += path.metadata()?.len();
+        }
+        Ok(total)
+    }
+
+    /// Number of data entries, counted once and then kept up to date by
+    /// appends. Rewrites reset the cache.
+    pub(crate) fn entry_count(&self) -> Result<u64> {
+        let mut cached = self.entry_count.lock()?;
+        if let Some(count) = *cached {
+            return Ok(count);
+        }
+        let mut count = 0;
+        for record in self.all_records()? {
+            if record?.kind == EntryKind::Data {
+                count += 1;
+            }
+        }
+        *cached = Some(count);
+        Ok(count)
+    }
+
+    /// Like [`read_all`](Self::read_all), but fails with
+    /// [`WalError::LimitExceeded`] as soon as the entries would number more
+    /// than `max_entries` or their payloads add up to more than `max_bytes`,
+    /// so an unexpectedly large log cannot exhaust memory. Records are
+    /// streamed, and reading stops at the first entry over either cap.
+    pub fn read_all_capped(&self, max_entries: usize, max_bytes: usize) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let mut entries = Vec::new();
+        let mut bytes = 0usize;
+        for record in self.records()? {
+            let record = record?;
+            if record.kind != EntryKind::Data {
+                continue;
+            }
+            bytes = bytes.saturating_add(record.data.len());
+            if entries.len() == max_entries || bytes > max_bytes {
+                return Err(WalError::LimitExceeded);
+            }
+            entries.push(record);
+        }
+        Ok(entries)
+    }
+}
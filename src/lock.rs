@@ -0,0 +1,24 @@
+//! Keeping two handles from writing the same log at once.
+
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, WalError};
+
+pub(crate) fn lock_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+/// Takes an exclusive advisory lock on `<path>.lock`, held until the
+/// returned file is closed, failing with [`WalError::Locked`] if another
+/// handle holds it.
+pub(crate) fn lock_exclusive(path: &Path) -> Result<File> {
+    let file = File::create(lock_path(path))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(WalError::Locked),
+        Err(TryLockError::Error(err)) => Err(err.into()),
+    }
+}
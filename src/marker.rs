@@ -0,0 +1,88 @@
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::iter::Records;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends a marker record carrying `label`. Markers take an ID like any
+    /// other record but are not returned by [`read_all`](Self::read_all).
+    pub fn append_marker(&mut self, label: &str) -> Result<LogEntry> {
+        self.append_record(LogEntry {
+            data: label.as_bytes().to_vec(),
+            kind: EntryKind::Marker,
+            ..LogEntry::default()
+        })
+    }
+
+    /// Data entries appended before the first marker labelled `label`, to
+    /// reconstruct state as of that checkpoint. If there is no such marker,
+    /// every data entry is returned, as for the first group of
+    /// [`iter_by_run`](Self::iter_by_run).
+    pub fn iter_until_marker(&self, label: &str) -> Result<Vec<LogEntry>> {
+        let _file = self.lock_file()?;
+        let mut entries = Vec::new();
+        for record in self.records()? {
+            let record = record?;
+            match record.kind {
+                EntryKind::Data => entries.push(record),
+                EntryKind::Marker if record.data == label.as_bytes() => break,
+                _ => {}
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Groups data entries into runs separated by markers labelled
+    /// `restart_label`.
+    ///
+    /// Like [`str::split`], `n` restart markers produce `n + 1` groups: the
+    /// entries before the first marker, those between each pair, and those
+    /// after the last one. Groups may be empty. Markers with other labels are
+    /// ignored.
+    pub fn iter_by_run(
+        &self,
+        restart_label: &str,
+    ) -> Result<impl Iterator<Item = Result<Vec<LogEntry>>>> {
+        Ok(Runs {
+            records: self.records()?,
+            label: restart_label.to_string(),
+            done: false,
+        })
+    }
+}
+
+struct Runs {
+    records: Records,
+    label: String,
+    done: bool,
+}
+
+impl Iterator for Runs {
+    type Item = Result<Vec<LogEntry>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut run = Vec::new();
+        loop {
+            match self.records.next() {
+                None => {
+                    self.done = true;
+                    return Some(Ok(run));
+                }
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+                Some(Ok(entry)) => match entry.kind {
+                    EntryKind::Data => run.push(entry),
+                    EntryKind::Marker if entry.marker_label() == Some(self.label.as_str()) => {
+                        return Some(Ok(run));
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+}
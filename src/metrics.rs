@@ -0,0 +1,75 @@
+//! Counters of the traffic through a log, for export to monitoring.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::wal::WriteAheadLog;
+
+/// Traffic through a [`WriteAheadLog`] handle since it was opened, as
+/// reported by [`WriteAheadLog::metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalMetrics {
+    /// Data entries appended. Markers and other bookkeeping records are
+    /// left out.
+    pub entries_appended: u64,
+    /// Encoded bytes appended to the active file, bookkeeping records
+    /// included. Rewrites are counted by
+    /// [`write_amplification`](WriteAheadLog::write_amplification) instead.
+    pub bytes_written: u64,
+    /// Records appended since the active file was last synced.
+    pub appends_since_sync: u64,
+    /// Calls to the read methods: [`read_all`](WriteAheadLog::read_all)
+    /// and its variants, [`iter`](WriteAheadLog::iter),
+    /// [`read_into`](WriteAheadLog::read_into),
+    /// [`get`](WriteAheadLog::get), [`read_ids`](WriteAheadLog::read_ids),
+    /// [`read_range`](WriteAheadLog::read_range),
+    /// [`tail`](WriteAheadLog::tail) and [`read_at`](WriteAheadLog::read_at).
+    pub reads: u64,
+    /// Undecodable records skipped or reported by reads, counted each time
+    /// a read comes across one, internal scans included.
+    pub corrupted_entries_skipped: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MetricCounters {
+    entries_appended: AtomicU64,
+    bytes_written: AtomicU64,
+    appends_since_sync: AtomicU64,
+    reads: AtomicU64,
+    corrupted: AtomicU64,
+}
+
+impl MetricCounters {
+    pub(crate) fn appended(&self, records: u64, entries: u64, bytes: u64) {
+        self.entries_appended.fetch_add(entries, Ordering::Relaxed);
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+        self.appends_since_sync
+            .fetch_add(records, Ordering::Relaxed);
+    }
+
+    pub(crate) fn synced(&self) {
+        self.appends_since_sync.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn read(&self) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn corrupted(&self) {
+        self.corrupted.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl WriteAheadLog {
+    /// A snapshot of the log's counters. Reading them takes no lock, so it
+    /// is cheap enough to poll from a metrics exporter.
+    pub fn metrics(&self) -> WalMetrics {
+        let counters = &self.metrics;
+        WalMetrics {
+            entries_appended: counters.entries_appended.load(Ordering::Relaxed),
+            bytes_written: counters.bytes_written.load(Ordering::Relaxed),
+            appends_since_sync: counters.appends_since_sync.load(Ordering::Relaxed),
+            reads: counters.reads.load(Ordering::Relaxed),
+            corrupted_entries_skipped: counters.corrupted.load(Ordering::Relaxed),
+        }
+    }
+}
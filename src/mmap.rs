@@ -0,0 +1,124 @@
+//! Read-only memory maps of log files, for reads that parse records where
+//! they lie instead of copying them out through a buffer first.
+
+use std::fs::File;
+use std::io;
+
+/// A read-only view of a whole file as it was when mapped.
+pub(crate) struct Mmap {
+    #[cfg(unix)]
+    ptr: *const u8,
+    #[cfg(unix)]
+    len: usize,
+    /// Where the platform has no `mmap`, the file is read into memory.
+    #[cfg(not(unix))]
+    bytes: Vec<u8>,
+}
+
+// The mapping is read-only and never handed out mutably.
+#[cfg(unix)]
+unsafe impl Send for Mmap {}
+#[cfg(unix)]
+unsafe impl Sync for Mmap {}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_void};
+
+    pub(super) const PROT_READ: c_int = 1;
+    pub(super) const MAP_PRIVATE: c_int = 2;
+    pub(super) const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        // `off_t` is pointer-sized on the targets this builds for.
+        pub(super) fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: isize,
+        ) -> *mut c_void;
+        pub(super) fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+}
+
+impl Mmap {
+    /// Maps the whole of `file` as it is now.
+    #[cfg(unix)]
+    pub(crate) fn map(file: &File) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "file is too large to map"))?;
+        if len == 0 {
+            // Empty mappings are refused; there is nothing to read anyway.
+            return Ok(Mmap {
+                ptr: std::ptr::NonNull::dangling().as_ptr(),
+                len: 0,
+            });
+        }
+        // SAFETY: a fresh read-only private mapping of an open file; the
+        // kernel picks the address.
+        let ptr = unsafe {
+            sys::mmap(
+                std::ptr::null_mut(),
+                len,
+                sys::PROT_READ,
+                sys::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == sys::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn map(file: &File) -> io::Result<Self> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut file = file;
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut bytes)?;
+        Ok(Mmap { bytes })
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        #[cfg(unix)]
+        // SAFETY: `ptr` points to `len` mapped bytes that live until drop.
+        // They would fault if the file were cut short underneath, so only
+        // sealed segments are mapped, and those are never cut in place.
+        unsafe {
+            std::slice::from_raw_parts(self.ptr, self.len)
+        }
+        #[cfg(not(unix))]
+        &self.bytes
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: unmaps exactly the region `map` mapped.
+            unsafe {
+                sys::munmap(self.ptr as *mut _, self.len);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mmap")
+            .field("len", &self.as_slice().len())
+            .finish()
+    }
+}
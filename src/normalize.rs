@@ -0,0 +1,133 @@
+//! Rewriting hand-edited or legacy JSON logs into the canonical layout.
+//!
+//! Readers split JSON logs on newlines, so a record pretty-printed over
+//! several lines, or padded with blank lines, reads as damage and throws
+//! off anything that counts frames or byte offsets.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::header;
+use crate::json::{self, Value};
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Outcome of [`WriteAheadLog::normalize`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NormalizeStats {
+    /// Records found, markers and other streams included.
+    pub records: u64,
+    /// Records whose stored form changed, because they were spread over
+    /// several lines or not written compactly.
+    pub normalized: u64,
+    /// Blank lines removed.
+    pub blank_lines: u64,
+    /// Lines that hold no decodable record, kept as they were.
+    pub unparsed: u64,
+}
+
+impl WriteAheadLog {
+    /// Rewrites the JSON log at `path`, every segment included, into one
+    /// compact record per line: blank lines are dropped and records spread
+    /// over several lines or written with extra whitespace are re-encoded
+    /// as the log itself would write them. Payloads keep their array or
+    /// base64 form. Lines that do not decode are kept unchanged and counted
+    /// in [`NormalizeStats::unparsed`].
+    ///
+    /// Call it before opening the log, not while a handle is open. Each
+    /// file that changes is replaced with an atomic rename; the others are
+    /// left untouched. Alignment padding counts as extra whitespace and is
+    /// removed.
+    pub fn normalize<P: AsRef<Path>>(path: P) -> Result<NormalizeStats> {
+        let mut stats = NormalizeStats::default();
+        for path in segment::all_segments(path.as_ref())? {
+            if !path.exists() {
+                continue;
+            }
+            let contents = fs::read(&path)?;
+            let (header, records) = header::split(&contents);
+            let mut canonical = header.to_vec();
+            canonical.extend(normalize_contents(records, &mut stats));
+            if canonical != contents {
+                replace(&path, &canonical)?;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn normalize_contents(contents: &[u8], stats: &mut NormalizeStats) -> Vec<u8> {
+    let mut out = Vec::with_capacity(contents.len());
+    let mut rest = contents;
+    loop {
+        let blank = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        stats.blank_lines += rest[..blank].iter().filter(|&&b| b == b'\n').count() as u64;
+        let indented = blank > 0 && rest[blank - 1] != b'\n';
+        rest = &rest[blank..];
+        if rest.is_empty() {
+            return out;
+        }
+        match parse_record(rest) {
+            Some((line, len)) => {
+                stats.records += 1;
+                let original = &rest[..len];
+                let ends_line = rest.get(len) == Some(&b'\n');
+                if indented || !ends_line || original != line.as_bytes() {
+                    stats.normalized += 1;
+                }
+                out.extend_from_slice(line.as_bytes());
+                out.push(b'\n');
+                // The record's own newline is not a blank line.
+                rest = &rest[len + usize::from(ends_line)..];
+            }
+            None => {
+                stats.unparsed += 1;
+                let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                out.extend_from_slice(&rest[..end]);
+                out.push(b'\n');
+                rest = &rest[(end + 1).min(rest.len())..];
+            }
+        }
+    }
+}
+
+/// Decodes the record at the start of `input`, which may span lines,
+/// returning its canonical line and how many bytes it took up, trailing
+/// spaces and tabs included.
+fn parse_record(input: &[u8]) -> Option<(String, usize)> {
+    // Only the record's own bytes need to be UTF-8.
+    let text = match std::str::from_utf8(input) {
+        Ok(text) => text,
+        Err(err) => std::str::from_utf8(&input[..err.valid_up_to()]).ok()?,
+    };
+    let (value, mut len) = json::parse_prefix(text).ok()?;
+    let (entry, dict_compressed) = LogEntry::from_json_value(&value).ok()?;
+    len += input[len..]
+        .iter()
+        .take_while(|&&b| b == b' ' || b == b'\t' || b == b'\r')
+        .count();
+    let next = input.get(len);
+    if next.is_some_and(|&b| b != b'\n') {
+        // Something else follows on the same line.
+        return None;
+    }
+    let base64_data = matches!(value.get("data"), Some(Value::String(_)));
+    Some((entry.to_json(base64_data, dict_compressed), len))
+}
+
+/// Replaces the file at `path` with `contents` through a synced temp file
+/// and a rename.
+fn replace(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".rewrite");
+    let temp = PathBuf::from(temp);
+    let mut out = File::create(&temp)?;
+    out.write_all(contents)?;
+    out.sync_data()?;
+    durable::rename(&temp, path)?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Addressing records by their byte offset in the active file, for indexes
+//! kept outside the log.
+
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends `data` like [`append`](Self::append) and returns the entry
+    /// with the byte offset it starts at in the active file, to be handed
+    /// to [`read_at`](Self::read_at) later.
+    ///
+    /// The entry is written straight away: with
+    /// [`group_commit`](crate::WriteAheadLogBuilder::group_commit), queued
+    /// records are flushed first so the offset is known.
+    pub fn append_at(&mut self, data: Vec<u8>) -> Result<(LogEntry, u64)> {
+        self.flush()?;
+        self.gate.pass(self.pause_mode)?;
+        let entry = LogEntry {
+            data,
+            timestamp: self.now()?,
+            ..LogEntry::default()
+        };
+        self.take_tokens(1)?;
+        self.make_room(1)?;
+        self.write_appended(entry)
+    }
+
+    /// Reads the record starting at `offset` in the active file, of any
+    /// kind, without scanning from the start. Offsets from
+    /// [`append_at`](Self::append_at) hold until the file is sealed by
+    /// rotation or rewritten, e.g. by [`compact`](Self::compact); an offset
+    /// that is not at the start of a record fails with
+    /// [`WalError::InvalidEntry`].
+    pub fn read_at(&self, offset: u64) -> Result<LogEntry> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+        if self.format.read_frame(&mut reader, &mut buf)? == 0 {
+            return Err(WalError::InvalidEntry(format!(
+                "no record at offset {offset}"
+            )));
+        }
+        self.format
+            .decode_with(&buf, self.dictionary.as_deref(), self.cipher.as_deref())
+    }
+}
@@ -0,0 +1,96 @@
+//! Consumer progress committed atomically with produced entries.
+//!
+//! The progress value is written into the entry itself, so the single line
+//! write is the commit point: after a crash either the entry and its progress
+//! are both in the log or neither is. A `<path>.progress` sidecar caches the
+//! latest value so readers need not trust the log alone, and is rebuilt from
+//! the log whenever it lags behind.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends `data` and records that everything up to `processed_up_to`
+    /// has been consumed, as one atomic step.
+    ///
+    /// The entry is synced to disk before the sidecar is updated; if the
+    /// process dies in between, [`processed_up_to`](Self::processed_up_to)
+    /// recovers the value from the entry.
+    pub fn append_and_checkpoint(
+        &mut self,
+        data: Vec<u8>,
+        processed_up_to: u64,
+    ) -> Result<LogEntry> {
+        self.check_writable()?;
+        let entry = self.append_record(LogEntry {
+            data,
+            processed_up_to: Some(processed_up_to),
+            ..LogEntry::default()
+        })?;
+        self.flush()?;
+        self.sync_file(&*self.lock_file()?, self.current_id)?;
+        write_sidecar(&self.progress_path(), processed_up_to, entry.id)?;
+        Ok(entry)
+    }
+
+    /// The most recent progress committed with
+    /// [`append_and_checkpoint`](Self::append_and_checkpoint), or `None` if
+    /// there is none.
+    pub fn processed_up_to(&self) -> Result<Option<u64>> {
+        let sidecar = read_sidecar(&self.progress_path())?;
+        let mut latest = sidecar;
+        for record in self.records()? {
+            let record = record?;
+            if let Some(processed) = record.processed_up_to {
+                if latest.is_none_or(|(_, id)| record.id > id) {
+                    latest = Some((processed, record.id));
+                }
+            }
+        }
+        if latest != sidecar && !self.read_only {
+            if let Some((processed, id)) = latest {
+                write_sidecar(&self.progress_path(), processed, id)?;
+            }
+        }
+        Ok(latest.map(|(processed, _)| processed))
+    }
+
+    pub(crate) fn progress_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".progress");
+        PathBuf::from(path)
+    }
+}
+
+/// Reads `(processed_up_to, entry_id)` from the sidecar, ignoring a missing
+/// or unreadable file.
+fn read_sidecar(path: &Path) -> Result<Option<(u64, u64)>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut parts = text.split_whitespace().map(str::parse::<u64>);
+    Ok(match (parts.next(), parts.next()) {
+        (Some(Ok(processed)), Some(Ok(id))) => Some((processed, id)),
+        _ => None,
+    })
+}
+
+/// Replaces the sidecar via a synced temp file and rename.
+fn write_sidecar(path: &Path, processed_up_to: u64, entry_id: u64) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = fs::File::create(&tmp)?;
+    writeln!(file, "{processed_up_to} {entry_id}")?;
+    file.sync_data()?;
+    durable::rename(&tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::marker::PhantomData;
+use std::path::Path;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// A protobuf message, encoded in the wire format.
+///
+/// This has the shape of `prost::Message`'s encoding methods, so a
+/// forwarding impl for generated types is two lines each.
+pub trait Message: Default + Sized {
+    /// Encodes the message into a new buffer.
+    fn encode_to_vec(&self) -> Vec<u8>;
+    /// Decodes a message from `buf`, describing the problem on failure.
+    fn decode(buf: &[u8]) -> std::result::Result<Self, String>;
+}
+
+/// A [`WriteAheadLog`] whose payloads are protobuf messages of type `M`.
+///
+/// Like [`TypedWal`](crate::TypedWal), messages are stored in the ordinary
+/// `data` field, but entries are decoded one at a time as they are read.
+#[derive(Debug)]
+pub struct ProtoWal<M> {
+    wal: WriteAheadLog,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M: Message> ProtoWal<M> {
+    /// Opens the log at `path` with default options.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from_wal(WriteAheadLog::new(path)?))
+    }
+
+    /// Wraps an already opened log.
+    pub fn from_wal(wal: WriteAheadLog) -> Self {
+        ProtoWal {
+            wal,
+            _message: PhantomData,
+        }
+    }
+
+    /// The underlying byte-level log.
+    pub fn inner(&self) -> &WriteAheadLog {
+        &self.wal
+    }
+
+    /// Unwraps the underlying byte-level log.
+    pub fn into_inner(self) -> WriteAheadLog {
+        self.wal
+    }
+
+    /// Encodes and appends `msg`.
+    pub fn append(&mut self, msg: &M) -> Result<LogEntry> {
+        self.wal.append(msg.encode_to_vec())
+    }
+
+    /// Streams every data entry as its ID and decoded message. A payload
+    /// that does not decode yields a [`WalError::Serialization`] error for
+    /// that entry, and iteration can carry on past it.
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<(u64, M)>>> {
+        let records = self.wal.records()?;
+        Ok(records.filter_map(|record| match record {
+            Ok(record) if record.kind != EntryKind::Data => None,
+            Ok(record) => Some(
+                M::decode(&record.data)
+                    .map(|msg| (record.id, msg))
+                    .map_err(WalError::Serialization),
+            ),
+            Err(err) => Some(Err(err)),
+        }))
+    }
+}
@@ -0,0 +1,110 @@
+//! Time-based retention: dropping the part of the log older than a cutoff.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::durable;
+use crate::entry::EntryKind;
+use crate::error::Result;
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Removes the entries stamped before `cutoff_ts`, returning how many
+    /// data entries went.
+    ///
+    /// Records are appended in time order, so rather than filtering the log
+    /// the prune finds the first record stamped at or after the cutoff and
+    /// drops everything before it: earlier segments are deleted, and the
+    /// segment holding that record has its tail copied to
+    /// `<segment>.prune`, synced and renamed over it. Everything before the
+    /// cut goes, including markers and records of other streams. If the
+    /// clock stepped back while the log was written, older entries beyond
+    /// the cut are kept. IDs keep counting up from where they were.
+    pub fn prune_before(&self, cutoff_ts: u64) -> Result<usize> {
+        self.check_writable()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        let segments = segment::all_segments(&self.path)?;
+        let mut removed = 0;
+        let mut passed = false;
+        let mut cut = None;
+        let mut buf = Vec::new();
+        'scan: for (index, path) in segments.iter().enumerate() {
+            let mut reader = BufReader::new(File::open(path)?);
+            let mut offset = header::skip(&mut reader)?;
+            loop {
+                let consumed = self.format.read_frame(&mut reader, &mut buf)?;
+                if consumed == 0 {
+                    break;
+                }
+                if let Ok((record, _)) = self.format.decode_flagged(&buf) {
+                    if record.timestamp >= cutoff_ts {
+                        cut = Some((index, offset));
+                        break 'scan;
+                    }
+                    removed += usize::from(
+                        record.kind == EntryKind::Data
+                            && record.stream == 0
+                            && !self.is_cleared(&record),
+                    );
+                }
+                passed = true;
+                offset += consumed as u64;
+            }
+        }
+        if !passed {
+            return Ok(0);
+        }
+
+        // Older segments go first, oldest first, so a crash part way
+        // leaves a suffix of the log.
+        let (index, offset) = match cut {
+            Some(cut) => cut,
+            None => (segments.len() - 1, file.metadata()?.len()),
+        };
+        self.keep_ids_below(self.current_id)?;
+        for path in &segments[..index] {
+            fs::remove_file(path)?;
+        }
+        durable::sync_dir(&self.path)?;
+        let target = &segments[index];
+        if *target == self.path {
+            cut_front(target, &file, offset, &self.header())?;
+            *file = segment::open_active(&self.path, &self.header())?;
+        } else {
+            cut_front(target, &File::open(target)?, offset, &self.header())?;
+        }
+        drop(file);
+
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(removed)
+    }
+
+    /// Removes the entries more than `age` old by the log's clock. See
+    /// [`prune_before`](Self::prune_before).
+    pub fn prune_older_than(&self, age: Duration) -> Result<usize> {
+        self.prune_before(self.now()?.saturating_sub(age.as_secs()))
+    }
+}
+
+/// Replaces the file at `path`, read through `file`, with `header` followed
+/// by its bytes from `offset` on, written to `<path>.prune` and renamed over
+/// it.
+fn cut_front(path: &Path, file: &File, offset: u64, header: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".prune");
+    let temp = PathBuf::from(temp);
+    let mut out = File::create(&temp)?;
+    out.write_all(header)?;
+    let mut reader = file.try_clone()?;
+    reader.seek(SeekFrom::Start(offset))?;
+    io::copy(&mut reader, &mut out)?;
+    out.sync_data()?;
+    durable::rename(&temp, path)?;
+    Ok(())
+}
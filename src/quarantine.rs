@@ -0,0 +1,98 @@
+//! Preservation of undecodable records for later forensics.
+//!
+//! Each quarantined record is appended to `<path>.quarantine` as a header
+//! line followed by the record's bytes exactly as they were found:
+//!
+//! ```text
+//! offset=<byte offset> len=<byte count> segment=<file name>
+//! <raw bytes>
+//! ```
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::Result;
+
+#[derive(Debug)]
+pub(crate) struct Quarantine {
+    path: PathBuf,
+    /// `(segment, offset)` pairs already written, loaded from the file on
+    /// first use so repeated reads do not quarantine a record twice.
+    seen: Mutex<Option<HashSet<(String, u64)>>>,
+}
+
+impl Quarantine {
+    pub(crate) fn new(log_path: &Path) -> Self {
+        let mut path = log_path.as_os_str().to_owned();
+        path.push(".quarantine");
+        Quarantine {
+            path: PathBuf::from(path),
+            seen: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `raw`, found at `offset` in `segment`, unless it was already
+    /// quarantined.
+    pub(crate) fn record(&self, segment: &Path, offset: u64, raw: &[u8]) -> Result<()> {
+        let name = segment
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut seen = self.seen.lock()?;
+        let seen = match &mut *seen {
+            Some(seen) => seen,
+            None => seen.insert(self.load()?),
+        };
+        if !seen.insert((name.clone(), offset)) {
+            return Ok(());
+        }
+        let mut out = format!("offset={offset} len={} segment={name}\n", raw.len()).into_bytes();
+        out.extend_from_slice(raw);
+        out.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&out)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    fn load(&self) -> Result<HashSet<(String, u64)>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut seen = HashSet::new();
+        let mut rest = &bytes[..];
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            let header = String::from_utf8_lossy(&rest[..end]);
+            let mut offset = None;
+            let mut len = None;
+            let mut segment = None;
+            for field in header.split(' ') {
+                match field.split_once('=') {
+                    Some(("offset", v)) => offset = v.parse::<u64>().ok(),
+                    Some(("len", v)) => len = v.parse::<usize>().ok(),
+                    Some(("segment", v)) => segment = Some(v.to_string()),
+                    _ => {}
+                }
+            }
+            let (Some(offset), Some(len), Some(segment)) = (offset, len, segment) else {
+                break;
+            };
+            seen.insert((segment, offset));
+            let skip = (end + 1 + len + 1).min(rest.len());
+            rest = &rest[skip..];
+        }
+        Ok(seen)
+    }
+}
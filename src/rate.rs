@@ -0,0 +1,59 @@
+//! Rejecting appends beyond a sustained rate.
+
+use std::path::Path;
+use std::time::Instant;
+
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// Allows up to `burst` appends at once, refilling at `rate` per second.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    /// Starts full.
+    pub(crate) fn new(rate: f64, burst: u32) -> Self {
+        TokenBucket {
+            rate,
+            burst: f64::from(burst),
+            tokens: f64::from(burst),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Takes `n` tokens if that many are available, or none at all.
+    fn try_take(&mut self, n: usize) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.refilled = now;
+        if self.tokens < n as f64 {
+            return false;
+        }
+        self.tokens -= n as f64;
+        true
+    }
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path` allowing on average `rate` appends per
+    /// second, with bursts of up to `burst`. See
+    /// [`WriteAheadLogBuilder::token_bucket`](crate::WriteAheadLogBuilder::token_bucket).
+    pub fn with_token_bucket<P: AsRef<Path>>(path: P, rate: f64, burst: u32) -> Result<Self> {
+        Self::builder(path).token_bucket(rate, burst).build()
+    }
+
+    /// Takes a token for each of `n` appends about to be made, failing with
+    /// [`WalError::RateLimited`] if the bucket cannot cover them all.
+    pub(crate) fn take_tokens(&self, n: usize) -> Result<()> {
+        match &self.token_bucket {
+            Some(bucket) if !bucket.lock()?.try_take(n) => Err(WalError::RateLimited),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Read-only handles, for tools such as auditors that must never change a
+//! log.
+
+use std::path::Path;
+
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Opens the log at `path` for reading only. The file must already
+    /// exist; reads such as [`read_all`](Self::read_all),
+    /// [`iter`](Self::iter), [`get`](Self::get) and [`len`](Self::len) work
+    /// as usual, while appends, [`clear`](Self::clear),
+    /// [`clear_id`](Self::clear_id) and every other change fail with
+    /// [`WalError::ReadOnly`]. See
+    /// [`WriteAheadLogBuilder::read_only`](crate::WriteAheadLogBuilder::read_only).
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::builder(path).read_only(true).build()
+    }
+
+    /// Whether the log was opened read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fails with [`WalError::ReadOnly`] if the log may not be changed.
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(WalError::ReadOnly);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+//! A log confined to a byte range of a file shared with other data.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::{Mutex, PoisonError};
+
+use crate::clock::{Clock, SystemClock};
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::format::Format;
+use crate::wal::WriteAheadLog;
+
+/// A log kept in `[base_offset, base_offset + len)` of a file, returned by
+/// [`WriteAheadLog::within`].
+///
+/// Records are [`Format::Binary`] with a checksum, and the last one is
+/// followed by a zero length prefix marking the end, so the region must be
+/// zeroed before first use. A record cut short by a crash fails its checksum
+/// and is treated as the end; the next append overwrites it. Nothing outside
+/// the region is ever read or written.
+#[derive(Debug)]
+pub struct RegionWal {
+    file: Mutex<File>,
+    base: u64,
+    len: u64,
+    /// Offset within the region of the end of the last record.
+    end: u64,
+    next_id: u64,
+}
+
+impl WriteAheadLog {
+    /// Opens a log occupying `len` bytes of `file` from `base_offset`, for
+    /// embedding one in a larger file such as a reserved region of a
+    /// database. Appends that would cross the end of the region fail with
+    /// [`WalError::LogFull`]. See [`RegionWal`].
+    pub fn within(file: File, base_offset: u64, len: u64) -> Result<RegionWal> {
+        let mut region = RegionWal {
+            file: Mutex::new(file),
+            base: base_offset,
+            len,
+            end: 0,
+            next_id: 0,
+        };
+        let (records, end) = region.scan()?;
+        region.end = end;
+        region.next_id = records.iter().map(|e| e.id + 1).max().unwrap_or(0);
+        Ok(region)
+    }
+}
+
+impl RegionWal {
+    /// The ID the next appended entry will receive.
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Bytes of the region taken up by records.
+    pub fn used_bytes(&self) -> u64 {
+        self.end
+    }
+
+    /// Size of the region.
+    pub fn capacity_bytes(&self) -> u64 {
+        self.len
+    }
+
+    /// Appends `data` as a new entry, failing with [`WalError::LogFull`] if
+    /// it does not fit in what is left of the region.
+    pub fn append(&mut self, data: Vec<u8>) -> Result<LogEntry> {
+        let mut entry = LogEntry {
+            id: self.next_id,
+            timestamp: SystemClock.now_unix()?,
+            data,
+            ..LogEntry::default()
+        };
+        entry.checksum = entry.compute_checksum();
+        let mut record = Format::Binary.encode(&entry);
+        let after = self.end + record.len() as u64;
+        if after > self.len {
+            return Err(WalError::LogFull);
+        }
+        if after + 4 <= self.len {
+            record.extend_from_slice(&[0; 4]);
+        }
+        let file = self.file.get_mut().unwrap();
+        file.seek(SeekFrom::Start(self.base + self.end))?;
+        file.write_all(&record)?;
+        file.flush()?;
+        self.end = after;
+        self.next_id += 1;
+        Ok(entry)
+    }
+
+    /// Reads every data entry in the region, in order.
+    pub fn read_all(&self) -> Result<Vec<LogEntry>> {
+        Ok(self
+            .scan()?
+            .0
+            .into_iter()
+            .filter(|e| e.kind == EntryKind::Data)
+            .collect())
+    }
+
+    /// Syncs the file to disk.
+    pub fn sync(&self) -> Result<()> {
+        self.file.lock()?.sync_data()?;
+        Ok(())
+    }
+
+    /// Releases the file.
+    pub fn into_file(self) -> File {
+        self.file
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reads records from the start of the region up to the end marker or
+    /// the first damaged record, and the offset where they end.
+    fn scan(&self) -> Result<(Vec<LogEntry>, u64)> {
+        let mut file = self.file.lock()?;
+        file.seek(SeekFrom::Start(self.base))?;
+        let mut reader = (&mut *file).take(self.len);
+        let mut records = Vec::new();
+        let mut end = 0;
+        let mut prefix = [0u8; 4];
+        while end + 4 <= self.len && reader.read_exact(&mut prefix).is_ok() {
+            let body_len = u64::from(u32::from_le_bytes(prefix));
+            if body_len == 0 || end + 4 + body_len > self.len {
+                break;
+            }
+            let mut body = vec![0; body_len as usize];
+            reader.read_exact(&mut body)?;
+            match Format::Binary.decode_with(&body, None, None) {
+                Ok(entry) if entry.has_checksum() && entry.is_checksum_valid() => {
+                    records.push(entry)
+                }
+                _ => break,
+            }
+            end += 4 + body_len;
+        }
+        Ok((records, end))
+    }
+}
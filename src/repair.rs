@@ -0,0 +1,169 @@
+//! Recovering a damaged log by dropping the records that no longer read.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use crate::durable;
+use crate::error::{Result, WalError};
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Outcome of [`WriteAheadLog::repair`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Records kept, markers and other streams included.
+    pub kept: u64,
+    /// Records dropped because they did not decode or failed their
+    /// checksum, counting trailing bytes that hold no whole frame as one.
+    pub dropped: u64,
+    /// Bytes the dropped records took up, framing included.
+    pub dropped_bytes: u64,
+    /// Where the dropped bytes lay in the files as they were before the
+    /// repair, adjacent records merged into one range, in file order.
+    pub dropped_ranges: Vec<(PathBuf, Range<u64>)>,
+}
+
+impl RepairReport {
+    /// Whether the log was intact, so nothing was rewritten.
+    pub fn is_clean(&self) -> bool {
+        self.dropped == 0
+    }
+
+    fn drop_range(&mut self, path: &Path, range: Range<u64>) {
+        self.dropped += 1;
+        self.dropped_bytes += range.end - range.start;
+        match self.dropped_ranges.last_mut() {
+            Some((last, prev)) if last == path && prev.end == range.start => prev.end = range.end,
+            _ => self.dropped_ranges.push((path.to_path_buf(), range)),
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Rebuilds a log damaged by a partial disk failure, keeping every
+    /// record that decodes and, if it carries one, matches its checksum,
+    /// and dropping the rest, which reads would skip or fail on. Unlike
+    /// [`compact`](Self::compact), which reclaims the space of cleared
+    /// entries, this only removes damage, and reports where it was.
+    ///
+    /// Each segment is streamed a record at a time into `<segment>.repair`
+    /// and, if anything was dropped from it, renamed over the original, so
+    /// a crash leaves every segment either as it was or repaired. Kept
+    /// records are copied byte for byte. In the binary formats a damaged
+    /// length makes the rest of the segment unreadable, and it is dropped
+    /// as a single range.
+    ///
+    /// IDs keep counting up from where they were, even past dropped
+    /// records that held the largest ones, as on reopening. A payload that fails to decrypt fails the repair with
+    /// nothing changed, since the key rather than the record may be at
+    /// fault. Records queued by group commit are written first.
+    pub fn repair(&mut self) -> Result<RepairReport> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        self.flush()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        let mut report = RepairReport::default();
+        let mut replaced = Vec::new();
+        for path in segment::all_segments(&self.path)? {
+            match self.repair_into_temp(&path, &mut report) {
+                Ok(Some(temp)) => replaced.push((temp, path)),
+                Ok(None) => {}
+                Err(err) => {
+                    for (temp, _) in replaced {
+                        let _ = fs::remove_file(temp);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        if replaced.is_empty() {
+            return Ok(report);
+        }
+        self.keep_ids_below(self.current_id)?;
+        for (temp, path) in replaced {
+            durable::rename(&temp, &path)?;
+        }
+        *file = segment::open_active(&self.path, &self.header())?;
+        drop(file);
+
+        self.tombstones.lock()?.clear();
+        let (next_id, tombstones) = self.load_ids()?;
+        *self.tombstones.lock()? = tombstones;
+        self.current_id = self.continue_after(next_id);
+        self.durable_id.fetch_min(self.current_id, Ordering::AcqRel);
+        self.idempotency_keys = None;
+        self.stream_ids.lock()?.clear();
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(report)
+    }
+
+    /// Writes the intact records of the segment at `path` to a temporary
+    /// file, returning its path if anything was dropped, or removing it and
+    /// returning `None` if not.
+    fn repair_into_temp(&self, path: &Path, report: &mut RepairReport) -> Result<Option<PathBuf>> {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".repair");
+        let temp = PathBuf::from(temp);
+        let dropped_before = report.dropped;
+        let result = self.copy_intact(path, &temp, report);
+        if result.is_err() || report.dropped == dropped_before {
+            let _ = fs::remove_file(&temp);
+        }
+        result?;
+        Ok((report.dropped > dropped_before).then_some(temp))
+    }
+
+    fn copy_intact(&self, path: &Path, temp: &Path, report: &mut RepairReport) -> Result<()> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut out = BufWriter::new(File::create(temp)?);
+        out.write_all(&self.header())?;
+        let framer = self.format.framer();
+        let mut offset = header::skip(&mut reader)?;
+        let mut buf = Vec::new();
+        loop {
+            let consumed = match self.format.read_frame(&mut reader, &mut buf) {
+                Ok(consumed) => consumed as u64,
+                // A varint length that never ends.
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => 0,
+                Err(err) => return Err(err.into()),
+            };
+            if consumed == 0 {
+                break;
+            }
+            if self.is_intact(&buf)? {
+                report.kept += 1;
+                out.write_all(&framer.frame(std::mem::take(&mut buf)))?;
+            } else {
+                report.drop_range(path, offset..offset + consumed);
+            }
+            offset += consumed;
+        }
+        if offset < len {
+            report.drop_range(path, offset..len);
+        }
+        out.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_data()?;
+        Ok(())
+    }
+
+    /// Whether `body` decodes to a record whose checksum, if any, matches.
+    fn is_intact(&self, body: &[u8]) -> Result<bool> {
+        match self
+            .format
+            .decode_with(body, self.dictionary.as_deref(), self.cipher.as_deref())
+        {
+            Ok(record) => Ok(record.is_checksum_valid()),
+            Err(err @ WalError::Decryption { .. }) => Err(err),
+            Err(_) => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! At-least-once recovery: entries are removed only after they are handled.
+
+use std::collections::HashSet;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Streams the data entries to `f` in file order, as
+    /// [`iter`](Self::iter) would yield them, then removes those `f`
+    /// returned `Ok(true)` for in a single
+    /// [`compact_retain`](Self::compact_retain) pass. Entries it returned
+    /// `Ok(false)` for stay in the log.
+    ///
+    /// If `f` fails, replay stops there: the entries handled so far are
+    /// still removed and the error is returned, leaving that entry and the
+    /// rest for the next run. A crash before the removal is made has
+    /// everything replayed again, so `f` should tolerate seeing an entry
+    /// twice.
+    pub fn replay<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&LogEntry) -> Result<bool>,
+    {
+        let records = {
+            let _file = self.lock_file()?;
+            self.records()?
+        };
+        let mut processed = HashSet::new();
+        let mut outcome = Ok(());
+        for record in records {
+            let record = match record {
+                Ok(record) if record.kind == EntryKind::Data => record,
+                Ok(_) => continue,
+                Err(err) => {
+                    outcome = Err(err);
+                    break;
+                }
+            };
+            match f(&record) {
+                Ok(true) => {
+                    processed.insert(record.id);
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    outcome = Err(err);
+                    break;
+                }
+            }
+        }
+        if !processed.is_empty() {
+            self.compact_retain(|e| {
+                e.stream != 0 || e.kind != EntryKind::Data || !processed.contains(&e.id)
+            })?;
+        }
+        outcome
+    }
+}
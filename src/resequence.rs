@@ -0,0 +1,107 @@
+//! Renumbering a sparse log so that its IDs are contiguous again.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+use crate::durable;
+use crate::entry::EntryKind;
+use crate::error::Result;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Maps each old ID to its new one, as returned by
+/// [`WriteAheadLog::resequence`].
+pub type ResequenceMap = BTreeMap<u64, u64>;
+
+impl WriteAheadLog {
+    /// Rewrites the log with contiguous IDs from 0, or from the
+    /// [`start_id`](crate::WriteAheadLogBuilder::start_id) it was opened
+    /// with, in the existing order, and returns the mapping from old to new
+    /// IDs so that external references can be updated.
+    ///
+    /// Status records are retargeted, and dropped if their entry is gone.
+    /// Entries removed by [`clear_id`](Self::clear_id) are dropped together
+    /// with their tombstones.
+    /// Checksums and digests are recomputed, since they cover the ID. Other
+    /// streams keep their own numbering. Queued group-commit records are
+    /// written first.
+    ///
+    /// The log is written to a temp file that replaces the active file in a
+    /// single rename, with any sealed segments folded into it and deleted
+    /// afterwards; a crash in between leaves them to be cleaned up by hand.
+    pub fn resequence(&mut self) -> Result<ResequenceMap> {
+        self.check_writable()?;
+        self.flush()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        let mut records = Vec::new();
+        for record in self.all_records()? {
+            records.push(record?);
+        }
+        let present: HashSet<u64> = records
+            .iter()
+            .filter(|r| r.stream == 0)
+            .map(|r| r.id)
+            .collect();
+        // Reads already skip what tombstones cleared, so the tombstones
+        // themselves can go.
+        records.retain(|r| match r.kind {
+            _ if r.stream != 0 => true,
+            EntryKind::Status => r.target.is_some_and(|t| present.contains(&t)),
+            EntryKind::Tombstone => false,
+            _ => true,
+        });
+
+        let mut map = ResequenceMap::new();
+        for record in records.iter_mut().filter(|r| r.stream == 0) {
+            let id = self.start_id + map.len() as u64;
+            map.insert(record.id, id);
+            record.id = id;
+        }
+        for record in &mut records {
+            if record.stream == 0 {
+                record.target = record.target.map(|t| map[&t]);
+            }
+            if record.has_checksum() {
+                record.checksum = record.compute_checksum();
+            }
+            if let (false, Some(hasher)) = (record.digest.is_empty(), &self.hasher) {
+                record.digest = record.compute_digest(&**hasher);
+            }
+        }
+
+        let mut temp = self.path.as_os_str().to_owned();
+        temp.push(".resequence");
+        let temp = PathBuf::from(temp);
+        let mut out = File::create(&temp)?;
+        out.write_all(&self.header())?;
+        let mut written = 0;
+        for record in &records {
+            let bytes = self.encode_record(record)?;
+            out.write_all(&bytes)?;
+            written += bytes.len() as u64;
+        }
+        out.sync_data()?;
+        let sealed = segment::sealed_segments(&self.path)?;
+        durable::rename(&temp, &self.path)?;
+        *file = segment::open_active(&self.path, &self.header())?;
+        for (_, path) in sealed {
+            fs::remove_file(path)?;
+        }
+        durable::sync_dir(&self.path)?;
+        drop(file);
+
+        self.writes.rewritten(written);
+        self.current_id = self.start_id + map.len() as u64;
+        self.reset_ids_below(self.current_id)?;
+        self.durable_id.store(self.current_id, Ordering::Release);
+        self.idempotency_keys = None;
+        self.tombstones.lock()?.clear();
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(map)
+    }
+}
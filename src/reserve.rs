@@ -0,0 +1,114 @@
+//! Two-phase appends: reserve an ID now, commit its payload later.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::group_commit::Pending;
+use crate::wal::WriteAheadLog;
+
+/// An ID handed out by [`reserve`](WriteAheadLog::reserve) and not yet
+/// written. Pass it to [`commit`](WriteAheadLog::commit) to write the entry;
+/// dropping it instead aborts the entry and leaves its ID unused.
+#[derive(Debug, PartialEq, Eq)]
+#[must_use = "dropping a reservation aborts it, leaving a gap in the IDs"]
+pub struct ReservedEntry {
+    id: u64,
+}
+
+impl ReservedEntry {
+    /// The ID the entry will have once committed.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl WriteAheadLog {
+    /// Takes the next ID for an entry whose payload is not ready yet,
+    /// writing nothing. The entry reaches the log only through
+    /// [`commit`](Self::commit).
+    ///
+    /// A reservation that is dropped rather than committed is aborted, and
+    /// its ID is never handed out again: the log is left with a gap in its
+    /// IDs there, which reopening the log does not fill. IDs are only ever
+    /// taken, not returned, so
+    /// [`next_id`](Self::next_id) and [`durable_id`](Self::durable_id) count
+    /// outstanding reservations as used.
+    ///
+    /// Records must reach the file in ID order, so a reservation can only
+    /// be committed until a record with a larger ID is written: append,
+    /// clear or commit anything reserved later first and the commit fails
+    /// with [`WalError::Overtaken`]. With
+    /// [`group_commit`](crate::WriteAheadLogBuilder::group_commit), queued
+    /// [`append_deferred`](Self::append_deferred) records are given their
+    /// IDs first so that they stay ahead of the reservation.
+    pub fn reserve(&mut self) -> ReservedEntry {
+        self.assign_deferred();
+        let id = self.current_id;
+        self.current_id += 1;
+        ReservedEntry { id }
+    }
+
+    /// Writes `data` as the entry `reserved` was taken for, with the time
+    /// of the commit as its timestamp, subject to the same pausing, rate
+    /// and size limits, syncing and group commit as
+    /// [`append`](Self::append).
+    ///
+    /// Fails with [`WalError::Overtaken`] if a record with a larger ID has
+    /// been written or queued since; the reservation is then aborted. Fails
+    /// with [`WalError::InvalidEntry`] for an ID this log has not handed
+    /// out.
+    pub fn commit(&mut self, reserved: ReservedEntry, data: Vec<u8>) -> Result<LogEntry> {
+        let id = reserved.id;
+        if id >= self.current_id {
+            return Err(WalError::InvalidEntry(format!(
+                "ID {id} was not reserved from this log"
+            )));
+        }
+        let overtaken = self.written_id.load(Ordering::Acquire) > id
+            || self
+                .queue
+                .iter()
+                .any(|item| item.assigned && item.entry.id > id);
+        if overtaken {
+            return Err(WalError::Overtaken { id });
+        }
+        self.gate.pass(self.pause_mode)?;
+        let mut entry = LogEntry {
+            id,
+            data,
+            timestamp: self.now()?,
+            ..LogEntry::default()
+        };
+        self.take_tokens(1)?;
+        self.make_room(1)?;
+        self.finalize(&mut entry);
+        if self.group_commit {
+            // Deferred records queued since the reservation take larger IDs
+            // when flushed, so the entry goes ahead of them.
+            let at = self
+                .queue
+                .iter()
+                .position(|item| !item.assigned)
+                .unwrap_or(self.queue.len());
+            self.queue.insert(
+                at,
+                Pending {
+                    entry: entry.clone(),
+                    slot: None,
+                    assigned: true,
+                    on_durable: None,
+                },
+            );
+            return Ok(entry);
+        }
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        self.write_record(&mut file, &entry)?;
+        self.sync_for_policy(&file, 1, id + 1)?;
+        drop(file);
+        self.forward([&entry])?;
+        Ok(entry)
+    }
+}
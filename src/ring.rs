@@ -0,0 +1,216 @@
+//! A fixed-size circular log that overwrites its oldest entries.
+//!
+//! The file is a 32-byte header followed by `capacity` slots of equal size:
+//!
+//! ```text
+//! magic "WALYRNG1" | capacity u64 | slot size u64 | next id u64 | slots...
+//! ```
+//!
+//! Entry `id` lives in slot `id % capacity`, encoded as a
+//! [`Format::CompactBinary`] record with a checksum and zero-filled to the
+//! slot size. The file is allocated in full when created and never grows.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::clock::{Clock, SystemClock};
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::format::Format;
+use crate::lock;
+
+const MAGIC: &[u8; 8] = b"WALYRNG1";
+const HEADER_LEN: u64 = 32;
+/// Offset of the next-ID field within the header.
+const NEXT_ID_OFFSET: u64 = 24;
+/// Slot size used by [`RingWal::new`].
+pub const DEFAULT_SLOT_BYTES: usize = 4096;
+
+/// A log of the most recent `capacity` entries, kept in a file of fixed
+/// size. Once full, each append overwrites the oldest entry in place.
+///
+/// Unlike [`WriteAheadLog`](crate::WriteAheadLog) rotation this never
+/// creates or deletes files, and every append writes one slot and the
+/// header, whatever the log's size. A crash between the two leaves the
+/// header behind the slot; the slot's entry is then lost and the one it
+/// replaced is skipped by reads, since its ID no longer matches.
+///
+/// Like the log, the ring holds an exclusive advisory lock on
+/// `<path>.lock` while open.
+#[derive(Debug)]
+pub struct RingWal {
+    path: PathBuf,
+    file: File,
+    capacity: u64,
+    slot_bytes: usize,
+    next_id: u64,
+    /// Holds the advisory lock on `<path>.lock` until dropped.
+    _lock: File,
+}
+
+impl RingWal {
+    /// Opens the ring at `path`, creating it with room for `capacity`
+    /// entries of up to [`DEFAULT_SLOT_BYTES`] encoded bytes each.
+    pub fn new<P: AsRef<Path>>(path: P, capacity: usize) -> Result<Self> {
+        Self::with_slot_size(path, capacity, DEFAULT_SLOT_BYTES)
+    }
+
+    /// Opens the ring at `path`, creating it with `capacity` slots of
+    /// `slot_bytes` each. An existing ring must have been created with the
+    /// same dimensions, or this fails with [`WalError::ConfigMismatch`].
+    /// Fails with [`WalError::InvalidConfig`] if the file would be too
+    /// large to address, and with [`WalError::Locked`] if another handle
+    /// has the ring open.
+    pub fn with_slot_size<P: AsRef<Path>>(
+        path: P,
+        capacity: usize,
+        slot_bytes: usize,
+    ) -> Result<Self> {
+        if capacity == 0 || slot_bytes == 0 {
+            return Err(WalError::InvalidConfig(
+                "ring capacity and slot size must be greater than zero".to_string(),
+            ));
+        }
+        let (capacity, slot_bytes) = (capacity as u64, slot_bytes as u64);
+        let file_len = capacity
+            .checked_mul(slot_bytes)
+            .and_then(|slots| slots.checked_add(HEADER_LEN))
+            .ok_or_else(|| {
+                WalError::InvalidConfig(format!(
+                    "a ring of {capacity} slots of {slot_bytes} bytes is too large"
+                ))
+            })?;
+        let path = path.as_ref().to_path_buf();
+        let lock = lock::lock_exclusive(&path)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let next_id = if file.metadata()?.len() == 0 {
+            let mut header = Vec::with_capacity(HEADER_LEN as usize);
+            header.extend_from_slice(MAGIC);
+            header.extend_from_slice(&capacity.to_le_bytes());
+            header.extend_from_slice(&slot_bytes.to_le_bytes());
+            header.extend_from_slice(&0u64.to_le_bytes());
+            file.write_all(&header)?;
+            file.set_len(file_len)?;
+            file.sync_data()?;
+            0
+        } else {
+            let mut header = [0u8; HEADER_LEN as usize];
+            file.read_exact(&mut header)?;
+            if &header[..8] != MAGIC {
+                return Err(WalError::InvalidEntry(
+                    "not a ring log: bad magic".to_string(),
+                ));
+            }
+            let field = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+            if (field(8), field(16)) != (capacity, slot_bytes) {
+                return Err(WalError::ConfigMismatch(format!(
+                    "ring was created with {} slots of {} bytes",
+                    field(8),
+                    field(16)
+                )));
+            }
+            field(NEXT_ID_OFFSET as usize)
+        };
+        Ok(RingWal {
+            path,
+            file,
+            capacity,
+            slot_bytes: slot_bytes as usize,
+            next_id,
+            _lock: lock,
+        })
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many entries the ring holds when full.
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Number of entries currently held.
+    pub fn len(&self) -> usize {
+        self.next_id.min(self.capacity) as usize
+    }
+
+    /// Whether nothing has been appended yet.
+    pub fn is_empty(&self) -> bool {
+        self.next_id == 0
+    }
+
+    /// The ID the next appended entry will receive. IDs keep counting up
+    /// across wrap-arounds.
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Appends `data`, overwriting the oldest entry if the ring is full.
+    /// Fails with [`WalError::InvalidConfig`] if the encoded entry does not
+    /// fit in a slot.
+    pub fn append(&mut self, data: Vec<u8>) -> Result<LogEntry> {
+        let mut entry = LogEntry {
+            id: self.next_id,
+            timestamp: SystemClock.now_unix()?,
+            data,
+            ..LogEntry::default()
+        };
+        entry.checksum = entry.compute_checksum();
+        let mut slot = Format::CompactBinary.encode(&entry);
+        if slot.len() > self.slot_bytes {
+            return Err(WalError::InvalidConfig(format!(
+                "entry of {} bytes does not fit the ring's {}-byte slots",
+                slot.len(),
+                self.slot_bytes
+            )));
+        }
+        slot.resize(self.slot_bytes, 0);
+        self.file
+            .seek(SeekFrom::Start(self.slot_offset(entry.id)))?;
+        self.file.write_all(&slot)?;
+        self.file.seek(SeekFrom::Start(NEXT_ID_OFFSET))?;
+        self.file.write_all(&(entry.id + 1).to_le_bytes())?;
+        self.next_id = entry.id + 1;
+        Ok(entry)
+    }
+
+    /// Reads the entries held, oldest first. Slots whose record is damaged
+    /// or out of place are skipped.
+    pub fn read_all(&self) -> Result<Vec<LogEntry>> {
+        let first = self.next_id.saturating_sub(self.capacity);
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut slot = vec![0u8; self.slot_bytes];
+        let mut buf = Vec::new();
+        let mut entries = Vec::with_capacity(self.len());
+        for id in first..self.next_id {
+            reader.seek(SeekFrom::Start(self.slot_offset(id)))?;
+            reader.read_exact(&mut slot)?;
+            if Format::CompactBinary.read_frame(&mut &slot[..], &mut buf)? == 0 {
+                continue;
+            }
+            match Format::CompactBinary.decode_with(&buf, None, None) {
+                Ok(entry) if entry.id == id && entry.is_checksum_valid() => entries.push(entry),
+                _ => {}
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Syncs the ring file to disk.
+    pub fn sync(&self) -> Result<()> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    fn slot_offset(&self, id: u64) -> u64 {
+        HEADER_LEN + (id % self.capacity) * self.slot_bytes as u64
+    }
+}
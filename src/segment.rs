@@ -0,0 +1,208 @@
+//! Optional rotation of the log into numbered segment files.
+//!
+//! The active segment always lives at the configured path. When it grows past
+//! [`max_segment_bytes`](crate::WriteAheadLogBuilder::max_segment_bytes) it is
+//! sealed by renaming it to `<path>.000001`, `<path>.000002`, ... and a fresh
+//! file takes its place. Reads stitch sealed segments and the active one
+//! together in sequence order.
+
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use crate::durable;
+use crate::entry::LogEntry;
+use crate::error::Result;
+use crate::header;
+use crate::wal::WriteAheadLog;
+
+/// Sealed segments of the log at `path`, ordered oldest first. Numbers are
+/// padded to six digits but may outgrow them, so they are compared by value.
+pub(crate) fn sealed_segments(path: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let (dir, name) = split_path(path);
+    let prefix = format!("{name}.");
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(seq) = file_name
+            .to_str()
+            .and_then(|n| n.strip_prefix(&prefix))
+            .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|s| s.parse().ok())
+        else {
+            continue;
+        };
+        segments.push((seq, entry.path()));
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// The sealed segments followed by the active file, oldest first.
+pub(crate) fn all_segments(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = sealed_segments(path)?.into_iter().map(|(_, p)| p).collect();
+    paths.push(path.to_path_buf());
+    Ok(paths)
+}
+
+fn split_path(path: &Path) -> (&Path, String) {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (dir, name)
+}
+
+pub(crate) fn segment_path(path: &Path, seq: u64) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{seq:06}"));
+    PathBuf::from(name)
+}
+
+fn archive_path(path: &Path, stamp: u64, attempt: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    match attempt {
+        0 => name.push(format!(".{stamp}.archive")),
+        n => name.push(format!(".{stamp}-{n}.archive")),
+    }
+    PathBuf::from(name)
+}
+
+/// Opens the active file for appending, creating it with `header` if it
+/// does not exist.
+pub(crate) fn open_active(path: &Path, header: &[u8]) -> Result<File> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    header::write_if_empty(&mut file, header)?;
+    Ok(file)
+}
+
+impl WriteAheadLog {
+    /// Paths of every segment backing the log, oldest first. Without rotation
+    /// this is just [`path`](Self::path).
+    pub fn segments(&self) -> Result<Vec<PathBuf>> {
+        all_segments(&self.path)
+    }
+
+    /// Seals the active segment if writing `incoming` more bytes would take
+    /// it past the configured limit.
+    pub(crate) fn maybe_rotate(&self, file: &mut File, incoming: u64) -> Result<()> {
+        let Some(limit) = self.max_segment_bytes else {
+            return Ok(());
+        };
+        if self.compacting.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let len = file.metadata()?.len() + self.buffered_bytes()?;
+        if len > self.header_len() && len + incoming > limit {
+            self.seal_active(file)?;
+        }
+        Ok(())
+    }
+
+    /// Renames the active file to the next segment number, replaces `file`
+    /// with a fresh active file and applies the segment cap. Returns the
+    /// sealed segment's path.
+    pub(crate) fn seal_active(&self, file: &mut File) -> Result<PathBuf> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        let sealed = sealed_segments(&self.path)?;
+        let next = sealed.last().map_or(1, |(seq, _)| seq + 1);
+        let target = segment_path(&self.path, next);
+        self.drain_write_buffer(file)?;
+        file.sync_data()?;
+        durable::rename(&self.path, &target)?;
+        *file = open_active(&self.path, &self.header())?;
+        self.evict_segments()?;
+        Ok(target)
+    }
+
+    /// Moves the active file out of the log to a timestamped archive,
+    /// `<path>.<unix-seconds>.archive`, and continues in a fresh file. The
+    /// swap happens under the log's lock so no append lands in between, and
+    /// IDs keep counting up from where they were, also once the log is
+    /// reopened, so they stay unique across the log and its archives.
+    /// Queued group-commit records are written first. Sealed segments stay
+    /// part of the log.
+    pub fn rotate_now(&mut self) -> Result<PathBuf> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        self.flush()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        let stamp = self.now()?;
+        self.keep_ids_below(self.current_id)?;
+        let mut target = archive_path(&self.path, stamp, 0);
+        let mut attempt = 0;
+        while target.exists() {
+            attempt += 1;
+            target = archive_path(&self.path, stamp, attempt);
+        }
+        self.sync_file(&file, self.current_id)?;
+        durable::rename(&self.path, &target)?;
+        *file = open_active(&self.path, &self.header())?;
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(target)
+    }
+
+    /// Deletes the oldest sealed segments until the total count, active file
+    /// included, is within `max_segments`.
+    fn evict_segments(&self) -> Result<()> {
+        let Some(max) = self.max_segments else {
+            return Ok(());
+        };
+        let sealed = sealed_segments(&self.path)?;
+        let excess = (sealed.len() + 1).saturating_sub(max);
+        if excess > 0 {
+            self.keep_ids_below(self.current_id)?;
+        }
+        for (_, path) in sealed.into_iter().take(excess) {
+            if let Some(callback) = &self.on_segment_evicted {
+                callback(&path);
+            }
+            fs::remove_file(&path)?;
+            *self.entry_count.lock()? = None;
+            self.invalidate_cache();
+        }
+        if excess > 0 {
+            durable::sync_dir(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Applies `f` to the records of every segment in turn, rewriting those
+    /// for which it returns `true`, each with an atomic replace. `active` is
+    /// reopened on the replacement of the active file.
+    pub(crate) fn rewrite_segments<F>(&self, active: &mut File, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Vec<LogEntry>) -> bool,
+    {
+        self.check_writable()?;
+        self.check_epoch()?;
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        for (_, path) in sealed_segments(&self.path)? {
+            let mut records = self.read_segment(&path, &File::open(&path)?)?;
+            if f(&mut records) {
+                let written = self.rewrite_records(&path, &records)?;
+                self.writes.rewritten(written);
+            }
+        }
+        let mut records = self.read_segment(&self.path, active)?;
+        if f(&mut records) {
+            let written = self.rewrite_records(&self.path, &records)?;
+            *active = open_active(&self.path, &self.header())?;
+            self.writes.rewritten(written);
+        }
+        Ok(())
+    }
+}
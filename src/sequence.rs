@@ -0,0 +1,59 @@
+//! A durable counter kept beside the log in a `<path>.sequence` sidecar,
+//! for sequence numbers shared with other systems.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Returns the next value of the log's sequence counter, starting from
+    /// 0, and persists the increment before returning it. The counter is
+    /// independent of entry IDs and survives reopening, so no value is ever
+    /// handed out twice.
+    pub fn next_sequence(&self) -> Result<u64> {
+        self.check_writable()?;
+        let _file = self.lock_file()?;
+        let path = self.sequence_path();
+        let next = read_counter(&path)?;
+        let after = next
+            .checked_add(1)
+            .ok_or_else(|| WalError::InvalidEntry("sequence counter overflowed".to_string()))?;
+        write_counter(&path, after)?;
+        Ok(next)
+    }
+
+    pub(crate) fn sequence_path(&self) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".sequence");
+        PathBuf::from(path)
+    }
+}
+
+/// Reads the next value to hand out. A missing sidecar means none has been
+/// yet; an unreadable one is an error, since guessing could repeat a value.
+fn read_counter(path: &Path) -> Result<u64> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    text.trim()
+        .parse()
+        .map_err(|_| WalError::InvalidEntry(format!("corrupt sequence file {}", path.display())))
+}
+
+/// Replaces the sidecar via a synced temp file and rename.
+fn write_counter(path: &Path, next: u64) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = fs::File::create(&tmp)?;
+    writeln!(file, "{next}")?;
+    file.sync_data()?;
+    durable::rename(&tmp, path)?;
+    Ok(())
+}
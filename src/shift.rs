@@ -0,0 +1,37 @@
+//! Moving a whole log through time.
+
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Adds `offset_secs` to the timestamp of every record, markers and
+    /// other streams included, e.g. to replay a historical log as if it were
+    /// written just now. Timestamps that would fall below 0 become 0. IDs,
+    /// payloads and [`expires_at`](crate::LogEntry::expires_at) are left
+    /// alone; checksums and digests are recomputed, since they cover the
+    /// timestamp.
+    ///
+    /// Each segment is replaced with an atomic rename, so a log that has
+    /// not been rotated is shifted all at once. With several segments a
+    /// crash can leave some shifted and others not. Records queued by group
+    /// commit are not shifted.
+    pub fn shift_timestamps(&self, offset_secs: i64) -> Result<()> {
+        if offset_secs == 0 {
+            return Ok(());
+        }
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        self.rewrite_segments(&mut file, |records| {
+            for record in records.iter_mut() {
+                record.timestamp = record.timestamp.saturating_add_signed(offset_secs);
+                if record.has_checksum() {
+                    record.checksum = record.compute_checksum();
+                }
+                if let (false, Some(hasher)) = (record.digest.is_empty(), &self.hasher) {
+                    record.digest = record.compute_digest(&**hasher);
+                }
+            }
+            !records.is_empty()
+        })
+    }
+}
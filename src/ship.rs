@@ -0,0 +1,154 @@
+//! Live shipping of log records to a TCP collector.
+
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::iter::{Decoding, Records};
+use crate::wal::WriteAheadLog;
+
+/// How often the shipper looks for new records or retries a connection.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A background thread started by [`WriteAheadLog::ship_to`] that follows
+/// the log and writes every record to a TCP connection. Stops when dropped.
+#[derive(Debug)]
+pub struct Shipper {
+    stop: Arc<AtomicBool>,
+    shipped: Arc<AtomicU64>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Shipper {
+    /// The ID the next shipped record must have at least; every record below
+    /// it has been written to a connection.
+    pub fn shipped_up_to(&self) -> u64 {
+        self.shipped.load(Ordering::Acquire)
+    }
+
+    /// Stops the shipper and waits for its thread to exit.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Shipper {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl WriteAheadLog {
+    /// Ships every record, from the start of the log, to `addr`. See
+    /// [`ship_to_from`](Self::ship_to_from).
+    pub fn ship_to(&self, addr: SocketAddr) -> Result<Shipper> {
+        self.ship_to_from(addr, 0)
+    }
+
+    /// Starts a thread that sends the encoded bytes of each record with ID
+    /// `from_id` or above to `addr`, following the log as it grows.
+    ///
+    /// When the connection fails the shipper reconnects and resumes with the
+    /// first record it has not yet written. A collector that closes the
+    /// connection is noticed before the next send, but records written
+    /// into a connection that breaks mid-flight can be lost; this is a
+    /// building block, not an acknowledged delivery protocol. Records queued
+    /// by group commit are shipped once flushed.
+    pub fn ship_to_from(&self, addr: SocketAddr, from_id: u64) -> Result<Shipper> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let shipped = Arc::new(AtomicU64::new(from_id));
+        let mut worker = Worker {
+            path: self.path.clone(),
+            // The follower decides for itself what to do with bad records.
+            decoding: Decoding {
+                quarantine: None,
+                ..self.decoding()
+            },
+            addr,
+            stop: Arc::clone(&stop),
+            shipped: Arc::clone(&shipped),
+            conn: None,
+        };
+        let thread = thread::Builder::new()
+            .name("waly-shipper".to_string())
+            .spawn(move || worker.run())?;
+        Ok(Shipper {
+            stop,
+            shipped,
+            thread: Some(thread),
+        })
+    }
+}
+
+struct Worker {
+    path: PathBuf,
+    decoding: Decoding,
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    shipped: Arc<AtomicU64>,
+    conn: Option<TcpStream>,
+}
+
+impl Worker {
+    fn run(&mut self) {
+        while !self.stop.load(Ordering::Acquire) {
+            if self.conn.as_ref().is_none_or(peer_closed) {
+                self.conn = TcpStream::connect(self.addr).ok();
+            }
+            if self.conn.is_some() && self.ship_new().is_err() {
+                self.conn = None;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Writes the records not yet shipped, advancing the position after each.
+    fn ship_new(&mut self) -> io::Result<()> {
+        let Some(conn) = &mut self.conn else {
+            return Ok(());
+        };
+        let Ok(records) = Records::open(&self.path, self.decoding.clone(), Some(0)) else {
+            return Ok(());
+        };
+        for record in records {
+            let Ok(record) = record else {
+                break;
+            };
+            if record.id < self.shipped.load(Ordering::Acquire) {
+                continue;
+            }
+            if peer_closed(conn) {
+                return Err(io::ErrorKind::ConnectionReset.into());
+            }
+            conn.write_all(&self.decoding.format.encode(&record))?;
+            conn.flush()?;
+            self.shipped.store(record.id + 1, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+/// Whether the other end has closed `conn`, checked without blocking.
+fn peer_closed(conn: &TcpStream) -> bool {
+    if conn.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let closed = match conn.peek(&mut [0u8; 1]) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(err) => err.kind() != io::ErrorKind::WouldBlock,
+    };
+    closed || conn.set_nonblocking(false).is_err()
+}
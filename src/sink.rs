@@ -0,0 +1,95 @@
+//! Forwarding appended records to other destinations, such as a message
+//! queue or a metrics pipe, as they are written.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// A destination every appended record is also written to, set with
+/// [`WriteAheadLogBuilder::sinks`](crate::WriteAheadLogBuilder::sinks).
+pub trait Sink: Send {
+    /// Takes one record that has just been appended to the log.
+    fn write_entry(&mut self, entry: &LogEntry) -> Result<()>;
+}
+
+/// What an append does when a [`Sink`] fails, chosen with
+/// [`WriteAheadLogBuilder::sink_error_policy`](crate::WriteAheadLogBuilder::sink_error_policy).
+#[derive(Clone, Default)]
+pub enum SinkErrorPolicy {
+    /// Carry on as if the sink had succeeded.
+    Ignore,
+    /// Hand the error to the callback, with the index of the sink that
+    /// failed and the entry it failed on, and carry on. See
+    /// [`callback`](Self::callback).
+    Callback(Arc<SinkFailed>),
+    /// Return the error from the append. The record is in the log by then,
+    /// so retrying the append writes it twice.
+    #[default]
+    Fail,
+}
+
+/// Called with the index of a sink that failed, the entry and the error.
+pub type SinkFailed = dyn Fn(usize, &LogEntry, &WalError) + Send + Sync;
+
+impl SinkErrorPolicy {
+    /// [`SinkErrorPolicy::Callback`] with `callback`, e.g. to log failures.
+    pub fn callback<F>(callback: F) -> Self
+    where
+        F: Fn(usize, &LogEntry, &WalError) + Send + Sync + 'static,
+    {
+        SinkErrorPolicy::Callback(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for SinkErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkErrorPolicy::Ignore => f.write_str("Ignore"),
+            SinkErrorPolicy::Callback(_) => f.write_str("Callback(<callback>)"),
+            SinkErrorPolicy::Fail => f.write_str("Fail"),
+        }
+    }
+}
+
+pub(crate) type Sinks = Mutex<Vec<Box<dyn Sink>>>;
+
+impl WriteAheadLog {
+    /// Opens the log at `path` forwarding appends to `sinks`. See
+    /// [`WriteAheadLogBuilder::sinks`](crate::WriteAheadLogBuilder::sinks).
+    pub fn with_sinks<P: AsRef<Path>>(path: P, sinks: Vec<Box<dyn Sink>>) -> Result<Self> {
+        Self::builder(path).sinks(sinks).build()
+    }
+
+    /// Hands `entries`, just appended, to every sink in turn. All sinks get
+    /// every entry even if one fails; under [`SinkErrorPolicy::Fail`] the
+    /// first failure is returned at the end.
+    pub(crate) fn forward<'a, I>(&self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a LogEntry>,
+    {
+        let Some(sinks) = &self.sinks else {
+            return Ok(());
+        };
+        let mut sinks = sinks.lock()?;
+        let mut failed: Option<WalError> = None;
+        for entry in entries {
+            for (index, sink) in sinks.iter_mut().enumerate() {
+                let Err(err) = sink.write_entry(entry) else {
+                    continue;
+                };
+                match &self.sink_error_policy {
+                    SinkErrorPolicy::Ignore => {}
+                    SinkErrorPolicy::Callback(callback) => callback(index, entry, &err),
+                    SinkErrorPolicy::Fail => {
+                        failed.get_or_insert(err);
+                    }
+                }
+            }
+        }
+        failed.map_or(Ok(()), Err)
+    }
+}
@@ -0,0 +1,242 @@
+//! Time-ordered reads and ID-ordered rewrites via an external merge sort,
+//! for logs that are out of order and too large to sort in memory in one go.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::durable;
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::format::Format;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Memory [`iter_time_sorted`](WriteAheadLog::iter_time_sorted) and
+/// [`sort_by_id`](WriteAheadLog::sort_by_id) may use for a run before
+/// spilling it to disk.
+const DEFAULT_SORT_BUDGET: usize = 64 << 20;
+
+/// Rough in-memory cost of an entry beyond its payload.
+const ENTRY_OVERHEAD: usize = 64;
+
+impl WriteAheadLog {
+    /// Every data entry sorted by `(timestamp, id)`, for time-based analysis
+    /// of logs written across clock regressions. See
+    /// [`iter_time_sorted_within`](Self::iter_time_sorted_within).
+    pub fn iter_time_sorted(&self) -> Result<Vec<LogEntry>> {
+        self.iter_time_sorted_within(DEFAULT_SORT_BUDGET)
+    }
+
+    /// Like [`iter_time_sorted`](Self::iter_time_sorted), sorting runs of
+    /// at most about `budget` bytes in memory. Each full run is spilled to a
+    /// temp file beside the log, and the runs are merged at the end.
+    pub fn iter_time_sorted_within(&self, budget: usize) -> Result<Vec<LogEntry>> {
+        let _file = self.lock_file()?;
+        let mut runs = Runs::new(self.path.clone(), time_key);
+        let mut run = Vec::new();
+        let mut run_bytes = 0;
+        for record in self.records()? {
+            let record = record?;
+            if record.kind != EntryKind::Data {
+                continue;
+            }
+            run_bytes += record.data.len() + ENTRY_OVERHEAD;
+            run.push(record);
+            if run_bytes >= budget {
+                runs.spill(&mut run)?;
+                run_bytes = 0;
+            }
+        }
+        run.sort_unstable_by_key(time_key);
+        if runs.paths.is_empty() {
+            return Ok(run);
+        }
+        let mut merged = Vec::new();
+        runs.merge(run, |entry| {
+            merged.push(entry);
+            Ok(())
+        })?;
+        Ok(merged)
+    }
+
+    /// Rewrites the log with its records in ID order, e.g. after a botched
+    /// merge, so that lookups relying on ascending IDs such as
+    /// [`get`](Self::get) and [`read_ids`](Self::read_ids) find everything
+    /// again. Returns how many records were out of place, i.e. came after a
+    /// record with a larger ID; if none were, nothing is rewritten. See
+    /// [`sort_by_id_within`](Self::sort_by_id_within).
+    pub fn sort_by_id(&self) -> Result<usize> {
+        self.sort_by_id_within(DEFAULT_SORT_BUDGET)
+    }
+
+    /// Like [`sort_by_id`](Self::sort_by_id), sorting runs of at most about
+    /// `budget` bytes in memory and spilling them to temp files beside the
+    /// log, as [`iter_time_sorted_within`](Self::iter_time_sorted_within)
+    /// does.
+    ///
+    /// The sort is stable, so records sharing an ID keep their order. Each
+    /// stream is sorted by its own IDs, with stream 0 first and the others
+    /// after it in stream order. Records of every kind are kept, apart from
+    /// entries already removed by [`clear_id`](Self::clear_id). As with
+    /// [`resequence`](Self::resequence), the result replaces the active file
+    /// in a single rename and sealed segments are deleted afterwards.
+    pub fn sort_by_id_within(&self, budget: usize) -> Result<usize> {
+        self.check_writable()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        let mut runs = Runs::new(self.path.clone(), id_key);
+        let mut run = Vec::new();
+        let mut run_bytes = 0;
+        let mut out_of_place = 0;
+        let mut largest = None;
+        for record in self.all_records()? {
+            let record = record?;
+            let key = id_key(&record);
+            if largest.is_some_and(|largest| key < largest) {
+                out_of_place += 1;
+            } else {
+                largest = Some(key);
+            }
+            run_bytes += record.data.len() + ENTRY_OVERHEAD;
+            run.push(record);
+            if run_bytes >= budget {
+                runs.spill(&mut run)?;
+                run_bytes = 0;
+            }
+        }
+        if out_of_place == 0 {
+            return Ok(0);
+        }
+        run.sort_by_key(id_key);
+
+        let mut temp = self.path.as_os_str().to_owned();
+        temp.push(".sorted");
+        let temp = PathBuf::from(temp);
+        let mut out = BufWriter::new(File::create(&temp)?);
+        out.write_all(&self.header())?;
+        let mut written = 0;
+        runs.merge(run, |record| {
+            let bytes = self.encode_record(&record)?;
+            out.write_all(&bytes)?;
+            written += bytes.len() as u64;
+            Ok(())
+        })?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_data()?;
+        let sealed = segment::sealed_segments(&self.path)?;
+        durable::rename(&temp, &self.path)?;
+        *file = segment::open_active(&self.path, &self.header())?;
+        for (_, path) in sealed {
+            fs::remove_file(path)?;
+        }
+        durable::sync_dir(&self.path)?;
+        self.writes.rewritten(written);
+        self.invalidate_cache();
+        Ok(out_of_place)
+    }
+}
+
+fn time_key(entry: &LogEntry) -> (u64, u64) {
+    (entry.timestamp, entry.id)
+}
+
+fn id_key(entry: &LogEntry) -> (u64, u64) {
+    (u64::from(entry.stream), entry.id)
+}
+
+/// Runs sorted by `key` spilled to disk, deleted again when dropped. Runs
+/// are kept in [`Format::CompactBinary`], which carries every field of an
+/// entry.
+struct Runs {
+    log: PathBuf,
+    paths: Vec<PathBuf>,
+    key: fn(&LogEntry) -> (u64, u64),
+}
+
+impl Runs {
+    fn new(log: PathBuf, key: fn(&LogEntry) -> (u64, u64)) -> Self {
+        Runs {
+            log,
+            paths: Vec::new(),
+            key,
+        }
+    }
+
+    fn spill(&mut self, run: &mut Vec<LogEntry>) -> Result<()> {
+        run.sort_by_key(self.key);
+        let mut path = self.log.as_os_str().to_owned();
+        path.push(format!(".sort-{}", self.paths.len()));
+        let path = PathBuf::from(path);
+        self.paths.push(path.clone());
+        let mut out = BufWriter::new(File::create(&path)?);
+        for entry in run.drain(..) {
+            out.write_all(&Format::CompactBinary.encode(&entry))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Merges the spilled runs and the in-memory `last` run, already sorted,
+    /// handing each entry to `emit` in turn. Entries with equal keys come
+    /// out in the order they were spilled, so a stable sort of each run
+    /// keeps the merge stable.
+    fn merge<F>(&self, last: Vec<LogEntry>, mut emit: F) -> Result<()>
+    where
+        F: FnMut(LogEntry) -> Result<()>,
+    {
+        let mut sources: Vec<Run> = Vec::with_capacity(self.paths.len() + 1);
+        for path in &self.paths {
+            sources.push(Run::File(BufReader::new(File::open(path)?), Vec::new()));
+        }
+        sources.push(Run::Memory(last.into_iter()));
+
+        let mut heads = Vec::with_capacity(sources.len());
+        let mut heap = BinaryHeap::new();
+        for (index, source) in sources.iter_mut().enumerate() {
+            let head = source.next()?;
+            if let Some(entry) = &head {
+                heap.push(Reverse(((self.key)(entry), index)));
+            }
+            heads.push(head);
+        }
+        while let Some(Reverse((_, index))) = heap.pop() {
+            if let Some(entry) = heads[index].take() {
+                emit(entry)?;
+            }
+            heads[index] = sources[index].next()?;
+            if let Some(entry) = &heads[index] {
+                heap.push(Reverse(((self.key)(entry), index)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+enum Run {
+    File(BufReader<File>, Vec<u8>),
+    Memory(std::vec::IntoIter<LogEntry>),
+}
+
+impl Run {
+    fn next(&mut self) -> Result<Option<LogEntry>> {
+        match self {
+            Run::File(reader, buf) => {
+                if Format::CompactBinary.read_frame(reader, buf)? == 0 {
+                    return Ok(None);
+                }
+                Format::CompactBinary.decode_with(buf, None, None).map(Some)
+            }
+            Run::Memory(entries) => Ok(entries.next()),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::entry::EntryKind;
+use crate::error::Result;
+use crate::iter::{Decoding, SegmentReader};
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// Bytes written through a [`WriteAheadLog`] handle since it was opened, as
+/// reported by [`WriteAheadLog::write_amplification`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteAmpStats {
+    /// Encoded size of every record appended.
+    pub logical_bytes: u64,
+    /// Everything written to segment files: appended records plus records
+    /// rewritten by operations such as [`compact`](WriteAheadLog::compact).
+    pub physical_bytes: u64,
+}
+
+impl WriteAmpStats {
+    /// Physical bytes per logical byte, or `None` before anything has been
+    /// appended.
+    pub fn ratio(&self) -> Option<f64> {
+        (self.logical_bytes > 0).then(|| self.physical_bytes as f64 / self.logical_bytes as f64)
+    }
+}
+
+/// How the bytes of a log's files divide between live and dead records, as
+/// reported by [`WriteAheadLog::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalStats {
+    /// Size of every segment file, headers and any torn final write
+    /// included.
+    pub file_bytes: u64,
+    /// Data entries that have not been cleared: what reads return, across
+    /// every logical stream.
+    pub live_entries: u64,
+    /// Bytes the live entries' records take up, framing included.
+    pub live_bytes: u64,
+    /// Records that [`compact`](WriteAheadLog::compact) would drop:
+    /// tombstones, the entries they cleared, and records that do not decode.
+    pub dead_entries: u64,
+    /// Bytes the dead records take up, framing included.
+    pub dead_bytes: u64,
+}
+
+impl WalStats {
+    /// Fraction of the file bytes, between 0 and 1, taken up by dead
+    /// records, or 0 for empty files. Headers and bookkeeping records such
+    /// as markers count as neither live nor dead, so a log with nothing
+    /// cleared has a fragmentation of 0 but a
+    /// [`live_bytes`](Self::live_bytes) somewhat below its size.
+    pub fn fragmentation(&self) -> f64 {
+        if self.file_bytes == 0 {
+            return 0.0;
+        }
+        self.dead_bytes as f64 / self.file_bytes as f64
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct WriteCounters {
+    logical: AtomicU64,
+    physical: AtomicU64,
+}
+
+impl WriteCounters {
+    pub(crate) fn appended(&self, bytes: u64) {
+        self.logical.fetch_add(bytes, Ordering::Relaxed);
+        self.physical.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn rewritten(&self, bytes: u64) {
+        self.physical.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+impl WriteAheadLog {
+    /// Logical versus physical bytes written over this handle's lifetime,
+    /// showing how much rewrites cost on top of plain appends.
+    pub fn write_amplification(&self) -> WriteAmpStats {
+        WriteAmpStats {
+            logical_bytes: self.writes.logical.load(Ordering::Relaxed),
+            physical_bytes: self.writes.physical.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sizes of the live and dead records in the log, to judge whether a
+    /// [`compact`](Self::compact) is worth running. Computed in one
+    /// streaming pass over every segment that decodes record headers but
+    /// not payloads, so it works without the encryption key and keeps no
+    /// records in memory.
+    pub fn stats(&self) -> Result<WalStats> {
+        let file = self.lock_file()?;
+        let mut stats = WalStats {
+            file_bytes: self.total_bytes(&file)?,
+            ..WalStats::default()
+        };
+        let decoding = Decoding {
+            payloads: false,
+            ..self.decoding()
+        };
+        for path in segment::all_segments(&self.path)? {
+            let mut reader = SegmentReader::new(&path, File::open(&path)?, decoding.clone());
+            while let Some((decoded, bytes)) = reader.next_sized()? {
+                let (live, dead) = match decoded {
+                    Ok(record) if record.kind == EntryKind::Tombstone => (false, true),
+                    Ok(record) if self.is_cleared(&record) => (false, true),
+                    Ok(record) => (record.kind == EntryKind::Data, false),
+                    Err(_) => (false, true),
+                };
+                if live {
+                    stats.live_entries += 1;
+                    stats.live_bytes += bytes;
+                } else if dead {
+                    stats.dead_entries += 1;
+                    stats.dead_bytes += bytes;
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
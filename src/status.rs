@@ -0,0 +1,54 @@
+//! Retry bookkeeping: status records that mark entries done or failed.
+//!
+//! Statuses are appended as their own records rather than rewriting the
+//! entry, and are applied on read; the latest status for an entry wins.
+
+use std::collections::HashMap;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+pub(crate) const DONE: &[u8] = b"done";
+const FAILED: &[u8] = b"failed";
+
+impl WriteAheadLog {
+    /// Records that entry `id` was processed successfully, removing it from
+    /// [`pending`](Self::pending).
+    pub fn mark_done(&mut self, id: u64) -> Result<LogEntry> {
+        self.append_status(id, DONE)
+    }
+
+    /// Records a failed attempt at processing entry `id`. It stays pending.
+    pub fn mark_failed(&mut self, id: u64) -> Result<LogEntry> {
+        self.append_status(id, FAILED)
+    }
+
+    /// Data entries whose latest status is not done, in order.
+    pub fn pending(&self) -> Result<Vec<LogEntry>> {
+        let _file = self.lock_file()?;
+        let mut entries = Vec::new();
+        let mut done = HashMap::new();
+        for record in self.records()? {
+            let record = record?;
+            match (record.kind, record.target) {
+                (EntryKind::Data, _) => entries.push(record),
+                (EntryKind::Status, Some(target)) => {
+                    done.insert(target, record.data == DONE);
+                }
+                _ => {}
+            }
+        }
+        entries.retain(|e| done.get(&e.id) != Some(&true));
+        Ok(entries)
+    }
+
+    fn append_status(&mut self, id: u64, status: &[u8]) -> Result<LogEntry> {
+        self.append_record(LogEntry {
+            data: status.to_vec(),
+            kind: EntryKind::Status,
+            target: Some(id),
+            ..LogEntry::default()
+        })
+    }
+}
@@ -0,0 +1,93 @@
+//! Independent logical logs sharing one physical file.
+//!
+//! Every entry carries a `stream` number. Stream 0 is the log itself, which
+//! is all that [`WriteAheadLog`]'s own methods see. The other streams are
+//! reached through [`StreamView`]s and number their entries from 0
+//! independently of each other and of the log.
+
+use std::sync::Arc;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// One logical stream of a [`WriteAheadLog`], returned by
+/// [`WriteAheadLog::stream`].
+#[derive(Debug, Clone, Copy)]
+pub struct StreamView<'a> {
+    wal: &'a WriteAheadLog,
+    stream: u32,
+}
+
+impl WriteAheadLog {
+    /// A view of logical stream `stream_id`. The view of stream 0 reads the
+    /// log itself but cannot append to it; use [`append`](Self::append).
+    pub fn stream(&self, stream_id: u32) -> StreamView<'_> {
+        StreamView {
+            wal: self,
+            stream: stream_id,
+        }
+    }
+}
+
+impl StreamView<'_> {
+    /// The stream's number.
+    pub fn id(&self) -> u32 {
+        self.stream
+    }
+
+    /// Appends `data` to this stream with the stream's next ID. Stream
+    /// entries are written straight away, even under group commit.
+    pub fn append(&self, data: Vec<u8>) -> Result<LogEntry> {
+        if self.stream == 0 {
+            return Err(WalError::InvalidConfig(
+                "stream 0 is the log itself; use WriteAheadLog::append".to_string(),
+            ));
+        }
+        let wal = self.wal;
+        wal.gate.pass(wal.pause_mode)?;
+        wal.take_tokens(1)?;
+        let file = Arc::clone(&wal.file);
+        let mut file = file.lock()?;
+        let mut next_ids = wal.stream_ids.lock()?;
+        let id = match next_ids.get(&self.stream) {
+            Some(&id) => id,
+            None => {
+                let mut next = 0;
+                for record in wal.stream_records(Some(self.stream))? {
+                    next = next.max(record?.id + 1);
+                }
+                next
+            }
+        };
+        let mut entry = LogEntry {
+            id,
+            timestamp: wal.now()?,
+            data,
+            stream: self.stream,
+            ..LogEntry::default()
+        };
+        wal.finalize(&mut entry);
+        wal.write_record(&mut file, &entry)?;
+        next_ids.insert(self.stream, id + 1);
+        if !wal.group_commit {
+            wal.sync_for_policy(&file, 1, wal.current_id)?;
+        }
+        drop((next_ids, file));
+        wal.forward([&entry])?;
+        Ok(entry)
+    }
+
+    /// Every data entry of this stream, in order.
+    pub fn read_all(&self) -> Result<Vec<LogEntry>> {
+        let _file = self.wal.lock_file()?;
+        let mut entries = Vec::new();
+        for record in self.wal.stream_records(Some(self.stream))? {
+            let record = record?;
+            if record.kind == EntryKind::Data {
+                entries.push(record);
+            }
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+/// When appends are synced to disk with `sync_data`, beyond the flush each
+/// one always gets. Records that are flushed but not synced reach the OS
+/// and survive the process crashing, but not a power loss.
+///
+/// Under [`group_commit`](crate::WriteAheadLogBuilder::group_commit) the
+/// policy does not apply, since every [`flush`](WriteAheadLog::flush)
+/// syncs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Sync after every append.
+    Always,
+    /// Sync after every `n` appends. `EveryN(0)` is the same as
+    /// [`Always`](Self::Always).
+    EveryN(usize),
+    /// Sync on the first append at least this long after the last sync.
+    /// There is no background timer, so a log that stops being appended to
+    /// is not synced until [`sync`](WriteAheadLog::sync) or drop.
+    Interval(Duration),
+    /// Leave writing back to the OS.
+    #[default]
+    Never,
+}
+
+/// Appends made since the active file was last synced.
+#[derive(Debug)]
+pub(crate) struct Unsynced {
+    appends: usize,
+    since: Instant,
+}
+
+impl Default for Unsynced {
+    fn default() -> Self {
+        Unsynced {
+            appends: 0,
+            since: Instant::now(),
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Writes any records queued by group commit and syncs the active file,
+    /// making everything appended so far durable.
+    pub fn sync(&mut self) -> Result<()> {
+        self.flush()?;
+        let file = Arc::clone(&self.file);
+        let file = file.lock()?;
+        self.sync_file(&file, self.current_id)
+    }
+
+    /// Every ID below this is known to have been synced to disk. It trails
+    /// [`next_id`](Self::next_id) while appends sit in the OS cache or the
+    /// group-commit queue; the difference is what a crash could lose.
+    /// Records already in the log when it was opened count as durable.
+    pub fn durable_id(&self) -> u64 {
+        self.durable_id.load(Ordering::Acquire)
+    }
+
+    /// The policy appends are synced under.
+    pub fn sync_policy(&self) -> SyncPolicy {
+        self.sync_policy
+    }
+
+    /// Changes when appends are synced; see [`SyncPolicy`]. Appends already
+    /// made count towards the new policy.
+    pub fn set_sync_policy(&mut self, policy: SyncPolicy) {
+        self.sync_policy = policy;
+    }
+
+    /// Writes out buffered appends and syncs the active `file`, recording
+    /// that IDs below `up_to` are durable.
+    pub(crate) fn sync_file(&self, file: &File, up_to: u64) -> Result<()> {
+        self.drain_write_buffer(file)?;
+        file.sync_data()?;
+        self.synced(up_to)
+    }
+
+    /// Writes any records queued by group commit or buffered, returning a
+    /// handle to the active file for the caller to sync and the ID below
+    /// which that makes everything durable; see [`synced`](Self::synced).
+    #[cfg(feature = "tokio")]
+    pub(crate) fn prepare_sync(&mut self) -> Result<(File, u64)> {
+        self.flush()?;
+        let file = self.lock_file()?.try_clone()?;
+        Ok((file, self.current_id))
+    }
+
+    /// Records that IDs below `up_to` are durable, once the active file has
+    /// been synced.
+    pub(crate) fn synced(&self, up_to: u64) -> Result<()> {
+        self.durable_id.fetch_max(up_to, Ordering::AcqRel);
+        *self.unsynced.lock()? = Unsynced::default();
+        self.metrics.synced();
+        Ok(())
+    }
+
+    /// Counts `appends` just written to `file` and syncs it if the policy
+    /// calls for it.
+    pub(crate) fn sync_for_policy(&self, file: &File, appends: usize, up_to: u64) -> Result<()> {
+        let due = {
+            let mut unsynced = self.unsynced.lock()?;
+            unsynced.appends += appends;
+            match self.sync_policy {
+                SyncPolicy::Always => true,
+                SyncPolicy::EveryN(n) => unsynced.appends >= n,
+                SyncPolicy::Interval(interval) => unsynced.since.elapsed() >= interval,
+                SyncPolicy::Never => false,
+            }
+        };
+        if due {
+            self.sync_file(file, up_to)?;
+        }
+        Ok(())
+    }
+}
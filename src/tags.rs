@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Appends `data` with string key-value [`tags`](LogEntry::tags), such
+    /// as `level=error`, that readers can filter on without decoding the
+    /// payload. An [`append_transform`](crate::WriteAheadLogBuilder::append_transform)
+    /// still runs and may add to them. Plain [`append`](Self::append)
+    /// writes no tags, and untagged entries take no space for them.
+    pub fn append_with_tags(
+        &mut self,
+        data: Vec<u8>,
+        tags: BTreeMap<String, String>,
+    ) -> Result<LogEntry> {
+        self.append_record(LogEntry {
+            data,
+            tags,
+            ..LogEntry::default()
+        })
+    }
+
+    /// Returns the data entries whose tags satisfy `predicate`, in the
+    /// order [`read_all`](Self::read_all) would. Untagged entries are
+    /// tested with an empty map.
+    pub fn read_filtered<F>(&self, mut predicate: F) -> Result<Vec<LogEntry>>
+    where
+        F: FnMut(&BTreeMap<String, String>) -> bool,
+    {
+        self.metrics.read();
+        let mut matches = Vec::new();
+        for entry in self.records()? {
+            let entry = entry?;
+            if entry.kind == EntryKind::Data && predicate(&entry.tags) {
+                matches.push(entry);
+            }
+        }
+        Ok(matches)
+    }
+}
@@ -0,0 +1,138 @@
+//! Reading the newest entries without decoding the rest of the log.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::format::Format;
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// How much of a JSON segment is read at a time, working back from its end.
+const TAIL_CHUNK: u64 = 8 * 1024;
+
+impl WriteAheadLog {
+    /// The last `n` data entries in ascending ID order, or all of them if
+    /// the log holds fewer, as the end of [`read_all`](Self::read_all)
+    /// would return them.
+    ///
+    /// JSON segments are read backwards from the end in chunks, newest
+    /// segment first, so only the records near the end are decoded. The
+    /// binary formats have no delimiter to search for from the end: their
+    /// frames are walked forwards, but still only the last entries are
+    /// decoded.
+    pub fn tail(&self, n: usize) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let mut newest_first = Vec::new();
+        for path in segment::all_segments(&self.path)?.iter().rev() {
+            if newest_first.len() >= n {
+                break;
+            }
+            let file = File::open(path)?;
+            match self.format {
+                Format::Json | Format::JsonBase64 => self.tail_lines(file, n, &mut newest_first)?,
+                Format::Binary | Format::CompactBinary => {
+                    self.tail_frames(file, n, &mut newest_first)?
+                }
+            }
+        }
+        newest_first.reverse();
+        Ok(newest_first)
+    }
+
+    /// Collects data entries from the lines of `file`, last line first,
+    /// until `tail` holds `n`.
+    fn tail_lines(&self, mut file: File, n: usize, tail: &mut Vec<LogEntry>) -> Result<()> {
+        let start_of_records = header::skip(&mut BufReader::new(&file))?;
+        let mut pos = file.metadata()?.len();
+        // The start of a line whose beginning lies before `pos`.
+        let mut carry = Vec::new();
+        let mut chunk = Vec::new();
+        while pos > start_of_records && tail.len() < n {
+            let start = pos.saturating_sub(TAIL_CHUNK).max(start_of_records);
+            chunk.resize((pos - start) as usize, 0);
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&carry);
+            pos = start;
+            let (partial, lines) = if start == start_of_records {
+                (&[][..], &chunk[..])
+            } else {
+                match chunk.iter().position(|&b| b == b'\n') {
+                    Some(at) => (&chunk[..at], &chunk[at + 1..]),
+                    None => {
+                        carry = std::mem::take(&mut chunk);
+                        continue;
+                    }
+                }
+            };
+            for line in lines.rsplit(|&b| b == b'\n') {
+                if tail.len() >= n {
+                    break;
+                }
+                self.collect_tail(line, tail)?;
+            }
+            carry = partial.to_vec();
+        }
+        Ok(())
+    }
+
+    /// Collects data entries from the frames of `file`, newest first, until
+    /// `tail` holds `n`. Frames are located front to back, then decoded
+    /// back to front.
+    fn tail_frames(&self, file: File, n: usize, tail: &mut Vec<LogEntry>) -> Result<()> {
+        let mut reader = BufReader::new(file);
+        let mut buf = Vec::new();
+        let mut offsets = Vec::new();
+        let mut offset = header::skip(&mut reader)?;
+        loop {
+            let read = self.format.read_frame(&mut reader, &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            if self.format.is_data_frame(&buf) {
+                offsets.push(offset);
+            }
+            offset += read as u64;
+        }
+        for &offset in offsets.iter().rev() {
+            if tail.len() >= n {
+                break;
+            }
+            reader.seek(SeekFrom::Start(offset))?;
+            self.format.read_frame(&mut reader, &mut buf)?;
+            self.collect_tail(&buf, tail)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes `body` and adds it to `tail` if it is a data entry that
+    /// reads would return. Undecodable records are skipped, but a payload
+    /// that fails to decrypt or to match its checksum is an error.
+    fn collect_tail(&self, body: &[u8], tail: &mut Vec<LogEntry>) -> Result<()> {
+        if !self.format.is_data_frame(body) {
+            return Ok(());
+        }
+        match self
+            .format
+            .decode_with(body, self.dictionary.as_deref(), self.cipher.as_deref())
+        {
+            Ok(entry)
+                if entry.kind == EntryKind::Data
+                    && entry.stream == 0
+                    && !self.is_cleared(&entry) =>
+            {
+                if !entry.is_checksum_valid() {
+                    return Err(WalError::ChecksumMismatch { id: entry.id });
+                }
+                tail.push(entry);
+            }
+            Err(err @ WalError::Decryption { .. }) => return Err(err),
+            _ => {}
+        }
+        Ok(())
+    }
+}
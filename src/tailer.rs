@@ -0,0 +1,149 @@
+//! Pushing new entries to a handler from a background thread.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::iter::{Decoding, Records};
+use crate::wal::WriteAheadLog;
+
+/// How often the tailer looks for new entries.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A background thread started by [`WriteAheadLog::tail_with`] that calls
+/// a handler for every new data entry. Stops when dropped.
+#[derive(Debug)]
+pub struct TailHandle {
+    stop: Arc<AtomicBool>,
+    next: Arc<AtomicU64>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl TailHandle {
+    /// The ID the next entry handed to the handler must have at least;
+    /// every entry below it has been handled.
+    pub fn handled_up_to(&self) -> u64 {
+        self.next.load(Ordering::Acquire)
+    }
+
+    /// Whether the tailer has stopped by itself, because the handler or a
+    /// read failed. [`stop`](Self::stop) returns the error.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stops the tailer and waits for its thread to exit, letting the
+    /// handler finish the entry it is on. Returns the error that stopped
+    /// the tailer early, if any.
+    pub fn stop(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Release);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TailHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl WriteAheadLog {
+    /// Calls `handler` for every data entry appended from now on. See
+    /// [`tail_with_from`](Self::tail_with_from).
+    pub fn tail_with<F>(&self, handler: F) -> Result<TailHandle>
+    where
+        F: FnMut(&LogEntry) -> Result<()> + Send + 'static,
+    {
+        self.tail_with_from(self.current_id, handler)
+    }
+
+    /// Starts a thread that follows the log as it grows and calls `handler`
+    /// for each data entry with ID `from_id` or above, in ID order: the
+    /// push-based counterpart of polling [`iter`](Self::iter).
+    /// Pass 0 to start from the beginning of the log.
+    ///
+    /// The log is polled every few milliseconds. Markers, bookkeeping
+    /// records and cleared entries are skipped, and records queued by group
+    /// commit are handed over once flushed. If `handler` returns an error,
+    /// or reading the log fails, the tailer stops and
+    /// [`TailHandle::stop`] returns the error.
+    pub fn tail_with_from<F>(&self, from_id: u64, mut handler: F) -> Result<TailHandle>
+    where
+        F: FnMut(&LogEntry) -> Result<()> + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let next = Arc::new(AtomicU64::new(from_id));
+        let worker = Worker {
+            path: self.path.clone(),
+            // The tailer decides for itself what to do with bad records.
+            decoding: Decoding {
+                quarantine: None,
+                metrics: None,
+                ..self.decoding()
+            },
+            stop: Arc::clone(&stop),
+            next: Arc::clone(&next),
+        };
+        let thread = thread::Builder::new()
+            .name("waly-tailer".to_string())
+            .spawn(move || worker.run(&mut handler))?;
+        Ok(TailHandle {
+            stop,
+            next,
+            thread: Some(thread),
+        })
+    }
+}
+
+struct Worker {
+    path: PathBuf,
+    decoding: Decoding,
+    stop: Arc<AtomicBool>,
+    next: Arc<AtomicU64>,
+}
+
+impl Worker {
+    fn run(&self, handler: &mut dyn FnMut(&LogEntry) -> Result<()>) -> Result<()> {
+        while !self.stop.load(Ordering::Acquire) {
+            self.handle_new(handler)?;
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Hands the entries not yet handled to `handler`, advancing the
+    /// position after each.
+    fn handle_new(&self, handler: &mut dyn FnMut(&LogEntry) -> Result<()>) -> Result<()> {
+        // A segment may be mid-rotation; the next poll sees it settled.
+        let Ok(records) = Records::open(&self.path, self.decoding.clone(), Some(0)) else {
+            return Ok(());
+        };
+        for record in records {
+            let record = record?;
+            if record.kind != EntryKind::Data || record.id < self.next.load(Ordering::Acquire) {
+                continue;
+            }
+            if self.stop.load(Ordering::Acquire) {
+                break;
+            }
+            handler(&record)?;
+            self.next.store(record.id + 1, Ordering::Release);
+        }
+        Ok(())
+    }
+}
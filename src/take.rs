@@ -0,0 +1,35 @@
+//! At-most-once consumption: entries are removed before they are handed out.
+
+use std::sync::Arc;
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Removes the oldest data entry from the log and returns it, or `None`
+    /// if there is none. The removal is synced to disk before returning, so
+    /// a crash while the entry is being processed loses it rather than
+    /// delivering it again.
+    ///
+    /// Queued group-commit records are written first. The entry is removed
+    /// with a tombstone, as by [`clear_id`](Self::clear_id).
+    pub fn take_next(&mut self) -> Result<Option<LogEntry>> {
+        self.flush()?;
+        let oldest = {
+            let _file = self.lock_file()?;
+            let mut data = self
+                .records()?
+                .filter(|r| r.as_ref().map_or(true, |r| r.kind == EntryKind::Data));
+            data.next().transpose()?
+        };
+        let Some(entry) = oldest else {
+            return Ok(None);
+        };
+        self.clear_id(entry.id)?;
+        let file = Arc::clone(&self.file);
+        let file = file.lock()?;
+        self.sync_file(&file, self.current_id)?;
+        Ok(Some(entry))
+    }
+}
@@ -0,0 +1,53 @@
+//! Reads bounded by a deadline, for storage that may stall.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::iter::Records;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Like [`read_all`](Self::read_all), but gives up with
+    /// [`WalError::Timeout`] if the read has not finished within `timeout`.
+    ///
+    /// The read runs on a worker thread through its own file handles,
+    /// decoding and deduplicating as `read_all` does. Buffered appends are
+    /// written first, and the log stays locked until the worker has opened
+    /// every segment or the time is up, so the read sees the log as it was
+    /// when called. On timeout the worker is left to finish or stay blocked
+    /// in the filesystem on its own, and whatever it reads is discarded.
+    pub fn read_all_timeout(&self, timeout: Duration) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let deadline = Instant::now() + timeout;
+        let file = self.lock_file()?;
+        let (path, decoding, dedup) = (self.path.clone(), self.decoding(), self.dedup_on_read);
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let read = || -> Result<Vec<LogEntry>> {
+                let records = Records::open(&path, decoding, Some(0));
+                let _ = opened_tx.send(());
+                let mut entries = Vec::new();
+                for record in records? {
+                    let record = record?;
+                    if record.kind == EntryKind::Data {
+                        entries.push(record);
+                    }
+                }
+                if let Some(policy) = dedup {
+                    policy.dedup(&mut entries);
+                }
+                Ok(entries)
+            };
+            let _ = tx.send(read());
+        });
+        let opened = opened_rx.recv_timeout(timeout);
+        drop(file);
+        opened.map_err(|_| WalError::Timeout)?;
+        rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .map_err(|_| WalError::Timeout)?
+    }
+}
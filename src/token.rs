@@ -0,0 +1,42 @@
+use std::time::SystemTime;
+
+use crate::error::Result;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+/// A snapshot of the log's on-disk state, taken with
+/// [`WriteAheadLog::read_token`] and compared by
+/// [`WriteAheadLog::modified_since`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadToken {
+    segments: usize,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl WriteAheadLog {
+    /// Captures the log's current size and modification time. Take one right
+    /// after a read to later tell whether re-reading is needed.
+    pub fn read_token(&self) -> Result<ReadToken> {
+        let _file = self.lock_file()?;
+        let mut token = ReadToken {
+            segments: 0,
+            len: 0,
+            modified: None,
+        };
+        for path in segment::all_segments(&self.path)? {
+            let metadata = path.metadata()?;
+            token.segments += 1;
+            token.len += metadata.len();
+            token.modified = token.modified.max(metadata.modified().ok());
+        }
+        Ok(token)
+    }
+
+    /// Whether the log has changed since `token` was taken. Rests on file
+    /// sizes and modification times, so a same-sized rewrite within the
+    /// filesystem's timestamp granularity can go unnoticed.
+    pub fn modified_since(&self, token: ReadToken) -> Result<bool> {
+        Ok(self.read_token()? != token)
+    }
+}
@@ -0,0 +1,101 @@
+//! Rolling the log back to a checkpoint by cutting off its tail.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+use crate::durable;
+use crate::entry::EntryKind;
+use crate::error::Result;
+use crate::header;
+use crate::segment;
+use crate::wal::WriteAheadLog;
+
+impl WriteAheadLog {
+    /// Removes every entry with an ID greater than `id`, for rolling back
+    /// to a checkpoint, and returns how many data entries went. Entries
+    /// already cleared are not counted, as [`len`](Self::len) does not
+    /// count them either. The next append gets `id + 1`.
+    ///
+    /// Rather than rewriting the log, the segment holding the first record
+    /// past `id` is cut at that record's offset with `set_len`, and later
+    /// segments are deleted. A sealed segment is not cut in place, as reads
+    /// may have it memory-mapped: what comes before the cut is copied into
+    /// a fresh active file instead. Everything after the cut goes, including
+    /// markers and records of other streams. Records queued by group commit
+    /// are written first, so they are rolled back too.
+    pub fn truncate_after(&mut self, id: u64) -> Result<usize> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        self.flush()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        let segments = segment::all_segments(&self.path)?;
+        let mut cut = None;
+        let mut removed = 0;
+        let mut buf = Vec::new();
+        for (index, path) in segments.iter().enumerate() {
+            let mut reader = BufReader::new(File::open(path)?);
+            let mut offset = header::skip(&mut reader)?;
+            loop {
+                let consumed = self.format.read_frame(&mut reader, &mut buf)?;
+                if consumed == 0 {
+                    break;
+                }
+                let decoded = self.format.decode_with(
+                    &buf,
+                    self.dictionary.as_deref(),
+                    self.cipher.as_deref(),
+                );
+                if let Some(record) = decoded.ok().filter(|r| r.stream == 0 && r.id > id) {
+                    cut.get_or_insert((index, offset));
+                    removed +=
+                        usize::from(record.kind == EntryKind::Data && !self.is_cleared(&record));
+                }
+                offset += consumed as u64;
+            }
+        }
+        let Some((index, offset)) = cut else {
+            return Ok(0);
+        };
+
+        // Later segments go first, newest first, so a crash part way
+        // leaves a prefix of the log.
+        for path in segments[index + 1..].iter().rev() {
+            fs::remove_file(path)?;
+        }
+        durable::sync_dir(&self.path)?;
+        let target = &segments[index];
+        if *target == self.path {
+            let cut_file = OpenOptions::new().write(true).open(target)?;
+            cut_file.set_len(offset)?;
+            cut_file.sync_data()?;
+        } else {
+            let mut temp = self.path.as_os_str().to_owned();
+            temp.push(".truncate");
+            let temp = PathBuf::from(temp);
+            let mut out = File::create(&temp)?;
+            io::copy(&mut File::open(target)?.take(offset), &mut out)?;
+            out.sync_data()?;
+            durable::rename(&temp, &self.path)?;
+            fs::remove_file(target)?;
+            durable::sync_dir(&self.path)?;
+        }
+        *file = segment::open_active(&self.path, &self.header())?;
+        drop(file);
+
+        // Tombstones past the cut are gone, reviving what they cleared.
+        self.tombstones.lock()?.clear();
+        let (_, tombstones) = self.load_ids()?;
+        *self.tombstones.lock()? = tombstones;
+        self.current_id = id + 1;
+        self.reset_ids_below(self.current_id)?;
+        self.durable_id.fetch_min(self.current_id, Ordering::AcqRel);
+        self.idempotency_keys = None;
+        self.stream_ids.lock()?.clear();
+        *self.entry_count.lock()? = None;
+        self.invalidate_cache();
+        Ok(removed)
+    }
+}
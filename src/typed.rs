@@ -0,0 +1,99 @@
+use std::marker::PhantomData;
+use std::path::Path;
+
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// Converts a value to and from the bytes stored in an entry's `data`.
+///
+/// Return [`WalError::Serialization`] from either method when the value
+/// cannot be encoded or the bytes do not describe a valid value.
+pub trait Payload: Sized {
+    /// Encodes the value for storage.
+    fn to_bytes(&self) -> Result<Vec<u8>>;
+    /// Decodes a value previously produced by [`to_bytes`](Self::to_bytes).
+    fn from_bytes(bytes: &[u8]) -> Result<Self>;
+}
+
+impl Payload for Vec<u8> {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.clone())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Payload for String {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.as_bytes().to_vec())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        String::from_utf8(bytes.to_vec()).map_err(|e| WalError::Serialization(e.to_string()))
+    }
+}
+
+/// A [`WriteAheadLog`] whose payloads are values of `T`.
+///
+/// Values are stored in the ordinary `data` field, so a typed log can still be
+/// opened and read with the byte-level API.
+#[derive(Debug)]
+pub struct TypedWal<T> {
+    wal: WriteAheadLog,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<T: Payload> TypedWal<T> {
+    /// Opens the log at `path` with default options.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from_wal(WriteAheadLog::new(path)?))
+    }
+
+    /// Wraps an already opened log.
+    pub fn from_wal(wal: WriteAheadLog) -> Self {
+        TypedWal {
+            wal,
+            _payload: PhantomData,
+        }
+    }
+
+    /// The underlying byte-level log.
+    pub fn inner(&self) -> &WriteAheadLog {
+        &self.wal
+    }
+
+    /// Unwraps the underlying byte-level log.
+    pub fn into_inner(self) -> WriteAheadLog {
+        self.wal
+    }
+
+    /// Encodes and appends `value`.
+    pub fn append(&mut self, value: &T) -> Result<LogEntry> {
+        let data = value.to_bytes()?;
+        self.wal.append(data)
+    }
+
+    /// Decodes every entry, failing on the first one that does not decode.
+    pub fn read_all(&self) -> Result<Vec<T>> {
+        self.wal
+            .read_all()?
+            .iter()
+            .map(|e| T::from_bytes(&e.data))
+            .collect()
+    }
+
+    /// Decodes every entry independently, pairing each ID with its own
+    /// outcome so one undecodable entry does not hide the rest. Useful when
+    /// the payload schema has changed over the life of the log.
+    pub fn read_all_lenient(&self) -> Result<Vec<(u64, Result<T>)>> {
+        Ok(self
+            .wal
+            .read_all()?
+            .iter()
+            .map(|e| (e.id, T::from_bytes(&e.data)))
+            .collect())
+    }
+}
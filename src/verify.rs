@@ -0,0 +1,110 @@
+use crate::entry::EntryKind;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// How many mismatching IDs a [`VerifyReport`] lists.
+const REPORTED_MISMATCHES: usize = 16;
+
+/// Outcome of [`WriteAheadLog::verify_checksums_streaming`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Records whose checksum matched.
+    pub valid: u64,
+    /// Records whose checksum did not match.
+    pub mismatched: u64,
+    /// Records written without a checksum, which cannot be checked.
+    pub unchecked: u64,
+    /// IDs of the first few mismatching records, in file order.
+    pub first_mismatches: Vec<u64>,
+}
+
+impl VerifyReport {
+    /// Whether every checked record matched.
+    pub fn is_ok(&self) -> bool {
+        self.mismatched == 0
+    }
+}
+
+impl WriteAheadLog {
+    /// Recomputes the checksum of every record, markers and other streams
+    /// included, one record at a time, so memory stays bounded however
+    /// large the log. Records that fail to decode are skipped as by reads;
+    /// see [`count_resilient`](Self::count_resilient) for those.
+    pub fn verify_checksums_streaming(&self) -> Result<VerifyReport> {
+        let _file = self.lock_file()?;
+        let mut report = VerifyReport::default();
+        for record in self.unchecked_records()? {
+            let record = record?;
+            if !record.has_checksum() {
+                report.unchecked += 1;
+            } else if record.is_checksum_valid() {
+                report.valid += 1;
+            } else {
+                report.mismatched += 1;
+                if report.first_mismatches.len() < REPORTED_MISMATCHES {
+                    report.first_mismatches.push(record.id);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// IDs of every record in every segment, in file order, whose stored
+    /// checksum does not match its contents: the entries reads fail on
+    /// with [`WalError::ChecksumMismatch`]. Unlike reads, the scan carries
+    /// on past each one. Records without a checksum count as intact, and
+    /// records that do not decode at all, having no ID to report, are
+    /// skipped as by reads.
+    pub fn verify(&self) -> Result<Vec<u64>> {
+        let _file = self.lock_file()?;
+        let mut corrupted = Vec::new();
+        for record in self.unchecked_records()? {
+            let record = record?;
+            if !record.is_checksum_valid() {
+                corrupted.push(record.id);
+            }
+        }
+        Ok(corrupted)
+    }
+
+    /// Checks that the log's data entries are exactly `expected`, as
+    /// `(id, data)` pairs in order, returning [`WalError::Mismatch`] at the
+    /// first divergence. Mainly a testing aid.
+    pub fn assert_entries(&self, expected: &[(u64, &[u8])]) -> Result<()> {
+        let _file = self.lock_file()?;
+        let mut index = 0;
+        for record in self.records()? {
+            let record = record?;
+            if record.kind != EntryKind::Data {
+                continue;
+            }
+            let Some(&(id, data)) = expected.get(index) else {
+                return Err(mismatch(
+                    index,
+                    format!("unexpected extra entry with id {}", record.id),
+                ));
+            };
+            if record.id != id {
+                return Err(mismatch(
+                    index,
+                    format!("expected id {id}, found {}", record.id),
+                ));
+            }
+            if record.data != data {
+                return Err(mismatch(
+                    index,
+                    format!("entry {id} has data {:?}, expected {data:?}", record.data),
+                ));
+            }
+            index += 1;
+        }
+        match expected.get(index) {
+            Some((id, _)) => Err(mismatch(index, format!("missing entry with id {id}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn mismatch(index: usize, detail: String) -> WalError {
+    WalError::Mismatch { index, detail }
+}
@@ -0,0 +1,840 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::auto_compact::Compactor;
+use crate::builder::WriteAheadLogBuilder;
+use crate::cache::{GetCache, ReadAllCache};
+use crate::callback::{AppendTransform, Callback, DropError, SegmentEvicted};
+use crate::clock::Clock;
+use crate::compress::Compression;
+use crate::dictionary::{self, Dictionary};
+use crate::durable;
+use crate::encrypt::{Cipher, Encryption};
+use crate::entry::{EntryKind, LogEntry};
+use crate::error::{Result, WalError};
+use crate::fence;
+use crate::format::Format;
+use crate::gate::{AppendGate, PauseMode};
+use crate::group_commit::Pending;
+use crate::hasher::{self, Hasher};
+use crate::header;
+use crate::high_water;
+use crate::id_set::IdSet;
+use crate::idempotency::{DedupPolicy, SeenKeys};
+use crate::lock;
+use crate::metrics::MetricCounters;
+use crate::quarantine::Quarantine;
+use crate::rate::TokenBucket;
+use crate::segment;
+use crate::sink::{SinkErrorPolicy, Sinks};
+use crate::stats::WriteCounters;
+use crate::sync::{SyncPolicy, Unsynced};
+use crate::write_buffer::WriteBuffer;
+
+/// An append-only log of [`LogEntry`] records, stored in a single file or,
+/// with rotation enabled, a series of segments. Records are newline-delimited
+/// JSON unless another [`Format`] is configured.
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    pub(crate) path: PathBuf,
+    pub(crate) file: Arc<Mutex<File>>,
+    pub(crate) current_id: u64,
+    /// ID handed out first in a log without records.
+    pub(crate) start_id: u64,
+    pub(crate) checksums: bool,
+    pub(crate) format: Format,
+    pub(crate) max_segment_bytes: Option<u64>,
+    pub(crate) max_segments: Option<usize>,
+    pub(crate) on_segment_evicted: Option<Callback<SegmentEvicted>>,
+    pub(crate) quarantine: Option<Arc<Quarantine>>,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) max_entries: Option<u64>,
+    /// Whether appends past `max_entries` evict the oldest entries rather
+    /// than fail.
+    pub(crate) evict_oldest: bool,
+    /// Data entries evicted through this handle.
+    pub(crate) evicted: u64,
+    /// Cached number of data entries; `None` until counted or after a
+    /// rewrite.
+    pub(crate) entry_count: Arc<Mutex<Option<u64>>>,
+    pub(crate) group_commit: bool,
+    /// Records queued by group commit, in append order.
+    pub(crate) queue: Vec<Pending>,
+    pub(crate) gate: AppendGate,
+    pub(crate) pause_mode: PauseMode,
+    pub(crate) writes: Arc<WriteCounters>,
+    pub(crate) metrics: Arc<MetricCounters>,
+    pub(crate) get_cache: Option<Arc<Mutex<GetCache>>>,
+    /// Last `read_all` result, if caching it is enabled.
+    pub(crate) read_all_cache: Option<Arc<Mutex<ReadAllCache>>>,
+    /// Whether scans read files through memory maps.
+    pub(crate) mmap_reads: bool,
+    /// Next ID of each logical stream other than 0, filled in on first use.
+    pub(crate) stream_ids: Mutex<HashMap<u32, u64>>,
+    pub(crate) on_drop_error: Option<Callback<DropError>>,
+    pub(crate) durable_id: AtomicU64,
+    /// One past the largest ID written by this handle, which
+    /// [`commit`](Self::commit) checks a reservation against.
+    pub(crate) written_id: AtomicU64,
+    /// ID of each idempotency key seen; `None` until first used.
+    pub(crate) idempotency_keys: Option<SeenKeys>,
+    /// How many idempotency keys to remember, if bounded.
+    pub(crate) max_idempotency_keys: Option<usize>,
+    /// Set by [`anonymous`](Self::anonymous): delete the files on drop.
+    pub(crate) anonymous: bool,
+    /// Held for the whole of any rewrite of the log's files, so that a
+    /// snapshot compaction never races another rewrite.
+    pub(crate) rewrite_lock: Arc<Mutex<()>>,
+    /// Set while a snapshot compaction runs; rotation is put off meanwhile.
+    pub(crate) compacting: Arc<AtomicBool>,
+    /// The next ID stored beside the log, below which reopening it never
+    /// goes.
+    pub(crate) high_water: Arc<AtomicU64>,
+    /// Dictionary payloads are compressed against, if configured or stored
+    /// beside the log.
+    pub(crate) dictionary: Option<Arc<Dictionary>>,
+    /// Key encrypted payloads are sealed and opened with, if configured.
+    pub(crate) cipher: Option<Arc<Cipher>>,
+    /// Records are padded to a multiple of this many bytes, if set.
+    pub(crate) alignment: Option<usize>,
+    pub(crate) hasher: Option<Callback<dyn Hasher>>,
+    pub(crate) sync_policy: SyncPolicy,
+    pub(crate) unsynced: Mutex<Unsynced>,
+    /// Appends not yet written to the active file, if buffering is on.
+    /// Shared with the background compaction's view of the log.
+    pub(crate) write_buffer: Option<Arc<WriteBuffer>>,
+    pub(crate) token_bucket: Option<Mutex<TokenBucket>>,
+    pub(crate) append_transform: Option<Callback<AppendTransform>>,
+    pub(crate) discarded_on_open: u64,
+    /// Epoch this handle writes under; see [`WriteAheadLogBuilder::epoch`].
+    pub(crate) epoch: Option<u64>,
+    pub(crate) clock: Callback<dyn Clock>,
+    /// IDs of the stream-0 records cleared by tombstones, which reads skip.
+    pub(crate) tombstones: Arc<Mutex<HashSet<u64>>>,
+    /// Holds the advisory lock on `<path>.lock`, if taken, until dropped.
+    pub(crate) _lock: Option<File>,
+    /// Destinations appended records are forwarded to.
+    pub(crate) sinks: Option<Callback<Sinks>>,
+    pub(crate) sink_error_policy: SinkErrorPolicy,
+    /// Codec appended data entries are compressed with, if any.
+    pub(crate) compression: Option<Compression>,
+    /// Scheme appended data entries are encrypted with, if any.
+    pub(crate) encryption: Option<Encryption>,
+    /// Which of the data entries sharing an idempotency key `read_all`
+    /// keeps, if it collapses them.
+    pub(crate) dedup_on_read: Option<DedupPolicy>,
+    /// Background compaction thread, stopped when the log is dropped.
+    pub(crate) compactor: Option<Compactor>,
+    /// Set by [`open_read_only`](Self::open_read_only): every change to the
+    /// log fails with [`WalError::ReadOnly`].
+    pub(crate) read_only: bool,
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path` with default options, creating the file if it
+    /// does not exist. The parent directory must already exist; see
+    /// [`WriteAheadLogBuilder::create_dirs`] otherwise.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::builder(path).build()
+    }
+
+    /// Returns a builder for opening the log at `path` with custom options.
+    pub fn builder<P: AsRef<Path>>(path: P) -> WriteAheadLogBuilder {
+        WriteAheadLogBuilder::new(path)
+    }
+
+    pub(crate) fn open(options: WriteAheadLogBuilder) -> Result<Self> {
+        let path = options.path;
+        let read_only = options.read_only;
+        let lock = (options.lock && !read_only)
+            .then(|| lock::lock_exclusive(&path))
+            .transpose()?;
+        if let Some(epoch) = options.epoch {
+            let _claiming = match lock {
+                Some(_) => None,
+                None => Some(lock::lock_exclusive(&path)?),
+            };
+            fence::claim(&path, epoch)?;
+        }
+        let dictionary = dictionary::load(&path, options.compression_dict)?;
+        options.format.check_recorded(&path)?;
+        let file = if read_only {
+            // Outdated segments are read as they are rather than upgraded.
+            header::check(&path)?;
+            File::open(&path)?
+        } else {
+            let header = header::bytes(options.format, dictionary.as_ref());
+            header::check_or_upgrade(&path, &header)?;
+            segment::open_active(&path, &header)?
+        };
+        let dictionary = dictionary.map(Arc::new);
+        let quarantine = options.quarantine.then(|| Arc::new(Quarantine::new(&path)));
+        let hasher = hasher::load_or_store(&path, options.hasher, !read_only)?;
+        let high_water = high_water::stored(&path)?;
+        let mut wal = WriteAheadLog {
+            path,
+            file: Arc::new(Mutex::new(file)),
+            current_id: 0,
+            start_id: options.start_id,
+            checksums: options.checksums,
+            format: options.format,
+            max_segment_bytes: options.max_segment_bytes,
+            max_segments: options.max_segments,
+            on_segment_evicted: options.on_segment_evicted,
+            quarantine,
+            max_file_size: options.max_file_size,
+            max_entries: options.max_entries,
+            evict_oldest: options.evict_oldest,
+            evicted: 0,
+            entry_count: Arc::new(Mutex::new(None)),
+            group_commit: options.group_commit,
+            queue: Vec::new(),
+            gate: AppendGate::default(),
+            pause_mode: options.pause_mode,
+            writes: Arc::default(),
+            metrics: Arc::default(),
+            get_cache: options
+                .get_cache
+                .map(|cap| Arc::new(Mutex::new(GetCache::new(cap)))),
+            read_all_cache: options.read_all_cache.then(Arc::default),
+            mmap_reads: options.mmap,
+            stream_ids: Mutex::new(HashMap::new()),
+            on_drop_error: options.on_drop_error,
+            durable_id: AtomicU64::new(0),
+            written_id: AtomicU64::new(0),
+            idempotency_keys: None,
+            max_idempotency_keys: options.max_idempotency_keys,
+            anonymous: false,
+            rewrite_lock: Arc::default(),
+            compacting: Arc::default(),
+            high_water: Arc::new(AtomicU64::new(high_water)),
+            dictionary,
+            cipher: options.encryption.clone().map(Arc::new),
+            alignment: options.alignment,
+            hasher,
+            sync_policy: options.sync_policy,
+            unsynced: Mutex::new(Unsynced::default()),
+            write_buffer: (options.write_buffer > 0)
+                .then(|| Arc::new(WriteBuffer::new(options.write_buffer))),
+            token_bucket: options
+                .token_bucket
+                .map(|(rate, burst)| Mutex::new(TokenBucket::new(rate, burst))),
+            append_transform: options.append_transform,
+            discarded_on_open: 0,
+            epoch: options.epoch,
+            clock: options.clock,
+            tombstones: Arc::default(),
+            _lock: lock,
+            sinks: options.sinks,
+            sink_error_policy: options.sink_error_policy,
+            compression: options.compression,
+            encryption: options.encryption.as_ref().map(|_| {
+                if options.authenticate_metadata {
+                    Encryption::ChaCha20Poly1305WithMetadata
+                } else {
+                    Encryption::ChaCha20Poly1305
+                }
+            }),
+            dedup_on_read: options.dedup_on_read,
+            compactor: None,
+            read_only,
+        };
+        let recovery = wal.latest_checkpoint(&*wal.file.lock()?);
+        let resume_at = recovery.as_ref().map_or(0, |r| r.resume_at);
+        if !read_only {
+            wal.discarded_on_open = wal.trim_torn_tail(&*wal.file.lock()?, resume_at)?;
+        }
+        let (next_id, tombstones) = match recovery {
+            Some(recovery) => wal.load_ids_after(recovery)?,
+            None => wal.load_ids()?,
+        };
+        wal.current_id = wal.continue_after(next_id);
+        *wal.tombstones.lock()? = tombstones;
+        *wal.durable_id.get_mut() = wal.current_id;
+        if let Some((policy, interval)) = options.background_compaction {
+            wal.compactor = Some(Compactor::spawn(
+                wal.compaction_view(),
+                policy,
+                interval,
+                options.on_compaction_error,
+            )?);
+        }
+        Ok(wal)
+    }
+
+    /// Opens the log at `path`, reporting to `handler` any error from the
+    /// flush and sync made when it is dropped. See
+    /// [`WriteAheadLogBuilder::on_drop_error`].
+    pub fn with_drop_error_handler<P: AsRef<Path>>(
+        path: P,
+        handler: Box<dyn Fn(WalError) + Send + Sync>,
+    ) -> Result<Self> {
+        Self::builder(path).on_drop_error(handler).build()
+    }
+
+    /// Opens the log at `path`, passing every record to `transform` before
+    /// it is written. See [`WriteAheadLogBuilder::append_transform`].
+    pub fn with_append_transform<P: AsRef<Path>>(
+        path: P,
+        transform: Box<dyn Fn(&mut LogEntry) + Send + Sync>,
+    ) -> Result<Self> {
+        Self::builder(path).append_transform(transform).build()
+    }
+
+    /// Opens the log at `path`, moving undecodable records it comes across
+    /// into `<path>.quarantine`. See
+    /// [`WriteAheadLogBuilder::quarantine`].
+    pub fn with_quarantine<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::builder(path).quarantine(true).build()
+    }
+
+    /// Opens the log at `path`, sealing the active file into numbered
+    /// segments (`<path>.000001`, ...) once it would grow past `bytes`. See
+    /// [`WriteAheadLogBuilder::max_segment_bytes`].
+    pub fn with_max_segment_bytes<P: AsRef<Path>>(path: P, bytes: u64) -> Result<Self> {
+        Self::builder(path).max_segment_bytes(bytes).build()
+    }
+
+    /// Opens the log at `path` padding each record to a multiple of
+    /// `alignment` bytes. See [`WriteAheadLogBuilder::alignment`].
+    pub fn with_alignment<P: AsRef<Path>>(path: P, alignment: usize) -> Result<Self> {
+        Self::builder(path).alignment(alignment).build()
+    }
+
+    /// Opens the log at `path` with records encoded in `format`, such as the
+    /// length-prefixed [`Format::Binary`] for raw payloads. See
+    /// [`WriteAheadLogBuilder::format`].
+    pub fn with_format<P: AsRef<Path>>(path: P, format: Format) -> Result<Self> {
+        Self::builder(path).format(format).build()
+    }
+
+    /// Like [`with_format`](Self::with_format), but first checks that the
+    /// log's records are in `expected`, and fails with
+    /// [`WalError::FormatMismatch`] if they are in another format. The
+    /// format is recorded in the header of each file; for files written
+    /// before it was, it is judged by their first record. The two JSON
+    /// formats read each other's records and count as the same. An empty
+    /// log passes.
+    ///
+    /// Opening a log in the wrong format is worse than garbled reads: the
+    /// open takes what it cannot decode at the end for a torn write and
+    /// cuts it off.
+    pub fn open_as<P: AsRef<Path>>(path: P, expected: Format) -> Result<Self> {
+        expected.check_file(path.as_ref())?;
+        Self::with_format(path, expected)
+    }
+
+    /// Opens the log at `path` writing payloads as base64 strings. See
+    /// [`Format::JsonBase64`].
+    pub fn with_base64_data<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::builder(path).format(Format::JsonBase64).build()
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The ID the next appended entry will receive.
+    pub fn next_id(&self) -> u64 {
+        self.current_id
+    }
+
+    /// Appends `data` as a new entry and flushes it to the file, syncing as
+    /// the [`SyncPolicy`] says, or with
+    /// [`group_commit`](WriteAheadLogBuilder::group_commit) queues it for the
+    /// next [`flush`](Self::flush).
+    pub fn append(&mut self, data: Vec<u8>) -> Result<LogEntry> {
+        self.append_record(LogEntry {
+            data,
+            ..LogEntry::default()
+        })
+    }
+
+    /// Assigns the next ID and the current time to `entry` and writes it.
+    pub(crate) fn append_record(&mut self, mut entry: LogEntry) -> Result<LogEntry> {
+        self.check_writable()?;
+        self.gate.pass(self.pause_mode)?;
+        entry.timestamp = self.now()?;
+        self.take_tokens(1)?;
+        if entry.kind == EntryKind::Data {
+            self.make_room(1)?;
+        }
+        if self.group_commit {
+            self.assign_deferred();
+            self.assign(&mut entry);
+            self.queue.push(Pending {
+                entry: entry.clone(),
+                slot: None,
+                assigned: true,
+                on_durable: None,
+            });
+            return Ok(entry);
+        }
+        self.write_appended(entry).map(|(entry, _)| entry)
+    }
+
+    /// Stamps `entry` and writes it straight to the file, bypassing any
+    /// group-commit queue, returning it with the offset it starts at in the
+    /// active file.
+    pub(crate) fn write_appended(&mut self, mut entry: LogEntry) -> Result<(LogEntry, u64)> {
+        self.stamp(&mut entry);
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        let offset = self.write_record(&mut file, &entry)?;
+        self.current_id += 1;
+        self.sync_for_policy(&file, 1, self.current_id)?;
+        drop(file);
+        self.forward([&entry])?;
+        Ok((entry, offset))
+    }
+
+    /// Gives `entry` the next ID and its checksum, consuming the ID.
+    pub(crate) fn assign(&mut self, entry: &mut LogEntry) {
+        self.stamp(entry);
+        self.current_id += 1;
+    }
+
+    pub(crate) fn stamp(&self, entry: &mut LogEntry) {
+        entry.id = self.current_id;
+        self.finalize(entry);
+    }
+
+    /// Runs the append transform, if any, on an `entry` whose ID and
+    /// timestamp are final, then fills in its checksum and digest as
+    /// configured.
+    pub(crate) fn finalize(&self, entry: &mut LogEntry) {
+        if entry.kind == EntryKind::Data && entry.compression.is_none() {
+            entry.compression = self.compression;
+        }
+        if entry.kind == EntryKind::Data && entry.encryption.is_none() {
+            entry.encryption = self.encryption;
+        }
+        if let Some(transform) = &self.append_transform {
+            transform(entry);
+        }
+        if self.checksums {
+            entry.checksum = entry.compute_checksum();
+        }
+        if let Some(hasher) = &self.hasher {
+            entry.digest = entry.compute_digest(&**hasher);
+        }
+    }
+
+    /// Encodes and writes a stamped `entry` to the active file, subject to
+    /// the configured limits and rotation.
+    pub(crate) fn write_record(&self, file: &mut File, entry: &LogEntry) -> Result<u64> {
+        self.write_records(file, std::slice::from_ref(entry))
+    }
+
+    /// Encodes `entry` as this log stores it: in its format, compressed and
+    /// padded as configured. Fails with [`WalError::Io`] if the entry is to
+    /// be encrypted and no nonce can be drawn.
+    pub(crate) fn encode_record(&self, entry: &LogEntry) -> Result<Vec<u8>> {
+        self.format.encode_with(
+            entry,
+            self.dictionary.as_deref(),
+            self.cipher.as_deref(),
+            self.alignment,
+        )
+    }
+
+    /// Replaces the file at `path` with one holding `records`, written to
+    /// `<path>.rewrite`, synced and renamed over it so that a crash leaves
+    /// either the old contents or the new. Returns the number of bytes
+    /// written. Open handles to the old file, the active one included, must
+    /// be reopened.
+    pub(crate) fn rewrite_records(&self, path: &Path, records: &[LogEntry]) -> Result<u64> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".rewrite");
+        let temp = PathBuf::from(temp);
+        self.keep_ids_below(self.current_id)?;
+        let mut out = File::create(&temp)?;
+        out.write_all(&self.header())?;
+        let mut written = 0;
+        for record in records {
+            let record = self.encode_record(record)?;
+            out.write_all(&record)?;
+            written += record.len() as u64;
+        }
+        out.sync_data()?;
+        durable::rename(&temp, path)?;
+        Ok(written)
+    }
+
+    /// Writes stamped `entries` back-to-back with a single write and flush,
+    /// or adds them to the write buffer, returning the offset in the active
+    /// file where they start. Limits are
+    /// checked for the group as a whole, and the group is never split across
+    /// segments.
+    pub(crate) fn write_records(&self, file: &mut File, entries: &[LogEntry]) -> Result<u64> {
+        self.check_writable()?;
+        let mut buf = Vec::new();
+        for entry in entries {
+            buf.extend_from_slice(&self.encode_record(entry)?);
+        }
+        let data = entries.iter().filter(|e| e.kind == EntryKind::Data).count() as u64;
+        self.check_epoch()?;
+        self.check_limits(file, buf.len() as u64, data)?;
+        self.maybe_rotate(file, buf.len() as u64)?;
+        let offset = match &self.write_buffer {
+            Some(buffer) => buffer.push(file, &buf)?,
+            None => {
+                let offset = file.seek(SeekFrom::End(0))?;
+                file.write_all(&buf)?;
+                file.flush()?;
+                offset
+            }
+        };
+        self.writes.appended(buf.len() as u64);
+        self.metrics
+            .appended(entries.len() as u64, data, buf.len() as u64);
+        if let Some(cache) = &self.get_cache {
+            let mut cache = cache.lock()?;
+            for entry in entries {
+                cache.invalidate(entry.id);
+            }
+        }
+        self.invalidate_read_all_cache();
+        if let Some(id) = entries.iter().filter(|e| e.stream == 0).map(|e| e.id).max() {
+            self.written_id.fetch_max(id + 1, Ordering::AcqRel);
+        }
+        if let Some(count) = self.entry_count.lock()?.as_mut() {
+            *count += data;
+        }
+        Ok(offset)
+    }
+
+    /// Reads every data entry in file order. Lines that fail to parse are
+    /// skipped, as are bookkeeping records such as markers. Duplicate
+    /// idempotency keys are collapsed if
+    /// [`dedup_on_read`](WriteAheadLogBuilder::dedup_on_read) is set.
+    ///
+    /// File order is ID order for logs written only through this API, but
+    /// not necessarily for files put together by other means; see
+    /// [`read_all_sorted`](Self::read_all_sorted). With
+    /// [`read_all_cache`](WriteAheadLogBuilder::read_all_cache) enabled, a
+    /// read with no write since the last one returns that result again
+    /// without touching the records.
+    pub fn read_all(&self) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let stamps = match &self.read_all_cache {
+            Some(cache) => {
+                let stamps = self.file_stamps()?;
+                if let Some(entries) = cache.lock()?.lookup(&stamps) {
+                    return Ok(entries);
+                }
+                Some(stamps)
+            }
+            None => None,
+        };
+        let mut entries = Vec::new();
+        for record in self.records()? {
+            let record = record?;
+            if record.kind == EntryKind::Data {
+                entries.push(record);
+            }
+        }
+        if let Some(policy) = self.dedup_on_read {
+            policy.dedup(&mut entries);
+        }
+        if let (Some(cache), Some(stamps)) = (&self.read_all_cache, stamps) {
+            cache.lock()?.insert(stamps, entries.clone());
+        }
+        Ok(entries)
+    }
+
+    /// Like [`read_all`](Self::read_all), but in ascending ID order whatever
+    /// the order of the records in the files.
+    pub fn read_all_sorted(&self) -> Result<Vec<LogEntry>> {
+        let mut entries = self.read_all()?;
+        entries.sort_by_key(|e| e.id);
+        Ok(entries)
+    }
+
+    /// Like [`read_all`](Self::read_all), but as a queue that yields the
+    /// oldest entry first from [`pop_front`](VecDeque::pop_front).
+    pub fn read_all_deque(&self) -> Result<VecDeque<LogEntry>> {
+        Ok(self.read_all()?.into())
+    }
+
+    /// Looks up the data entry with the given ID, streaming records until it
+    /// is found or passed, without collecting the rest. Returns `Ok(None)`
+    /// if there is no such entry, including when it is a marker or was
+    /// cleared.
+    ///
+    /// The scan relies on IDs ascending through the log, which appends,
+    /// rewrites and [`resequence`](Self::resequence) all preserve. A file
+    /// stitched together out of order by other means can hide entries that lie
+    /// beyond a larger ID until [`sort_by_id`](Self::sort_by_id) repairs it.
+    /// Results are cached when a
+    /// [`get_cache`](WriteAheadLogBuilder::get_cache) is configured.
+    pub fn get(&self, id: u64) -> Result<Option<LogEntry>> {
+        self.metrics.read();
+        if let Some(cache) = &self.get_cache {
+            if let Some(entry) = cache.lock()?.lookup(id) {
+                return Ok(entry);
+            }
+        }
+        let _file = self.lock_file()?;
+        let mut found = None;
+        for record in self.records()? {
+            let record = record?;
+            if record.id >= id {
+                if record.id == id && record.kind == EntryKind::Data {
+                    found = Some(record);
+                }
+                break;
+            }
+        }
+        if let Some(cache) = &self.get_cache {
+            cache.lock()?.insert(id, found.clone());
+        }
+        Ok(found)
+    }
+
+    /// The data entry at position `index` in file order, counting from 0
+    /// and skipping bookkeeping records, or `None` past the end. Streams
+    /// records up to that position and stops, so the cost grows with
+    /// `index`. Positions shift when entries are removed, unlike IDs.
+    pub fn nth(&self, index: usize) -> Result<Option<LogEntry>> {
+        let _file = self.lock_file()?;
+        let mut data = self
+            .records()?
+            .filter(|r| r.as_ref().map_or(true, |r| r.kind == EntryKind::Data));
+        data.nth(index).transpose()
+    }
+
+    /// Reads the data entries whose IDs are in `ids`.
+    ///
+    /// IDs ascend through the log, so the set is walked in step with it and
+    /// the scan stops once the largest wanted ID has been passed. `ids` may
+    /// be a `BTreeSet<u64>` or, with the `roaring` feature, a roaring
+    /// bitmap; see [`IdSet`].
+    pub fn read_ids<S: IdSet + ?Sized>(&self, ids: &S) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        let _file = self.lock_file()?;
+        let mut wanted = ids.ascending().peekable();
+        let mut entries = Vec::new();
+        for record in self.records()? {
+            let record = record?;
+            while wanted.next_if(|&id| id < record.id).is_some() {}
+            let Some(&next) = wanted.peek() else {
+                break;
+            };
+            if next == record.id && record.kind == EntryKind::Data {
+                entries.push(record);
+                wanted.next();
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Looks up each of `ids` in a single pass over the log, returning the
+    /// results in the order asked for, with `None` for IDs that are not
+    /// data entries. Duplicates get a copy each. See
+    /// [`read_ids`](Self::read_ids).
+    pub fn get_many(&self, ids: &[u64]) -> Result<Vec<Option<LogEntry>>> {
+        let found: HashMap<u64, LogEntry> = self
+            .read_ids(&ids.iter().copied().collect::<BTreeSet<u64>>())?
+            .into_iter()
+            .map(|e| (e.id, e))
+            .collect();
+        Ok(ids.iter().map(|id| found.get(id).cloned()).collect())
+    }
+
+    /// Reads the data entries with `from_ts <= timestamp <= to_ts`, both
+    /// ends inclusive, in file order. An empty range, `from_ts > to_ts`,
+    /// yields nothing.
+    ///
+    /// Entries are appended in time order, so the scan stops at the first
+    /// data entry stamped after `to_ts`. If the clock stepped back while the
+    /// log was written (see
+    /// [`timestamp_regressions`](Self::timestamp_regressions)), entries in
+    /// range that lie beyond that point are missed.
+    pub fn read_range(&self, from_ts: u64, to_ts: u64) -> Result<Vec<LogEntry>> {
+        self.metrics.read();
+        if from_ts > to_ts {
+            return Ok(Vec::new());
+        }
+        let _file = self.lock_file()?;
+        let mut entries = Vec::new();
+        for record in self.records()? {
+            let record = record?;
+            if record.kind != EntryKind::Data {
+                continue;
+            }
+            if record.timestamp > to_ts {
+                break;
+            }
+            if record.timestamp >= from_ts {
+                entries.push(record);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The distinct timestamps of the data entries, in ascending order.
+    pub fn distinct_timestamps(&self) -> Result<Vec<u64>> {
+        let _file = self.lock_file()?;
+        let mut timestamps: Vec<u64> = Vec::new();
+        for record in self.records()? {
+            let record = record?;
+            if record.kind == EntryKind::Data && timestamps.last() != Some(&record.timestamp) {
+                timestamps.push(record.timestamp);
+            }
+        }
+        // Timestamps only go backwards if the clock did; handle it anyway.
+        if !timestamps.is_sorted() {
+            timestamps.sort_unstable();
+            timestamps.dedup();
+        }
+        Ok(timestamps)
+    }
+
+    /// Finds where the clock stepped back while the log was written: the
+    /// `(id, prev_timestamp)` of every data entry whose timestamp is earlier
+    /// than that of the data entry before it, in file order.
+    pub fn timestamp_regressions(&self) -> Result<Vec<(u64, u64)>> {
+        let _file = self.lock_file()?;
+        let mut regressions = Vec::new();
+        let mut prev = None;
+        for record in self.records()? {
+            let record = record?;
+            if record.kind != EntryKind::Data {
+                continue;
+            }
+            if let Some(prev) = prev.filter(|&p| record.timestamp < p) {
+                regressions.push((record.id, prev));
+            }
+            prev = Some(record.timestamp);
+        }
+        Ok(regressions)
+    }
+
+    /// Removes the entry with the given ID by appending a tombstone for it.
+    /// Reads skip the entry from then on, but it stays in the file until
+    /// [`compact`](Self::compact) drops it together with its tombstone, so
+    /// removing many entries costs one rewrite rather than one each.
+    ///
+    /// Returns whether a data entry with that ID was found and removed.
+    /// Clearing an ID not yet handed out, one already cleared, or one that
+    /// belongs to a marker or other bookkeeping record does nothing and
+    /// returns `false`.
+    ///
+    /// Queued group-commit records are written first. The tombstone is not
+    /// held up by [`pause`](Self::pause) or rate limiting and is synced as
+    /// the [`SyncPolicy`] says, but it does count towards
+    /// [`max_file_size`](WriteAheadLogBuilder::max_file_size).
+    pub fn clear_id(&mut self, id: u64) -> Result<bool> {
+        self.check_writable()?;
+        self.flush()?;
+        if id >= self.current_id || self.tombstones.lock()?.contains(&id) || !self.holds_data(id)? {
+            return Ok(false);
+        }
+        let mut tombstone = LogEntry {
+            kind: EntryKind::Tombstone,
+            target: Some(id),
+            timestamp: self.now()?,
+            ..LogEntry::default()
+        };
+        self.stamp(&mut tombstone);
+        let file = Arc::clone(&self.file);
+        let mut file = file.lock()?;
+        self.write_record(&mut file, &tombstone)?;
+        self.current_id += 1;
+        self.tombstones.lock()?.insert(id);
+        *self.entry_count.lock()? = None;
+        if let Some(cache) = &self.get_cache {
+            cache.lock()?.invalidate(id);
+        }
+        self.sync_for_policy(&file, 1, self.current_id)?;
+        drop(file);
+        self.forward([&tombstone])?;
+        Ok(true)
+    }
+
+    /// Whether a data entry with the given ID is in the log, scanning as
+    /// [`get`](Self::get) does.
+    fn holds_data(&self, id: u64) -> Result<bool> {
+        let _file = self.lock_file()?;
+        for record in self.record_headers()? {
+            let record = record?;
+            if record.id >= id {
+                return Ok(record.id == id && record.kind == EntryKind::Data);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Computes the next free ID from the records in every segment, and
+    /// collects the IDs their tombstones clear.
+    pub(crate) fn load_ids(&self) -> Result<(u64, HashSet<u64>)> {
+        let mut next = 0;
+        let mut cleared = HashSet::new();
+        for record in self.record_headers()? {
+            let record = record?;
+            next = next.max(record.id + 1);
+            if let (EntryKind::Tombstone, Some(target)) = (record.kind, record.target) {
+                cleared.insert(target);
+            }
+        }
+        Ok((next, cleared))
+    }
+
+    /// Path of the quarantine file, if quarantining is enabled.
+    pub fn quarantine_path(&self) -> Option<&Path> {
+        self.quarantine.as_deref().map(Quarantine::path)
+    }
+
+    /// Removes every entry, deleting any sealed segments. IDs keep counting
+    /// up from where they were, also once the log is reopened. The active
+    /// file is swapped for an empty one with a rename, as in
+    /// [`compact`](Self::compact).
+    pub fn clear(&mut self) -> Result<()> {
+        self.check_writable()?;
+        self.check_epoch()?;
+        let _rewrite = self.rewrite_lock.lock()?;
+        let mut file = self.lock_file()?;
+        self.keep_ids_below(self.current_id)?;
+        for (_, path) in segment::sealed_segments(&self.path)? {
+            fs::remove_file(path)?;
+        }
+        self.rewrite_records(&self.path, &[])?;
+        *file = segment::open_active(&self.path, &self.header())?;
+        self.tombstones.lock()?.clear();
+        *self.entry_count.lock()? = Some(0);
+        self.invalidate_cache();
+        Ok(())
+    }
+}
+
+impl Drop for WriteAheadLog {
+    /// Makes a best-effort attempt to write records still queued by group
+    /// commit and sync the file. Errors go to the
+    /// [`on_drop_error`](WriteAheadLogBuilder::on_drop_error) handler, if
+    /// any; call [`flush`](Self::flush) beforehand to handle them directly.
+    fn drop(&mut self) {
+        self.compactor.take();
+        if self.anonymous {
+            self.remove_files();
+            return;
+        }
+        if self.read_only {
+            return;
+        }
+        let result = self
+            .flush()
+            .and_then(|()| Ok(self.lock_file()?.sync_data()?));
+        if let (Err(err), Some(handler)) = (result, &self.on_drop_error) {
+            handler(err);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Coalescing small appends in memory before they reach the active file.
+//!
+//! Buffered bytes are only ever touched under the log's file lock, and are
+//! written out before anything else takes that lock, so a reader holding it
+//! sees the file exactly as if every append had gone straight to disk.
+
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::Result;
+use crate::wal::WriteAheadLog;
+
+/// Encoded records appended but not yet written to the active file.
+#[derive(Debug)]
+pub(crate) struct WriteBuffer {
+    capacity: usize,
+    pending: Mutex<Vec<u8>>,
+}
+
+impl WriteBuffer {
+    pub(crate) fn new(capacity: usize) -> Self {
+        WriteBuffer {
+            capacity,
+            pending: Mutex::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Bytes waiting to be written.
+    pub(crate) fn len(&self) -> Result<u64> {
+        Ok(self.pending.lock()?.len() as u64)
+    }
+
+    /// Buffers `bytes` for the active `file`, writing everything buffered
+    /// out once it reaches capacity. Returns the offset in the file that
+    /// `bytes` will start at.
+    pub(crate) fn push(&self, file: &File, bytes: &[u8]) -> Result<u64> {
+        let mut pending = self.pending.lock()?;
+        let offset = file.metadata()?.len() + pending.len() as u64;
+        pending.extend_from_slice(bytes);
+        if pending.len() >= self.capacity {
+            write_out(file, &mut pending)?;
+        }
+        Ok(offset)
+    }
+
+    /// Writes everything buffered to the active `file`.
+    pub(crate) fn drain(&self, file: &File) -> Result<()> {
+        write_out(file, &mut *self.pending.lock()?)
+    }
+}
+
+/// Writes `pending` to the end of `file` in one go and empties it. If the
+/// write fails, the file is cut back to where it was and the buffered
+/// records are lost, as they would be in a crash.
+fn write_out(mut file: &File, pending: &mut Vec<u8>) -> Result<()> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let len = file.metadata()?.len();
+    let result = file.write_all(pending);
+    pending.clear();
+    if let Err(err) = result {
+        file.set_len(len)?;
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+impl WriteAheadLog {
+    /// Locks the active file for reading or rewriting it, first writing out
+    /// any buffered appends so that none are missed.
+    pub(crate) fn lock_file(&self) -> Result<MutexGuard<'_, File>> {
+        let file = self.file.lock()?;
+        self.drain_write_buffer(&file)?;
+        Ok(file)
+    }
+
+    /// Writes any buffered appends to the active `file`, which the caller
+    /// has locked.
+    pub(crate) fn drain_write_buffer(&self, file: &File) -> Result<()> {
+        match &self.write_buffer {
+            Some(buffer) => buffer.drain(file),
+            None => Ok(()),
+        }
+    }
+
+    /// Bytes of appended records held in the
+    /// [`write_buffer`](crate::WriteAheadLogBuilder::write_buffer) and not
+    /// yet written to the file. Always 0 without one.
+    pub fn buffered_bytes(&self) -> Result<u64> {
+        match &self.write_buffer {
+            Some(buffer) => buffer.len(),
+            None => Ok(0),
+        }
+    }
+}
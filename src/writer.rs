@@ -0,0 +1,148 @@
+//! Appends from many threads through a single background writer.
+//!
+//! Producers hand their payloads to the writer over a bounded channel and
+//! wait for its answer. The writer takes whatever has queued up, assigns IDs
+//! and writes it as one batch with a single write, flush and sync, so
+//! concurrent appenders share the cost of reaching the disk instead of
+//! taking turns on the log's lock.
+
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use crate::entry::LogEntry;
+use crate::error::{Result, WalError};
+use crate::wal::WriteAheadLog;
+
+/// Number of appends that may wait for the writer before
+/// [`ConcurrentWriter::append`] blocks, unless another is given.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A cloneable handle for appending to a [`WriteAheadLog`] from many
+/// threads at once, made with [`WriteAheadLog::into_concurrent`].
+///
+/// The log is owned by a background writer thread. Dropping the last clone
+/// waits for the writer to write everything still queued and then drops the
+/// log, which syncs it.
+#[derive(Debug, Clone)]
+pub struct ConcurrentWriter {
+    requests: SyncSender<Request>,
+    /// Joins the writer thread when the last handle is dropped.
+    _writer: Arc<Writer>,
+    capacity: usize,
+}
+
+#[derive(Debug)]
+struct Request {
+    data: Vec<u8>,
+    ack: SyncSender<Result<LogEntry>>,
+}
+
+/// Joins the writer thread once the last handle, and with it the last
+/// sender, is gone.
+#[derive(Debug)]
+struct Writer {
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if let Some(thread) = self
+            .thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl ConcurrentWriter {
+    /// Opens the log at `path` with default options and a channel of
+    /// [`DEFAULT_CHANNEL_CAPACITY`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        WriteAheadLog::new(path)?.into_concurrent(DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// Appends `data` as a new entry, returning once the writer has written
+    /// and synced it.
+    ///
+    /// The entry shares a write with whatever else was queued alongside it,
+    /// and the batch lands or fails as a whole, as with
+    /// [`append_batch`](WriteAheadLog::append_batch): if it would break a
+    /// limit, every append in it fails with the same error. An error from
+    /// the sync is reported too, though the entries were written.
+    pub fn append(&self, data: Vec<u8>) -> Result<LogEntry> {
+        let (ack, answer) = mpsc::sync_channel(1);
+        self.requests
+            .send(Request { data, ack })
+            .map_err(|_| stopped())?;
+        answer.recv().map_err(|_| stopped())?
+    }
+
+    /// How many appends may be queued for the writer before
+    /// [`append`](Self::append) blocks. Also the most entries written as
+    /// one batch.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl WriteAheadLog {
+    /// Hands the log to a background writer thread and returns a handle for
+    /// appending to it from any number of threads. Up to `capacity` appends
+    /// queue for the writer before further ones block; it must be at least
+    /// 1. See [`ConcurrentWriter`].
+    pub fn into_concurrent(self, capacity: usize) -> Result<ConcurrentWriter> {
+        if capacity == 0 {
+            return Err(WalError::InvalidConfig(
+                "the channel capacity must be at least 1".to_string(),
+            ));
+        }
+        let (requests, queued) = mpsc::sync_channel(capacity);
+        let thread = thread::Builder::new()
+            .name("waly-writer".to_string())
+            .spawn(move || run(self, queued, capacity))?;
+        Ok(ConcurrentWriter {
+            requests,
+            _writer: Arc::new(Writer {
+                thread: Mutex::new(Some(thread)),
+            }),
+            capacity,
+        })
+    }
+}
+
+/// Writes queued appends in batches of up to `capacity` until every sender
+/// is gone and the queue is drained.
+fn run(mut wal: WriteAheadLog, queued: Receiver<Request>, capacity: usize) {
+    while let Ok(first) = queued.recv() {
+        let batch = std::iter::once(first).chain(queued.try_iter().take(capacity - 1));
+        let (items, acks): (Vec<_>, Vec<_>) = batch.map(|r| (r.data, r.ack)).unzip();
+        match wal
+            .append_chunk(items)
+            .and_then(|entries| wal.sync().map(|()| entries))
+        {
+            Ok(entries) => {
+                for (entry, ack) in entries.into_iter().zip(acks) {
+                    let _ = ack.send(Ok(entry));
+                }
+            }
+            Err(err) => {
+                for ack in acks {
+                    let _ = ack.send(Err(err.duplicate()));
+                }
+            }
+        }
+    }
+}
+
+fn stopped() -> WalError {
+    WalError::Io(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "the log's writer thread has stopped",
+    ))
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{Format, WalError, WriteAheadLog};
+
+#[test]
+fn records_start_at_aligned_offsets() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        for alignment in [512, 4096, 3] {
+            let dir = TempDir::new();
+            let path = dir.join("aligned.wal");
+            let mut wal = WriteAheadLog::builder(&path)
+                .format(format)
+                .alignment(alignment)
+                .build()
+                .unwrap();
+            let mut expected = Vec::new();
+            for len in [0, 1, 10, 127, 128, 600, 5000, 16_383, 16_384] {
+                expected.push(wal.append(vec![7; len]).unwrap());
+                let offset = std::fs::metadata(&path).unwrap().len();
+                assert_eq!(
+                    (offset - HEADER_LEN) % alignment as u64,
+                    0,
+                    "{format:?} {alignment} {len}"
+                );
+            }
+            wal.append_marker("end").unwrap();
+            drop(wal);
+
+            let wal = WriteAheadLog::builder(&path)
+                .format(format)
+                .build()
+                .unwrap();
+            assert_eq!(wal.read_all().unwrap(), expected, "{format:?} {alignment}");
+            assert_eq!(wal.next_id(), expected.len() as u64 + 1);
+        }
+    }
+}
+
+#[test]
+fn zero_alignment_is_rejected() {
+    let dir = TempDir::new();
+    let err = WriteAheadLog::with_alignment(dir.join("bad.wal"), 0).unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)), "{err:?}");
+}
@@ -0,0 +1,21 @@
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn anonymous_log_works_and_is_removed_on_drop() {
+    let mut wal = WriteAheadLog::anonymous().unwrap();
+    let path = wal.path().to_path_buf();
+    assert!(path.is_file());
+
+    let entry = wal.append(b"ephemeral".to_vec()).unwrap();
+    assert_eq!(wal.read_all().unwrap(), vec![entry]);
+
+    drop(wal);
+    assert!(!path.exists());
+}
+
+#[test]
+fn anonymous_logs_do_not_share_files() {
+    let first = WriteAheadLog::anonymous().unwrap();
+    let second = WriteAheadLog::anonymous().unwrap();
+    assert_ne!(first.path(), second.path());
+}
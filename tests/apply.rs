@@ -0,0 +1,96 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WalOp, WriteAheadLog};
+
+fn ids(wal: &WriteAheadLog) -> Vec<u64> {
+    wal.read_all().unwrap().iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn apply_appends_and_deletes_together() {
+    let dir = TempDir::new();
+    let path = dir.join("tx.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..4u8 {
+        wal.append(vec![i]).unwrap();
+    }
+
+    let appended = wal
+        .apply(vec![
+            WalOp::Delete(1),
+            WalOp::Append(b"x".to_vec()),
+            WalOp::Delete(3),
+            WalOp::Append(b"y".to_vec()),
+            WalOp::Delete(42),
+        ])
+        .unwrap();
+    let new_ids: Vec<u64> = appended.iter().map(|e| e.id).collect();
+    assert_eq!(new_ids, [4, 5]);
+    assert_eq!(ids(&wal), [0, 2, 4, 5]);
+    assert_eq!(wal.durable_id(), 6);
+
+    let appended = wal.apply(vec![WalOp::Append(b"z".to_vec())]).unwrap();
+    assert_eq!(appended[0].id, 6);
+    drop(wal);
+    assert_eq!(ids(&WriteAheadLog::new(&path).unwrap()), [0, 2, 4, 5, 6]);
+}
+
+#[test]
+fn failed_apply_changes_nothing() {
+    let dir = TempDir::new();
+    let path = dir.join("tx.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_entries(4)
+        .build()
+        .unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    let before = std::fs::read(&path).unwrap();
+
+    let ops = vec![
+        WalOp::Delete(0),
+        WalOp::Append(b"a".to_vec()),
+        WalOp::Append(b"b".to_vec()),
+    ];
+    assert!(matches!(wal.apply(ops), Err(WalError::LogFull)));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    assert_eq!(wal.next_id(), 3);
+    assert_eq!(wal.append(b"c".to_vec()).unwrap().id, 3);
+}
+
+#[test]
+fn crash_before_the_rename_leaves_the_log_untouched() {
+    let dir = TempDir::new();
+    let path = dir.join("tx.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    drop(wal);
+    // What a crash part-way through writing the replacement leaves behind.
+    std::fs::write(dir.join("tx.wal.rewrite"), b"{\"id\":0,\"timest").unwrap();
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(ids(&wal), [0, 1, 2]);
+    wal.apply(vec![WalOp::Delete(0), WalOp::Append(b"x".to_vec())])
+        .unwrap();
+    assert_eq!(ids(&wal), [1, 2, 3]);
+}
+
+#[test]
+fn apply_refuses_deletes_in_sealed_segments() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("tx.wal"))
+        .max_segment_bytes(100)
+        .build()
+        .unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+    let ops = vec![WalOp::Append(b"x".to_vec()), WalOp::Delete(0)];
+    assert!(matches!(wal.apply(ops), Err(WalError::InvalidConfig(_))));
+    assert_eq!(ids(&wal), [0, 1, 2, 3, 4, 5]);
+    assert_eq!(wal.next_id(), 6);
+}
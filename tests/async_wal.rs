@@ -0,0 +1,181 @@
+mod common;
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+
+use common::TempDir;
+use waly_rs::{AsyncWriteAheadLog, WalError, WriteAheadLog};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn second_open_of_same_path_is_locked() {
+    let dir = TempDir::new();
+    let path = dir.join("async.wal");
+    block_on(async {
+        let wal = AsyncWriteAheadLog::open(&path).await.unwrap();
+        wal.append(b"a".to_vec()).await.unwrap();
+
+        let second = AsyncWriteAheadLog::open(&path).await;
+        assert!(matches!(second, Err(WalError::Locked)));
+
+        wal.close().await.unwrap();
+        let reopened = AsyncWriteAheadLog::open(&path).await.unwrap();
+        assert_eq!(reopened.read_all().await.unwrap().len(), 1);
+    });
+}
+
+#[test]
+fn chunked_batch_appends_every_item() {
+    let dir = TempDir::new();
+    block_on(async {
+        let wal = AsyncWriteAheadLog::open(dir.join("async.wal"))
+            .await
+            .unwrap();
+        let items: Vec<Vec<u8>> = (0..10u8).map(|i| vec![i]).collect();
+
+        let entries = wal.append_batch_chunked(items, 3).await.unwrap();
+        assert_eq!(entries.last().map(|e| e.id), Some(9));
+        assert_eq!(wal.read_all().await.unwrap(), entries);
+    });
+}
+
+#[test]
+fn clear_id_and_sync_match_the_blocking_log() {
+    let dir = TempDir::new();
+    let path = dir.join("async.wal");
+    block_on(async {
+        let wal = AsyncWriteAheadLog::open(&path).await.unwrap();
+        for i in 0..3u8 {
+            wal.append(vec![i]).await.unwrap();
+        }
+        wal.clear_id(1).await.unwrap();
+        wal.sync().await.unwrap();
+        // The rewrite replaced the file; the handle still holds the log.
+        let second = AsyncWriteAheadLog::open(&path).await;
+        assert!(matches!(second, Err(WalError::Locked)));
+        let ids: Vec<u64> = wal.read_all().await.unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, [0, 2]);
+        wal.close().await.unwrap();
+    });
+    let ids: Vec<u64> = WriteAheadLog::new(&path)
+        .unwrap()
+        .read_all()
+        .unwrap()
+        .iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(ids, [0, 2]);
+}
+
+#[test]
+fn failing_operations_resolve_with_their_error() {
+    let dir = TempDir::new();
+    let path = dir.join("async.wal");
+    let log = WriteAheadLog::builder(&path)
+        .max_entries(1)
+        .build()
+        .unwrap();
+    block_on(async {
+        let wal = AsyncWriteAheadLog::from(log);
+        wal.append(b"a".to_vec()).await.unwrap();
+        assert!(matches!(
+            wal.append(b"b".to_vec()).await,
+            Err(WalError::LogFull)
+        ));
+        assert_eq!(wal.read_all().await.unwrap().len(), 1);
+    });
+}
+
+#[test]
+fn a_panicking_operation_poisons_the_log_instead_of_hanging() {
+    let dir = TempDir::new();
+    let path = dir.join("async.wal");
+    let panicking = Arc::new(AtomicBool::new(false));
+    let clock = Arc::clone(&panicking);
+    let log = WriteAheadLog::builder(&path)
+        .clock(move || {
+            assert!(!clock.load(Ordering::SeqCst), "clock stopped");
+            1
+        })
+        .build()
+        .unwrap();
+    block_on(async {
+        let wal = AsyncWriteAheadLog::from(log);
+        wal.append(b"a".to_vec()).await.unwrap();
+        panicking.store(true, Ordering::SeqCst);
+        assert!(matches!(
+            wal.append(b"b".to_vec()).await,
+            Err(WalError::Poisoned)
+        ));
+        assert!(matches!(wal.sync().await, Err(WalError::Poisoned)));
+        assert!(matches!(wal.clear_id(0).await, Err(WalError::Poisoned)));
+    });
+}
+
+#[test]
+fn close_waits_for_the_last_clone_to_release_the_log() {
+    let dir = TempDir::new();
+    let path = dir.join("async.wal");
+    block_on(async {
+        let wal = AsyncWriteAheadLog::open(&path).await.unwrap();
+        let clone = wal.clone();
+        wal.append(b"a".to_vec()).await.unwrap();
+        wal.close().await.unwrap();
+        assert!(matches!(
+            AsyncWriteAheadLog::open(&path).await,
+            Err(WalError::Locked)
+        ));
+        clone.append(b"b".to_vec()).await.unwrap();
+        clone.close().await.unwrap();
+    });
+    assert_eq!(
+        WriteAheadLog::new(&path).unwrap().read_all().unwrap().len(),
+        2
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn sync_within_a_tokio_runtime_makes_appends_durable() {
+    let dir = TempDir::new();
+    let path = dir.join("async.wal");
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let wal = AsyncWriteAheadLog::open(&path).await.unwrap();
+        for i in 0..3u8 {
+            wal.append(vec![i]).await.unwrap();
+        }
+        wal.sync().await.unwrap();
+        assert_eq!(wal.read_all().await.unwrap().len(), 3);
+        wal.close().await.unwrap();
+    });
+    assert_eq!(
+        WriteAheadLog::new(&path).unwrap().read_all().unwrap().len(),
+        3
+    );
+}
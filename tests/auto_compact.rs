@@ -0,0 +1,131 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::TempDir;
+use waly_rs::{CompactionPolicy, WalError, WriteAheadLog};
+
+const INTERVAL: Duration = Duration::from_millis(10);
+
+/// Polls `cond` until it holds or a few seconds have passed.
+fn eventually(mut cond: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if cond() {
+            return true;
+        }
+        thread::sleep(INTERVAL);
+    }
+    false
+}
+
+#[test]
+fn heavy_deletions_shrink_the_file_in_the_background() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut wal =
+        WriteAheadLog::with_background_compaction(&path, CompactionPolicy::default(), INTERVAL)
+            .unwrap();
+    for i in 0..40u8 {
+        wal.append(vec![i; 64]).unwrap();
+    }
+    for id in 0..30 {
+        wal.mark_done(id).unwrap();
+    }
+    let before = std::fs::metadata(&path).unwrap().len();
+
+    assert!(eventually(|| wal.read_all().unwrap().len() == 10));
+    assert!(std::fs::metadata(&path).unwrap().len() < before / 2);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, (30..40).collect::<Vec<_>>());
+    assert_eq!(wal.pending().unwrap().len(), 10);
+
+    // Appends carry on against the compacted file.
+    let entry = wal.append(b"after".to_vec()).unwrap();
+    assert_eq!(entry.id, 70);
+    assert_eq!(wal.get(70).unwrap(), Some(entry));
+}
+
+#[test]
+fn leaves_the_log_alone_below_the_thresholds() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let policy = CompactionPolicy {
+        dead_ratio: Some(0.9),
+        ..CompactionPolicy::default()
+    };
+    let mut wal = WriteAheadLog::builder(&path)
+        .background_compaction(policy, INTERVAL)
+        .build()
+        .unwrap();
+    for i in 0..10u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.mark_done(0).unwrap();
+    wal.mark_done(1).unwrap();
+    wal.mark_failed(2).unwrap();
+    thread::sleep(INTERVAL * 10);
+    assert_eq!(wal.read_all().unwrap().len(), 10);
+}
+
+#[test]
+fn a_failed_status_revives_an_entry() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("app.wal"))
+        .background_compaction(
+            CompactionPolicy {
+                dead_ratio: None,
+                dead_age: Some(Duration::ZERO),
+                min_bytes: 0,
+            },
+            INTERVAL,
+        )
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    wal.mark_done(1).unwrap();
+    wal.mark_failed(1).unwrap();
+    wal.mark_done(0).unwrap();
+
+    assert!(eventually(|| wal.read_all().unwrap().len() == 1));
+    assert_eq!(wal.read_all().unwrap()[0].data, b"b");
+    assert_eq!(wal.pending().unwrap().len(), 1);
+}
+
+#[test]
+fn errors_go_to_the_handler() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let errors = Arc::new(AtomicUsize::new(0));
+    let seen = Arc::clone(&errors);
+    let mut wal = WriteAheadLog::builder(&path)
+        .background_compaction(CompactionPolicy::default(), INTERVAL)
+        .on_compaction_error(move |_| {
+            seen.fetch_add(1, Ordering::Relaxed);
+        })
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.mark_done(0).unwrap();
+    // A directory where the temp file should go makes the rewrite fail.
+    std::fs::create_dir(dir.join("app.wal.compact")).unwrap();
+
+    assert!(eventually(|| errors.load(Ordering::Relaxed) > 0));
+    drop(wal);
+}
+
+#[test]
+fn rejects_a_zero_interval() {
+    let dir = TempDir::new();
+    let err = WriteAheadLog::with_background_compaction(
+        dir.join("app.wal"),
+        CompactionPolicy::default(),
+        Duration::ZERO,
+    )
+    .unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn chunked_batch_reports_progress_per_chunk() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("batch.wal")).unwrap();
+    wal.append(b"before".to_vec()).unwrap();
+
+    let items: Vec<Vec<u8>> = (0..1000u32).map(|i| i.to_le_bytes().to_vec()).collect();
+    let mut calls = Vec::new();
+    let entries = wal
+        .append_batch_chunked(items.clone(), 128, |written, total| {
+            calls.push((written, total))
+        })
+        .unwrap();
+
+    assert_eq!(calls.len(), 8);
+    assert_eq!(calls.first(), Some(&(128, 1000)));
+    assert_eq!(calls.last(), Some(&(1000, 1000)));
+    assert_eq!(
+        entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+        (1..=1000).collect::<Vec<_>>()
+    );
+    let stored: Vec<_> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .skip(1)
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(stored, items);
+    assert_eq!(wal.next_id(), 1001);
+}
+
+#[test]
+fn failed_chunk_keeps_earlier_chunks_and_ids() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("batch.wal"))
+        .max_entries(5)
+        .build()
+        .unwrap();
+
+    let items = vec![vec![0u8]; 8];
+    let mut written = 0;
+    let result = wal.append_batch_chunked(items, 4, |done, _| written = done);
+
+    assert!(matches!(result, Err(WalError::LogFull)));
+    assert_eq!(written, 4);
+    assert_eq!(wal.read_all().unwrap().len(), 4);
+    assert_eq!(wal.next_id(), 4);
+}
+
+#[test]
+fn append_batch_is_all_or_nothing() {
+    let dir = TempDir::new();
+    let path = dir.join("batch.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_entries(4)
+        .build()
+        .unwrap();
+    wal.append(b"first".to_vec()).unwrap();
+
+    let entries = wal
+        .append_batch(vec![b"a".to_vec(), b"b".to_vec()])
+        .unwrap();
+    let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1, 2]);
+    assert_eq!(&wal.read_all().unwrap()[1..], entries);
+
+    let len = std::fs::metadata(&path).unwrap().len();
+    let result = wal.append_batch(vec![b"c".to_vec(), b"d".to_vec()]);
+    assert!(matches!(result, Err(WalError::LogFull)));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+    assert_eq!(wal.next_id(), 3);
+    assert!(wal.append_batch(Vec::new()).unwrap().is_empty());
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog, WriteAheadLogBuilder};
+
+#[test]
+fn create_dirs_creates_missing_parents() {
+    let dir = TempDir::new();
+    let path = dir.join("a/b/c/log.wal");
+
+    let mut wal = WriteAheadLog::builder(&path)
+        .create_dirs(true)
+        .build()
+        .unwrap();
+    wal.append(b"first".to_vec()).unwrap();
+
+    assert!(dir.join("a/b/c").is_dir());
+    assert!(path.is_file());
+    assert_eq!(wal.read_all().unwrap().len(), 1);
+}
+
+#[test]
+fn missing_parent_is_an_error_by_default() {
+    let dir = TempDir::new();
+    let path = dir.join("missing/log.wal");
+
+    let err = WriteAheadLog::new(&path).unwrap_err();
+    assert!(matches!(err, WalError::Io(_)));
+    assert!(!dir.join("missing").exists());
+}
+
+#[test]
+fn path_can_be_given_after_the_options() {
+    let dir = TempDir::new();
+    let path = dir.join("later.wal");
+    let mut wal = WriteAheadLogBuilder::default()
+        .checksums(true)
+        .path(&path)
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    assert!(path.is_file());
+
+    let err = WriteAheadLogBuilder::default().build().unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)));
+}
+
+#[test]
+fn contradictory_options_are_refused_at_build() {
+    let dir = TempDir::new();
+    let path = dir.join("refused.wal");
+    let builders = [
+        WriteAheadLog::builder(&path).max_entries(0),
+        WriteAheadLog::builder(&path).max_file_size(6),
+        WriteAheadLog::builder(&path).max_segment_bytes(0),
+        WriteAheadLog::builder(&path).on_compaction_error(|_| {}),
+        WriteAheadLog::builder(&path)
+            .max_entries(10)
+            .evict_oldest(true)
+            .group_commit(true),
+    ];
+    for builder in builders {
+        let err = builder.build().unwrap_err();
+        assert!(matches!(err, WalError::InvalidConfig(_)), "{err}");
+    }
+    assert!(!path.exists());
+}
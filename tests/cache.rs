@@ -0,0 +1,109 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{CacheStats, WriteAheadLog};
+
+#[test]
+fn repeated_get_is_served_from_cache() {
+    let dir = TempDir::new();
+    let path = dir.join("cached.wal");
+    let mut wal = WriteAheadLog::with_get_cache(&path, 8).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+
+    let first = wal.get(1).unwrap().unwrap();
+    // Wiping the file behind the log's back proves the next lookup never
+    // reads it.
+    std::fs::write(&path, b"").unwrap();
+    assert_eq!(wal.get(1).unwrap(), Some(first));
+    assert_eq!(
+        wal.get_cache_stats(),
+        Some(CacheStats { hits: 1, misses: 1 })
+    );
+}
+
+#[test]
+fn writes_invalidate_cached_lookups() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::with_get_cache(dir.join("cached.wal"), 8).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    assert!(wal.get(0).unwrap().is_some());
+    assert_eq!(wal.get(1).unwrap(), None);
+    let appended = wal.append(b"b".to_vec()).unwrap();
+    assert_eq!(wal.get(1).unwrap(), Some(appended));
+
+    wal.clear_id(0).unwrap();
+    assert_eq!(wal.get(0).unwrap(), None);
+}
+
+#[test]
+fn least_recently_used_is_evicted() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::with_get_cache(dir.join("cached.wal"), 2).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+
+    wal.get(0).unwrap();
+    wal.get(1).unwrap();
+    wal.get(0).unwrap();
+    wal.get(2).unwrap();
+    // 1 was the least recently used of the three and has been evicted.
+    wal.get(0).unwrap();
+    wal.get(1).unwrap();
+    assert_eq!(
+        wal.get_cache_stats(),
+        Some(CacheStats { hits: 2, misses: 4 })
+    );
+}
+
+#[test]
+fn get_without_cache_reports_no_stats() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("plain.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append_marker("m").unwrap();
+
+    assert_eq!(wal.get(0).unwrap().unwrap().data, b"a");
+    assert_eq!(wal.get(1).unwrap(), None);
+    assert_eq!(wal.get_cache_stats(), None);
+}
+
+#[test]
+fn read_all_cache_holds_until_the_log_changes() {
+    let dir = TempDir::new();
+    let path = dir.join("read_all.wal");
+    // Without checksums, so the byte rewritten below reads back as it is.
+    let mut wal = WriteAheadLog::builder(&path)
+        .read_all_cache(true)
+        .checksums(false)
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    let first = wal.read_all().unwrap();
+
+    // Rewriting a payload byte in place, keeping the length and
+    // modification time, proves the next read never parses the file.
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    let at = bytes.len() - 4;
+    bytes[at] = b'9';
+    std::fs::write(&path, &bytes).unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    assert_eq!(wal.read_all().unwrap(), first);
+
+    wal.append(b"c".to_vec()).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 3);
+    assert!(wal.clear_id(2).unwrap());
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+    wal.clear().unwrap();
+    assert!(wal.read_all().unwrap().is_empty());
+}
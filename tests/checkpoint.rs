@@ -0,0 +1,83 @@
+mod common;
+
+use std::path::Path;
+
+use common::TempDir;
+use waly_rs::{EntryKind, Format, WriteAheadLog};
+
+fn sidecar(path: &Path) -> std::path::PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".checkpoint");
+    sidecar.into()
+}
+
+#[test]
+fn checkpoints_are_skipped_by_reads_and_keep_cleared_ids() {
+    for format in [Format::Json, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("checkpoint.wal");
+        let mut wal = WriteAheadLog::with_format(&path, format).unwrap();
+        for i in 0..4u8 {
+            wal.append(vec![i]).unwrap();
+        }
+        wal.clear_id(1).unwrap();
+        let checkpoint = wal.checkpoint().unwrap();
+        assert_eq!(checkpoint.kind, EntryKind::Checkpoint);
+        assert_eq!(checkpoint.id, 5);
+        wal.append(b"after".to_vec()).unwrap();
+        wal.clear_id(2).unwrap();
+        drop(wal);
+        assert!(sidecar(&path).exists());
+
+        let wal = WriteAheadLog::with_format(&path, format).unwrap();
+        assert_eq!(wal.next_id(), 8, "{format:?}");
+        let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, [0, 3, 6], "{format:?}");
+        assert_eq!(wal.entry_breakdown().unwrap().checkpoints, 1);
+    }
+}
+
+#[test]
+fn open_scans_only_past_the_checkpoint() {
+    let dir = TempDir::new();
+    let path = dir.join("skip.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.checkpoint().unwrap();
+    wal.append(b"after".to_vec()).unwrap();
+    drop(wal);
+
+    // Renumbering a record before the checkpoint in place, keeping the
+    // file's layout, is only noticed by a full scan.
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, text.replacen("\"id\":0,", "\"id\":9,", 1)).unwrap();
+    assert_eq!(WriteAheadLog::new(&path).unwrap().next_id(), 5);
+
+    std::fs::write(sidecar(&path), "garbage").unwrap();
+    assert_eq!(WriteAheadLog::new(&path).unwrap().next_id(), 10);
+    std::fs::remove_file(sidecar(&path)).unwrap();
+    assert_eq!(WriteAheadLog::new(&path).unwrap().next_id(), 10);
+}
+
+#[test]
+fn a_moved_checkpoint_falls_back_to_a_full_scan() {
+    let dir = TempDir::new();
+    let path = dir.join("moved.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.checkpoint().unwrap();
+    wal.append(b"after".to_vec()).unwrap();
+    wal.clear_id(0).unwrap();
+    wal.compact().unwrap();
+    wal.append(b"last".to_vec()).unwrap();
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 7);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1, 2, 4, 6]);
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use std::io::Write;
+
+use common::TempDir;
+use waly_rs::{Format, WalError, WriteAheadLog};
+
+#[test]
+fn backfill_checksums_migrates_a_legacy_log() {
+    let dir = TempDir::new();
+    let path = dir.join("legacy.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(false)
+        .build()
+        .unwrap();
+    for i in 0..4u8 {
+        wal.append(vec![i; 8]).unwrap();
+    }
+    wal.append_marker("restart").unwrap();
+    drop(wal);
+
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(true)
+        .build()
+        .unwrap();
+    let fresh = wal.append(b"new".to_vec()).unwrap();
+    assert!(fresh.has_checksum());
+
+    assert_eq!(wal.backfill_checksums().unwrap(), 5);
+    let entries = wal.read_all().unwrap();
+    assert_eq!(entries.len(), 5);
+    assert!(entries
+        .iter()
+        .all(|e| e.has_checksum() && e.is_checksum_valid()));
+
+    assert_eq!(wal.backfill_checksums().unwrap(), 0);
+}
+
+#[test]
+fn backfill_refuses_to_bless_a_corrupt_checksum() {
+    let dir = TempDir::new();
+    let path = dir.join("bad.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[1]}}"#).unwrap();
+    writeln!(
+        file,
+        r#"{{"id":1,"timestamp":1,"data":[2],"checksum":12345}}"#
+    )
+    .unwrap();
+    drop(file);
+
+    let wal = WriteAheadLog::builder(&path)
+        .checksums(true)
+        .build()
+        .unwrap();
+    let before = std::fs::read(&path).unwrap();
+    let err = wal.backfill_checksums().unwrap_err();
+    assert!(matches!(err, WalError::ChecksumMismatch { id: 1 }));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn manifest_flags_exactly_the_altered_entry() {
+    let dir = TempDir::new();
+    let path = dir.join("replica.wal");
+    // The manifest is for logs whose records carry no checksum.
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(false)
+        .build()
+        .unwrap();
+    for i in 0..5u8 {
+        wal.append(vec![i; 4]).unwrap();
+    }
+    let manifest = wal.crc_manifest().unwrap();
+    assert_eq!(manifest.len(), 5);
+    assert!(wal.verify_against_manifest(&manifest).unwrap().is_empty());
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, text.replacen("[3,3,3,3]", "[3,3,3,9]", 1)).unwrap();
+    assert_eq!(wal.verify_against_manifest(&manifest).unwrap(), vec![3]);
+
+    wal.clear_id(1).unwrap();
+    wal.append(b"new".to_vec()).unwrap();
+    assert_eq!(
+        wal.verify_against_manifest(&manifest).unwrap(),
+        vec![1, 3, 6]
+    );
+}
+
+#[test]
+fn reads_fail_on_a_checksum_mismatch_and_verify_lists_them_all() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("flipped.wal");
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .format(format)
+                .checksums(true)
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+        for i in 0..5u8 {
+            wal.append(vec![b'a' + i; 4]).unwrap();
+        }
+        drop(wal);
+
+        // Flip one payload byte of entries 1 and 3, leaving their framing
+        // and checksums as they were.
+        let mut bytes = std::fs::read(&path).unwrap();
+        if format == Format::Json {
+            let text = String::from_utf8(bytes).unwrap();
+            bytes = text
+                .replacen("[98,98,98,98]", "[122,98,98,98]", 1)
+                .replacen("[100,100,100,100]", "[122,100,100,100]", 1)
+                .into_bytes();
+        } else {
+            for payload in [b"bbbb", b"dddd"] {
+                let at = bytes.windows(4).position(|w| w == payload).unwrap();
+                bytes[at] = b'z';
+            }
+        }
+        std::fs::write(&path, bytes).unwrap();
+
+        let wal = open();
+        let mismatch = |result: waly_rs::Result<Vec<waly_rs::LogEntry>>| {
+            matches!(result, Err(WalError::ChecksumMismatch { id: 1 }))
+        };
+        assert!(mismatch(wal.read_all()), "{format:?}");
+        assert!(mismatch(wal.read_all_strict()), "{format:?}");
+        assert!(mismatch(wal.iter().unwrap().collect()), "{format:?}");
+        // Tails may read from the end, reaching entry 3 first.
+        assert!(
+            matches!(wal.tail(5), Err(WalError::ChecksumMismatch { id: 1 | 3 })),
+            "{format:?}"
+        );
+        let mut seen = Vec::new();
+        let err = wal.read_into(&mut Vec::new(), |id, _, _| seen.push(id));
+        assert!(matches!(err, Err(WalError::ChecksumMismatch { id: 1 })));
+        assert_eq!(seen, [0]);
+        assert_eq!(wal.verify().unwrap(), [1, 3], "{format:?}");
+    }
+}
+
+#[test]
+fn entries_without_a_checksum_are_unchecked() {
+    let dir = TempDir::new();
+    let path = dir.join("legacy.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[1]}}"#).unwrap();
+    writeln!(file, r#"{{"id":1,"timestamp":1,"data":[2],"checksum":0}}"#).unwrap();
+    drop(file);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+    assert!(wal.verify().unwrap().is_empty());
+}
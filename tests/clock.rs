@@ -0,0 +1,51 @@
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use common::TempDir;
+use waly_rs::{Clock, Result, WalError, WriteAheadLog};
+
+#[test]
+fn injected_clock_stamps_entries() {
+    let dir = TempDir::new();
+    let time = Arc::new(AtomicU64::new(1_000));
+    let clock = {
+        let time = Arc::clone(&time);
+        move || time.load(Ordering::SeqCst)
+    };
+    let mut wal = WriteAheadLog::with_clock(dir.join("app.wal"), clock).unwrap();
+
+    assert_eq!(wal.append(b"a".to_vec()).unwrap().timestamp, 1_000);
+    time.store(1_005, Ordering::SeqCst);
+    wal.append(b"b".to_vec()).unwrap();
+    wal.append_batch(vec![b"c".to_vec(), b"d".to_vec()])
+        .unwrap();
+
+    let timestamps: Vec<u64> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.timestamp)
+        .collect();
+    assert_eq!(timestamps, [1_000, 1_005, 1_005, 1_005]);
+    assert_eq!(wal.read_range(1_001, 2_000).unwrap().len(), 3);
+}
+
+struct BrokenClock;
+
+impl Clock for BrokenClock {
+    fn now_unix(&self) -> Result<u64> {
+        Err(WalError::Clock("no time".to_string()))
+    }
+}
+
+#[test]
+fn clock_errors_fail_the_append() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::with_clock(dir.join("app.wal"), BrokenClock).unwrap();
+
+    assert!(matches!(wal.append(b"a".to_vec()), Err(WalError::Clock(_))));
+    assert_eq!(wal.next_id(), 0);
+    assert!(wal.read_all().unwrap().is_empty());
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn clear_id_appends_tombstones_until_compact() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("tomb.wal");
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .format(format)
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+        for i in 0..6u8 {
+            wal.append(vec![i]).unwrap();
+        }
+        let size = std::fs::metadata(&path).unwrap().len();
+        for id in [0, 2, 3] {
+            assert!(wal.clear_id(id).unwrap());
+        }
+        // Clearing again, or clearing an ID never written, adds nothing.
+        assert!(!wal.clear_id(2).unwrap());
+        assert!(!wal.clear_id(100).unwrap());
+
+        let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, [1, 4, 5]);
+        assert_eq!(wal.len().unwrap(), 3);
+        assert!(wal.get(2).unwrap().is_none());
+        // The file only grew.
+        assert!(std::fs::metadata(&path).unwrap().len() > size);
+        assert_eq!(wal.entry_breakdown().unwrap().tombstones, 3);
+        drop(wal);
+
+        let wal = open();
+        let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, [1, 4, 5]);
+        wal.compact().unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() < size);
+        let breakdown = wal.entry_breakdown().unwrap();
+        assert_eq!((breakdown.live, breakdown.tombstones), (3, 0));
+        assert_eq!(wal.next_id(), 9);
+        let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, [1, 4, 5]);
+    }
+}
+
+#[test]
+fn clear_id_reports_whether_it_removed_anything() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("found.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    let marker = wal.append_marker("checkpoint").unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    wal.clear_id(0).unwrap();
+    wal.compact().unwrap();
+
+    // Gone by compaction, a marker, and one not yet written.
+    assert!(!wal.clear_id(0).unwrap());
+    assert!(!wal.clear_id(marker.id).unwrap());
+    assert!(!wal.clear_id(wal.next_id()).unwrap());
+    assert_eq!(wal.entry_breakdown().unwrap().tombstones, 0);
+    assert!(wal.clear_id(2).unwrap());
+    assert!(wal.read_all().unwrap().is_empty());
+}
+
+#[test]
+fn compact_retain_removes_rejected_records() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("retain.wal"))
+        .max_segment_bytes(120)
+        .build()
+        .unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![b'0' + i]).unwrap();
+    }
+    assert!(wal.segments().unwrap().len() > 1);
+
+    let removed = wal.compact_retain(|e| e.id % 2 == 0).unwrap();
+
+    assert_eq!(removed, 3);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 2, 4]);
+    let leftovers = std::fs::read_dir(dir.path())
+        .unwrap()
+        .filter(|e| {
+            e.as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .is_some_and(|x| x == "compact")
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+    assert_eq!(wal.compact_retain(|_| true).unwrap(), 0);
+}
+
+#[test]
+fn reads_and_appends_proceed_during_compaction() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("busy.wal")).unwrap();
+    for i in 0..8u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    let started = AtomicBool::new(false);
+    let done = AtomicBool::new(false);
+    let wal = &wal;
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let removed = wal
+                .compact_retain(|e| {
+                    started.store(true, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(40));
+                    e.stream != 0 || e.id >= 4
+                })
+                .unwrap();
+            assert_eq!(removed, 4);
+            done.store(true, Ordering::SeqCst);
+        });
+
+        while !started.load(Ordering::SeqCst) {
+            thread::yield_now();
+        }
+        let mut reads = 0;
+        let mut appended = 0u8;
+        let stream = wal.stream(1);
+        while !done.load(Ordering::SeqCst) {
+            let begun = Instant::now();
+            assert!(!wal.read_all().unwrap().is_empty());
+            assert!(begun.elapsed() < Duration::from_millis(100));
+            if appended < 3 {
+                stream.append(vec![appended]).unwrap();
+                appended += 1;
+            }
+            reads += 1;
+        }
+        assert!(reads > 3);
+    });
+
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [4, 5, 6, 7]);
+    let streamed: Vec<Vec<u8>> = wal
+        .stream(1)
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(streamed, [vec![0], vec![1], vec![2]]);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Compatibility, Format, WriteAheadLog};
+#[cfg(feature = "xxhash")]
+use waly_rs::{Crc32, XxHash64};
+
+fn verdict(path: &std::path::Path, config: waly_rs::WriteAheadLogBuilder) -> Compatibility {
+    WriteAheadLog::check_compatibility(path, &config).unwrap()
+}
+
+#[test]
+fn format_changes_are_judged_by_the_first_record() {
+    let dir = TempDir::new();
+    let path = dir.join("format.wal");
+    let config = |format| WriteAheadLog::builder(&path).format(format);
+    assert_eq!(
+        verdict(&path, config(Format::Binary)),
+        Compatibility::Compatible
+    );
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    drop(wal);
+    let before = std::fs::read(&path).unwrap();
+
+    assert_eq!(
+        verdict(&path, config(Format::Json)),
+        Compatibility::Compatible
+    );
+    assert_eq!(
+        verdict(&path, config(Format::JsonBase64)),
+        Compatibility::Compatible
+    );
+    for format in [Format::Binary, Format::CompactBinary] {
+        assert!(matches!(
+            verdict(&path, config(format)),
+            Compatibility::Incompatible { .. }
+        ));
+    }
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn enabling_checksums_needs_a_backfill() {
+    let dir = TempDir::new();
+    let path = dir.join("sums.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(false)
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    drop(wal);
+
+    let checked = || WriteAheadLog::builder(&path).checksums(true);
+    let Compatibility::NeedsMigration { reason } = verdict(&path, checked()) else {
+        panic!("expected a migration");
+    };
+    assert!(reason.contains("backfill_checksums"));
+
+    WriteAheadLog::new(&path)
+        .unwrap()
+        .backfill_checksums()
+        .unwrap();
+    assert_eq!(verdict(&path, checked()), Compatibility::Compatible);
+}
+
+#[test]
+fn stored_epoch_must_match() {
+    let dir = TempDir::new();
+    let path = dir.join("stored.wal");
+    let mut wal = WriteAheadLog::builder(&path).epoch(5).build().unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    drop(wal);
+
+    let builder = || WriteAheadLog::builder(&path);
+    assert_eq!(verdict(&path, builder()), Compatibility::Compatible);
+    assert_eq!(
+        verdict(&path, builder().epoch(6)),
+        Compatibility::Compatible
+    );
+    assert!(matches!(
+        verdict(&path, builder().epoch(4)),
+        Compatibility::Incompatible { .. }
+    ));
+}
+
+#[cfg(feature = "xxhash")]
+#[test]
+fn stored_hasher_must_match() {
+    let dir = TempDir::new();
+    let path = dir.join("stored.wal");
+    let mut wal = WriteAheadLog::builder(&path).hasher(Crc32).build().unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    drop(wal);
+
+    let builder = || WriteAheadLog::builder(&path);
+    assert_eq!(verdict(&path, builder()), Compatibility::Compatible);
+    assert_eq!(
+        verdict(&path, builder().hasher(Crc32)),
+        Compatibility::Compatible
+    );
+    assert!(matches!(
+        verdict(&path, builder().hasher(XxHash64)),
+        Compatibility::Incompatible { .. }
+    ));
+}
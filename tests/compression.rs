@@ -0,0 +1,110 @@
+#![cfg(feature = "compression")]
+
+mod common;
+
+use std::io::Write;
+
+use common::TempDir;
+use waly_rs::{Compression, Format, WalError, WriteAheadLog};
+
+/// A large, repetitive JSON document.
+fn json_blob(len: usize) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(len + 64);
+    let mut i = 0;
+    while blob.len() < len {
+        blob.extend_from_slice(
+            format!("{{\"step\":{i},\"tool\":\"search\",\"status\":\"ok\"}},").as_bytes(),
+        );
+        i += 1;
+    }
+    blob
+}
+
+#[test]
+fn payloads_round_trip_through_compression() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("compressed.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .compression(Compression::Lz77)
+            .checksums(true)
+            .build()
+            .unwrap();
+        let big = json_blob(2 << 20);
+        let empty = wal.append(Vec::new()).unwrap();
+        let large = wal.append(big.clone()).unwrap();
+        assert_eq!(large.compression, Some(Compression::Lz77));
+        assert_eq!(large.data, big);
+
+        let entries = wal.read_all().unwrap();
+        assert_eq!(entries, [empty, large]);
+        assert!(entries[0].data.is_empty());
+        assert_eq!(entries[1].data, big);
+        assert!(entries.iter().all(|e| e.is_checksum_valid()));
+        assert!(std::fs::metadata(&path).unwrap().len() < big.len() as u64 / 2);
+    }
+}
+
+#[test]
+fn mixed_logs_stay_readable() {
+    let dir = TempDir::new();
+    let path = dir.join("mixed.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"plain".to_vec()).unwrap();
+    drop(wal);
+
+    let mut wal = WriteAheadLog::with_compression(&path, Compression::Lz77).unwrap();
+    wal.append(json_blob(4096)).unwrap();
+    wal.append_marker("checkpoint").unwrap();
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let entries: Vec<_> = wal.iter().unwrap().map(Result::unwrap).collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        (entries[0].compression, &entries[0].data[..]),
+        (None, &b"plain"[..])
+    );
+    assert_eq!(entries[1].compression, Some(Compression::Lz77));
+    assert_eq!(entries[1].data, json_blob(4096));
+    // Markers are left alone.
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(raw.matches("\"compression\":\"lz77\"").count(), 1);
+}
+
+#[test]
+fn corrupt_records_cannot_decompress_past_their_stated_length() {
+    // Both state 3 bytes: one then asks for a 2^40-byte match, the other
+    // carries 5 literals.
+    for data in [
+        "3,3,97,98,99,128,128,128,128,128,32,1,0,0",
+        "3,5,97,98,99,100,101,0",
+    ] {
+        let dir = TempDir::new();
+        let path = dir.join("corrupt.wal");
+        let mut wal = WriteAheadLog::with_compression(&path, Compression::Lz77).unwrap();
+        let good = wal.append(json_blob(1024)).unwrap();
+        drop(wal);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(
+            file,
+            r#"{{"id":1,"timestamp":1,"data":[{data}],"compression":"lz77"}}"#
+        )
+        .unwrap();
+        drop(file);
+
+        let wal = WriteAheadLog::new(&path).unwrap();
+        let err = wal.read_all_strict().unwrap_err();
+        assert!(
+            matches!(&err, WalError::InvalidEntry(reason) if reason.contains("stated length of 3 bytes")),
+            "{err:?}"
+        );
+        // Lenient reads skip it like any other undecodable record.
+        assert_eq!(wal.read_all().unwrap(), [good]);
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn min_acked_trails_the_slowest_consumer_across_restarts() {
+    let dir = TempDir::new();
+    let path = dir.join("pubsub.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..10u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    assert_eq!(wal.min_acked().unwrap(), 10);
+
+    let billing = wal.register_consumer("billing").unwrap();
+    let audit = wal.register_consumer("audit").unwrap();
+    assert_ne!(billing, audit);
+    assert_eq!(wal.register_consumer("billing").unwrap(), billing);
+    assert_eq!(wal.min_acked().unwrap(), 0);
+
+    wal.ack(billing, 6).unwrap();
+    wal.ack(audit, 2).unwrap();
+    assert_eq!(wal.min_acked().unwrap(), 3);
+    // A stale ack does not move the consumer back.
+    wal.ack(audit, 1).unwrap();
+    assert_eq!(wal.min_acked().unwrap(), 3);
+    assert!(matches!(
+        wal.ack(audit, 10),
+        Err(WalError::InvalidConfig(_))
+    ));
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.register_consumer("audit").unwrap(), audit);
+    assert_eq!(wal.min_acked().unwrap(), 3);
+    wal.ack(audit, 8).unwrap();
+    assert_eq!(wal.min_acked().unwrap(), 7);
+}
+
+#[test]
+fn compact_acked_keeps_what_a_consumer_still_needs() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("acked.wal")).unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    let fast = wal.register_consumer("fast").unwrap();
+    let slow = wal.register_consumer("slow").unwrap();
+    wal.ack(fast, 5).unwrap();
+    wal.ack(slow, 1).unwrap();
+
+    assert_eq!(wal.compact_acked().unwrap(), 2);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [2, 3, 4, 5]);
+    assert_eq!(wal.entry_breakdown().unwrap().markers, 1);
+
+    wal.ack(slow, 4).unwrap();
+    assert_eq!(wal.compact_acked().unwrap(), 3);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [5]);
+}
+
+#[test]
+fn ids_keep_counting_up_after_compacting_everything_and_reopening() {
+    let dir = TempDir::new();
+    let path = dir.join("acked.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    let only = wal.register_consumer("only").unwrap();
+    wal.ack(only, 2).unwrap();
+    assert_eq!(wal.compact_acked().unwrap(), 3);
+    assert!(wal.read_all().unwrap().is_empty());
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.append(b"unprocessed".to_vec()).unwrap().id, 3);
+    assert_eq!(wal.min_acked().unwrap(), 3);
+    assert_eq!(wal.compact_acked().unwrap(), 0);
+    assert_eq!(wal.read_all().unwrap().len(), 1);
+}
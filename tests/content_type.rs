@@ -0,0 +1,63 @@
+mod common;
+
+use std::io::Write;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn content_type_round_trips_across_reopen() {
+    let dir = TempDir::new();
+    let path = dir.join("typed.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+
+    let json = wal
+        .append_typed(br#"{"k":"v"}"#.to_vec(), "application/json")
+        .unwrap();
+    let plain = wal.append(b"raw".to_vec()).unwrap();
+    assert_eq!(json.content_type.as_deref(), Some("application/json"));
+    assert_eq!(plain.content_type, None);
+
+    drop(wal);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap(), vec![json, plain]);
+}
+
+#[test]
+fn filter_by_content_type_returns_only_matches() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("typed.wal")).unwrap();
+
+    wal.append_typed(b"{}".to_vec(), "application/json")
+        .unwrap();
+    wal.append_typed(vec![0x89, b'P', b'N', b'G'], "image/png")
+        .unwrap();
+    wal.append(b"untagged".to_vec()).unwrap();
+    wal.append_typed(b"[]".to_vec(), "application/json")
+        .unwrap();
+
+    let ids: Vec<u64> = wal
+        .filter_by_content_type("application/json")
+        .unwrap()
+        .iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(ids, vec![0, 3]);
+    assert_eq!(wal.filter_by_content_type("image/png").unwrap().len(), 1);
+    assert!(wal.filter_by_content_type("text/plain").unwrap().is_empty());
+}
+
+#[test]
+fn legacy_entries_default_to_no_content_type() {
+    let dir = TempDir::new();
+    let path = dir.join("legacy.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[104,105]}}"#).unwrap();
+    drop(file);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let entries = wal.read_all().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].data, b"hi");
+    assert_eq!(entries[0].content_type, None);
+}
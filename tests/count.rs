@@ -0,0 +1,245 @@
+mod common;
+
+use std::io::Write;
+
+use common::TempDir;
+use waly_rs::{CountReport, EntryBreakdown, Format, WriteAheadLog};
+
+#[test]
+fn mixed_valid_and_corrupt_json_records() {
+    let dir = TempDir::new();
+    let path = dir.join("damaged.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append_marker("m").unwrap();
+    {
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"id\":2,\"timest\n").unwrap();
+        file.write_all(b"not json at all\n").unwrap();
+    }
+    wal.append(b"b".to_vec()).unwrap();
+
+    assert_eq!(
+        wal.count_resilient().unwrap(),
+        CountReport {
+            valid: 3,
+            corrupt: 2,
+            estimated_total: 5,
+        }
+    );
+}
+
+#[test]
+fn torn_binary_tail_is_estimated() {
+    let dir = TempDir::new();
+    let path = dir.join("torn.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    let len = std::fs::metadata(&path).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_len(len - 3)
+        .unwrap();
+
+    assert_eq!(
+        wal.count_resilient().unwrap(),
+        CountReport {
+            valid: 1,
+            corrupt: 0,
+            estimated_total: 2,
+        }
+    );
+}
+
+#[test]
+fn truncate_to_last_valid_cuts_trailing_garbage() {
+    for format in [Format::Json, Format::Binary] {
+        let dir = TempDir::new();
+        let path = dir.join("tail.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+        wal.append(b"a".to_vec()).unwrap();
+        wal.append(b"b".to_vec()).unwrap();
+        let prefix = std::fs::read(&path).unwrap();
+        {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            file.write_all(b"\x07garbage\ngarbage").unwrap();
+        }
+
+        assert_eq!(wal.truncate_to_last_valid().unwrap(), prefix.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), prefix);
+        assert_eq!(wal.append(b"c".to_vec()).unwrap().id, 2);
+        assert_eq!(wal.read_all().unwrap().len(), 3);
+    }
+}
+
+#[test]
+fn logical_len_stops_before_preallocated_space() {
+    for format in [Format::Json, Format::Binary] {
+        let dir = TempDir::new();
+        let path = dir.join("prealloc.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+        assert_eq!(wal.logical_len().unwrap(), common::HEADER_LEN);
+        wal.append(b"a".to_vec()).unwrap();
+        wal.append(b"b".to_vec()).unwrap();
+        let end = std::fs::metadata(&path).unwrap().len();
+        // Zero-filled space reserved past the end, as preallocation leaves.
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(end + 4096)
+            .unwrap();
+
+        assert_eq!(wal.logical_len().unwrap(), end);
+        assert!(wal.logical_len().unwrap() < std::fs::metadata(&path).unwrap().len());
+    }
+}
+
+#[test]
+fn entry_breakdown_counts_by_kind() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("mixed.wal")).unwrap();
+    for i in 0..5u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    wal.mark_done(0).unwrap();
+    wal.mark_failed(1).unwrap();
+    wal.clear_id(2).unwrap();
+    wal.stream(3).append(b"side".to_vec()).unwrap();
+
+    assert_eq!(
+        wal.entry_breakdown().unwrap(),
+        EntryBreakdown {
+            live: 5,
+            markers: 1,
+            statuses: 2,
+            tombstones: 1,
+            checkpoints: 0,
+            total: 9,
+        }
+    );
+}
+
+#[test]
+fn torn_final_write_is_cut_on_open() {
+    for format in [Format::Json, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("torn.wal");
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .format(format)
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+        wal.append(b"ok".to_vec()).unwrap();
+        assert_eq!(wal.discarded_on_open(), 0);
+        drop(wal);
+        let good_len = std::fs::metadata(&path).unwrap().len();
+
+        let fragment: &[u8] = match format {
+            Format::Json => b"{\"id\":1,\"timestamp\":17000",
+            _ => &[40, 1, 2, 3],
+        };
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(fragment)
+            .unwrap();
+
+        let mut wal = open();
+        assert_eq!(wal.discarded_on_open(), fragment.len() as u64, "{format:?}");
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), good_len);
+        assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 1);
+        let data: Vec<Vec<u8>> = wal
+            .read_all()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.data)
+            .collect();
+        assert_eq!(data, [b"ok".to_vec(), b"next".to_vec()], "{format:?}");
+    }
+}
+
+#[test]
+fn complete_corrupt_lines_survive_open() {
+    let dir = TempDir::new();
+    let path = dir.join("corrupt.wal");
+    WriteAheadLog::new(&path)
+        .unwrap()
+        .append(b"ok".to_vec())
+        .unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"not json\n").unwrap();
+    let len = std::fs::metadata(&path).unwrap().len();
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.discarded_on_open(), 0);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+}
+
+#[test]
+fn len_matches_read_all_without_decoding() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let mut wal = WriteAheadLog::builder(dir.join("app.wal"))
+            .format(format)
+            .max_segment_bytes(256)
+            // A tag named like a field must not fool the JSON scan.
+            .append_transform(|e| {
+                e.tags.insert("kind".to_string(), "marker".to_string());
+            })
+            .build()
+            .unwrap();
+        assert_eq!(wal.len().unwrap(), 0);
+        assert!(wal.is_empty().unwrap());
+
+        for i in 0..20u8 {
+            wal.append(vec![i; 8]).unwrap();
+        }
+        wal.append_marker("checkpoint").unwrap();
+        wal.mark_done(3).unwrap();
+        wal.stream(7).append(b"other".to_vec()).unwrap();
+        for id in [0, 5, 19] {
+            wal.clear_id(id).unwrap();
+        }
+        wal.append(b"last".to_vec()).unwrap();
+
+        assert!(wal.segments().unwrap().len() > 1, "{format:?}");
+        assert_eq!(
+            wal.len().unwrap(),
+            wal.read_all().unwrap().len(),
+            "{format:?}"
+        );
+        assert_eq!(wal.len().unwrap(), 18, "{format:?}");
+        assert!(!wal.is_empty().unwrap());
+    }
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Cursor, WriteAheadLog};
+
+fn ids(entries: &[waly_rs::LogEntry]) -> Vec<u64> {
+    entries.iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn returns_only_entries_appended_since_the_last_call() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("cursor.wal")).unwrap();
+    let mut cursor = Cursor::new();
+    assert!(wal.read_since(&mut cursor).unwrap().is_empty());
+
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.clear_id(1).unwrap();
+    assert_eq!(ids(&wal.read_since(&mut cursor).unwrap()), [0, 2]);
+    assert_eq!(cursor.last_id(), Some(3));
+    assert!(wal.read_since(&mut cursor).unwrap().is_empty());
+
+    let offset = cursor.offset();
+    wal.append(b"new".to_vec()).unwrap();
+    let entries = wal.read_since(&mut cursor).unwrap();
+    assert_eq!(ids(&entries), [4]);
+    assert_eq!(entries[0].data, b"new");
+    assert!(cursor.offset() > offset);
+    assert!(!cursor.was_reset());
+}
+
+#[test]
+fn follows_the_log_across_rotation() {
+    let dir = TempDir::new();
+    let path = dir.join("rotate.wal");
+    let mut wal = WriteAheadLog::with_max_segment_bytes(&path, 80).unwrap();
+    let mut cursor = Cursor::new();
+    wal.append(b"a".to_vec()).unwrap();
+    assert_eq!(ids(&wal.read_since(&mut cursor).unwrap()), [0]);
+
+    for i in 0..5u8 {
+        wal.append(vec![i; 20]).unwrap();
+    }
+    assert!(wal.segments().unwrap().len() > 2);
+    assert_eq!(ids(&wal.read_since(&mut cursor).unwrap()), [1, 2, 3, 4, 5]);
+    assert!(!cursor.was_reset());
+}
+
+#[test]
+fn starts_over_when_the_log_is_rewritten() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("reset.wal")).unwrap();
+    let mut cursor = Cursor::new();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.read_since(&mut cursor).unwrap();
+
+    wal.clear().unwrap();
+    wal.append(b"after".to_vec()).unwrap();
+    assert_eq!(ids(&wal.read_since(&mut cursor).unwrap()), [3]);
+    assert!(cursor.was_reset());
+
+    wal.append(b"more".to_vec()).unwrap();
+    wal.clear_id(3).unwrap();
+    wal.compact().unwrap();
+    wal.append(b"last".to_vec()).unwrap();
+    assert_eq!(ids(&wal.read_since(&mut cursor).unwrap()), [4, 6]);
+    assert!(cursor.was_reset());
+    assert!(wal.read_since(&mut cursor).unwrap().is_empty());
+    assert!(!cursor.was_reset());
+}
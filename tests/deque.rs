@@ -0,0 +1,23 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn deque_pops_oldest_first() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("queue.wal")).unwrap();
+    for job in ["first", "second", "third"] {
+        wal.append(job.as_bytes().to_vec()).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+
+    let mut queue = wal.read_all_deque().unwrap();
+    assert_eq!(queue.len(), 3);
+    for (id, job) in ["first", "second", "third"].into_iter().enumerate() {
+        let entry = queue.pop_front().unwrap();
+        assert_eq!(entry.id, id as u64);
+        assert_eq!(entry.data, job.as_bytes());
+    }
+    assert!(queue.pop_front().is_none());
+}
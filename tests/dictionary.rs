@@ -0,0 +1,128 @@
+mod common;
+
+use std::io::Write;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{WalError, WriteAheadLog};
+
+const DICTIONARY: &[u8] = br#"{"event":"page_view","user_id":,"session":"","path":"/products/","referrer":"https://www.example.com/","ok":true}"#;
+
+fn payload(i: u32) -> Vec<u8> {
+    format!(
+        r#"{{"event":"page_view","user_id":{i},"session":"s{}","path":"/products/{}","referrer":"https://www.example.com/","ok":true}}"#,
+        i * 7919 % 1000,
+        i % 13
+    )
+    .into_bytes()
+}
+
+fn log_size(dir: &TempDir, name: &str, dictionary: Option<&[u8]>) -> u64 {
+    let path = dir.join(name);
+    let mut wal = match dictionary {
+        Some(dict) => WriteAheadLog::with_compression_dict(&path, dict.to_vec()).unwrap(),
+        None => WriteAheadLog::new(&path).unwrap(),
+    };
+    for i in 0..200 {
+        wal.append(payload(i)).unwrap();
+    }
+    std::fs::metadata(&path).unwrap().len()
+}
+
+#[test]
+fn dictionary_shrinks_small_similar_entries() {
+    let dir = TempDir::new();
+    let plain = log_size(&dir, "plain.wal", None);
+    // Sanity check: without matches to reach back to, the codec gains
+    // nothing on entries this short.
+    let no_dict = log_size(&dir, "empty.wal", Some(b""));
+    let with_dict = log_size(&dir, "dict.wal", Some(DICTIONARY));
+
+    assert!(no_dict * 10 > plain * 9, "{no_dict} vs {plain}");
+    assert!(with_dict * 3 < no_dict, "{with_dict} vs {no_dict}");
+}
+
+#[test]
+fn compressed_entries_round_trip_and_reopen() {
+    let dir = TempDir::new();
+    let path = dir.join("events.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .compression_dict(DICTIONARY.to_vec())
+        .checksums(true)
+        .build()
+        .unwrap();
+    let mut expected = Vec::new();
+    for i in 0..20 {
+        expected.push(wal.append(payload(i)).unwrap());
+    }
+    expected.push(wal.append(vec![0, 255, 1]).unwrap());
+    expected.push(wal.append(Vec::new()).unwrap());
+    assert_eq!(wal.read_all().unwrap(), expected);
+    drop(wal);
+
+    // The dictionary is kept in the header, not beside the log, and is
+    // picked up without being passed again.
+    let header = std::fs::read(&path).unwrap();
+    assert!(header
+        .windows(DICTIONARY.len())
+        .position(|w| w == DICTIONARY)
+        .is_some_and(|at| at < HEADER_LEN as usize + 4));
+    assert!(!dir.join("events.wal.dict").exists());
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap(), expected);
+    assert!(wal
+        .read_all()
+        .unwrap()
+        .iter()
+        .all(|e| e.is_checksum_valid()));
+    wal.clear_id(3).unwrap();
+    expected.remove(3);
+    assert_eq!(wal.read_all().unwrap(), expected);
+    drop(wal);
+
+    let err = WriteAheadLog::with_compression_dict(&path, b"other".to_vec()).unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)), "{err:?}");
+}
+
+#[test]
+fn a_dictionary_given_to_an_existing_log_goes_in_its_header() {
+    let dir = TempDir::new();
+    let path = dir.join("late.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    let plain = wal.append(payload(0)).unwrap();
+    drop(wal);
+
+    let mut wal = WriteAheadLog::with_compression_dict(&path, DICTIONARY.to_vec()).unwrap();
+    let packed = wal.append(payload(1)).unwrap();
+    drop(wal);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap(), [plain, packed]);
+}
+
+#[test]
+fn a_match_past_the_stated_length_is_refused() {
+    let dir = TempDir::new();
+    let path = dir.join("corrupt.wal");
+    let mut wal = WriteAheadLog::with_compression_dict(&path, DICTIONARY.to_vec()).unwrap();
+    wal.append(payload(0)).unwrap();
+    drop(wal);
+
+    // States 2 bytes, then asks for a 2^40-byte match against the
+    // dictionary, which must fail before anything is allocated for it.
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    writeln!(
+        file,
+        r#"{{"id":1,"timestamp":1,"data":[2,0,128,128,128,128,128,32,1,0,0],"dict_compressed":true}}"#
+    )
+    .unwrap();
+    drop(file);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let err = wal.read_all_strict().unwrap_err();
+    assert!(
+        matches!(&err, WalError::InvalidEntry(reason) if reason.contains("stated length of 2 bytes")),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+fn ids(wal: &WriteAheadLog) -> Vec<u64> {
+    wal.read_all().unwrap().iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn draining_everything_empties_the_log() {
+    let dir = TempDir::new();
+    let path = dir.join("drain.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..5u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.clear_id(2).unwrap();
+
+    let mut drain = wal.drain().unwrap();
+    let data: Vec<Vec<u8>> = drain.by_ref().map(|e| e.unwrap().data).collect();
+    assert_eq!(data, [[0], [1], [3], [4]]);
+    assert_eq!(drain.finish().unwrap(), 4);
+    assert!(ids(&wal).is_empty());
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 6);
+}
+
+#[test]
+fn stopping_early_keeps_the_rest() {
+    let dir = TempDir::new();
+    let path = dir.join("partial.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..5u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    for entry in wal.drain().unwrap() {
+        if entry.unwrap().id == 1 {
+            break;
+        }
+    }
+    assert_eq!(ids(&wal), [2, 3, 4]);
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(ids(&wal), [2, 3, 4]);
+}
+
+#[test]
+fn an_unused_drain_removes_nothing() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("unused.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    assert_eq!(wal.drain().unwrap().finish().unwrap(), 0);
+    drop(wal.drain().unwrap());
+    assert_eq!(ids(&wal), [0]);
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "encryption")]
+
+mod common;
+
+use common::TempDir;
+use waly_rs::{Encryption, Format, WalError, WriteAheadLog};
+
+const KEY: [u8; 32] = [0x42; 32];
+
+#[test]
+fn payloads_are_encrypted_at_rest_and_read_back() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("secret.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .encryption(KEY)
+            .checksums(true)
+            .build()
+            .unwrap();
+        let entry = wal.append(b"card number 4111".to_vec()).unwrap();
+        assert_eq!(entry.encryption, Some(Encryption::ChaCha20Poly1305));
+        assert_eq!(entry.data, b"card number 4111");
+        wal.append_marker("checkpoint").unwrap();
+        drop(wal);
+
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.windows(4).any(|w| w == b"card"));
+
+        let wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .encryption(KEY)
+            .build()
+            .unwrap();
+        assert_eq!(wal.read_all().unwrap(), std::slice::from_ref(&entry));
+        let entries: Vec<_> = wal.iter().unwrap().map(Result::unwrap).collect();
+        assert!(entries[0].is_checksum_valid());
+        assert_eq!(entries, [entry]);
+    }
+}
+
+#[test]
+fn wrong_key_or_tampering_fails_to_decrypt() {
+    let dir = TempDir::new();
+    let path = dir.join("tamper.wal");
+    let mut wal = WriteAheadLog::with_encryption(&path, KEY).unwrap();
+    wal.append(b"first".to_vec()).unwrap();
+    drop(wal);
+
+    let wal = WriteAheadLog::with_encryption(&path, [0x24; 32]).unwrap();
+    assert!(matches!(
+        wal.read_all(),
+        Err(WalError::Decryption { id: 0 })
+    ));
+    assert!(matches!(
+        wal.iter().unwrap().next(),
+        Some(Err(WalError::Decryption { id: 0 }))
+    ));
+    drop(wal);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert!(matches!(
+        wal.read_all(),
+        Err(WalError::Decryption { id: 0 })
+    ));
+    drop(wal);
+
+    // Flip a bit of the ciphertext, keeping the record well-formed.
+    let text = std::fs::read_to_string(&path).unwrap();
+    let start = text.find("\"data\":[").unwrap() + "\"data\":[".len();
+    let end = start + text[start..].find(',').unwrap();
+    let byte: u8 = text[start..end].parse().unwrap();
+    let tampered = format!("{}{}{}", &text[..start], byte ^ 1, &text[end..]);
+    std::fs::write(&path, tampered).unwrap();
+    let wal = WriteAheadLog::with_encryption(&path, KEY).unwrap();
+    assert!(matches!(
+        wal.read_all(),
+        Err(WalError::Decryption { id: 0 })
+    ));
+}
+
+#[test]
+fn authenticated_metadata_stops_payloads_moving_between_entries() {
+    for authenticate in [false, true] {
+        let dir = TempDir::new();
+        let path = dir.join("swap.wal");
+        // Checksums would catch the swap as well; they are left off to
+        // show what authenticating the metadata adds.
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .encryption(KEY)
+                .authenticate_metadata(authenticate)
+                .checksums(false)
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+        wal.append(b"pay alice".to_vec()).unwrap();
+        wal.append(b"pay mallory".to_vec()).unwrap();
+        drop(wal);
+
+        // Swap the stored payloads of the two entries.
+        let text = std::fs::read_to_string(&path).unwrap();
+        let (header, records) = text.split_at(common::HEADER_LEN as usize);
+        let lines: Vec<&str> = records.lines().collect();
+        let data = |line: &str| {
+            let start = line.find("\"data\":").unwrap();
+            let end = start + line[start..].find(']').unwrap() + 1;
+            (start, end)
+        };
+        let ((s0, e0), (s1, e1)) = (data(lines[0]), data(lines[1]));
+        let swapped = format!(
+            "{header}{}{}{}\n{}{}{}\n",
+            &lines[0][..s0],
+            &lines[1][s1..e1],
+            &lines[0][e0..],
+            &lines[1][..s1],
+            &lines[0][s0..e0],
+            &lines[1][e1..],
+        );
+        std::fs::write(&path, swapped).unwrap();
+
+        let wal = open();
+        if authenticate {
+            assert!(matches!(
+                wal.read_all(),
+                Err(WalError::Decryption { id: 0 })
+            ));
+        } else {
+            assert_eq!(wal.read_all().unwrap()[0].data, b"pay mallory");
+        }
+    }
+}
+
+#[test]
+fn authenticate_metadata_needs_encryption() {
+    let dir = TempDir::new();
+    let result = WriteAheadLog::builder(dir.join("plain.wal"))
+        .authenticate_metadata(true)
+        .build();
+    assert!(matches!(result, Err(WalError::InvalidConfig(_))));
+}
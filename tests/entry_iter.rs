@@ -0,0 +1,59 @@
+mod common;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn iter_streams_data_entries() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("app.wal"))
+        .max_segment_bytes(120)
+        .build()
+        .unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    wal.append(b"last".to_vec()).unwrap();
+    assert!(wal.segments().unwrap().len() > 1);
+
+    let entries: Vec<_> = wal.iter().unwrap().map(|e| e.unwrap()).collect();
+    assert_eq!(entries, wal.read_all().unwrap());
+    assert_eq!(entries.len(), 7);
+}
+
+#[test]
+fn iter_yields_errors_for_bad_lines() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    writeln!(
+        OpenOptions::new().append(true).open(&path).unwrap(),
+        "not json"
+    )
+    .unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+
+    let mut iter = wal.iter().unwrap();
+    assert_eq!(iter.next().unwrap().unwrap().data, b"a");
+    assert!(matches!(iter.next(), Some(Err(WalError::InvalidEntry(_)))));
+    assert_eq!(iter.next().unwrap().unwrap().data, b"b");
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn iter_is_unaffected_by_appends() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("app.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+
+    let mut iter = wal.iter().unwrap();
+    assert_eq!(iter.next().unwrap().unwrap().data, b"a");
+    wal.append(b"c".to_vec()).unwrap();
+    assert_eq!(iter.next().unwrap().unwrap().data, b"b");
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn appends_past_the_limit_evict_the_oldest_entries() {
+    let dir = TempDir::new();
+    let path = dir.join("rolling.wal");
+    let mut wal = WriteAheadLog::with_rolling_window(&path, 3).unwrap();
+    assert_eq!(wal.entry_limit(), Some(3));
+    for i in 0..5u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    wal.append_batch(vec![vec![5], vec![6]]).unwrap();
+
+    let data: Vec<u8> = wal.read_all().unwrap().iter().map(|e| e.data[0]).collect();
+    assert_eq!(data, [4, 5, 6]);
+    assert_eq!(wal.len().unwrap(), 3);
+    assert_eq!(wal.evicted(), 4);
+    assert!(matches!(
+        wal.append_batch(vec![vec![7]; 4]),
+        Err(WalError::LogFull)
+    ));
+
+    let before = std::fs::metadata(&path).unwrap().len();
+    wal.compact().unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() < before);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::with_rolling_window(&path, 3).unwrap();
+    assert_eq!(wal.evicted(), 0);
+    wal.append(vec![7]).unwrap();
+    let data: Vec<u8> = wal.read_all().unwrap().iter().map(|e| e.data[0]).collect();
+    assert_eq!(data, [5, 6, 7]);
+    assert_eq!(wal.evicted(), 1);
+}
+
+#[test]
+fn evict_oldest_needs_an_entry_limit() {
+    let dir = TempDir::new();
+    let result = WriteAheadLog::builder(dir.join("unbounded.wal"))
+        .evict_oldest(true)
+        .build();
+    assert!(matches!(result, Err(WalError::InvalidConfig(_))));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use std::time::Duration;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn next_expiry_and_compaction_track_ttls() {
+    for format in [Format::Json, Format::Binary] {
+        let dir = TempDir::new();
+        let mut wal = WriteAheadLog::builder(dir.join("ttl.wal"))
+            .format(format)
+            .build()
+            .unwrap();
+        let expired = wal
+            .append_with_ttl(b"gone".to_vec(), Duration::ZERO)
+            .unwrap();
+        let late = wal
+            .append_with_ttl(b"late".to_vec(), Duration::from_secs(1000))
+            .unwrap();
+        let soon = wal
+            .append_with_ttl(b"soon".to_vec(), Duration::from_secs(500))
+            .unwrap();
+        let forever = wal.append(b"forever".to_vec()).unwrap();
+
+        assert_eq!(wal.next_expiry().unwrap(), expired.expires_at);
+        assert_eq!(wal.compact_expired().unwrap(), 1);
+        assert_eq!(wal.read_all().unwrap(), vec![late, soon.clone(), forever]);
+        assert_eq!(wal.next_expiry().unwrap(), soon.expires_at);
+        assert_eq!(wal.compact_expired().unwrap(), 0);
+    }
+}
+
+#[test]
+fn log_without_ttls_has_no_expiry() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("ttl.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    assert_eq!(wal.next_expiry().unwrap(), None);
+    assert_eq!(wal.compact_expired().unwrap(), 0);
+}
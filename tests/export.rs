@@ -0,0 +1,126 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{CsvColumn, DumpFormat, Format, WriteAheadLog};
+
+#[test]
+fn exported_slice_opens_as_independent_log() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("full.wal"))
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    for i in 0..10u8 {
+        wal.append(vec![i]).unwrap();
+    }
+
+    let out = dir.join("slice.wal");
+    assert_eq!(wal.export_range(3, 7, &out).unwrap(), 4);
+
+    let mut slice = WriteAheadLog::builder(&out)
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    let entries = slice.read_all().unwrap();
+    assert_eq!(
+        entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+        [3, 4, 5, 6]
+    );
+    assert_eq!(entries, wal.read_all().unwrap()[3..7]);
+    assert_eq!(slice.next_id(), 7);
+    assert_eq!(slice.append(b"more".to_vec()).unwrap().id, 7);
+}
+
+/// Splits RFC 4180 CSV into rows of fields.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    rows
+}
+
+#[test]
+fn csv_export_round_trips_fields() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("app.wal")).unwrap();
+    let a = wal.append(b"plain".to_vec()).unwrap();
+    wal.append_marker("skipped").unwrap();
+    let b = wal.append(b"comma, \"quote\"\nline".to_vec()).unwrap();
+    let c = wal.append(vec![0xFF, 0x00]).unwrap();
+
+    let mut out = Vec::new();
+    wal.export_csv(&mut out).unwrap();
+    let rows = parse_csv(std::str::from_utf8(&out).unwrap());
+    assert_eq!(rows[0], ["id", "timestamp", "data_len", "data_base64"]);
+    assert_eq!(rows.len(), 4);
+    for (row, entry) in rows[1..].iter().zip([&a, &b, &c]) {
+        assert_eq!(row[0], entry.id.to_string());
+        assert_eq!(row[1], entry.timestamp.to_string());
+        assert_eq!(row[2], entry.data.len().to_string());
+    }
+    assert_eq!(rows[1][3], "cGxhaW4=");
+    assert_eq!(rows[3][3], "/wA=");
+
+    let mut out = Vec::new();
+    wal.export_csv_with(&mut out, &[CsvColumn::Id, CsvColumn::DataText])
+        .unwrap();
+    let rows = parse_csv(std::str::from_utf8(&out).unwrap());
+    assert_eq!(rows[0], ["id", "data_text"]);
+    assert_eq!(rows[2], ["2", "comma, \"quote\"\nline"]);
+}
+
+#[test]
+fn dump_writes_json_lines_or_csv() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("dump.wal")).unwrap();
+    let a = wal.append(b"say \"hi\"".to_vec()).unwrap();
+    wal.append_marker("skipped").unwrap();
+    let b = wal.append(vec![0xFF, 0x00]).unwrap();
+
+    let mut out = Vec::new();
+    wal.dump(&mut out, DumpFormat::JsonLines).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            format!(
+                "{{\"id\": 0, \"timestamp\": {}, \"data\": \"say \\\"hi\\\"\"}}",
+                a.timestamp
+            ),
+            format!(
+                "{{\"id\": 2, \"timestamp\": {}, \"data_base64\": \"/wA=\"}}",
+                b.timestamp
+            ),
+        ]
+    );
+
+    let mut out = Vec::new();
+    wal.dump(&mut out, DumpFormat::Csv).unwrap();
+    let rows = parse_csv(std::str::from_utf8(&out).unwrap());
+    assert_eq!(rows[0], ["id", "timestamp", "data_base64"]);
+    assert_eq!(
+        rows[2],
+        ["2".to_string(), b.timestamp.to_string(), "/wA=".to_string()]
+    );
+}
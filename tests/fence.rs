@@ -0,0 +1,111 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Result, WalError, WriteAheadLog};
+
+/// Opens `path` for `epoch` without the advisory lock, standing in for
+/// writers on different hosts sharing the storage.
+fn open_fenced(path: &std::path::Path, epoch: u64) -> Result<WriteAheadLog> {
+    WriteAheadLog::builder(path)
+        .epoch(epoch)
+        .lock(false)
+        .build()
+}
+
+#[test]
+fn stale_epoch_is_fenced_off() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut old_primary = open_fenced(&path, 1).unwrap();
+    old_primary.append(b"before failover".to_vec()).unwrap();
+
+    let mut new_primary = open_fenced(&path, 2).unwrap();
+    assert_eq!(new_primary.epoch(), Some(2));
+
+    let err = old_primary.append(b"zombie".to_vec()).unwrap_err();
+    assert!(matches!(
+        err,
+        WalError::Fenced {
+            held: 1,
+            current: 2
+        }
+    ));
+    new_primary.append(b"after failover".to_vec()).unwrap();
+
+    let data: Vec<_> = new_primary
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(
+        data,
+        [b"before failover".to_vec(), b"after failover".to_vec()]
+    );
+}
+
+#[test]
+fn opening_with_an_older_epoch_fails() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    drop(open_fenced(&path, 5).unwrap());
+
+    assert!(matches!(
+        open_fenced(&path, 4),
+        Err(WalError::Fenced {
+            held: 4,
+            current: 5
+        })
+    ));
+    let mut same = open_fenced(&path, 5).unwrap();
+    same.append(b"x".to_vec()).unwrap();
+    // Handles without an epoch are not fenced.
+    WriteAheadLog::new(&path)
+        .unwrap()
+        .append(b"y".to_vec())
+        .unwrap();
+}
+
+#[test]
+fn stale_epoch_cannot_rewrite_or_remove_entries() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut stale = open_fenced(&path, 1).unwrap();
+    for i in 0..3u8 {
+        stale.append(vec![i]).unwrap();
+    }
+    stale.clear_id(1).unwrap();
+    let current = open_fenced(&path, 2).unwrap();
+
+    let fenced = |result: Result<()>| {
+        matches!(
+            result,
+            Err(WalError::Fenced {
+                held: 1,
+                current: 2
+            })
+        )
+    };
+    assert!(fenced(stale.clear()));
+    assert!(fenced(stale.truncate_after(0).map(drop)));
+    assert!(fenced(stale.truncate_to_last_valid().map(drop)));
+    assert!(fenced(stale.compact()));
+    assert!(fenced(stale.compact_retain(|_| false).map(drop)));
+    assert!(fenced(stale.compact_expired().map(drop)));
+    assert!(fenced(stale.rotate_now().map(drop)));
+    assert!(fenced(stale.repair().map(drop)));
+    assert!(fenced(stale.drain().map(drop)));
+
+    let ids: Vec<_> = current.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 2]);
+}
+
+#[test]
+fn claiming_an_epoch_fails_while_the_lock_is_held() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let holder = WriteAheadLog::new(&path).unwrap();
+    assert!(matches!(open_fenced(&path, 1), Err(WalError::Locked)));
+    drop(holder);
+    open_fenced(&path, 1).unwrap();
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WalOp, WriteAheadLog};
+
+fn log_of(dir: &TempDir, name: &str, format: Format, items: &[&[u8]]) -> WriteAheadLog {
+    // Without checksums, which would no longer match once the timestamps
+    // are zeroed.
+    let mut wal = WriteAheadLog::builder(dir.join(name))
+        .format(format)
+        .checksums(false)
+        .build()
+        .unwrap();
+    for item in items {
+        wal.append(item.to_vec()).unwrap();
+    }
+    wal
+}
+
+/// Rewrites every timestamp to 0 so logs written at different times
+/// compare equal.
+fn zero_timestamps(wal: &WriteAheadLog) {
+    let path = wal.path();
+    let text = std::fs::read_to_string(path).unwrap();
+    let text = text
+        .lines()
+        .map(|line| {
+            let start = line.find("\"timestamp\":").unwrap() + 12;
+            let end = start + line[start..].find(',').unwrap();
+            format!("{}0{}\n", &line[..start], &line[end..])
+        })
+        .collect::<String>();
+    std::fs::write(path, text).unwrap();
+}
+
+#[test]
+fn fingerprint_tracks_contents_and_order() {
+    let dir = TempDir::new();
+    let a = log_of(&dir, "a.wal", Format::Json, &[b"one", b"two", b"three"]);
+    let b = log_of(&dir, "b.wal", Format::Json, &[b"one", b"two", b"three"]);
+    let reordered = log_of(&dir, "c.wal", Format::Json, &[b"two", b"one", b"three"]);
+    let altered = log_of(&dir, "d.wal", Format::Json, &[b"one", b"twO", b"three"]);
+    for wal in [&a, &b, &reordered, &altered] {
+        zero_timestamps(wal);
+    }
+
+    let fingerprint = a.fingerprint().unwrap();
+    assert_eq!(b.fingerprint().unwrap(), fingerprint);
+    assert_ne!(reordered.fingerprint().unwrap(), fingerprint);
+    assert_ne!(altered.fingerprint().unwrap(), fingerprint);
+    assert_ne!(
+        WriteAheadLog::new(dir.join("empty.wal"))
+            .unwrap()
+            .fingerprint()
+            .unwrap(),
+        fingerprint
+    );
+}
+
+#[test]
+fn fingerprint_changes_with_every_mutation() {
+    let dir = TempDir::new();
+    let mut wal = log_of(&dir, "a.wal", Format::CompactBinary, &[b"x", b"y"]);
+    let mut seen = vec![wal.fingerprint().unwrap()];
+    assert_eq!(wal.fingerprint().unwrap(), seen[0]);
+
+    wal.append_marker("m").unwrap();
+    seen.push(wal.fingerprint().unwrap());
+    wal.apply(vec![WalOp::Delete(0)]).unwrap();
+    seen.push(wal.fingerprint().unwrap());
+    wal.append(b"x".to_vec()).unwrap();
+    seen.push(wal.fingerprint().unwrap());
+
+    let mut unique = seen.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), seen.len());
+}
@@ -0,0 +1,323 @@
+mod common;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{Format, WalError, WalFormat, WriteAheadLog};
+
+fn open(path: &std::path::Path, format: Format) -> WriteAheadLog {
+    WriteAheadLog::builder(path)
+        .format(format)
+        .checksums(true)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn every_format_round_trips() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let path = dir.join("log.wal");
+        let mut wal = open(&path, format);
+
+        let plain = wal.append(b"line\nbreak".to_vec()).unwrap();
+        wal.append_marker("restart").unwrap();
+        let typed = wal.append_typed(vec![0, 255, 10, 13], "image/png").unwrap();
+        let empty = wal.append(Vec::new()).unwrap();
+        drop(wal);
+
+        let wal = open(&path, format);
+        assert_eq!(wal.next_id(), 4, "{format:?}");
+        assert_eq!(
+            wal.read_all().unwrap(),
+            vec![plain, typed, empty],
+            "{format:?}"
+        );
+    }
+}
+
+#[test]
+fn wal_format_encodes_records_as_the_log_writes_them() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let path = dir.join("log.wal");
+        let mut wal = open(&path, format);
+        let entry = wal.append_typed(vec![0, 255, 10, 13], "image/png").unwrap();
+        drop(wal);
+
+        let record = std::fs::read(&path).unwrap()[HEADER_LEN as usize..].to_vec();
+        assert_eq!(format.encode(&entry), record, "{format:?}");
+        assert_eq!(format.decode(&record).unwrap(), entry, "{format:?}");
+
+        let torn = &record[..record.len() / 2];
+        assert!(
+            matches!(format.decode(torn), Err(WalError::InvalidEntry(_))),
+            "{format:?}"
+        );
+        let mut followed = record.clone();
+        followed.push(b'\n');
+        assert!(
+            matches!(format.decode(&followed), Err(WalError::InvalidEntry(_))),
+            "{format:?}"
+        );
+    }
+}
+
+#[test]
+fn compact_binary_is_smallest_for_small_ids() {
+    let mut sizes = Vec::new();
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("log.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .checksums(false)
+            .build()
+            .unwrap();
+        for i in 0..100u8 {
+            wal.append(vec![i, i]).unwrap();
+        }
+        sizes.push(std::fs::metadata(&path).unwrap().len());
+    }
+    let (json, binary, compact) = (sizes[0], sizes[1], sizes[2]);
+    assert!(compact < binary, "compact {compact} vs binary {binary}");
+    assert!(binary < json, "binary {binary} vs json {json}");
+    // id, timestamp and length fit in 1 + 5 + 1 varint bytes plus a 1-byte
+    // frame length, against 4 + 8 + 8 + 4 fixed bytes.
+    assert_eq!(compact, HEADER_LEN + 100 * (1 + 1 + 5 + 1 + 2));
+    assert_eq!(binary, HEADER_LEN + 100 * (4 + 8 + 8 + 4 + 2));
+}
+
+#[test]
+fn with_format_stores_raw_payloads() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    let payload = vec![0xAB; 1000];
+    let mut wal = WriteAheadLog::with_format(&path, Format::Binary).unwrap();
+    wal.append(payload.clone()).unwrap();
+    drop(wal);
+
+    let len = std::fs::metadata(&path).unwrap().len();
+    assert!(len < 1100, "{len} bytes on disk");
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(
+        u32::from_le_bytes(bytes[HEADER_LEN as usize..][..4].try_into().unwrap()) as u64,
+        len - HEADER_LEN - 4
+    );
+    let wal = WriteAheadLog::with_format(&path, Format::Binary).unwrap();
+    assert_eq!(wal.read_all().unwrap()[0].data, payload);
+}
+
+#[test]
+fn torn_binary_tail_is_ignored() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    let mut wal = open(&path, Format::CompactBinary);
+    wal.append(b"kept".to_vec()).unwrap();
+    wal.append(b"torn".to_vec()).unwrap();
+    drop(wal);
+
+    let len = std::fs::metadata(&path).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_len(len - 2)
+        .unwrap();
+
+    let wal = open(&path, Format::CompactBinary);
+    let entries = wal.read_all().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].data, b"kept");
+}
+
+#[test]
+fn delimiter_bytes_in_fields_stay_escaped() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    let mut wal = open(&path, Format::Json);
+    let newlines = wal.append(vec![b'\n'; 4]).unwrap();
+    let typed = wal
+        .append_typed(b"\n\r\n".to_vec(), "text/\nplain")
+        .unwrap();
+    wal.append_marker("line\nbreak").unwrap();
+
+    let raw = std::fs::read(&path).unwrap();
+    assert_eq!(raw.iter().filter(|&&b| b == b'\n').count(), 3);
+    assert_eq!(wal.read_all().unwrap(), vec![newlines, typed]);
+}
+
+#[test]
+fn newline_payloads_frame_as_one_record_in_every_format() {
+    let payloads = [
+        vec![0x0A],
+        vec![0x0A; 300],
+        b"{\"id\":99,\"timestamp\":0,\"data\":[]}\n".to_vec(),
+        vec![0x00, 0x0A, 0xFF, 0x0A, 0x0D],
+    ];
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let path = dir.join("log.wal");
+        let mut wal = open(&path, format);
+        for payload in &payloads {
+            wal.append(payload.clone()).unwrap();
+        }
+        drop(wal);
+
+        let wal = open(&path, format);
+        let data: Vec<Vec<u8>> = wal
+            .read_all()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.data)
+            .collect();
+        assert_eq!(data, payloads, "{format:?}");
+        assert_eq!(wal.next_id(), payloads.len() as u64, "{format:?}");
+        assert_eq!(wal.get(2).unwrap().unwrap().data, payloads[2], "{format:?}");
+    }
+}
+
+#[test]
+fn base64_data_is_written_as_a_string() {
+    let dir = TempDir::new();
+    let path = dir.join("b64.wal");
+    let mut wal = WriteAheadLog::with_base64_data(&path).unwrap();
+    for data in [&b"Hello"[..], b"", b"a", b"ab", b"abc", &[0, 255, 10, 13]] {
+        wal.append(data.to_vec()).unwrap();
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let first = text[HEADER_LEN as usize..].lines().next().unwrap();
+    assert!(first.starts_with("{\"id\":0,\"timestamp\":"), "{first}");
+    assert!(first.contains(",\"data\":\"SGVsbG8=\""), "{first}");
+    let data: Vec<Vec<u8>> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(
+        data,
+        [&b"Hello"[..], b"", b"a", b"ab", b"abc", &[0, 255, 10, 13]]
+    );
+}
+
+#[test]
+fn json_formats_read_both_data_forms() {
+    let dir = TempDir::new();
+    let path = dir.join("mixed.wal");
+    std::fs::write(
+        &path,
+        "{\"id\":0,\"timestamp\":1,\"data\":[104,105]}\n\
+         {\"id\":1,\"timestamp\":2,\"data\":\"aGk=\"}\n\
+         {\"id\":2,\"timestamp\":3,\"data\":\"not base64!\"}\n",
+    )
+    .unwrap();
+
+    for format in [Format::Json, Format::JsonBase64] {
+        let wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+        let entries = wal.read_all().unwrap();
+        assert_eq!(entries.len(), 2, "{format:?}");
+        assert!(entries.iter().all(|e| e.data == b"hi"), "{format:?}");
+    }
+}
+
+#[test]
+fn open_as_rejects_a_log_in_another_format() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"json".to_vec()).unwrap();
+    drop(wal);
+    let before = std::fs::read(&path).unwrap();
+
+    assert!(matches!(
+        WriteAheadLog::open_as(&path, Format::Binary),
+        Err(WalError::FormatMismatch {
+            expected: Format::Binary,
+            found: Format::Json,
+        })
+    ));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+
+    let wal = WriteAheadLog::open_as(&path, Format::JsonBase64).unwrap();
+    assert_eq!(wal.read_all().unwrap()[0].data, b"json");
+    drop(wal);
+
+    let binary = dir.join("binary.wal");
+    let mut wal = WriteAheadLog::open_as(&binary, Format::Binary).unwrap();
+    wal.append(b"bin".to_vec()).unwrap();
+    drop(wal);
+    assert!(matches!(
+        WriteAheadLog::open_as(&binary, Format::Json),
+        Err(WalError::FormatMismatch {
+            found: Format::Binary,
+            ..
+        })
+    ));
+    assert_eq!(
+        WriteAheadLog::open_as(&binary, Format::Binary)
+            .unwrap()
+            .read_all()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn the_format_in_the_header_is_checked_on_every_open() {
+    let dir = TempDir::new();
+    let path = dir.join("empty.wal");
+    drop(WriteAheadLog::with_format(&path, Format::Binary).unwrap());
+    let before = std::fs::read(&path).unwrap();
+
+    // There is no record to judge by, but the header says.
+    for open in [WriteAheadLog::with_format, WriteAheadLog::open_as] {
+        assert!(matches!(
+            open(&path, Format::CompactBinary),
+            Err(WalError::FormatMismatch {
+                expected: Format::CompactBinary,
+                found: Format::Binary,
+            })
+        ));
+    }
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn open_as_judges_files_without_a_recorded_format_by_their_first_record() {
+    let dir = TempDir::new();
+    let path = dir.join("older.wal");
+    std::fs::write(
+        &path,
+        b"WALY\x02\x00\x00\x00\x00\x00{\"id\":0,\"timestamp\":1,\"data\":[1]}\n",
+    )
+    .unwrap();
+
+    assert!(matches!(
+        WriteAheadLog::open_as(&path, Format::Binary),
+        Err(WalError::FormatMismatch {
+            expected: Format::Binary,
+            found: Format::Json,
+        })
+    ));
+    let wal = WriteAheadLog::open_as(&path, Format::Json).unwrap();
+    assert_eq!(wal.read_all().unwrap()[0].data, [1]);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{PauseMode, WalError, WriteAheadLog};
+
+#[test]
+fn paused_appends_error_by_default() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("gate.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    wal.pause();
+    assert!(matches!(wal.append(b"b".to_vec()), Err(WalError::Paused)));
+    assert_eq!(wal.read_all().unwrap().len(), 1);
+
+    wal.resume();
+    assert_eq!(wal.append(b"c".to_vec()).unwrap().id, 1);
+}
+
+#[test]
+fn blocked_append_proceeds_after_resume() {
+    let dir = TempDir::new();
+    let path = dir.join("gate.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .pause_mode(PauseMode::Block)
+        .build()
+        .unwrap();
+    let gate = wal.append_gate();
+    gate.pause();
+
+    let (done, finished) = mpsc::channel();
+    let producer = thread::spawn(move || {
+        let entry = wal.append(b"queued".to_vec()).unwrap();
+        done.send(()).unwrap();
+        entry
+    });
+
+    assert!(finished.recv_timeout(Duration::from_millis(100)).is_err());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), HEADER_LEN);
+
+    gate.resume();
+    let entry = producer.join().unwrap();
+    assert_eq!(entry.data, b"queued");
+    assert!(!gate.is_paused());
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{PendingState, WalError, WriteAheadLog};
+
+fn ids(wal: &WriteAheadLog) -> Vec<u64> {
+    wal.read_all().unwrap().iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn appends_are_invisible_until_flushed() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .build()
+        .unwrap();
+
+    let entry = wal.append(b"a".to_vec()).unwrap();
+    assert_eq!(entry.id, 0);
+    assert!(wal.read_all().unwrap().is_empty());
+
+    wal.flush().unwrap();
+    assert_eq!(wal.read_all().unwrap(), vec![entry]);
+}
+
+#[test]
+fn deferred_ids_resolve_on_flush() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .build()
+        .unwrap();
+
+    let first = wal.append_deferred(b"a".to_vec()).unwrap();
+    let second = wal.append_deferred(b"b".to_vec()).unwrap();
+    assert_eq!(first.state(), PendingState::Queued);
+    assert_eq!(first.id(), None);
+
+    wal.flush().unwrap();
+    assert_eq!(first.state(), PendingState::Durable(0));
+    assert_eq!(second.id(), Some(1));
+}
+
+#[test]
+fn deferred_ids_leave_no_gap_after_partial_batch_failure() {
+    let dir = TempDir::new();
+    let path = dir.join("group.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .group_commit(true)
+        .max_entries(3)
+        .build()
+        .unwrap();
+
+    let batch: Vec<_> = (0..5)
+        .map(|i| wal.append_deferred(vec![i]).unwrap())
+        .collect();
+    assert!(matches!(wal.flush(), Err(WalError::LogFull)));
+
+    let states: Vec<_> = batch.iter().map(|p| p.state()).collect();
+    assert_eq!(
+        states,
+        vec![
+            PendingState::Durable(0),
+            PendingState::Durable(1),
+            PendingState::Durable(2),
+            PendingState::Discarded,
+            PendingState::Discarded,
+        ]
+    );
+    assert_eq!(ids(&wal), vec![0, 1, 2]);
+    assert_eq!(wal.next_id(), 3);
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 3);
+}
+
+#[test]
+fn eager_ids_skip_records_dropped_by_failed_flush() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .max_entries(3)
+        .build()
+        .unwrap();
+
+    for i in 0..5 {
+        wal.append(vec![i]).unwrap();
+    }
+    assert!(matches!(wal.flush(), Err(WalError::LogFull)));
+
+    assert_eq!(ids(&wal), vec![0, 1, 2]);
+    assert_eq!(wal.next_id(), 5);
+}
+
+#[test]
+fn drop_flushes_queued_records() {
+    let dir = TempDir::new();
+    let path = dir.join("group.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .group_commit(true)
+        .build()
+        .unwrap();
+    wal.append_deferred(b"a".to_vec()).unwrap();
+    drop(wal);
+
+    assert_eq!(ids(&WriteAheadLog::new(&path).unwrap()), vec![0]);
+}
+
+#[test]
+fn append_then_fires_after_the_covering_flush() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .build()
+        .unwrap();
+    let (durable, acked) = std::sync::mpsc::channel();
+
+    for data in [b"a", b"b"] {
+        let durable = durable.clone();
+        wal.append_then(data.to_vec(), move |entry| durable.send(entry.id).unwrap())
+            .unwrap();
+    }
+    assert!(acked.try_recv().is_err());
+
+    wal.flush().unwrap();
+    assert_eq!(acked.try_iter().collect::<Vec<_>>(), vec![0, 1]);
+}
+
+#[test]
+fn append_then_fires_immediately_without_group_commit() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("plain.wal")).unwrap();
+    let (durable, acked) = std::sync::mpsc::channel();
+
+    let entry = wal
+        .append_then(b"a".to_vec(), move |entry| durable.send(entry.id).unwrap())
+        .unwrap();
+    assert_eq!(acked.try_recv(), Ok(entry.id));
+}
+
+#[test]
+fn append_then_is_dropped_when_its_flush_fails() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .max_entries(1)
+        .build()
+        .unwrap();
+    let (durable, acked) = std::sync::mpsc::channel();
+
+    for data in [b"a", b"b"] {
+        let durable = durable.clone();
+        wal.append_then(data.to_vec(), move |entry| durable.send(entry.id).unwrap())
+            .unwrap();
+    }
+    assert!(wal.flush().is_err());
+    assert_eq!(acked.try_iter().collect::<Vec<_>>(), vec![0]);
+}
+
+#[test]
+fn failed_flush_on_drop_reaches_the_handler() {
+    let dir = TempDir::new();
+    let (errors, reported) = std::sync::mpsc::channel();
+    let errors = std::sync::Mutex::new(errors);
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .max_entries(1)
+        .on_drop_error(move |err| errors.lock().unwrap().send(err).unwrap())
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+
+    drop(wal);
+    assert!(matches!(reported.try_recv(), Ok(WalError::LogFull)));
+    assert!(reported.try_recv().is_err());
+}
+
+#[test]
+fn clean_drop_does_not_call_the_handler() {
+    let dir = TempDir::new();
+    let called = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let flag = std::sync::Arc::clone(&called);
+    let mut wal = WriteAheadLog::with_drop_error_handler(
+        dir.join("plain.wal"),
+        Box::new(move |_| flag.store(true, std::sync::atomic::Ordering::SeqCst)),
+    )
+    .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    drop(wal);
+    assert!(!called.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[test]
+fn durable_id_lags_until_sync() {
+    let dir = TempDir::new();
+    let path = dir.join("group.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .group_commit(true)
+        .build()
+        .unwrap();
+    assert_eq!(wal.durable_id(), 0);
+
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    assert_eq!((wal.durable_id(), wal.next_id()), (0, 2));
+
+    wal.sync().unwrap();
+    assert_eq!(wal.durable_id(), 2);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.durable_id(), 2);
+    wal.append(b"c".to_vec()).unwrap();
+    assert_eq!((wal.durable_id(), wal.next_id()), (2, 3));
+    wal.sync().unwrap();
+    assert_eq!(wal.durable_id(), 3);
+}
@@ -0,0 +1,110 @@
+#![cfg(all(feature = "xxhash", feature = "sha256"))]
+
+mod common;
+
+use common::TempDir;
+use waly_rs::{Crc32, Format, Hasher, Sha256, WalError, WriteAheadLog, XxHash64};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[test]
+fn hashers_match_reference_vectors() {
+    assert_eq!(hex(&Crc32.digest(b"123456789")), "cbf43926");
+    assert_eq!(hex(&XxHash64.digest(b"")), "ef46db3751d8e999");
+    assert_eq!(hex(&XxHash64.digest(b"abc")), "44bc2cf5ad770999");
+    assert_eq!(
+        hex(&XxHash64.digest(b"Nobody inspects the spammish repetition")),
+        "fbcea83c8a378bf1"
+    );
+    assert_eq!(
+        hex(&Sha256.digest(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex(&Sha256.digest(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    let long: Vec<u8> = (0..=255).cycle().take(1024).collect();
+    assert_eq!(
+        hex(&Sha256.digest(&long)),
+        "785b0751fc2c53dc14a4ce3d800e69ef9ce1009eb327ccf458afe09c242c26c9"
+    );
+}
+
+#[test]
+fn log_written_with_a_hasher_verifies() {
+    for format in [Format::Json, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("digests.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .hasher(Sha256)
+            .build()
+            .unwrap();
+        for i in 0..5u8 {
+            let entry = wal.append(vec![i; 10]).unwrap();
+            assert_eq!(entry.digest.len(), 32);
+        }
+        assert!(wal.verify_digests().unwrap().is_empty());
+        drop(wal);
+
+        // The stored hasher is picked up again without being named.
+        let wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+        assert!(wal.verify_digests().unwrap().is_empty(), "{format:?}");
+        let entries = wal.read_all().unwrap();
+        assert_eq!(entries[2].digest, entries[2].compute_digest(&Sha256));
+    }
+}
+
+#[test]
+fn tampered_entry_fails_verification() {
+    let dir = TempDir::new();
+    let path = dir.join("tamper.wal");
+    // Without CRC-32 checksums, which reads would trip over first.
+    let mut wal = WriteAheadLog::builder(&path)
+        .hasher(XxHash64)
+        .checksums(false)
+        .build()
+        .unwrap();
+    wal.append(b"one".to_vec()).unwrap();
+    wal.append(b"two".to_vec()).unwrap();
+    drop(wal);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    // "two" is [116,119,111]; make it "twp".
+    std::fs::write(&path, text.replace("[116,119,111]", "[116,119,112]")).unwrap();
+    let wal = WriteAheadLog::builder(&path)
+        .hasher(XxHash64)
+        .checksums(false)
+        .build()
+        .unwrap();
+    assert_eq!(wal.verify_digests().unwrap(), vec![1]);
+}
+
+#[test]
+fn reopening_with_another_hasher_is_a_mismatch() {
+    let dir = TempDir::new();
+    let path = dir.join("algo.wal");
+    let mut wal = WriteAheadLog::with_checksum(&path, XxHash64).unwrap();
+    wal.append(b"x".to_vec()).unwrap();
+    drop(wal);
+
+    let err = WriteAheadLog::with_checksum(&path, Sha256).unwrap_err();
+    assert!(matches!(err, WalError::ConfigMismatch(_)), "{err:?}");
+    assert!(err.to_string().contains("xxhash64"), "{err}");
+}
+
+#[test]
+fn verify_digests_needs_a_hasher() {
+    let dir = TempDir::new();
+    let wal = WriteAheadLog::new(dir.join("plain.wal")).unwrap();
+    assert!(matches!(
+        wal.verify_digests(),
+        Err(WalError::InvalidConfig(_))
+    ));
+}
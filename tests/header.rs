@@ -0,0 +1,116 @@
+mod common;
+
+use std::io::Write;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{Compatibility, Format, WalError, WriteAheadLog};
+
+/// The header of a fresh file: version 2, with no fields.
+/// The header of a file holding records in `format`, written by this build.
+fn current(format: Format) -> Vec<u8> {
+    let code = match format {
+        Format::Json => 0,
+        Format::JsonBase64 => 1,
+        Format::Binary => 2,
+        Format::CompactBinary => 3,
+    };
+    [b"WALY\x02\x00\x03\x00\x00\x00\x03\x01", &[code][..]].concat()
+}
+
+#[test]
+fn fresh_files_start_with_the_header() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("fresh.wal");
+        let mut wal = WriteAheadLog::with_format(&path, format).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), current(format));
+        assert_eq!(wal.next_id(), 0);
+
+        let first = wal.append(b"one".to_vec()).unwrap();
+        wal.append_marker("m").unwrap();
+        let second = wal.append(b"two".to_vec()).unwrap();
+        drop(wal);
+
+        let wal = WriteAheadLog::with_format(&path, format).unwrap();
+        assert_eq!(wal.next_id(), 3, "{format:?}");
+        assert_eq!(wal.read_all().unwrap(), [first.clone(), second.clone()]);
+        let iterated: Vec<_> = wal.iter().unwrap().map(Result::unwrap).collect();
+        assert_eq!(iterated, [first, second]);
+        assert_eq!(wal.len().unwrap(), 2);
+        assert_eq!(wal.discarded_on_open(), 0);
+    }
+}
+
+#[test]
+fn header_less_logs_are_upgraded_on_open() {
+    let dir = TempDir::new();
+    let path = dir.join("legacy.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[1]}}"#).unwrap();
+    writeln!(file, r#"{{"id":1,"timestamp":2,"data":[2]}}"#).unwrap();
+    drop(file);
+    let legacy = std::fs::read(&path).unwrap();
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    let upgraded = std::fs::read(&path).unwrap();
+    assert_eq!(upgraded[..HEADER_LEN as usize], current(Format::Json));
+    assert_eq!(&upgraded[HEADER_LEN as usize..], legacy);
+    assert_eq!(wal.append(vec![3]).unwrap().id, 2);
+    let data: Vec<Vec<u8>> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(data, [vec![1], vec![2], vec![3]]);
+}
+
+#[test]
+fn version_1_headers_are_upgraded_on_open() {
+    let dir = TempDir::new();
+    let path = dir.join("v1.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(b"WALY\x01\x00").unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[1]}}"#).unwrap();
+    drop(file);
+
+    // Read-only handles read it as it is.
+    let wal = WriteAheadLog::builder(&path)
+        .read_only(true)
+        .build()
+        .unwrap();
+    assert_eq!(wal.read_all().unwrap()[0].data, [1]);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert!(std::fs::read(&path)
+        .unwrap()
+        .starts_with(&current(Format::Json)));
+    assert_eq!(wal.append(vec![2]).unwrap().id, 1);
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let dir = TempDir::new();
+    let path = dir.join("future.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(b"WALY\x03\x00").unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[1]}}"#).unwrap();
+    drop(file);
+    let before = std::fs::read(&path).unwrap();
+
+    let err = WriteAheadLog::new(&path).unwrap_err();
+    assert!(matches!(
+        err,
+        WalError::UnsupportedVersion {
+            found: 3,
+            expected: 2
+        }
+    ));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    assert!(matches!(
+        WriteAheadLog::check_compatibility(&path, &WriteAheadLog::builder(&path)).unwrap(),
+        Compatibility::Incompatible { .. }
+    ));
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{DedupPolicy, Format, WriteAheadLog};
+
+#[test]
+fn same_key_is_written_once() {
+    for format in [Format::Json, Format::Binary] {
+        let dir = TempDir::new();
+        let path = dir.join("idem.wal");
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .format(format)
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+
+        let (first, written) = wal
+            .append_idempotent("order-1".into(), b"a".to_vec())
+            .unwrap();
+        assert!(written);
+        let (again, written) = wal
+            .append_idempotent("order-1".into(), b"retry".to_vec())
+            .unwrap();
+        assert!(!written);
+        assert_eq!(again, first);
+        assert_eq!(wal.read_all().unwrap(), vec![first.clone()]);
+
+        drop(wal);
+        let mut wal = open();
+        let (cold, written) = wal
+            .append_idempotent("order-1".into(), b"b".to_vec())
+            .unwrap();
+        assert!(!written);
+        assert_eq!(cold, first);
+        let (other, written) = wal
+            .append_idempotent("order-2".into(), b"c".to_vec())
+            .unwrap();
+        assert!(written);
+        assert_eq!(other.id, 1);
+    }
+}
+
+#[test]
+fn cleared_key_can_be_written_again() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("idem.wal")).unwrap();
+    let (first, _) = wal.append_idempotent("k".into(), b"a".to_vec()).unwrap();
+    wal.clear_id(first.id).unwrap();
+
+    let (second, written) = wal.append_idempotent("k".into(), b"b".to_vec()).unwrap();
+    assert!(written);
+    // The tombstone took ID 1.
+    assert_eq!(second.id, 2);
+}
+
+#[test]
+fn queued_entry_counts_under_group_commit() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("idem.wal"))
+        .group_commit(true)
+        .build()
+        .unwrap();
+    let (first, _) = wal.append_idempotent("k".into(), b"a".to_vec()).unwrap();
+
+    let (again, written) = wal.append_idempotent("k".into(), b"b".to_vec()).unwrap();
+    assert!(!written);
+    assert_eq!(again, first);
+}
+
+#[test]
+fn dedup_on_read_keeps_the_configured_duplicate() {
+    let dir = TempDir::new();
+    let path = dir.join("historical.wal");
+    // Written before appends were deduplicated.
+    let lines = [
+        r#"{"id":0,"timestamp":1,"data":[1],"idempotency_key":"a"}"#,
+        r#"{"id":1,"timestamp":1,"data":[2],"idempotency_key":"b"}"#,
+        r#"{"id":2,"timestamp":1,"data":[3]}"#,
+        r#"{"id":3,"timestamp":1,"data":[4],"idempotency_key":"a"}"#,
+        r#"{"id":4,"timestamp":1,"data":[5],"idempotency_key":"a"}"#,
+        r#"{"id":5,"timestamp":1,"data":[6]}"#,
+        r#"{"id":6,"timestamp":1,"data":[7],"idempotency_key":"b"}"#,
+    ];
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    let ids = |wal: &WriteAheadLog| -> Vec<u64> {
+        wal.read_all().unwrap().iter().map(|e| e.id).collect()
+    };
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(ids(&wal), [0, 1, 2, 3, 4, 5, 6]);
+    drop(wal);
+
+    let wal = WriteAheadLog::builder(&path)
+        .dedup_on_read(DedupPolicy::KeepFirst)
+        .build()
+        .unwrap();
+    assert_eq!(ids(&wal), [0, 1, 2, 5]);
+    drop(wal);
+
+    let wal = WriteAheadLog::builder(&path)
+        .dedup_on_read(DedupPolicy::KeepLast)
+        .build()
+        .unwrap();
+    assert_eq!(ids(&wal), [2, 4, 5, 6]);
+    assert_eq!(wal.read_all_deque().unwrap().len(), 4);
+}
+
+#[test]
+fn a_bounded_key_set_forgets_the_oldest_keys() {
+    let dir = TempDir::new();
+    let path = dir.join("bounded.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_idempotency_keys(2)
+        .build()
+        .unwrap();
+    for key in ["a", "b", "c"] {
+        assert!(wal.append_idempotent(key.into(), vec![]).unwrap().1);
+    }
+    assert!(!wal.append_idempotent("c".into(), vec![]).unwrap().1);
+    assert!(!wal.append_idempotent("b".into(), vec![]).unwrap().1);
+    // "a" fell out of the set, so its retry is written again.
+    assert!(wal.append_idempotent("a".into(), vec![]).unwrap().1);
+    drop(wal);
+
+    // A cold start keeps only the most recent keys.
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_idempotency_keys(1)
+        .build()
+        .unwrap();
+    assert!(!wal.append_idempotent("a".into(), vec![]).unwrap().1);
+    assert!(wal.append_idempotent("c".into(), vec![]).unwrap().1);
+}
+
+#[test]
+fn a_zero_bound_turns_deduplication_off() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("off.wal"))
+        .max_idempotency_keys(0)
+        .build()
+        .unwrap();
+    let (first, _) = wal.append_idempotent("k".into(), vec![1]).unwrap();
+    let (second, written) = wal.append_idempotent("k".into(), vec![2]).unwrap();
+    assert!(written);
+    assert_ne!(first.id, second.id);
+    assert_eq!(second.idempotency_key.as_deref(), Some("k"));
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn index_points_at_newest_id_per_key() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("index.wal")).unwrap();
+    for record in ["a=1", "b=1", "a=2", "c=1", "b=2", "a=3"] {
+        wal.append(record.as_bytes().to_vec()).unwrap();
+    }
+    wal.append_marker("a=marker").unwrap();
+
+    let index = wal
+        .build_index(|entry| entry.data.split(|&b| b == b'=').next().unwrap().to_vec())
+        .unwrap();
+
+    let expected: HashMap<Vec<u8>, u64> =
+        [(b"a".to_vec(), 5), (b"b".to_vec(), 4), (b"c".to_vec(), 3)].into();
+    assert_eq!(index, expected);
+}
+
+#[test]
+fn compact_by_key_keeps_latest_per_key() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("kv.wal"))
+        .max_segment_bytes(120)
+        .build()
+        .unwrap();
+    for record in ["a=1", "b=1", "a=2", "c=1", "b=2", "a=3"] {
+        wal.append(record.as_bytes().to_vec()).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    assert!(wal.segments().unwrap().len() > 1);
+
+    let removed = wal
+        .compact_by_key(|entry| entry.data.split(|&b| b == b'=').next().unwrap().to_vec())
+        .unwrap();
+
+    assert_eq!(removed, 3);
+    let survivors: Vec<_> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.id, String::from_utf8(e.data).unwrap()))
+        .collect();
+    assert_eq!(
+        survivors,
+        [
+            (3, "c=1".to_string()),
+            (4, "b=2".to_string()),
+            (5, "a=3".to_string())
+        ]
+    );
+    assert_eq!(wal.next_id(), 7);
+}
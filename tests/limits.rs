@@ -0,0 +1,107 @@
+mod common;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{Capacity, Format, WalError, WriteAheadLog};
+
+#[test]
+fn remaining_bytes_shrinks_with_each_append() {
+    let dir = TempDir::new();
+    let path = dir.join("capped.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .format(Format::Binary)
+        .checksums(false)
+        .max_file_size(1000)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        wal.remaining_capacity().unwrap(),
+        Capacity {
+            remaining_bytes: Some(1000 - HEADER_LEN),
+            remaining_entries: None,
+        }
+    );
+
+    // Binary records are 4 + 8 + 8 + 4 bytes of framing and fields plus the
+    // payload.
+    let record = 24 + 26;
+    for appended in 1..=5u64 {
+        wal.append(vec![b'x'; 26]).unwrap();
+        let capacity = wal.remaining_capacity().unwrap();
+        let remaining = 1000 - HEADER_LEN - appended * record;
+        assert_eq!(capacity.remaining_bytes, Some(remaining));
+        assert_eq!(capacity.remaining_entries, Some(remaining / record));
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            HEADER_LEN + appended * record
+        );
+    }
+}
+
+#[test]
+fn appends_past_the_byte_cap_are_refused() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("capped.wal"))
+        .format(Format::Binary)
+        .checksums(false)
+        .max_file_size(138)
+        .build()
+        .unwrap();
+
+    wal.append(vec![0; 26]).unwrap();
+    wal.append(vec![0; 26]).unwrap();
+    assert!(matches!(wal.append(vec![0; 26]), Err(WalError::LogFull)));
+    assert_eq!(wal.remaining_capacity().unwrap().remaining_entries, Some(0));
+    // A smaller record still fits, and the refused one did not use an ID.
+    assert_eq!(wal.append(Vec::new()).unwrap().id, 2);
+}
+
+#[test]
+fn entry_cap_counts_data_entries_only() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("capped.wal"))
+        .max_entries(3)
+        .build()
+        .unwrap();
+
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append_marker("restart").unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    assert_eq!(wal.remaining_capacity().unwrap().remaining_entries, Some(1));
+    wal.append(b"c".to_vec()).unwrap();
+    assert!(matches!(wal.append(b"d".to_vec()), Err(WalError::LogFull)));
+
+    wal.clear_id(0).unwrap();
+    assert_eq!(wal.remaining_capacity().unwrap().remaining_entries, Some(1));
+    wal.append(b"d".to_vec()).unwrap();
+}
+
+#[test]
+fn serialized_size_matches_file_growth() {
+    let dir = TempDir::new();
+    let options = [
+        (Format::Json, None, None),
+        (Format::JsonBase64, None, None),
+        (Format::Binary, None, Some(64)),
+        (Format::CompactBinary, Some(b"hello hello".to_vec()), None),
+        (Format::Json, Some(b"hello hello".to_vec()), Some(512)),
+    ];
+    for (i, (format, dict, alignment)) in options.into_iter().enumerate() {
+        let path = dir.join(format!("size{i}.wal"));
+        let mut builder = WriteAheadLog::builder(&path).format(format).checksums(true);
+        if let Some(dict) = dict {
+            builder = builder.compression_dict(dict);
+        }
+        if let Some(alignment) = alignment {
+            builder = builder.alignment(alignment);
+        }
+        let mut wal = builder.build().unwrap();
+        for data in [&b""[..], b"x", b"hello hello hello", &[0xAB; 300]] {
+            let predicted = wal.serialized_size(data).unwrap();
+            let before = std::fs::metadata(&path).unwrap().len();
+            wal.append(data.to_vec()).unwrap();
+            let grown = std::fs::metadata(&path).unwrap().len() - before;
+            assert_eq!(predicted as u64, grown, "{format:?} {}", data.len());
+        }
+    }
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn second_handle_on_the_same_path_is_locked_out() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut first = WriteAheadLog::new(&path).unwrap();
+    first.append(b"a".to_vec()).unwrap();
+
+    assert!(matches!(WriteAheadLog::new(&path), Err(WalError::Locked)));
+    // Other paths in the same directory are unaffected.
+    WriteAheadLog::new(dir.join("other.wal")).unwrap();
+
+    drop(first);
+    let mut reopened = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(reopened.append(b"b".to_vec()).unwrap().id, 1);
+}
+
+#[test]
+fn locking_can_be_turned_off() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let _writer = WriteAheadLog::new(&path).unwrap();
+    let reader = WriteAheadLog::builder(&path).lock(false).build().unwrap();
+    assert!(reader.read_all().unwrap().is_empty());
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn iter_by_run_splits_at_restart_markers() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("runs.wal")).unwrap();
+
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    wal.append_marker("restart").unwrap();
+    wal.append(b"c".to_vec()).unwrap();
+    wal.append_marker("other").unwrap();
+    wal.append(b"d".to_vec()).unwrap();
+    wal.append_marker("restart").unwrap();
+    wal.append(b"e".to_vec()).unwrap();
+
+    let runs: Vec<Vec<Vec<u8>>> = wal
+        .iter_by_run("restart")
+        .unwrap()
+        .map(|run| run.unwrap().into_iter().map(|e| e.data).collect())
+        .collect();
+
+    assert_eq!(
+        runs,
+        vec![
+            vec![b"a".to_vec(), b"b".to_vec()],
+            vec![b"c".to_vec(), b"d".to_vec()],
+            vec![b"e".to_vec()],
+        ]
+    );
+}
+
+#[test]
+fn markers_are_not_returned_by_read_all() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("runs.wal")).unwrap();
+
+    wal.append(b"a".to_vec()).unwrap();
+    let marker = wal.append_marker("restart").unwrap();
+    assert_eq!(marker.marker_label(), Some("restart"));
+    wal.append(b"b".to_vec()).unwrap();
+
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![0, 2]);
+
+    drop(wal);
+    let wal = WriteAheadLog::new(dir.join("runs.wal")).unwrap();
+    assert_eq!(wal.next_id(), 3);
+}
+
+#[test]
+fn iter_until_marker_stops_at_first_matching_marker() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("checkpoint.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append_marker("other").unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    wal.append_marker("checkpoint").unwrap();
+    wal.append(b"c".to_vec()).unwrap();
+    wal.append_marker("checkpoint").unwrap();
+    wal.append(b"d".to_vec()).unwrap();
+
+    let data = |entries: Vec<waly_rs::LogEntry>| -> Vec<Vec<u8>> {
+        entries.into_iter().map(|e| e.data).collect()
+    };
+    assert_eq!(
+        data(wal.iter_until_marker("checkpoint").unwrap()),
+        [b"a".to_vec(), b"b".to_vec()]
+    );
+    assert_eq!(wal.iter_until_marker("missing").unwrap().len(), 4);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::io::Write;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{WalMetrics, WriteAheadLog};
+
+#[test]
+fn appends_and_syncs_are_counted() {
+    let dir = TempDir::new();
+    let path = dir.join("metered.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.metrics(), WalMetrics::default());
+
+    for i in 0..3u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    let metrics = wal.metrics();
+    assert_eq!(metrics.entries_appended, 3);
+    assert_eq!(metrics.appends_since_sync, 4);
+    assert_eq!(
+        metrics.bytes_written,
+        std::fs::metadata(&path).unwrap().len() - HEADER_LEN
+    );
+
+    wal.sync().unwrap();
+    assert_eq!(wal.metrics().appends_since_sync, 0);
+    wal.append(vec![9]).unwrap();
+    assert_eq!(wal.metrics().appends_since_sync, 1);
+    assert_eq!(wal.metrics().entries_appended, 4);
+}
+
+#[test]
+fn reads_and_skipped_records_are_counted() {
+    let dir = TempDir::new();
+    let path = dir.join("damaged.wal");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, r#"{{"id":0,"timestamp":1,"data":[1]}}"#).unwrap();
+    writeln!(file, r#"{{"id":1,"timestamp":1,"data":[2"#).unwrap();
+    writeln!(file, r#"{{"id":2,"timestamp":1,"data":[3]}}"#).unwrap();
+    drop(file);
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    let before = wal.metrics();
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+    wal.get(2).unwrap();
+    assert!(wal.iter().unwrap().any(|r| r.is_err()));
+    let after = wal.metrics();
+    assert_eq!(after.reads, before.reads + 3);
+    assert_eq!(
+        after.corrupted_entries_skipped,
+        before.corrupted_entries_skipped + 3
+    );
+    assert_eq!(after.entries_appended, 0);
+}
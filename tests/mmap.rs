@@ -0,0 +1,91 @@
+#![cfg(feature = "mmap")]
+
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn mapped_reads_match_buffered_reads_in_every_format() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let path = dir.join("mapped.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .max_segment_bytes(200)
+            .build()
+            .unwrap();
+        for i in 0..20u8 {
+            wal.append(vec![i, b'\n', i]).unwrap();
+        }
+        wal.clear_id(3).unwrap();
+        let expected = wal.read_all().unwrap();
+        drop(wal);
+
+        let wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .max_segment_bytes(200)
+            .mmap(true)
+            .build()
+            .unwrap();
+        assert_eq!(wal.read_all().unwrap(), expected, "{format:?}");
+        let iterated: Vec<_> = wal.iter().unwrap().map(Result::unwrap).collect();
+        assert_eq!(iterated, expected, "{format:?}");
+        assert_eq!(wal.next_id(), 21, "{format:?}");
+    }
+}
+
+#[test]
+fn a_mapped_scan_sees_appends_made_after_it_started() {
+    for format in [Format::Json, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("growing.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .mmap(true)
+            .build()
+            .unwrap();
+        wal.append(b"first".to_vec()).unwrap();
+        let mut iter = wal.iter().unwrap();
+        assert_eq!(iter.next().unwrap().unwrap().data, b"first");
+
+        wal.append(b"second".to_vec()).unwrap();
+        assert_eq!(iter.next().unwrap().unwrap().data, b"second", "{format:?}");
+        assert!(iter.next().is_none());
+    }
+}
+
+#[test]
+fn a_mapped_scan_survives_truncating_the_log_under_it() {
+    for format in [Format::Json, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("cut.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .max_segment_bytes(64 * 1024)
+            .mmap(true)
+            .build()
+            .unwrap();
+        // Segments span many pages, so a map of one cut short underneath
+        // would fault rather than read zeroes.
+        for i in 0..100u8 {
+            wal.append(vec![i; 2048]).unwrap();
+        }
+        assert!(wal.segments().unwrap().len() > 2, "{format:?}");
+        let mut iter = wal.iter().unwrap();
+        assert_eq!(iter.next().unwrap().unwrap().id, 0);
+
+        wal.truncate_after(0).unwrap();
+        // The scan reads on from what it opened; the point is it does not
+        // fault on segments cut or removed since.
+        let rest: Vec<_> = iter.map(Result::unwrap).collect();
+        assert!(rest.iter().all(|e| e.id > 0), "{format:?}");
+        assert_eq!(wal.read_all().unwrap().len(), 1, "{format:?}");
+        assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 1, "{format:?}");
+    }
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{NormalizeStats, WriteAheadLog};
+
+#[test]
+fn messy_legacy_file_becomes_one_compact_record_per_line() {
+    let dir = TempDir::new();
+    let path = dir.join("legacy.wal");
+    let messy = concat!(
+        "{\"id\":0,\"timestamp\":10,\"data\":[97]}\n",
+        "\n",
+        "  { \"id\" : 1, \"timestamp\" : 11, \"data\" : [98, 99] }  \n",
+        "{\n",
+        "  \"id\": 2,\n",
+        "  \"timestamp\": 12,\n",
+        "  \"data\": \"ZA==\",\n",
+        "  \"kind\": \"marker\"\n",
+        "}\n",
+        "\n",
+        "\n",
+        "{\"id\":3,\"timestamp\":13,\"data\":[101]}",
+    );
+    std::fs::write(&path, messy).unwrap();
+
+    let stats = WriteAheadLog::normalize(&path).unwrap();
+    assert_eq!(
+        stats,
+        NormalizeStats {
+            records: 4,
+            normalized: 3,
+            blank_lines: 3,
+            unparsed: 0,
+        }
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        concat!(
+            "{\"id\":0,\"timestamp\":10,\"data\":[97]}\n",
+            "{\"id\":1,\"timestamp\":11,\"data\":[98,99]}\n",
+            "{\"id\":2,\"timestamp\":12,\"data\":\"ZA==\",\"kind\":\"marker\"}\n",
+            "{\"id\":3,\"timestamp\":13,\"data\":[101]}\n",
+        )
+    );
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let data: Vec<_> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(data, [b"a".to_vec(), b"bc".to_vec(), b"e".to_vec()]);
+    assert_eq!(wal.len().unwrap(), 3);
+    assert_eq!(wal.next_id(), 4);
+}
+
+#[test]
+fn canonical_file_is_left_alone_and_garbage_is_kept() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"x".to_vec()).unwrap();
+    wal.append_marker("m").unwrap();
+    drop(wal);
+    let before = std::fs::read(&path).unwrap();
+
+    let stats = WriteAheadLog::normalize(&path).unwrap();
+    assert_eq!((stats.records, stats.normalized), (2, 0));
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+
+    let mut damaged = before.clone();
+    damaged.extend_from_slice(b"not json\n\n");
+    std::fs::write(&path, &damaged).unwrap();
+    let stats = WriteAheadLog::normalize(&path).unwrap();
+    assert_eq!((stats.unparsed, stats.blank_lines), (1, 1));
+    let mut expected = before;
+    expected.extend_from_slice(b"not json\n");
+    assert_eq!(std::fs::read(&path).unwrap(), expected);
+}
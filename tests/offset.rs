@@ -0,0 +1,45 @@
+mod common;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{Format, WalError, WriteAheadLog};
+
+#[test]
+fn read_at_returns_the_entry_appended_there() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("offsets.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+        let mut index = Vec::new();
+        for key in ["alpha", "beta", "gamma"] {
+            index.push(wal.append_at(key.as_bytes().to_vec()).unwrap());
+            wal.append_marker("between").unwrap();
+        }
+        assert_eq!(index[0].1, HEADER_LEN);
+
+        for (entry, offset) in &index {
+            assert_eq!(&wal.read_at(*offset).unwrap(), entry, "{format:?}");
+        }
+        let end = std::fs::metadata(&path).unwrap().len();
+        assert!(matches!(wal.read_at(end), Err(WalError::InvalidEntry(_))));
+    }
+}
+
+#[test]
+fn append_at_flushes_queued_records_first() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .build()
+        .unwrap();
+    wal.append(b"queued".to_vec()).unwrap();
+    let (entry, offset) = wal.append_at(b"direct".to_vec()).unwrap();
+
+    assert!(offset > 0);
+    assert_eq!(entry.id, 1);
+    assert_eq!(wal.read_at(offset).unwrap(), entry);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 1]);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn a_panic_under_the_lock_fails_later_calls_instead_of_panicking() {
+    let dir = TempDir::new();
+    let path = dir.join("poison.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(100)
+        .max_segments(1)
+        .on_segment_evicted(|_| panic!("archive failed"))
+        .build()
+        .unwrap();
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+        for _ in 0..10 {
+            wal.append(vec![b'x'; 40]).unwrap();
+        }
+    }));
+    assert!(panicked.is_err());
+
+    assert!(matches!(
+        wal.append(b"next".to_vec()),
+        Err(WalError::Poisoned)
+    ));
+    assert!(matches!(wal.read_all(), Err(WalError::Poisoned)));
+    assert!(matches!(wal.sync(), Err(WalError::Poisoned)));
+    drop(wal);
+
+    // The records written before the panic are still there on reopen.
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert!(!wal.read_all().unwrap().is_empty());
+}
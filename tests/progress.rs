@@ -0,0 +1,68 @@
+mod common;
+
+use std::fs::OpenOptions;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn append_and_checkpoint_survives_reopen() {
+    let dir = TempDir::new();
+    let path = dir.join("out.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.processed_up_to().unwrap(), None);
+
+    wal.append_and_checkpoint(b"result-1".to_vec(), 10).unwrap();
+    wal.append_and_checkpoint(b"result-2".to_vec(), 20).unwrap();
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.processed_up_to().unwrap(), Some(20));
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+}
+
+#[test]
+fn crash_before_sidecar_update_keeps_both_applied() {
+    let dir = TempDir::new();
+    let path = dir.join("out.wal");
+    let sidecar = dir.join("out.wal.progress");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+
+    wal.append_and_checkpoint(b"result-1".to_vec(), 10).unwrap();
+    let stale = std::fs::read(&sidecar).unwrap();
+    wal.append_and_checkpoint(b"result-2".to_vec(), 20).unwrap();
+    drop(wal);
+
+    // The entry reached the log but the sidecar update was lost.
+    std::fs::write(&sidecar, stale).unwrap();
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+    assert_eq!(wal.processed_up_to().unwrap(), Some(20));
+}
+
+#[test]
+fn crash_before_entry_write_applies_neither() {
+    let dir = TempDir::new();
+    let path = dir.join("out.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+
+    wal.append_and_checkpoint(b"result-1".to_vec(), 10).unwrap();
+    let committed_len = std::fs::metadata(&path).unwrap().len();
+    wal.append_and_checkpoint(b"result-2".to_vec(), 20).unwrap();
+    drop(wal);
+
+    // Roll the log back to a torn write of the second entry, and the sidecar
+    // back to before it, as if the process died mid-append.
+    let file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_len(committed_len + 7).unwrap();
+    drop(file);
+    let sidecar = dir.join("out.wal.progress");
+    std::fs::write(&sidecar, "10 0\n").unwrap();
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let entries = wal.read_all().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].data, b"result-1");
+    assert_eq!(wal.processed_up_to().unwrap(), Some(10));
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "prost")]
+
+mod common;
+
+use common::TempDir;
+use waly_rs::{Message, ProtoWal, WalError, WriteAheadLog};
+
+/// `message Order { uint64 id = 1; string item = 2; }`, encoded by hand
+/// the way generated code would.
+#[derive(Debug, Default, PartialEq)]
+struct Order {
+    id: u64,
+    item: String,
+}
+
+fn put_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn take_varint(buf: &mut &[u8]) -> Result<u64, String> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first().ok_or("truncated varint")?;
+        *buf = rest;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(n);
+        }
+    }
+    Err("varint too long".into())
+}
+
+impl Message for Order {
+    fn encode_to_vec(&self) -> Vec<u8> {
+        let mut out = vec![0x08];
+        put_varint(&mut out, self.id);
+        out.push(0x12);
+        put_varint(&mut out, self.item.len() as u64);
+        out.extend_from_slice(self.item.as_bytes());
+        out
+    }
+
+    fn decode(mut buf: &[u8]) -> Result<Self, String> {
+        let mut order = Order::default();
+        while !buf.is_empty() {
+            match take_varint(&mut buf)? {
+                0x08 => order.id = take_varint(&mut buf)?,
+                0x12 => {
+                    let len = take_varint(&mut buf)? as usize;
+                    if len > buf.len() {
+                        return Err("truncated string".into());
+                    }
+                    let (item, rest) = buf.split_at(len);
+                    order.item = String::from_utf8(item.to_vec()).map_err(|e| e.to_string())?;
+                    buf = rest;
+                }
+                key => return Err(format!("unexpected key {key}")),
+            }
+        }
+        Ok(order)
+    }
+}
+
+#[test]
+fn proto_round_trip() {
+    let dir = TempDir::new();
+    let path = dir.join("orders.wal");
+    let mut wal = ProtoWal::<Order>::new(&path).unwrap();
+    let orders = [
+        Order {
+            id: 7,
+            item: "widget".into(),
+        },
+        Order {
+            id: 300,
+            item: String::new(),
+        },
+    ];
+    for order in &orders {
+        wal.append(order).unwrap();
+    }
+    drop(wal);
+
+    let wal = ProtoWal::<Order>::new(&path).unwrap();
+    let read: Vec<(u64, Order)> = wal.iter().unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(
+        read[0],
+        (
+            0,
+            Order {
+                id: 7,
+                item: "widget".into()
+            }
+        )
+    );
+    assert_eq!(read[1].1, orders[1]);
+}
+
+#[test]
+fn undecodable_payload_is_a_serialization_error() {
+    let dir = TempDir::new();
+    let path = dir.join("orders.wal");
+    let mut raw = WriteAheadLog::new(&path).unwrap();
+    raw.append(vec![0x12, 0x05, b'x']).unwrap();
+    let mut wal = ProtoWal::<Order>::from_wal(raw);
+    wal.append(&Order {
+        id: 1,
+        item: "ok".into(),
+    })
+    .unwrap();
+
+    let mut iter = wal.iter().unwrap();
+    assert!(matches!(iter.next(), Some(Err(WalError::Serialization(_)))));
+    assert_eq!(iter.next().unwrap().unwrap().0, 1);
+    assert!(iter.next().is_none());
+}
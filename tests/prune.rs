@@ -0,0 +1,78 @@
+mod common;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+fn clock(time: &Arc<AtomicU64>) -> impl Fn() -> u64 + Send + Sync + 'static {
+    let time = Arc::clone(time);
+    move || time.load(Ordering::SeqCst)
+}
+
+#[test]
+fn prune_before_drops_the_older_prefix() {
+    let dir = TempDir::new();
+    let path = dir.join("prune.wal");
+    let time = Arc::new(AtomicU64::new(100));
+    let mut wal = WriteAheadLog::with_clock(&path, clock(&time)).unwrap();
+    for t in [100, 110, 120, 130] {
+        time.store(t, Ordering::SeqCst);
+        wal.append(vec![t as u8]).unwrap();
+    }
+    wal.clear_id(0).unwrap();
+
+    // Entry 0 was already cleared, so only entry 1 counts.
+    assert_eq!(wal.prune_before(120).unwrap(), 1);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [2, 3]);
+    assert_eq!(wal.prune_before(120).unwrap(), 0);
+
+    assert_eq!(wal.append(b"new".to_vec()).unwrap().id, 5);
+    drop(wal);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [2, 3, 5]);
+}
+
+#[test]
+fn prune_spans_segments_and_can_empty_the_log() {
+    let dir = TempDir::new();
+    let path = dir.join("segments.wal");
+    let time = Arc::new(AtomicU64::new(0));
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(150)
+        .clock(clock(&time))
+        .build()
+        .unwrap();
+    for t in 0..10u64 {
+        time.store(t, Ordering::SeqCst);
+        wal.append(vec![b'x'; 20]).unwrap();
+    }
+    assert!(path.with_extension("wal.000001").exists());
+
+    assert_eq!(wal.prune_before(7).unwrap(), 7);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [7, 8, 9]);
+
+    assert_eq!(wal.prune_before(100).unwrap(), 3);
+    assert!(wal.read_all().unwrap().is_empty());
+    assert_eq!(wal.append(b"a".to_vec()).unwrap().id, 10);
+}
+
+#[test]
+fn prune_older_than_uses_the_logs_clock() {
+    let dir = TempDir::new();
+    let time = Arc::new(AtomicU64::new(1_000));
+    let mut wal = WriteAheadLog::with_clock(dir.join("age.wal"), clock(&time)).unwrap();
+    wal.append(b"old".to_vec()).unwrap();
+    time.store(90_000, Ordering::SeqCst);
+    wal.append(b"recent".to_vec()).unwrap();
+    time.store(100_000, Ordering::SeqCst);
+
+    let day = Duration::from_secs(24 * 60 * 60);
+    assert_eq!(wal.prune_older_than(day).unwrap(), 1);
+    assert_eq!(wal.read_all().unwrap()[0].data, b"recent");
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::io::Write;
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::WriteAheadLog;
+
+const GOOD_0: &str = r#"{"id":0,"timestamp":1,"data":[97]}"#;
+const BAD: &str = r#"{"id":1,"timestamp":1,"data":[9"#;
+const GOOD_2: &str = r#"{"id":2,"timestamp":1,"data":[99]}"#;
+
+fn write_log(path: &std::path::Path) {
+    let mut file = std::fs::File::create(path).unwrap();
+    writeln!(file, "{GOOD_0}").unwrap();
+    writeln!(file, "{BAD}").unwrap();
+    writeln!(file, "{GOOD_2}").unwrap();
+}
+
+#[test]
+fn corrupt_records_are_quarantined_with_their_offset() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    write_log(&path);
+
+    let wal = WriteAheadLog::with_quarantine(&path).unwrap();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![0, 2]);
+    assert_eq!(wal.next_id(), 3);
+
+    let quarantine_path = wal.quarantine_path().unwrap().to_path_buf();
+    assert_eq!(quarantine_path, dir.join("log.wal.quarantine"));
+    let expected = format!(
+        "offset={} len={} segment=log.wal\n{BAD}\n",
+        HEADER_LEN as usize + GOOD_0.len() + 1,
+        BAD.len()
+    );
+    assert_eq!(std::fs::read_to_string(&quarantine_path).unwrap(), expected);
+
+    // Reading again, or reopening, does not duplicate the evidence.
+    wal.read_all().unwrap();
+    drop(wal);
+    let wal = WriteAheadLog::with_quarantine(&path).unwrap();
+    wal.read_all().unwrap();
+    assert_eq!(std::fs::read_to_string(&quarantine_path).unwrap(), expected);
+}
+
+#[test]
+fn rewrites_keep_the_evidence_after_dropping_the_record() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    let mut wal = WriteAheadLog::with_quarantine(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    // Corruption that appears after open is first seen by the rewrite.
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    writeln!(file, "{BAD}").unwrap();
+    drop(file);
+    wal.clear_id(0).unwrap();
+    wal.compact().unwrap();
+
+    assert!(!std::fs::read_to_string(&path).unwrap().contains(BAD));
+    let quarantined = std::fs::read_to_string(wal.quarantine_path().unwrap()).unwrap();
+    assert!(quarantined.contains(BAD));
+}
+
+#[test]
+fn without_quarantine_nothing_is_written() {
+    let dir = TempDir::new();
+    let path = dir.join("log.wal");
+    write_log(&path);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+    assert_eq!(wal.quarantine_path(), None);
+    assert!(!dir.join("log.wal.quarantine").exists());
+}
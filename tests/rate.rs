@@ -0,0 +1,31 @@
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn token_bucket_allows_burst_then_rejects_then_recovers() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::with_token_bucket(dir.join("app.wal"), 20.0, 3).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    assert!(matches!(wal.append(vec![3]), Err(WalError::RateLimited)));
+    assert_eq!(wal.next_id(), 3);
+
+    thread::sleep(Duration::from_millis(120));
+    wal.append(vec![3]).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 4);
+}
+
+#[test]
+fn token_bucket_rejects_bad_config() {
+    let dir = TempDir::new();
+    for (rate, burst) in [(0.0, 1), (1.0, 0), (f64::NAN, 1)] {
+        let err = WriteAheadLog::with_token_bucket(dir.join("app.wal"), rate, burst).unwrap_err();
+        assert!(matches!(err, WalError::InvalidConfig(_)));
+    }
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn read_all_capped_refuses_logs_over_either_cap() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("capped.wal")).unwrap();
+    for i in 0..10u8 {
+        wal.append(vec![i; 100]).unwrap();
+        wal.append_marker("m").unwrap();
+    }
+
+    // Exactly at both caps still reads; markers do not count.
+    let entries = wal.read_all_capped(10, 1000).unwrap();
+    assert_eq!(entries, wal.read_all().unwrap());
+
+    assert!(matches!(
+        wal.read_all_capped(9, usize::MAX),
+        Err(WalError::LimitExceeded)
+    ));
+    assert!(matches!(
+        wal.read_all_capped(usize::MAX, 999),
+        Err(WalError::LimitExceeded)
+    ));
+    assert!(matches!(
+        wal.read_all_capped(0, 0),
+        Err(WalError::LimitExceeded)
+    ));
+
+    wal.clear().unwrap();
+    assert!(wal.read_all_capped(0, 0).unwrap().is_empty());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn selects_scattered_ids_from_large_log() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("large.wal"))
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    for i in 0..10_000u32 {
+        wal.append(i.to_le_bytes().to_vec()).unwrap();
+    }
+
+    let ids: BTreeSet<u64> = (0..10_000)
+        .filter(|id| id % 997 == 3)
+        .chain([20_000])
+        .collect();
+    let entries = wal.read_ids(&ids).unwrap();
+
+    let found: BTreeSet<u64> = entries.iter().map(|e| e.id).collect();
+    assert_eq!(
+        found,
+        ids.iter().copied().filter(|&id| id < 10_000).collect()
+    );
+    for entry in &entries {
+        assert_eq!(entry.data, (entry.id as u32).to_le_bytes());
+    }
+}
+
+#[test]
+fn empty_set_reads_nothing() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("ids.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    assert!(wal.read_ids(&BTreeSet::new()).unwrap().is_empty());
+}
+
+#[test]
+fn get_finds_single_data_entries() {
+    let dir = TempDir::new();
+    let path = dir.join("get.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..5u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    wal.clear_id(2).unwrap();
+
+    assert_eq!(wal.get(3).unwrap().unwrap().data, [3]);
+    assert_eq!(wal.get(2).unwrap(), None);
+    assert_eq!(wal.get(5).unwrap(), None);
+    assert_eq!(wal.get(99).unwrap(), None);
+    assert_eq!(wal.get(0).unwrap().unwrap().data, [0]);
+}
+
+#[test]
+fn get_stops_at_the_first_larger_id() {
+    let dir = TempDir::new();
+    let path = dir.join("unordered.wal");
+    let lines = [0, 5, 3].map(|id| format!("{{\"id\":{id},\"timestamp\":0,\"data\":[{id}]}}\n"));
+    std::fs::write(&path, lines.concat()).unwrap();
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.get(5).unwrap().unwrap().data, [5]);
+    assert_eq!(wal.get(3).unwrap(), None);
+}
+
+#[test]
+fn reopening_continues_after_the_largest_id() {
+    let dir = TempDir::new();
+    let path = dir.join("unordered.wal");
+    let lines = [3, 9, 4].map(|id| format!("{{\"id\":{id},\"timestamp\":0,\"data\":[]}}\n"));
+    std::fs::write(&path, lines.concat()).unwrap();
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 10);
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 10);
+}
+
+#[test]
+fn nth_counts_data_entries_by_position() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("nth.wal")).unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i]).unwrap();
+        if i == 1 {
+            wal.append_marker("page").unwrap();
+        }
+    }
+    wal.clear_id(0).unwrap();
+
+    assert_eq!(wal.nth(0).unwrap().unwrap().id, 1);
+    assert_eq!(wal.nth(2).unwrap().unwrap().data, [3]);
+    assert_eq!(wal.nth(4).unwrap().unwrap().id, 6);
+    assert_eq!(wal.nth(5).unwrap(), None);
+}
+
+#[test]
+fn get_many_keeps_input_order() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("app.wal")).unwrap();
+    for i in 0..50u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("m").unwrap();
+    wal.clear_id(12).unwrap();
+
+    let results = wal.get_many(&[40, 3, 999, 12, 50, 27, 3]).unwrap();
+    let data: Vec<Option<Vec<u8>>> = results.into_iter().map(|e| e.map(|e| e.data)).collect();
+    assert_eq!(
+        data,
+        [
+            Some(vec![40]),
+            Some(vec![3]),
+            None,
+            None,
+            None,
+            Some(vec![27]),
+            Some(vec![3]),
+        ]
+    );
+    assert!(wal.get_many(&[]).unwrap().is_empty());
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn read_into_matches_read_all() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let mut wal = WriteAheadLog::builder(dir.join("into.wal"))
+            .format(format)
+            .build()
+            .unwrap();
+        wal.append(b"plain".to_vec()).unwrap();
+        wal.append(Vec::new()).unwrap();
+        wal.append_marker("checkpoint").unwrap();
+        wal.append_typed(b"{}".to_vec(), "application/json")
+            .unwrap();
+        wal.stream(2).append(b"side".to_vec()).unwrap();
+        let cleared = wal.append(b"gone".to_vec()).unwrap();
+        wal.clear_id(cleared.id).unwrap();
+        wal.append(vec![0, 10, 255]).unwrap();
+
+        let mut seen = Vec::new();
+        let mut buf = Vec::new();
+        wal.read_into(&mut buf, |id, timestamp, data| {
+            seen.push((id, timestamp, data.to_vec()));
+        })
+        .unwrap();
+        let expected: Vec<_> = wal
+            .read_all()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.id, e.timestamp, e.data))
+            .collect();
+        assert_eq!(seen, expected, "{format:?}");
+    }
+}
+
+#[test]
+fn read_into_allocations_do_not_grow_with_the_log() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let allocations = |entries: u64| {
+            let dir = TempDir::new();
+            let mut wal = WriteAheadLog::builder(dir.join("scan.wal"))
+                .format(format)
+                .build()
+                .unwrap();
+            for _ in 0..entries {
+                wal.append(vec![255; 8]).unwrap();
+            }
+            let mut buf = Vec::with_capacity(8);
+            let mut sum = 0;
+            let before = ALLOCATIONS.load(Ordering::Relaxed);
+            wal.read_into(&mut buf, |id, _, data| {
+                assert_eq!(data, [255; 8]);
+                sum += id;
+            })
+            .unwrap();
+            let used = ALLOCATIONS.load(Ordering::Relaxed) - before;
+            assert_eq!(sum, entries * (entries - 1) / 2);
+            used
+        };
+        // Both logs have three-digit IDs, so JSON lines are the same length.
+        assert_eq!(allocations(101), allocations(1_000), "{format:?}");
+    }
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WalError, WriteAheadLog};
+
+#[test]
+fn read_all_strict_reports_the_corrupt_line() {
+    let dir = TempDir::new();
+    let path = dir.join("strict.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    assert_eq!(wal.read_all_strict().unwrap(), wal.read_all().unwrap());
+    drop(wal);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, text.replacen("\"id\":1,", "\"id\":x,", 1)).unwrap();
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 2);
+    match wal.read_all_strict() {
+        Err(WalError::InvalidEntry(msg)) => {
+            assert!(msg.contains("strict.wal line 2:"), "{msg}");
+            assert!(msg.contains(r#"bytes: "{\"id\":x,"#), "{msg}");
+        }
+        other => panic!("expected InvalidEntry, got {other:?}"),
+    }
+}
+
+#[test]
+fn read_all_strict_numbers_binary_records() {
+    let dir = TempDir::new();
+    let path = dir.join("strict.bin");
+    let mut wal = WriteAheadLog::builder(&path)
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    for _ in 0..3 {
+        wal.append(b"payload".to_vec()).unwrap();
+    }
+    drop(wal);
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    // Claim a payload longer than the record that holds it.
+    let last = bytes.windows(7).rposition(|w| w == b"payload").unwrap();
+    bytes[last - 4..last].copy_from_slice(&u32::MAX.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+    let wal = WriteAheadLog::builder(&path)
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    match wal.read_all_strict() {
+        Err(WalError::InvalidEntry(msg)) => assert!(msg.contains("record 3:"), "{msg}"),
+        other => panic!("expected InvalidEntry, got {other:?}"),
+    }
+}
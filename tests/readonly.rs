@@ -0,0 +1,72 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn read_only_handles_read_but_refuse_changes() {
+    let dir = TempDir::new();
+    let path = dir.join("audit.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.clear_id(1).unwrap();
+    let expected = wal.read_all().unwrap();
+
+    // Opening read-only needs no lock, so the writer can stay open.
+    let mut ro = WriteAheadLog::open_read_only(&path).unwrap();
+    assert!(ro.is_read_only());
+    assert_eq!(ro.read_all().unwrap(), expected);
+    let iterated: Vec<_> = ro.iter().unwrap().map(Result::unwrap).collect();
+    assert_eq!(iterated, expected);
+    assert_eq!(ro.get(2).unwrap().unwrap().data, [2]);
+    assert_eq!(ro.len().unwrap(), 2);
+    assert_eq!(ro.next_id(), 4);
+
+    let before = std::fs::read(&path).unwrap();
+    assert!(matches!(ro.append(b"x".to_vec()), Err(WalError::ReadOnly)));
+    assert!(matches!(ro.clear_id(0), Err(WalError::ReadOnly)));
+    assert!(matches!(ro.clear(), Err(WalError::ReadOnly)));
+    assert!(matches!(ro.compact(), Err(WalError::ReadOnly)));
+    assert!(matches!(ro.rotate_now(), Err(WalError::ReadOnly)));
+    drop(ro);
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn open_read_only_never_creates_the_log() {
+    let dir = TempDir::new();
+    let path = dir.join("missing.wal");
+    assert!(matches!(
+        WriteAheadLog::open_read_only(&path),
+        Err(WalError::Io(_))
+    ));
+    assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+
+    assert!(matches!(
+        WriteAheadLog::builder(&path)
+            .read_only(true)
+            .quarantine(true)
+            .build(),
+        Err(WalError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn read_only_leaves_a_torn_tail_in_place() {
+    let dir = TempDir::new();
+    let path = dir.join("torn.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"whole".to_vec()).unwrap();
+    drop(wal);
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes.extend_from_slice(b"{\"id\":1,\"timest");
+    std::fs::write(&path, &bytes).unwrap();
+
+    let ro = WriteAheadLog::open_read_only(&path).unwrap();
+    assert_eq!(ro.read_all().unwrap().len(), 1);
+    assert_eq!(ro.discarded_on_open(), 0);
+    drop(ro);
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+}
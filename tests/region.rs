@@ -0,0 +1,86 @@
+mod common;
+
+use std::fs::OpenOptions;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+const BASE: u64 = 100;
+const LEN: u64 = 300;
+
+#[test]
+fn region_log_stays_inside_its_range() {
+    let dir = TempDir::new();
+    let path = dir.join("db.bin");
+    let mut image = vec![0xAA; BASE as usize];
+    image.extend(vec![0; LEN as usize]);
+    image.extend(vec![0xBB; 100]);
+    std::fs::write(&path, &image).unwrap();
+    let open = || {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap()
+    };
+
+    let mut wal = WriteAheadLog::within(open(), BASE, LEN).unwrap();
+    assert_eq!(wal.capacity_bytes(), LEN);
+    let mut appended = Vec::new();
+    loop {
+        match wal.append(vec![7; 30]) {
+            Ok(entry) => appended.push(entry),
+            Err(WalError::LogFull) => break,
+            Err(err) => panic!("{err}"),
+        }
+    }
+    assert!(appended.len() >= 4);
+    assert!(wal.used_bytes() <= LEN);
+    assert_eq!(wal.read_all().unwrap(), appended);
+    drop(wal);
+
+    let after = std::fs::read(&path).unwrap();
+    assert_eq!(after.len(), image.len());
+    assert_eq!(after[..BASE as usize], image[..BASE as usize]);
+    assert_eq!(
+        after[(BASE + LEN) as usize..],
+        image[(BASE + LEN) as usize..]
+    );
+
+    let wal = WriteAheadLog::within(open(), BASE, LEN).unwrap();
+    assert_eq!(wal.read_all().unwrap(), appended);
+    assert_eq!(wal.next_id(), appended.len() as u64);
+}
+
+#[test]
+fn torn_record_in_region_is_overwritten() {
+    let dir = TempDir::new();
+    let path = dir.join("db.bin");
+    std::fs::write(&path, vec![0; 400]).unwrap();
+    let open = || {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap()
+    };
+
+    let mut wal = WriteAheadLog::within(open(), 50, 300).unwrap();
+    wal.append(b"kept".to_vec()).unwrap();
+    let torn_at = 50 + wal.used_bytes() as usize;
+    drop(wal);
+    let mut image = std::fs::read(&path).unwrap();
+    image[torn_at..torn_at + 8].copy_from_slice(&[40, 0, 0, 0, 1, 2, 3, 4]);
+    std::fs::write(&path, &image).unwrap();
+
+    let mut wal = WriteAheadLog::within(open(), 50, 300).unwrap();
+    assert_eq!(wal.read_all().unwrap().len(), 1);
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 1);
+    let data: Vec<Vec<u8>> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(data, [b"kept".to_vec(), b"next".to_vec()]);
+}
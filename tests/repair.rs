@@ -0,0 +1,105 @@
+mod common;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use common::TempDir;
+use waly_rs::{Format, WalError, WriteAheadLog};
+
+fn ids(wal: &WriteAheadLog) -> Vec<u64> {
+    wal.read_all().unwrap().iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn drops_damaged_records_and_reports_where_they_were() {
+    let dir = TempDir::new();
+    let path = dir.join("damaged.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(true)
+        .build()
+        .unwrap();
+    for i in 0..5u8 {
+        wal.append(vec![i]).unwrap();
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let damaged = text
+        .replacen("\"id\":1,", "\"ix\":1,", 1)
+        .replacen("\"data\":[2]", "\"data\":[9]", 1)
+        .replacen("\"id\":4,", "\"ix\":4,", 1);
+    std::fs::write(&path, &damaged).unwrap();
+    assert!(matches!(
+        wal.read_all(),
+        Err(WalError::ChecksumMismatch { id: 2 })
+    ));
+    assert_eq!(wal.verify().unwrap(), [2]);
+
+    let report = wal.repair().unwrap();
+    assert_eq!(report.kept, 2);
+    assert_eq!(report.dropped, 3);
+    assert_eq!(report.dropped_ranges.len(), 2);
+    let (file, first) = &report.dropped_ranges[0];
+    assert_eq!(file, &path);
+    let start = damaged.find("{\"ix\":1,").unwrap() as u64;
+    let end = damaged.find("{\"id\":3,").unwrap() as u64;
+    assert_eq!(*first, start..end);
+    assert_eq!(report.dropped_ranges[1].1.end, damaged.len() as u64);
+    assert_eq!(
+        report.dropped_bytes,
+        report
+            .dropped_ranges
+            .iter()
+            .map(|(_, r)| r.end - r.start)
+            .sum::<u64>()
+    );
+
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("ix"));
+    assert_eq!(ids(&wal), [0, 3]);
+    // ID 4 went to a dropped record, so it is not handed out again.
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 5);
+    assert!(wal.repair().unwrap().is_clean());
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(ids(&wal), [0, 3, 5]);
+}
+
+#[test]
+fn drops_trailing_bytes_that_hold_no_whole_frame() {
+    let dir = TempDir::new();
+    let path = dir.join("torn.wal");
+    let mut wal = WriteAheadLog::with_format(&path, Format::Binary).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    let len = std::fs::metadata(&path).unwrap().len();
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(&[0xFF, 0xFF, 0, 0, 1])
+        .unwrap();
+
+    let report = wal.repair().unwrap();
+    assert_eq!(report.kept, 3);
+    assert_eq!(report.dropped, 1);
+    assert_eq!(report.dropped_ranges, [(path.clone(), len..len + 5)]);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+    assert_eq!(ids(&wal), [0, 1, 2]);
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 3);
+}
+
+#[test]
+fn an_intact_log_is_left_alone() {
+    let dir = TempDir::new();
+    let path = dir.join("intact.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append_marker("m").unwrap();
+    let before = std::fs::read(&path).unwrap();
+    let report = wal.repair().unwrap();
+    assert!(report.is_clean());
+    assert_eq!(report.kept, 2);
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    assert!(!dir.join("intact.wal.repair").exists());
+}
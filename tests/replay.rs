@@ -0,0 +1,47 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn replay_removes_what_was_processed_and_stops_at_an_error() {
+    let dir = TempDir::new();
+    let path = dir.join("recover.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+
+    // Entry 1 is skipped for now and entry 4 fails, stopping the replay.
+    let mut seen = Vec::new();
+    let err = wal
+        .replay(|entry| {
+            seen.push(entry.id);
+            match entry.id {
+                1 => Ok(false),
+                4 => Err(WalError::InvalidEntry("downstream is down".to_string())),
+                _ => Ok(true),
+            }
+        })
+        .unwrap_err();
+    assert!(matches!(err, WalError::InvalidEntry(_)));
+    assert_eq!(seen, [0, 1, 2, 3, 4]);
+    drop(wal);
+
+    // The next run sees only what was left.
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1, 4, 5]);
+    assert_eq!(wal.entry_breakdown().unwrap().markers, 1);
+
+    let mut seen = Vec::new();
+    wal.replay(|entry| {
+        seen.push(entry.id);
+        Ok(true)
+    })
+    .unwrap();
+    assert_eq!(seen, [1, 4, 5]);
+    assert!(wal.read_all().unwrap().is_empty());
+    assert_eq!(wal.next_id(), 7);
+}
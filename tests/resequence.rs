@@ -0,0 +1,52 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{ResequenceMap, WriteAheadLog};
+
+#[test]
+fn resequence_makes_ids_contiguous() {
+    let dir = TempDir::new();
+    let path = dir.join("sparse.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(true)
+        .max_segment_bytes(150)
+        .build()
+        .unwrap();
+    for i in 0..8u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("checkpoint").unwrap();
+    wal.mark_done(6).unwrap();
+    wal.mark_done(1).unwrap();
+    for id in [0, 1, 3, 4] {
+        wal.clear_id(id).unwrap();
+    }
+    assert!(wal.segments().unwrap().len() > 1);
+
+    let map = wal.resequence().unwrap();
+
+    // The status record for the deleted entry 1 is dropped.
+    let expected: ResequenceMap = [(2, 0), (5, 1), (6, 2), (7, 3), (8, 4), (9, 5)].into();
+    assert_eq!(map, expected);
+    assert_eq!(wal.segments().unwrap().len(), 1);
+    let entries = wal.read_all().unwrap();
+    let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 1, 2, 3]);
+    let data: Vec<u8> = entries.iter().map(|e| e.data[0]).collect();
+    assert_eq!(data, [2, 5, 6, 7]);
+    assert!(entries.iter().all(|e| e.is_checksum_valid()));
+    let pending: Vec<u64> = wal.pending().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(pending, [0, 1, 3]);
+
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 6);
+    drop(wal);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 7);
+    let raw: String = wal
+        .segments()
+        .unwrap()
+        .iter()
+        .map(|p| std::fs::read_to_string(p).unwrap())
+        .collect();
+    assert_eq!(raw.matches("\"target\":2").count(), 1);
+}
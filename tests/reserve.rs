@@ -0,0 +1,84 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn reserved_ids_are_known_before_the_payload() {
+    let dir = TempDir::new();
+    let path = dir.join("reserve.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    let first = wal.reserve();
+    let second = wal.reserve();
+    assert_eq!((first.id(), second.id()), (1, 2));
+    assert_eq!(wal.next_id(), 3);
+    assert!(wal.read_all().unwrap().iter().all(|e| e.id == 0));
+
+    let entry = wal.commit(first, b"b".to_vec()).unwrap();
+    assert_eq!(entry.id, 1);
+    wal.commit(second, b"c".to_vec()).unwrap();
+    drop(wal);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let data: Vec<Vec<u8>> = wal
+        .read_all()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.data)
+        .collect();
+    assert_eq!(data, [b"a", b"b", b"c"]);
+    assert_eq!(wal.get(1).unwrap().unwrap().data, b"b");
+}
+
+#[test]
+fn dropping_a_reservation_leaves_a_gap() {
+    let dir = TempDir::new();
+    let path = dir.join("abort.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    drop(wal.reserve());
+    assert_eq!(wal.append(b"after".to_vec()).unwrap().id, 1);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1]);
+    assert_eq!(wal.reserve().id(), 2);
+}
+
+#[test]
+fn a_later_record_overtakes_the_reservation() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("overtaken.wal")).unwrap();
+    let reserved = wal.reserve();
+    wal.append(b"later".to_vec()).unwrap();
+    assert!(matches!(
+        wal.commit(reserved, b"late".to_vec()),
+        Err(WalError::Overtaken { id: 0 })
+    ));
+
+    let mut other = WriteAheadLog::new(dir.join("other.wal")).unwrap();
+    let foreign = other.reserve();
+    let mut empty = WriteAheadLog::new(dir.join("empty.wal")).unwrap();
+    assert!(matches!(
+        empty.commit(foreign, b"x".to_vec()),
+        Err(WalError::InvalidEntry(_))
+    ));
+}
+
+#[test]
+fn commits_stay_ahead_of_deferred_records_under_group_commit() {
+    let dir = TempDir::new();
+    let path = dir.join("group.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .group_commit(true)
+        .build()
+        .unwrap();
+    let reserved = wal.reserve();
+    let deferred = wal.append_deferred(b"deferred".to_vec()).unwrap();
+    wal.commit(reserved, b"reserved".to_vec()).unwrap();
+    wal.flush().unwrap();
+    assert_eq!(deferred.id(), Some(1));
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 1]);
+}
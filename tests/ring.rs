@@ -0,0 +1,81 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{RingWal, WalError};
+
+fn data(entries: &[waly_rs::LogEntry]) -> Vec<u8> {
+    entries.iter().map(|e| e.data[0]).collect()
+}
+
+#[test]
+fn ring_keeps_the_most_recent_entries_in_order() {
+    let dir = TempDir::new();
+    let path = dir.join("events.ring");
+    let mut ring = RingWal::with_slot_size(&path, 4, 64).unwrap();
+    let size = std::fs::metadata(&path).unwrap().len();
+
+    for i in 0..3u8 {
+        ring.append(vec![i]).unwrap();
+    }
+    assert_eq!(data(&ring.read_all().unwrap()), [0, 1, 2]);
+
+    for i in 3..10u8 {
+        ring.append(vec![i]).unwrap();
+    }
+    let entries = ring.read_all().unwrap();
+    assert_eq!(data(&entries), [6, 7, 8, 9]);
+    let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [6, 7, 8, 9]);
+    assert_eq!(ring.len(), 4);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+
+    drop(ring);
+    let mut ring = RingWal::with_slot_size(&path, 4, 64).unwrap();
+    assert_eq!(ring.next_id(), 10);
+    ring.append(vec![10]).unwrap();
+    assert_eq!(data(&ring.read_all().unwrap()), [7, 8, 9, 10]);
+}
+
+#[test]
+fn ring_rejects_oversized_entries_and_other_dimensions() {
+    let dir = TempDir::new();
+    let path = dir.join("events.ring");
+    let mut ring = RingWal::with_slot_size(&path, 2, 32).unwrap();
+    assert!(ring.is_empty());
+    let err = ring.append(vec![0; 64]).unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)));
+    assert!(ring.read_all().unwrap().is_empty());
+    drop(ring);
+
+    let err = RingWal::with_slot_size(&path, 3, 32).unwrap_err();
+    assert!(matches!(err, WalError::ConfigMismatch(_)));
+    assert!(matches!(
+        RingWal::new(dir.join("zero.ring"), 0),
+        Err(WalError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn ring_dimensions_that_overflow_are_refused() {
+    let dir = TempDir::new();
+    let path = dir.join("huge.ring");
+    for (capacity, slot_bytes) in [(usize::MAX, 2), (usize::MAX / 2, 2)] {
+        assert!(matches!(
+            RingWal::with_slot_size(&path, capacity, slot_bytes),
+            Err(WalError::InvalidConfig(_))
+        ));
+    }
+    assert!(!path.exists());
+}
+
+#[test]
+fn second_open_of_a_ring_is_locked() {
+    let dir = TempDir::new();
+    let path = dir.join("events.ring");
+    let mut ring = RingWal::new(&path, 4).unwrap();
+    ring.append(b"a".to_vec()).unwrap();
+
+    assert!(matches!(RingWal::new(&path, 4), Err(WalError::Locked)));
+    drop(ring);
+    assert_eq!(RingWal::new(&path, 4).unwrap().len(), 1);
+}
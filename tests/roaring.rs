@@ -0,0 +1,49 @@
+#![cfg(feature = "roaring")]
+
+mod common;
+
+use common::TempDir;
+use roaring::{RoaringBitmap, RoaringTreemap};
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn selects_scattered_ids_from_large_log() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("large.wal"))
+        .format(Format::Binary)
+        .build()
+        .unwrap();
+    for i in 0..10_000u32 {
+        wal.append(i.to_le_bytes().to_vec()).unwrap();
+    }
+
+    let ids: RoaringBitmap = (0..10_000)
+        .filter(|id| id % 997 == 3)
+        .chain([20_000])
+        .collect();
+    let entries = wal.read_ids(&ids).unwrap();
+
+    let found: Vec<u32> = entries.iter().map(|e| e.id as u32).collect();
+    assert_eq!(
+        found,
+        ids.iter().filter(|&id| id < 10_000).collect::<Vec<_>>()
+    );
+    for entry in &entries {
+        assert_eq!(entry.data, (entry.id as u32).to_le_bytes());
+    }
+}
+
+#[test]
+fn treemaps_select_like_bitmaps() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("ids.wal")).unwrap();
+    for i in 0..20u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.clear_id(4).unwrap();
+
+    let ids: RoaringTreemap = [1, 4, 7, 19, u64::MAX].into_iter().collect();
+    let found: Vec<u64> = wal.read_ids(&ids).unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(found, [1, 7, 19]);
+    assert!(wal.read_ids(&RoaringBitmap::new()).unwrap().is_empty());
+}
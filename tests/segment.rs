@@ -0,0 +1,248 @@
+mod common;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use common::{TempDir, HEADER_LEN};
+use waly_rs::{WalError, WriteAheadLog};
+
+#[test]
+fn rotation_seals_segments_and_reads_stitch_them() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(100)
+        .build()
+        .unwrap();
+
+    for i in 0..10u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+
+    let segments = wal.segments().unwrap();
+    assert!(segments.len() > 1);
+    assert_eq!(segments.last(), Some(&path));
+    assert!(dir.join("logs.wal.000001").is_file());
+
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, (0..10).collect::<Vec<_>>());
+
+    drop(wal);
+    let wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(100)
+        .build()
+        .unwrap();
+    assert_eq!(wal.next_id(), 10);
+}
+
+#[test]
+fn segments_stitch_in_id_order_for_every_reader() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let mut wal = WriteAheadLog::with_max_segment_bytes(&path, 80).unwrap();
+    for i in 0..12u8 {
+        wal.append(vec![i; 8]).unwrap();
+    }
+    let segments = wal.segments().unwrap();
+    assert!(segments.len() > 2);
+    assert!(segments[..segments.len() - 1]
+        .iter()
+        .all(|p| p.to_str().unwrap().len() == path.to_str().unwrap().len() + 7));
+
+    let streamed: Vec<u64> = wal.iter().unwrap().map(|e| e.unwrap().id).collect();
+    assert_eq!(streamed, (0..12).collect::<Vec<_>>());
+    assert_eq!(wal.read_all().unwrap().len(), 12);
+
+    // Only the highest segment holds the newest IDs; recovery must see it.
+    drop(wal);
+    std::fs::write(&path, b"").unwrap();
+    let wal = WriteAheadLog::with_max_segment_bytes(&path, 80).unwrap();
+    let last_sealed = wal.read_all().unwrap().last().unwrap().id;
+    assert_eq!(wal.next_id(), last_sealed + 1);
+}
+
+#[test]
+fn clear_id_reaches_into_sealed_segments() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(100)
+        .build()
+        .unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+
+    wal.clear_id(0).unwrap();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+
+    wal.clear().unwrap();
+    assert!(wal.read_all().unwrap().is_empty());
+    assert_eq!(wal.segments().unwrap(), vec![path]);
+}
+
+#[test]
+fn compact_replaces_the_file_instead_of_truncating_it() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    // A hard link keeps the old file reachable: an in-place rewrite would
+    // change it too, a rename over the path leaves it alone.
+    let old = dir.join("old.wal");
+    std::fs::hard_link(&path, &old).unwrap();
+
+    wal.clear_id(1).unwrap();
+    wal.compact().unwrap();
+    // The old file still holds all three entries and the tombstone.
+    assert_eq!(std::fs::read_to_string(&old).unwrap().lines().count(), 4);
+    std::fs::remove_file(&old).unwrap();
+    // The tombstone took ID 3.
+    assert_eq!(wal.append(b"next".to_vec()).unwrap().id, 4);
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 2, 4]);
+
+    std::fs::hard_link(&path, &old).unwrap();
+    wal.clear().unwrap();
+    assert_eq!(
+        WriteAheadLog::new(&old).unwrap().read_all().unwrap().len(),
+        3
+    );
+    assert_eq!(wal.append(b"after".to_vec()).unwrap().id, 5);
+    assert_eq!(wal.read_all().unwrap().len(), 1);
+    assert!(!dir.join("logs.wal.rewrite").exists());
+}
+
+#[test]
+fn max_segments_evicts_oldest_and_fires_callback() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let evicted: Arc<Mutex<Vec<PathBuf>>> = Arc::default();
+    let seen = Arc::clone(&evicted);
+
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(60)
+        .max_segments(3)
+        .on_segment_evicted(move |p| {
+            assert!(p.is_file(), "callback runs before deletion");
+            seen.lock().unwrap().push(p.to_path_buf());
+        })
+        .build()
+        .unwrap();
+
+    // Each record is larger than half the limit, so every append after the
+    // first seals the previous segment.
+    for i in 0..5u8 {
+        wal.append(vec![i; 8]).unwrap();
+    }
+
+    let segments = wal.segments().unwrap();
+    assert_eq!(segments.len(), 3);
+    assert_eq!(
+        *evicted.lock().unwrap(),
+        vec![dir.join("logs.wal.000001"), dir.join("logs.wal.000002")]
+    );
+    assert!(!dir.join("logs.wal.000001").exists());
+
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![2, 3, 4]);
+}
+
+#[test]
+fn zero_max_segments_is_rejected() {
+    let dir = TempDir::new();
+    let err = WriteAheadLog::builder(dir.join("logs.wal"))
+        .max_segments(0)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)));
+}
+
+#[test]
+fn rotate_now_archives_active_file_and_continues_ids() {
+    let dir = TempDir::new();
+    let path = dir.join("ship.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+
+    let archive = wal.rotate_now().unwrap();
+    assert!(archive.to_string_lossy().ends_with(".archive"));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), HEADER_LEN);
+    assert!(wal.read_all().unwrap().is_empty());
+    assert_eq!(wal.segments().unwrap(), vec![path.clone()]);
+
+    let archived = WriteAheadLog::new(&archive).unwrap().read_all().unwrap();
+    assert_eq!(
+        archived
+            .iter()
+            .map(|e| e.data.as_slice())
+            .collect::<Vec<_>>(),
+        [b"a", b"b"]
+    );
+
+    assert_eq!(wal.append(b"c".to_vec()).unwrap().id, 2);
+    let second = wal.rotate_now().unwrap();
+    assert_ne!(second, archive);
+
+    // The live file is empty, but IDs carry on after reopening.
+    drop(wal);
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 3);
+    assert_eq!(wal.append(b"d".to_vec()).unwrap().id, 3);
+}
+
+#[test]
+fn clear_keeps_ids_counting_up_after_reopening() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    wal.clear().unwrap();
+    assert_eq!(wal.next_id(), 2);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 2);
+    assert_eq!(wal.append(b"c".to_vec()).unwrap().id, 2);
+}
+
+#[test]
+fn segments_numbered_past_six_digits_are_read_in_order() {
+    let dir = TempDir::new();
+    let path = dir.join("logs.wal");
+    let open = || {
+        WriteAheadLog::builder(&path)
+            .max_segment_bytes(100)
+            .build()
+            .unwrap()
+    };
+    let mut wal = open();
+    for i in 0..10u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+    let sealed = wal.segments().unwrap().len() - 1;
+    drop(wal);
+    // Renumber the sealed segments as if a million had come before them.
+    for seq in (1..=sealed).rev() {
+        std::fs::rename(
+            dir.join(format!("logs.wal.{seq:06}")),
+            dir.join(format!("logs.wal.{}", 999_998 + seq)),
+        )
+        .unwrap();
+    }
+
+    let mut wal = open();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, (0..10).collect::<Vec<_>>());
+    for i in 10..20u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+    assert!(dir.join(format!("logs.wal.{}", 999_999 + sealed)).is_file());
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, (0..20).collect::<Vec<_>>());
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use std::collections::HashSet;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn sequence_keeps_increasing_across_reopen() {
+    let dir = TempDir::new();
+    let path = dir.join("seq.wal");
+    let mut seen = HashSet::new();
+    let mut last = None;
+    for _ in 0..3 {
+        let mut wal = WriteAheadLog::new(&path).unwrap();
+        for _ in 0..5 {
+            let value = wal.next_sequence().unwrap();
+            assert!(last.is_none_or(|last| value > last));
+            assert!(seen.insert(value));
+            last = Some(value);
+            wal.append(value.to_le_bytes().to_vec()).unwrap();
+        }
+    }
+    assert_eq!(last, Some(14));
+}
+
+#[test]
+fn corrupt_sequence_file_is_an_error() {
+    let dir = TempDir::new();
+    let path = dir.join("seq.wal");
+    let wal = WriteAheadLog::new(&path).unwrap();
+    std::fs::write(dir.join("seq.wal.sequence"), "garbage").unwrap();
+    assert!(wal.next_sequence().is_err());
+}
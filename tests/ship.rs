@@ -0,0 +1,61 @@
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn ids(lines: &[String]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| line.split(',').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn appended_entries_arrive_and_shipping_resumes_after_disconnect() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("ship.wal")).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (received, lines) = mpsc::channel();
+    thread::spawn(move || {
+        // First connection: read two records, then hang up.
+        let (conn, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(conn);
+        for _ in 0..2 {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            received.send(line).unwrap();
+        }
+        drop(reader);
+        received.send("closed".to_string()).unwrap();
+        // Second connection: read whatever arrives.
+        let (conn, _) = listener.accept().unwrap();
+        for line in BufReader::new(conn).lines() {
+            received.send(line.unwrap() + "\n").unwrap();
+        }
+    });
+
+    wal.append(b"a".to_vec()).unwrap();
+    let shipper = wal.ship_to(addr).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+
+    let first: Vec<String> = (0..2)
+        .map(|_| lines.recv_timeout(TIMEOUT).unwrap())
+        .collect();
+    assert_eq!(ids(&first), ["{\"id\":0", "{\"id\":1"]);
+    assert_eq!(lines.recv_timeout(TIMEOUT).unwrap(), "closed");
+
+    wal.append(b"c".to_vec()).unwrap();
+    let resumed = lines.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(ids(&[resumed]), ["{\"id\":2"]);
+    shipper.stop();
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::TempDir;
+use waly_rs::{LogEntry, Sink, SinkErrorPolicy, WalError, WriteAheadLog};
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<LogEntry>>>);
+
+impl Sink for Recorder {
+    fn write_entry(&mut self, entry: &LogEntry) -> waly_rs::Result<()> {
+        self.0.lock().unwrap().push(entry.clone());
+        Ok(())
+    }
+}
+
+struct Broken;
+
+impl Sink for Broken {
+    fn write_entry(&mut self, _: &LogEntry) -> waly_rs::Result<()> {
+        Err(WalError::Serialization("queue is down".to_string()))
+    }
+}
+
+#[test]
+fn sinks_receive_every_append_in_order() {
+    let dir = TempDir::new();
+    let first = Recorder::default();
+    let second = Recorder::default();
+    let mut wal = WriteAheadLog::with_sinks(
+        dir.join("tee.wal"),
+        vec![Box::new(first.clone()), Box::new(second.clone())],
+    )
+    .unwrap();
+    wal.append(b"one".to_vec()).unwrap();
+    wal.append_batch(vec![b"two".to_vec(), b"three".to_vec()])
+        .unwrap();
+    wal.append_marker("checkpoint").unwrap();
+    wal.clear_id(0).unwrap();
+
+    let ids: Vec<u64> = first.0.lock().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 1, 2, 3, 4]);
+    assert_eq!(first.0.lock().unwrap()[1].data, b"two");
+    assert_eq!(*first.0.lock().unwrap(), *second.0.lock().unwrap());
+}
+
+#[test]
+fn group_commit_forwards_on_flush() {
+    let dir = TempDir::new();
+    let sink = Recorder::default();
+    let mut wal = WriteAheadLog::builder(dir.join("group.wal"))
+        .group_commit(true)
+        .sinks(vec![Box::new(sink.clone())])
+        .build()
+        .unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+    assert!(sink.0.lock().unwrap().is_empty());
+
+    wal.flush().unwrap();
+    assert_eq!(*sink.0.lock().unwrap(), wal.read_all().unwrap());
+}
+
+#[test]
+fn sink_error_policy_decides_whether_appends_fail() {
+    let dir = TempDir::new();
+    let path = dir.join("broken.wal");
+    let recorder = Recorder::default();
+    let mut wal = WriteAheadLog::builder(&path)
+        .sinks(vec![Box::new(Broken), Box::new(recorder.clone())])
+        .build()
+        .unwrap();
+    let err = wal.append(b"x".to_vec()).unwrap_err();
+    assert!(matches!(err, WalError::Serialization(_)));
+    // The entry is in the log and later sinks still got it.
+    assert_eq!(wal.read_all().unwrap().len(), 1);
+    assert_eq!(recorder.0.lock().unwrap().len(), 1);
+    drop(wal);
+
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&failures);
+    let callback = SinkErrorPolicy::callback(move |index, entry: &LogEntry, err: &WalError| {
+        seen.lock()
+            .unwrap()
+            .push((index, entry.id, err.to_string()));
+    });
+    for policy in [SinkErrorPolicy::Ignore, callback] {
+        let mut wal = WriteAheadLog::builder(&path)
+            .sinks(vec![Box::new(recorder.clone()), Box::new(Broken)])
+            .sink_error_policy(policy)
+            .build()
+            .unwrap();
+        wal.append(b"y".to_vec()).unwrap();
+    }
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, 1);
+    assert_eq!(failures[0].1, 2);
+    assert!(failures[0].2.contains("queue is down"));
+}
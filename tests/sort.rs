@@ -0,0 +1,139 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::TempDir;
+use waly_rs::{LogEntry, WriteAheadLog};
+
+/// Writes JSON data records with the given `(id, timestamp)` pairs.
+fn write_log(path: &std::path::Path, records: &[(u64, u64)]) {
+    let lines: String = records
+        .iter()
+        .map(|(id, ts)| {
+            format!(
+                "{{\"id\":{id},\"timestamp\":{ts},\"data\":[{}]}}\n",
+                id % 256
+            )
+        })
+        .collect();
+    std::fs::write(path, lines).unwrap();
+}
+
+fn keys(entries: &[LogEntry]) -> Vec<(u64, u64)> {
+    entries.iter().map(|e| (e.timestamp, e.id)).collect()
+}
+
+#[test]
+fn entries_come_back_in_time_order() {
+    let dir = TempDir::new();
+    let path = dir.join("skewed.wal");
+    write_log(&path, &[(0, 100), (1, 90), (2, 110), (3, 90), (4, 95)]);
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append_marker("ignored").unwrap();
+
+    let sorted = wal.iter_time_sorted().unwrap();
+    assert_eq!(
+        keys(&sorted),
+        [(90, 1), (90, 3), (95, 4), (100, 0), (110, 2)]
+    );
+}
+
+#[test]
+fn large_input_spills_and_merges() {
+    let dir = TempDir::new();
+    let path = dir.join("large.wal");
+    let records: Vec<(u64, u64)> = (0..5000).map(|id| (id, id * 7919 % 1000)).collect();
+    write_log(&path, &records);
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    // A budget this small spills a run every few dozen entries.
+    let sorted = wal.iter_time_sorted_within(4096).unwrap();
+    let mut expected: Vec<(u64, u64)> = records.iter().map(|&(id, ts)| (ts, id)).collect();
+    expected.sort_unstable();
+    assert_eq!(keys(&sorted), expected);
+    assert!(sorted.iter().all(|e| e.data == [(e.id % 256) as u8]));
+    assert_eq!(sorted, wal.iter_time_sorted().unwrap());
+
+    let leftovers = std::fs::read_dir(dir.path())
+        .unwrap()
+        .filter(|e| {
+            e.as_ref()
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .contains(".sort-")
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[test]
+fn sort_by_id_repairs_a_shuffled_log() {
+    let dir = TempDir::new();
+    let path = dir.join("shuffled.wal");
+    // Timestamps follow IDs, as they would have before the bad merge.
+    let ids = [3, 0, 7, 1, 5, 2, 9, 4, 8, 6];
+    let records: Vec<(u64, u64)> = ids.iter().map(|&id| (id, 100 + id)).collect();
+    write_log(&path, &records);
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    // Lookups that stop once they pass the wanted ID miss entries.
+    assert_eq!(wal.get(2).unwrap(), None);
+
+    // Entries 0, 1, 5, 2, 4, 8 and 6 each come after a larger ID.
+    assert_eq!(wal.sort_by_id_within(256).unwrap(), 7);
+    let sorted: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+    assert_eq!(wal.get(2).unwrap().unwrap().data, [2]);
+    let wanted: BTreeSet<u64> = [1, 4, 6].into_iter().collect();
+    let found: Vec<u64> = wal
+        .read_ids(&wanted)
+        .unwrap()
+        .iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(found, [1, 4, 6]);
+    let range: Vec<u64> = wal
+        .read_range(102, 105)
+        .unwrap()
+        .iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(range, [2, 3, 4, 5]);
+
+    // An ordered log is left alone.
+    let before = std::fs::read(&path).unwrap();
+    assert_eq!(wal.sort_by_id().unwrap(), 0);
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn sort_by_id_is_stable_for_duplicates() {
+    let dir = TempDir::new();
+    let path = dir.join("duplicates.wal");
+    let lines = [
+        r#"{"id":2,"timestamp":1,"data":[20]}"#,
+        r#"{"id":1,"timestamp":1,"data":[10]}"#,
+        r#"{"id":2,"timestamp":1,"data":[21]}"#,
+        r#"{"id":0,"timestamp":1,"data":[0]}"#,
+        r#"{"id":2,"timestamp":1,"data":[22]}"#,
+    ];
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    assert_eq!(wal.sort_by_id_within(64).unwrap(), 2);
+    let data: Vec<u8> = wal.read_all().unwrap().iter().map(|e| e.data[0]).collect();
+    assert_eq!(data, [0, 10, 20, 21, 22]);
+}
+
+#[test]
+fn read_all_sorted_orders_by_id() {
+    let dir = TempDir::new();
+    let path = dir.join("shuffled.wal");
+    write_log(&path, &[(2, 100), (0, 100), (1, 100)]);
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    let ids = |entries: Vec<LogEntry>| entries.iter().map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(ids(wal.read_all().unwrap()), [2, 0, 1]);
+    assert_eq!(ids(wal.read_all_sorted().unwrap()), [0, 1, 2]);
+}
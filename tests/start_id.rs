@@ -0,0 +1,53 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+const SHARD: u64 = 3_000_000_000;
+
+fn open(path: &std::path::Path) -> WriteAheadLog {
+    WriteAheadLog::builder(path)
+        .start_id(SHARD)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn an_empty_log_starts_at_the_given_id() {
+    let dir = TempDir::new();
+    let path = dir.join("shard.wal");
+    let mut wal = open(&path);
+    assert_eq!(wal.next_id(), SHARD);
+    assert_eq!(wal.append(b"a".to_vec()).unwrap().id, SHARD);
+    assert_eq!(wal.append(b"b".to_vec()).unwrap().id, SHARD + 1);
+    drop(wal);
+
+    // Reopening continues from the records, with or without the option.
+    assert_eq!(open(&path).next_id(), SHARD + 2);
+    assert_eq!(WriteAheadLog::new(&path).unwrap().next_id(), SHARD + 2);
+}
+
+#[test]
+fn a_log_with_records_ignores_the_start_id() {
+    let dir = TempDir::new();
+    let path = dir.join("existing.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    drop(wal);
+
+    let mut wal = open(&path);
+    assert_eq!(wal.append(b"b".to_vec()).unwrap().id, 1);
+}
+
+#[test]
+fn resequence_renumbers_from_the_start_id() {
+    let dir = TempDir::new();
+    let mut wal = open(&dir.join("reseq.wal"));
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.clear_id(SHARD + 1).unwrap();
+    let map = wal.resequence().unwrap();
+    assert_eq!(map[&(SHARD + 2)], SHARD + 1);
+    assert_eq!(wal.next_id(), SHARD + 2);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn rewrites_show_up_as_amplification() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("amp.wal"))
+        .format(Format::Binary)
+        .checksums(false)
+        .build()
+        .unwrap();
+    assert_eq!(wal.write_amplification().ratio(), None);
+
+    // Binary records with a 6-byte payload are 30 bytes each.
+    for _ in 0..4 {
+        wal.append(b"abcdef".to_vec()).unwrap();
+    }
+    let stats = wal.write_amplification();
+    assert_eq!(stats.logical_bytes, 120);
+    assert_eq!(stats.physical_bytes, 120);
+    assert_eq!(stats.ratio(), Some(1.0));
+
+    // Deletes append tombstones; compaction rewrites the 2 survivors once.
+    wal.clear_id(0).unwrap();
+    wal.clear_id(1).unwrap();
+    wal.compact().unwrap();
+    // Binary tombstones are 45 bytes each.
+    let stats = wal.write_amplification();
+    assert_eq!(stats.logical_bytes, 120 + 90);
+    assert_eq!(stats.physical_bytes, 120 + 90 + 60);
+    assert_eq!(stats.ratio(), Some(270.0 / 210.0));
+}
+
+#[test]
+fn stats_split_the_file_into_live_and_dead_records() {
+    let dir = TempDir::new();
+    let path = dir.join("frag.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for _ in 0..4 {
+        wal.append(vec![b'x'; 100]).unwrap();
+    }
+    wal.append_marker("m").unwrap();
+    let stats = wal.stats().unwrap();
+    assert_eq!(stats.file_bytes, std::fs::metadata(&path).unwrap().len());
+    assert_eq!(stats.live_entries, 4);
+    assert_eq!((stats.dead_entries, stats.dead_bytes), (0, 0));
+    assert_eq!(stats.fragmentation(), 0.0);
+    assert!(stats.live_bytes < stats.file_bytes);
+
+    wal.clear_id(0).unwrap();
+    wal.clear_id(1).unwrap();
+    let stats = wal.stats().unwrap();
+    assert_eq!(stats.live_entries, 2);
+    assert_eq!(stats.dead_entries, 4);
+    assert!(stats.fragmentation() > 0.4, "{stats:?}");
+
+    wal.compact().unwrap();
+    let stats = wal.stats().unwrap();
+    assert_eq!(stats.live_entries, 2);
+    assert_eq!(stats.dead_bytes, 0);
+    assert_eq!(stats.file_bytes, std::fs::metadata(&path).unwrap().len());
+}
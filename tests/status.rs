@@ -0,0 +1,39 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+fn ids(entries: &[waly_rs::LogEntry]) -> Vec<u64> {
+    entries.iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn done_entries_leave_pending_across_reopen() {
+    for format in [Format::Json, Format::Binary] {
+        let dir = TempDir::new();
+        let path = dir.join("retry.wal");
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .format(format)
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+        for i in 0..4u8 {
+            wal.append(vec![i]).unwrap();
+        }
+
+        wal.mark_done(1).unwrap();
+        wal.mark_failed(2).unwrap();
+        wal.mark_failed(3).unwrap();
+        wal.mark_done(3).unwrap();
+        assert_eq!(ids(&wal.pending().unwrap()), [0, 2]);
+        assert_eq!(wal.read_all().unwrap().len(), 4);
+
+        drop(wal);
+        let mut wal = open();
+        assert_eq!(ids(&wal.pending().unwrap()), [0, 2]);
+        wal.mark_done(2).unwrap();
+        assert_eq!(ids(&wal.pending().unwrap()), [0]);
+    }
+}
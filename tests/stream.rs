@@ -0,0 +1,65 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WalError, WriteAheadLog};
+
+#[test]
+fn streams_have_their_own_entries_and_ids() {
+    for format in [Format::Json, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("shared.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+
+        wal.append(b"main".to_vec()).unwrap();
+        for i in 0..3u8 {
+            wal.stream(1).append(vec![b'a', i]).unwrap();
+            wal.stream(2).append(vec![b'b', i]).unwrap();
+        }
+        wal.append(b"main again".to_vec()).unwrap();
+
+        for (stream, tag) in [(1, b'a'), (2, b'b')] {
+            let entries = wal.stream(stream).read_all().unwrap();
+            assert_eq!(entries.iter().map(|e| e.id).collect::<Vec<_>>(), [0, 1, 2]);
+            assert!(entries
+                .iter()
+                .all(|e| e.stream == stream && e.data[0] == tag));
+        }
+        let main = wal.read_all().unwrap();
+        assert_eq!(main.iter().map(|e| e.id).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(wal.stream(0).read_all().unwrap(), main);
+
+        drop(wal);
+        let wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .build()
+            .unwrap();
+        assert_eq!(wal.next_id(), 2);
+        assert_eq!(wal.stream(1).append(b"more".to_vec()).unwrap().id, 3);
+    }
+}
+
+#[test]
+fn clear_id_only_touches_the_main_stream() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("shared.wal")).unwrap();
+    wal.append(b"main".to_vec()).unwrap();
+    wal.stream(7).append(b"side".to_vec()).unwrap();
+
+    wal.clear_id(0).unwrap();
+    assert!(wal.read_all().unwrap().is_empty());
+    assert_eq!(wal.stream(7).read_all().unwrap().len(), 1);
+}
+
+#[test]
+fn stream_zero_view_cannot_append() {
+    let dir = TempDir::new();
+    let wal = WriteAheadLog::new(dir.join("shared.wal")).unwrap();
+
+    assert!(matches!(
+        wal.stream(0).append(b"x".to_vec()),
+        Err(WalError::InvalidConfig(_))
+    ));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use std::time::Duration;
+
+use common::TempDir;
+use waly_rs::{SyncPolicy, WriteAheadLog};
+
+fn append_n(wal: &mut WriteAheadLog, n: u8) {
+    for i in 0..n {
+        wal.append(vec![i]).unwrap();
+    }
+}
+
+#[test]
+fn always_syncs_every_append() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("app.wal"))
+        .sync_policy(SyncPolicy::Always)
+        .build()
+        .unwrap();
+    append_n(&mut wal, 2);
+    assert_eq!(wal.durable_id(), 2);
+}
+
+#[test]
+fn every_n_syncs_in_groups() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("app.wal"))
+        .sync_policy(SyncPolicy::EveryN(3))
+        .build()
+        .unwrap();
+    append_n(&mut wal, 2);
+    assert_eq!(wal.durable_id(), 0);
+    append_n(&mut wal, 1);
+    assert_eq!(wal.durable_id(), 3);
+    wal.append_batch_chunked(vec![vec![0]; 4], 4, |_, _| {})
+        .unwrap();
+    assert_eq!(wal.durable_id(), 7);
+    append_n(&mut wal, 2);
+    assert_eq!(wal.durable_id(), 7);
+}
+
+#[test]
+fn never_leaves_syncing_to_sync() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("app.wal")).unwrap();
+    assert_eq!(wal.sync_policy(), SyncPolicy::Never);
+    append_n(&mut wal, 3);
+    assert_eq!(wal.durable_id(), 0);
+    wal.sync().unwrap();
+    assert_eq!(wal.durable_id(), 3);
+}
+
+#[test]
+fn interval_syncs_once_elapsed() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("app.wal")).unwrap();
+    wal.set_sync_policy(SyncPolicy::Interval(Duration::from_secs(3600)));
+    append_n(&mut wal, 2);
+    assert_eq!(wal.durable_id(), 0);
+    wal.set_sync_policy(SyncPolicy::Interval(Duration::ZERO));
+    append_n(&mut wal, 1);
+    assert_eq!(wal.durable_id(), 3);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+fn tags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn read_filtered_matches_on_tags() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("tags.wal");
+        let mut wal = WriteAheadLog::with_format(&path, format).unwrap();
+        wal.append_with_tags(
+            b"a".to_vec(),
+            tags(&[("level", "error"), ("source", "agent1")]),
+        )
+        .unwrap();
+        wal.append(b"b".to_vec()).unwrap();
+        let c = wal
+            .append_with_tags(b"c".to_vec(), tags(&[("level", "info")]))
+            .unwrap();
+        wal.append_with_tags(b"d".to_vec(), tags(&[("level", "error")]))
+            .unwrap();
+        wal.clear_id(3).unwrap();
+        assert_eq!(c.tags, tags(&[("level", "info")]));
+        drop(wal);
+
+        let wal = WriteAheadLog::with_format(&path, format).unwrap();
+        let errors = wal
+            .read_filtered(|tags| tags.get("level").is_some_and(|level| level == "error"))
+            .unwrap();
+        assert_eq!(errors.len(), 1, "{format:?}");
+        assert_eq!(errors[0].data, b"a");
+        assert_eq!(errors[0].tags["source"], "agent1");
+
+        let untagged = wal.read_filtered(BTreeMap::is_empty).unwrap();
+        assert_eq!(untagged.len(), 1, "{format:?}");
+        assert_eq!(untagged[0].data, b"b");
+    }
+}
+
+#[test]
+fn plain_appends_write_no_tags() {
+    let dir = TempDir::new();
+    let path = dir.join("plain.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"x".to_vec()).unwrap();
+    wal.append_with_tags(b"y".to_vec(), BTreeMap::new())
+        .unwrap();
+    drop(wal);
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(!text.contains("tags"));
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, WriteAheadLog};
+
+#[test]
+fn tail_matches_the_end_of_read_all() {
+    for format in [
+        Format::Json,
+        Format::JsonBase64,
+        Format::Binary,
+        Format::CompactBinary,
+    ] {
+        let dir = TempDir::new();
+        let mut wal = WriteAheadLog::builder(dir.join("tail.wal"))
+            .format(format)
+            .max_segment_bytes(16 * 1024)
+            .build()
+            .unwrap();
+        for i in 0..40u32 {
+            // Some records are longer than the chunks JSON is read in.
+            let len = if i % 7 == 0 { 5_000 } else { 10 };
+            wal.append(vec![i as u8; len]).unwrap();
+            if i % 5 == 0 {
+                wal.append_marker("mark").unwrap();
+            }
+        }
+        wal.stream(1).append(b"other".to_vec()).unwrap();
+        wal.clear_id(wal.next_id() - 3).unwrap();
+        assert!(wal.segments().unwrap().len() > 1);
+
+        let all = wal.read_all().unwrap();
+        for n in [0, 1, 3, 12, all.len(), all.len() + 5] {
+            let expected = &all[all.len().saturating_sub(n)..];
+            assert_eq!(wal.tail(n).unwrap(), expected, "{format:?}, n = {n}");
+        }
+    }
+}
+
+#[test]
+fn tail_of_an_empty_log_is_empty() {
+    let dir = TempDir::new();
+    let wal = WriteAheadLog::new(dir.join("empty.wal")).unwrap();
+    assert!(wal.tail(5).unwrap().is_empty());
+}
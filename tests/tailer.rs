@@ -0,0 +1,73 @@
+mod common;
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn handler_receives_entries_appended_while_tailing() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("tail.wal")).unwrap();
+    wal.append(b"old".to_vec()).unwrap();
+
+    let (handled, received) = mpsc::channel();
+    let tailer = wal
+        .tail_with(move |entry| {
+            handled.send(entry.data.clone()).unwrap();
+            Ok(())
+        })
+        .unwrap();
+    for i in 0..20u8 {
+        wal.append(vec![i]).unwrap();
+        if i == 10 {
+            wal.append_marker("halfway").unwrap();
+        }
+    }
+
+    let data: Vec<Vec<u8>> = (0..20)
+        .map(|_| received.recv_timeout(TIMEOUT).unwrap())
+        .collect();
+    assert_eq!(data, (0..20u8).map(|i| vec![i]).collect::<Vec<_>>());
+    let started = std::time::Instant::now();
+    while tailer.handled_up_to() < wal.next_id() {
+        assert!(started.elapsed() < TIMEOUT);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    tailer.stop().unwrap();
+    wal.append(b"after".to_vec()).unwrap();
+    assert!(received.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+#[test]
+fn tailing_from_the_start_and_handler_errors() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("from.wal")).unwrap();
+    for i in 0..3u8 {
+        wal.append(vec![i]).unwrap();
+    }
+
+    let (handled, received) = mpsc::channel();
+    let tailer = wal
+        .tail_with_from(0, move |entry| {
+            handled.send(entry.id).unwrap();
+            if entry.id == 1 {
+                return Err(WalError::InvalidEntry("refused".to_string()));
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(received.recv_timeout(TIMEOUT).unwrap(), 0);
+    assert_eq!(received.recv_timeout(TIMEOUT).unwrap(), 1);
+    let started = std::time::Instant::now();
+    while !tailer.is_finished() {
+        assert!(started.elapsed() < TIMEOUT);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    // The failed entry was not handled.
+    assert_eq!(tailer.handled_up_to(), 1);
+    assert!(matches!(tailer.stop(), Err(WalError::InvalidEntry(_))));
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn take_next_removes_entry_before_returning_it() {
+    let dir = TempDir::new();
+    let path = dir.join("jobs.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(120)
+        .build()
+        .unwrap();
+    for job in ["a", "b", "c", "d"] {
+        wal.append(job.as_bytes().to_vec()).unwrap();
+    }
+    wal.append_marker("start").unwrap();
+    assert!(wal.segments().unwrap().len() > 1);
+
+    let taken = wal.take_next().unwrap().unwrap();
+    assert_eq!((taken.id, taken.data.as_slice()), (0, &b"a"[..]));
+    // Another handle sees the removal straight away.
+    let other = WriteAheadLog::builder(&path).lock(false).build().unwrap();
+    let ids: Vec<u64> = other.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+    drop(other);
+
+    assert_eq!(wal.take_next().unwrap().unwrap().id, 1);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [2, 3]);
+    assert_eq!(wal.take_next().unwrap().unwrap().id, 2);
+    assert_eq!(wal.take_next().unwrap().unwrap().id, 3);
+    assert!(wal.take_next().unwrap().is_none());
+    wal.compact().unwrap();
+    let raw: String = wal
+        .segments()
+        .unwrap()
+        .iter()
+        .map(|p| std::fs::read_to_string(p).unwrap())
+        .collect();
+    assert_eq!(raw.matches("\"id\"").count(), 1);
+    assert!(raw.contains("\"kind\":\"marker\""));
+}
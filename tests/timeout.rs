@@ -0,0 +1,71 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TempDir;
+use waly_rs::{DedupPolicy, WalError, WriteAheadLog};
+
+#[test]
+fn read_all_timeout_returns_entries_in_time() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("quick.wal")).unwrap();
+    let entry = wal.append(b"fast".to_vec()).unwrap();
+    assert_eq!(
+        wal.read_all_timeout(Duration::from_secs(5)).unwrap(),
+        vec![entry]
+    );
+}
+
+#[test]
+fn read_all_timeout_reads_as_read_all_does() {
+    let dir = TempDir::new();
+    let path = dir.join("same.wal");
+    std::fs::write(
+        &path,
+        concat!(
+            r#"{"id":0,"timestamp":1,"data":[1],"idempotency_key":"a"}"#,
+            "\n",
+            r#"{"id":1,"timestamp":1,"data":[2],"idempotency_key":"a"}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+    let mut wal = WriteAheadLog::builder(&path)
+        .write_buffer(64 * 1024)
+        .dedup_on_read(DedupPolicy::KeepLast)
+        .build()
+        .unwrap();
+    // Still in the write buffer, not yet in the file.
+    wal.append(b"buffered".to_vec()).unwrap();
+    assert!(wal.buffered_bytes().unwrap() > 0);
+
+    let entries = wal.read_all_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(entries, wal.read_all().unwrap());
+    let ids: Vec<_> = entries.iter().map(|e| e.id).collect();
+    assert_eq!(ids, [1, 2]);
+}
+
+/// A FIFO posing as a sealed segment blocks the reader in `open` until a
+/// writer shows up, standing in for storage that has stalled.
+#[cfg(unix)]
+#[test]
+fn read_all_timeout_gives_up_on_stalled_storage() {
+    let dir = TempDir::new();
+    let path = dir.join("slow.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append(b"stuck".to_vec()).unwrap();
+    let fifo = dir.join("slow.wal.000001");
+    let status = std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let started = Instant::now();
+    let result = wal.read_all_timeout(Duration::from_millis(100));
+    assert!(matches!(result, Err(WalError::Timeout)), "{result:?}");
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // Release the abandoned worker.
+    drop(std::fs::OpenOptions::new().write(true).open(&fifo).unwrap());
+}
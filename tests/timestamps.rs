@@ -0,0 +1,131 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+/// Writes JSON records with the given `(id, timestamp)` pairs.
+fn write_log(path: &std::path::Path, records: &[(u64, u64)]) {
+    let lines: String = records
+        .iter()
+        .map(|(id, ts)| format!("{{\"id\":{id},\"timestamp\":{ts},\"data\":[]}}\n"))
+        .collect();
+    std::fs::write(path, lines).unwrap();
+}
+
+#[test]
+fn distinct_timestamps_are_deduplicated_and_sorted() {
+    let dir = TempDir::new();
+    let path = dir.join("times.wal");
+    write_log(
+        &path,
+        &[(0, 100), (1, 100), (2, 105), (3, 105), (4, 105), (5, 200)],
+    );
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append_marker("ignored").unwrap();
+
+    assert_eq!(wal.distinct_timestamps().unwrap(), vec![100, 105, 200]);
+}
+
+#[test]
+fn distinct_timestamps_survive_a_clock_step_back() {
+    let dir = TempDir::new();
+    let path = dir.join("times.wal");
+    write_log(&path, &[(0, 100), (1, 150), (2, 120), (3, 150)]);
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    assert_eq!(wal.distinct_timestamps().unwrap(), vec![100, 120, 150]);
+}
+
+#[test]
+fn timestamp_regressions_report_backward_steps() {
+    let dir = TempDir::new();
+    let path = dir.join("clock.wal");
+    write_log(
+        &path,
+        &[
+            (0, 100),
+            (1, 101),
+            (2, 90),
+            (3, 90),
+            (4, 95),
+            (5, 80),
+            (6, 200),
+        ],
+    );
+    let wal = WriteAheadLog::new(&path).unwrap();
+
+    assert_eq!(
+        wal.timestamp_regressions().unwrap(),
+        vec![(2, 101), (5, 95)]
+    );
+}
+
+#[test]
+fn timestamp_regressions_empty_for_steady_clock() {
+    let dir = TempDir::new();
+    let path = dir.join("steady.wal");
+    write_log(&path, &[(0, 100), (1, 100), (2, 101)]);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert!(wal.timestamp_regressions().unwrap().is_empty());
+}
+
+#[test]
+fn read_range_is_inclusive_at_both_ends() {
+    let dir = TempDir::new();
+    let path = dir.join("range.wal");
+    write_log(&path, &[(0, 100), (1, 105), (2, 110), (3, 110), (4, 120)]);
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    wal.append_marker("ignored").unwrap();
+
+    let ids = |from, to| -> Vec<u64> {
+        wal.read_range(from, to)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect()
+    };
+    assert_eq!(ids(105, 110), [1, 2, 3]);
+    assert_eq!(ids(110, 110), [2, 3]);
+    assert_eq!(ids(0, 99), [] as [u64; 0]);
+    assert_eq!(ids(121, 200), [] as [u64; 0]);
+    assert_eq!(ids(110, 105), [] as [u64; 0]);
+    assert_eq!(ids(0, u64::MAX), [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn shift_timestamps_moves_every_record_and_clamps_at_zero() {
+    let dir = TempDir::new();
+    let path = dir.join("shift.wal");
+    write_log(&path, &[(0, 100), (1, 150), (2, 400)]);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let timestamps = |wal: &WriteAheadLog| -> Vec<u64> {
+        wal.read_all()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.timestamp)
+            .collect()
+    };
+
+    wal.shift_timestamps(1_000).unwrap();
+    assert_eq!(timestamps(&wal), [1_100, 1_150, 1_400]);
+    wal.shift_timestamps(-1_200).unwrap();
+    assert_eq!(timestamps(&wal), [0, 0, 200]);
+
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    assert_eq!(ids, [0, 1, 2]);
+}
+
+#[test]
+fn shift_timestamps_keeps_checksums_valid() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::builder(dir.join("sums.wal"))
+        .checksums(true)
+        .build()
+        .unwrap();
+    let entry = wal.append(b"x".to_vec()).unwrap();
+
+    wal.shift_timestamps(-60).unwrap();
+    let shifted = wal.get(entry.id).unwrap().unwrap();
+    assert_eq!(shifted.timestamp, entry.timestamp - 60);
+    assert!(shifted.is_checksum_valid());
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+#[test]
+fn appends_flip_modified_since() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("token.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+
+    wal.read_all().unwrap();
+    let token = wal.read_token().unwrap();
+    assert!(!wal.modified_since(token).unwrap());
+    wal.read_all().unwrap();
+    assert!(!wal.modified_since(token).unwrap());
+
+    wal.append(b"b".to_vec()).unwrap();
+    assert!(wal.modified_since(token).unwrap());
+}
+
+#[test]
+fn deletes_flip_modified_since() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("token.wal")).unwrap();
+    wal.append(b"a".to_vec()).unwrap();
+    wal.append(b"b".to_vec()).unwrap();
+
+    let token = wal.read_token().unwrap();
+    wal.clear_id(0).unwrap();
+    assert!(wal.modified_since(token).unwrap());
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{EntryKind, Format, WriteAheadLog};
+
+#[test]
+fn append_transform_tags_every_entry() {
+    for format in [Format::Json, Format::Binary, Format::CompactBinary] {
+        let dir = TempDir::new();
+        let path = dir.join("app.wal");
+        let open = || {
+            WriteAheadLog::builder(&path)
+                .format(format)
+                .checksums(true)
+                .append_transform(|entry| {
+                    if entry.kind == EntryKind::Data {
+                        entry.tags.insert("host".into(), "node-1".into());
+                        entry.tags.insert("seq".into(), entry.id.to_string());
+                    }
+                })
+                .build()
+                .unwrap()
+        };
+        let mut wal = open();
+        let first = wal.append(b"a".to_vec()).unwrap();
+        assert_eq!(first.tags["host"], "node-1");
+        wal.append_marker("checkpoint").unwrap();
+        wal.append_batch_chunked(vec![b"b".to_vec(), b"c".to_vec()], 2, |_, _| {})
+            .unwrap();
+        drop(wal);
+
+        let wal = open();
+        let entries = wal.read_all().unwrap();
+        assert_eq!(entries.len(), 3, "{format:?}");
+        for entry in &entries {
+            assert_eq!(entry.tags["host"], "node-1", "{format:?}");
+            assert_eq!(entry.tags["seq"], entry.id.to_string(), "{format:?}");
+            assert!(entry.is_checksum_valid());
+        }
+        assert_eq!(entries[0], first);
+    }
+}
+
+#[test]
+fn with_append_transform_can_rewrite_data() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::with_append_transform(
+        dir.join("app.wal"),
+        Box::new(|entry| entry.data.extend_from_slice(b"!")),
+    )
+    .unwrap();
+    wal.append(b"hi".to_vec()).unwrap();
+    assert_eq!(wal.read_all().unwrap()[0].data, b"hi!");
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+fn ids(wal: &WriteAheadLog) -> Vec<u64> {
+    wal.read_all().unwrap().iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn truncate_after_rolls_back_to_a_checkpoint() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..10u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.append_marker("after").unwrap();
+
+    assert_eq!(wal.truncate_after(5).unwrap(), 4);
+    assert_eq!(ids(&wal), [0, 1, 2, 3, 4, 5]);
+    assert_eq!(wal.next_id(), 6);
+    assert_eq!(wal.append(b"new".to_vec()).unwrap().id, 6);
+    assert_eq!(wal.get(6).unwrap().unwrap().data, b"new");
+
+    assert_eq!(wal.truncate_after(100).unwrap(), 0);
+    assert_eq!(wal.next_id(), 7);
+
+    drop(wal);
+    let wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(ids(&wal), [0, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(wal.next_id(), 7);
+}
+
+#[test]
+fn truncate_after_drops_later_segments() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut wal = WriteAheadLog::with_max_segment_bytes(&path, 100).unwrap();
+    for i in 0..20u8 {
+        wal.append(vec![i; 10]).unwrap();
+    }
+    let before = wal.segments().unwrap().len();
+    assert!(before > 3);
+
+    assert_eq!(wal.truncate_after(2).unwrap(), 17);
+    assert!(wal.segments().unwrap().len() < before);
+    assert_eq!(ids(&wal), [0, 1, 2]);
+    assert_eq!(wal.len().unwrap(), 3);
+    assert_eq!(wal.append(vec![]).unwrap().id, 3);
+    assert_eq!(ids(&wal), [0, 1, 2, 3]);
+}
+
+#[test]
+fn truncate_after_does_not_count_cleared_entries() {
+    let dir = TempDir::new();
+    let mut wal = WriteAheadLog::new(dir.join("app.wal")).unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.clear_id(4).unwrap();
+    assert_eq!(wal.len().unwrap(), 5);
+
+    assert_eq!(wal.truncate_after(2).unwrap(), 2);
+    assert_eq!(wal.len().unwrap(), 3);
+    assert_eq!(ids(&wal), [0, 1, 2]);
+}
+
+#[test]
+fn truncate_after_lowers_the_ids_kept_through_compaction() {
+    let dir = TempDir::new();
+    let path = dir.join("app.wal");
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    for i in 0..6u8 {
+        wal.append(vec![i]).unwrap();
+    }
+    wal.compact_retain(|e| e.id < 4).unwrap();
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.next_id(), 6);
+    assert_eq!(wal.truncate_after(1).unwrap(), 2);
+    drop(wal);
+
+    let mut wal = WriteAheadLog::new(&path).unwrap();
+    assert_eq!(wal.append(b"again".to_vec()).unwrap().id, 2);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Payload, Result, TypedWal, WalError, WriteAheadLog};
+
+/// Current schema: `name:age`. Older entries stored only the name.
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+    age: u32,
+}
+
+impl Payload for User {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(format!("{}:{}", self.name, self.age).into_bytes())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let text =
+            std::str::from_utf8(bytes).map_err(|e| WalError::Serialization(e.to_string()))?;
+        let (name, age) = text
+            .split_once(':')
+            .ok_or_else(|| WalError::Serialization(format!("missing age in {text:?}")))?;
+        let age = age
+            .parse()
+            .map_err(|_| WalError::Serialization(format!("bad age in {text:?}")))?;
+        Ok(User {
+            name: name.to_string(),
+            age,
+        })
+    }
+}
+
+#[test]
+fn typed_round_trip() {
+    let dir = TempDir::new();
+    let mut wal = TypedWal::<User>::new(dir.join("users.wal")).unwrap();
+    let alice = User {
+        name: "alice".into(),
+        age: 30,
+    };
+    wal.append(&alice).unwrap();
+
+    assert_eq!(wal.read_all().unwrap(), vec![alice]);
+    assert_eq!(wal.inner().read_all().unwrap()[0].data, b"alice:30");
+}
+
+#[test]
+fn read_all_lenient_reports_each_entry() {
+    let dir = TempDir::new();
+    let path = dir.join("users.wal");
+
+    let mut raw = WriteAheadLog::new(&path).unwrap();
+    raw.append(b"legacy-bob".to_vec()).unwrap();
+    let mut wal = TypedWal::<User>::from_wal(raw);
+    wal.append(&User {
+        name: "carol".into(),
+        age: 41,
+    })
+    .unwrap();
+    let mut raw = wal.into_inner();
+    raw.append(b"dave:old".to_vec()).unwrap();
+    let wal = TypedWal::<User>::from_wal(raw);
+
+    assert!(matches!(wal.read_all(), Err(WalError::Serialization(_))));
+
+    let results = wal.read_all_lenient().unwrap();
+    let ids: Vec<u64> = results.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![0, 1, 2]);
+    assert!(matches!(results[0].1, Err(WalError::Serialization(_))));
+    assert_eq!(
+        results[1].1.as_ref().unwrap(),
+        &User {
+            name: "carol".into(),
+            age: 41
+        }
+    );
+    assert!(matches!(results[2].1, Err(WalError::Serialization(_))));
+}
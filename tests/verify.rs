@@ -0,0 +1,51 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{WalError, WriteAheadLog};
+
+fn log_with(dir: &TempDir, entries: &[&[u8]]) -> WriteAheadLog {
+    let mut wal = WriteAheadLog::new(dir.join("verify.wal")).unwrap();
+    for data in entries {
+        wal.append(data.to_vec()).unwrap();
+    }
+    wal
+}
+
+fn mismatch_index(result: waly_rs::Result<()>) -> usize {
+    match result {
+        Err(WalError::Mismatch { index, .. }) => index,
+        other => panic!("expected a mismatch, got {other:?}"),
+    }
+}
+
+#[test]
+fn matching_sequence_passes() {
+    let dir = TempDir::new();
+    let mut wal = log_with(&dir, &[b"a", b"b"]);
+    wal.append_marker("checkpoint").unwrap();
+
+    wal.assert_entries(&[(0, b"a"), (1, b"b")]).unwrap();
+}
+
+#[test]
+fn reports_first_divergence() {
+    let dir = TempDir::new();
+    let wal = log_with(&dir, &[b"a", b"b", b"c"]);
+
+    assert_eq!(
+        mismatch_index(wal.assert_entries(&[(0, b"a"), (2, b"b")])),
+        1
+    );
+    assert_eq!(
+        mismatch_index(wal.assert_entries(&[(0, b"a"), (1, b"x")])),
+        1
+    );
+    assert_eq!(
+        mismatch_index(wal.assert_entries(&[(0, b"a"), (1, b"b")])),
+        2
+    );
+    assert_eq!(
+        mismatch_index(wal.assert_entries(&[(0, b"a"), (1, b"b"), (2, b"c"), (3, b"d")])),
+        3
+    );
+}
@@ -0,0 +1,73 @@
+//! Kept in its own test binary so the allocation tracking below only sees
+//! this one test.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::TempDir;
+use waly_rs::WriteAheadLog;
+
+struct Tracking;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Tracking = Tracking;
+
+#[test]
+fn verify_checksums_streaming_finds_corruption_in_bounded_memory() {
+    let dir = TempDir::new();
+    let path = dir.join("big.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .checksums(true)
+        .build()
+        .unwrap();
+    for _ in 0..2000 {
+        wal.append(vec![b'a'; 1024]).unwrap();
+    }
+    drop(wal);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let log_len = contents.len();
+    let corrupted: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            if line.contains("\"id\":1234,") {
+                line.replacen("97,", "98,", 1)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    std::fs::write(&path, corrupted.join("\n") + "\n").unwrap();
+    drop((contents, corrupted));
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let before = LIVE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let report = wal.verify_checksums_streaming().unwrap();
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+
+    assert_eq!(report.valid, 1999);
+    assert_eq!(report.mismatched, 1);
+    assert_eq!(report.first_mismatches, [1234]);
+    assert!(!report.is_ok());
+    assert!(log_len > 4 * 1024 * 1024);
+    assert!(peak < 512 * 1024, "peak of {peak} bytes");
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::TempDir;
+use waly_rs::{Format, SyncPolicy, WriteAheadLog};
+
+fn file_len(path: &std::path::Path) -> u64 {
+    std::fs::metadata(path).unwrap().len()
+}
+
+#[test]
+fn buffered_appends_are_visible_to_reads_without_a_flush() {
+    for format in [Format::Json, Format::Binary] {
+        let dir = TempDir::new();
+        let path = dir.join("buffered.wal");
+        let mut wal = WriteAheadLog::builder(&path)
+            .format(format)
+            .write_buffer(64 * 1024)
+            .build()
+            .unwrap();
+        let empty = file_len(&path);
+        wal.append(b"first".to_vec()).unwrap();
+        wal.append(b"second".to_vec()).unwrap();
+        assert_eq!(file_len(&path), empty, "{format:?}");
+        assert!(wal.buffered_bytes().unwrap() > 0);
+
+        let entries = wal.read_all().unwrap();
+        assert_eq!(entries.len(), 2, "{format:?}");
+        assert_eq!(entries[1].data, b"second");
+        assert_eq!(wal.buffered_bytes().unwrap(), 0);
+        assert!(file_len(&path) > empty);
+
+        wal.append(b"third".to_vec()).unwrap();
+        let last = wal.iter().unwrap().last().unwrap().unwrap();
+        assert_eq!(last.data, b"third", "{format:?}");
+    }
+}
+
+#[test]
+fn the_buffer_is_written_out_when_full_flushed_synced_or_dropped() {
+    let dir = TempDir::new();
+    let path = dir.join("points.wal");
+    let open = || {
+        WriteAheadLog::builder(&path)
+            .write_buffer(100)
+            .build()
+            .unwrap()
+    };
+    let mut wal = open();
+    let empty = file_len(&path);
+    while file_len(&path) == empty {
+        wal.append(b"x".to_vec()).unwrap();
+        assert!(wal.buffered_bytes().unwrap() < 100);
+    }
+    assert_eq!(wal.buffered_bytes().unwrap(), 0);
+
+    wal.append(b"flushed".to_vec()).unwrap();
+    wal.flush().unwrap();
+    assert_eq!(wal.buffered_bytes().unwrap(), 0);
+
+    wal.set_sync_policy(SyncPolicy::Always);
+    wal.append(b"synced".to_vec()).unwrap();
+    assert_eq!(wal.buffered_bytes().unwrap(), 0);
+    assert_eq!(wal.durable_id(), wal.next_id());
+
+    wal.set_sync_policy(SyncPolicy::Never);
+    wal.append(b"dropped".to_vec()).unwrap();
+    let count = wal.next_id();
+    drop(wal);
+    let wal = open();
+    assert_eq!(wal.read_all().unwrap().len() as u64, count);
+    assert_eq!(wal.read_all().unwrap().last().unwrap().data, b"dropped");
+}
+
+#[test]
+fn buffering_keeps_rotation_and_compaction_in_order() {
+    let dir = TempDir::new();
+    let path = dir.join("rotate.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .max_segment_bytes(200)
+        .write_buffer(150)
+        .build()
+        .unwrap();
+    for i in 0..20u8 {
+        wal.append(vec![i; 8]).unwrap();
+    }
+    wal.clear_id(3).unwrap();
+    wal.compact().unwrap();
+    wal.append(b"after".to_vec()).unwrap();
+    assert!(wal.segments().unwrap().len() > 1);
+    for segment in wal.segments().unwrap() {
+        assert!(file_len(&segment) <= 200);
+    }
+    let ids: Vec<u64> = wal.read_all().unwrap().iter().map(|e| e.id).collect();
+    let expected: Vec<u64> = (0..20).filter(|&id| id != 3).chain([21]).collect();
+    assert_eq!(ids, expected);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use std::thread;
+
+use common::TempDir;
+use waly_rs::{ConcurrentWriter, WalError, WriteAheadLog, DEFAULT_CHANNEL_CAPACITY};
+
+#[test]
+fn producers_get_distinct_ids_and_drop_writes_everything() {
+    let dir = TempDir::new();
+    let path = dir.join("concurrent.wal");
+    let writer = ConcurrentWriter::open(&path).unwrap();
+    assert_eq!(writer.capacity(), DEFAULT_CHANNEL_CAPACITY);
+
+    let producers: Vec<_> = (0..8u8)
+        .map(|t| {
+            let writer = writer.clone();
+            thread::spawn(move || {
+                (0..50u8)
+                    .map(|i| writer.append(vec![t, i]).unwrap().id)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut ids: Vec<u64> = producers
+        .into_iter()
+        .flat_map(|p| p.join().unwrap())
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..400).collect::<Vec<_>>());
+    drop(writer);
+
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let entries = wal.read_all().unwrap();
+    assert_eq!(entries.len(), 400);
+    assert!(entries.windows(2).all(|w| w[0].id < w[1].id));
+    assert_eq!(wal.durable_id(), 400);
+}
+
+#[test]
+fn appends_report_the_writers_errors() {
+    let dir = TempDir::new();
+    let wal = WriteAheadLog::builder(dir.join("full.wal"))
+        .max_entries(2)
+        .build()
+        .unwrap();
+    let writer = wal.into_concurrent(4).unwrap();
+    writer.append(b"a".to_vec()).unwrap();
+    writer.append(b"b".to_vec()).unwrap();
+    assert!(matches!(
+        writer.append(b"c".to_vec()),
+        Err(WalError::LogFull)
+    ));
+}
+
+#[test]
+fn zero_capacity_is_refused() {
+    let dir = TempDir::new();
+    let wal = WriteAheadLog::new(dir.join("zero.wal")).unwrap();
+    assert!(matches!(
+        wal.into_concurrent(0),
+        Err(WalError::InvalidConfig(_))
+    ));
+}
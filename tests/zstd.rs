@@ -0,0 +1,64 @@
+#![cfg(all(feature = "zstd", feature = "compression"))]
+
+mod common;
+
+use common::TempDir;
+use waly_rs::{Compression, WalError, WriteAheadLog};
+
+const DICTIONARY: &[u8] = br#"{"event":"page_view","user_id":,"session":"","path":"/products/","referrer":"https://www.example.com/","ok":true}"#;
+
+fn payload(i: u32) -> Vec<u8> {
+    format!(
+        r#"{{"event":"page_view","user_id":{i},"session":"s{}","path":"/products/{}","referrer":"https://www.example.com/","ok":true}}"#,
+        i * 7919 % 1000,
+        i % 13
+    )
+    .into_bytes()
+}
+
+fn log_size(mut wal: WriteAheadLog) -> u64 {
+    for i in 0..200 {
+        wal.append(payload(i)).unwrap();
+    }
+    std::fs::metadata(wal.path()).unwrap().len()
+}
+
+#[test]
+fn zstd_dictionary_shrinks_small_similar_entries() {
+    let dir = TempDir::new();
+    let per_entry = log_size(
+        WriteAheadLog::builder(dir.join("per_entry.wal"))
+            .compression(Compression::Lz77)
+            .build()
+            .unwrap(),
+    );
+    let with_dict =
+        log_size(WriteAheadLog::with_zstd_dict(dir.join("zstd.wal"), DICTIONARY.to_vec()).unwrap());
+    assert!(with_dict * 2 < per_entry, "{with_dict} vs {per_entry}");
+}
+
+#[test]
+fn zstd_entries_round_trip_and_reopen() {
+    let dir = TempDir::new();
+    let path = dir.join("zstd.wal");
+    let mut wal = WriteAheadLog::builder(&path)
+        .zstd_dict(DICTIONARY.to_vec())
+        .checksums(true)
+        .build()
+        .unwrap();
+    let mut expected: Vec<_> = (0..20).map(|i| wal.append(payload(i)).unwrap()).collect();
+    expected.push(wal.append(vec![0, 255, 1]).unwrap());
+    expected.push(wal.append(Vec::new()).unwrap());
+    drop(wal);
+
+    // The dictionary is read back from the header.
+    let wal = WriteAheadLog::new(&path).unwrap();
+    let read = wal.read_all().unwrap();
+    assert_eq!(read, expected);
+    assert!(read.iter().all(|e| e.is_checksum_valid()));
+    drop(wal);
+
+    // The same bytes as an LZ77 dictionary are a different dictionary.
+    let err = WriteAheadLog::with_compression_dict(&path, DICTIONARY.to_vec()).unwrap_err();
+    assert!(matches!(err, WalError::InvalidConfig(_)), "{err:?}");
+}